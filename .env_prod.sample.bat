@@ -4,6 +4,11 @@ set BYBIT_SECRET=
 set BYBIT_PERPETUALS_URL=wss://stream.bytick.com/realtime_public
 set BYBIT_PERPETUALS_PRIVATE_URL=wss://stream.bytick.com/realtime_private
 set BYBIT_REST_URL=https://api.bybit.com
+set BYBIT_CONTRACT=linear
+set BYBIT_SYMBOL=BTCUSDT
+set BYBIT_V5_PUBLIC_URL=wss://stream.bybit.com/v5/public/linear
+set BYBIT_V5_PRIVATE_URL=wss://stream.bybit.com/v5/private
+set BYBIT_TRADE_WS_URL=wss://stream.bybit.com/v5/trade
 set PHEMEX_KEY=
 set PHEMEX_SECRET=
 set PHEMEX_PERPETUALS_URL=
@@ -17,4 +22,16 @@ set BINANCE_KEY=
 set BINANCE_SECRET=
 set BINANCE_PERPETUALS_URL=wss://fstream.binance.com
 set BINANCE_REST_URL=https://fapi.binance.com
+set BINANCE_WS_API_URL=wss://ws-fapi.binance.com/ws-fapi/v1
+set BINANCE_SPOT_REST_URL=https://api.binance.com
+set BINANCE_SPOT_STREAM_URL=wss://stream.binance.com:9443
+set BINANCE_SPOT_SYMBOL=btcusdt
+set BINANCE_SPOT_MARGIN=false
+set RISK_CONFIG=risk.json
+set STRATEGY=ladder
+set MARKET_MAKER_CONFIG=market_maker.json
+set GRID_CONFIG=grid.json
+set LOG_LEVEL=info
+set METRICS_ADDR=127.0.0.1:9184
+set PRICE_TICK=0.1
 set RUST_BACKTRACE=1
\ No newline at end of file
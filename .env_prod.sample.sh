@@ -4,6 +4,11 @@ export BYBIT_SECRET=
 export BYBIT_PERPETUALS_URL=wss://stream.bytick.com/realtime_public
 export BYBIT_PERPETUALS_PRIVATE_URL=wss://stream.bytick.com/realtime_private
 export BYBIT_REST_URL=https://api.bybit.com
+export BYBIT_CONTRACT=linear
+export BYBIT_SYMBOL=BTCUSDT
+export BYBIT_V5_PUBLIC_URL=wss://stream.bybit.com/v5/public/linear
+export BYBIT_V5_PRIVATE_URL=wss://stream.bybit.com/v5/private
+export BYBIT_TRADE_WS_URL=wss://stream.bybit.com/v5/trade
 export PHEMEX_KEY=
 export PHEMEX_SECRET=
 export PHEMEX_PERPETUALS_URL=
@@ -17,4 +22,16 @@ export BINANCE_KEY=
 export BINANCE_SECRET=
 export BINANCE_PERPETUALS_URL=wss://fstream.binance.com
 export BINANCE_REST_URL=https://fapi.binance.com
+export BINANCE_WS_API_URL=wss://ws-fapi.binance.com/ws-fapi/v1
+export BINANCE_SPOT_REST_URL=https://api.binance.com
+export BINANCE_SPOT_STREAM_URL=wss://stream.binance.com:9443
+export BINANCE_SPOT_SYMBOL=btcusdt
+export BINANCE_SPOT_MARGIN=false
+export RISK_CONFIG=risk.json
+export STRATEGY=ladder
+export MARKET_MAKER_CONFIG=market_maker.json
+export GRID_CONFIG=grid.json
+export LOG_LEVEL=info
+export METRICS_ADDR=127.0.0.1:9184
+export PRICE_TICK=0.1
 export RUST_BACKTRACE=1
\ No newline at end of file
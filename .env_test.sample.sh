@@ -4,6 +4,11 @@ export BYBIT_SECRET=
 export BYBIT_PERPETUALS_URL=wss://stream-testnet.bybit.com/realtime_public
 export BYBIT_PERPETUALS_PRIVATE_URL=wss://stream-testnet.bybit.com/realtime_private
 export BYBIT_REST_URL=https://api-testnet.bybit.com
+export BYBIT_CONTRACT=linear
+export BYBIT_SYMBOL=BTCUSDT
+export BYBIT_V5_PUBLIC_URL=wss://stream-testnet.bybit.com/v5/public/linear
+export BYBIT_V5_PRIVATE_URL=wss://stream-testnet.bybit.com/v5/private
+export BYBIT_TRADE_WS_URL=wss://stream-testnet.bybit.com/v5/trade
 export PHEMEX_KEY=
 export PHEMEX_SECRET=
 export PHEMEX_PERPETUALS_URL=
@@ -13,4 +18,16 @@ export BINANCE_KEY=
 export BINANCE_SECRET=
 export BINANCE_PERPETUALS_URL=wss://stream.binancefuture.com
 export BINANCE_REST_URL=https://testnet.binancefuture.com
+export BINANCE_WS_API_URL=wss://testnet.binancefuture.com/ws-fapi/v1
+export BINANCE_SPOT_REST_URL=https://testnet.binance.vision
+export BINANCE_SPOT_STREAM_URL=wss://testnet.binance.vision
+export BINANCE_SPOT_SYMBOL=btcusdt
+export BINANCE_SPOT_MARGIN=false
+export RISK_CONFIG=risk.json
+export STRATEGY=ladder
+export MARKET_MAKER_CONFIG=market_maker.json
+export GRID_CONFIG=grid.json
+export LOG_LEVEL=info
+export METRICS_ADDR=127.0.0.1:9184
+export PRICE_TICK=0.1
 export RUST_BACKTRACE=1
\ No newline at end of file
@@ -1265,7 +1265,7 @@ impl<'de> serde::de::Deserialize<'de> for Decimal128 {
             type Value = D128;
 
             fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-                fmt.write_str("float or string")
+                fmt.write_str("number or string")
             }
 
             fn visit_f64<E>(self, val: f64) -> Result<Self::Value, E>
@@ -1275,6 +1275,20 @@ impl<'de> serde::de::Deserialize<'de> for Decimal128 {
                 Ok(Decimal128::from(val))
             }
 
+            fn visit_i64<E>(self, val: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Decimal128::from(val))
+            }
+
+            fn visit_u64<E>(self, val: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Decimal128::from(val))
+            }
+
             fn visit_str<E>(self, val: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
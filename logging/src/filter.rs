@@ -0,0 +1,74 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::LogError;
+use crate::level::{parse_threshold, threshold_str, Level};
+
+/// Which levels get through for which modules.
+/// Written as a default level followed by module overrides, e.g. "info,strategy::grid=debug,backend=warn". A module
+/// path matches itself and everything under it, with or without the crate name in front, and the longest match wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    default: u8,
+    /// Longest path first so the first match is the most specific
+    directives: Vec<(String, u8)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter { default: Level::Info as u8, directives: Vec::new() }
+    }
+}
+
+impl Filter {
+    /// Highest level any module lets through, anything above it is rejected without a lookup
+    pub fn max(&self) -> u8 {
+        self.directives.iter().map(|(_, level)| *level).fold(self.default, u8::max)
+    }
+
+    /// The most verbose level module logs at
+    pub fn threshold(&self, module: &str) -> u8 {
+        match self.directives.iter().find(|(path, _)| matches(path, module)) {
+            Some((_, level)) => *level,
+            None => self.default,
+        }
+    }
+}
+
+fn matches(path: &str, module: &str) -> bool {
+    under(path, module) || module.split_once("::").map_or(false, |(_, rest)| under(path, rest))
+}
+
+fn under(path: &str, module: &str) -> bool {
+    module.starts_with(path) && (module.len() == path.len() || module[path.len()..].starts_with("::"))
+}
+
+impl FromStr for Filter {
+    type Err = LogError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('=') {
+                Some((path, level)) => {
+                    let path = path.trim();
+                    if path.is_empty() { return Err(LogError::Filter(part.to_string())); }
+                    filter.directives.retain(|(existing, _)| existing != path);
+                    filter.directives.push((path.to_string(), parse_threshold(level)?));
+                },
+                None => filter.default = parse_threshold(part)?,
+            }
+        }
+        filter.directives.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+        Ok(filter)
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", threshold_str(self.default).to_lowercase())?;
+        for (path, level) in &self.directives {
+            write!(f, ",{}={}", path, threshold_str(*level).to_lowercase())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use std::thread::Thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::LogError;
+use crate::level::Level;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// One readable line per record
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = LogError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            other => Err(LogError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+/// Everything a log call captured, the message is already formatted so nothing borrowed crosses threads
+pub struct Record {
+    pub time: SystemTime,
+    pub level: Level,
+    pub module: &'static str,
+    pub line: u32,
+    pub thread: Thread,
+    pub message: String,
+}
+
+impl Record {
+    /// Messages lead with a [TAG] by convention, it's split out as its own field in JSON
+    fn tag(&self) -> Option<(&str, &str)> {
+        let rest = self.message.strip_prefix('[')?;
+        let end = rest.find(']')?;
+        let tag = &rest[..end];
+        if tag.is_empty() || tag.len() > 24 || tag.contains('[') { return None; }
+        Some((tag, rest[end + 1..].trim_start()))
+    }
+
+    /// Appends the record to out as a line in the format
+    pub fn write(&self, format: Format, out: &mut String) {
+        match format {
+            Format::Text => {
+                let _ = writeln!(out, "{} {:<5} {}: {}", timestamp(self.time), self.level, self.module, self.message);
+            },
+            Format::Json => {
+                let _ = write!(out, "{{\"ts\":\"{}\",\"level\":\"{}\",\"module\":\"{}\",\"line\":{},\"thread\":",
+                    timestamp(self.time), self.level, self.module, self.line);
+                match self.thread.name() {
+                    Some(name) => escape(name, out),
+                    None => { let _ = write!(out, "\"{:?}\"", self.thread.id()); },
+                }
+                let message = match self.tag() {
+                    Some((tag, message)) => {
+                        out.push_str(",\"tag\":");
+                        escape(tag, out);
+                        message
+                    },
+                    None => &self.message,
+                };
+                out.push_str(",\"msg\":");
+                escape(message, out);
+                out.push_str("}\n");
+            },
+        }
+    }
+}
+
+/// JSON string with quotes
+fn escape(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// RFC 3339 in UTC to the microsecond
+pub fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (year, month, day) = civil(secs / 86400);
+    let of_day = secs % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z", year, month, day,
+        of_day / 3600, of_day % 3600 / 60, of_day % 60, since.subsec_micros())
+}
+
+/// Compact UTC time for file names
+pub fn file_stamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil(secs / 86400);
+    let of_day = secs % 86400;
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}", year, month, day, of_day / 3600, of_day % 3600 / 60, of_day % 60)
+}
+
+/// Year, month and day from days since the epoch, proleptic Gregorian
+fn civil(days: u64) -> (i64, u64, u64) {
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe as i64 + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::LogError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+/// Filters hold levels as numbers so nothing but a load is needed on the hot path, OFF lets nothing through
+pub const OFF: u8 = 0;
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+
+    pub fn from_u8(level: u8) -> Option<Level> {
+        match level {
+            1 => Some(Level::Error),
+            2 => Some(Level::Warn),
+            3 => Some(Level::Info),
+            4 => Some(Level::Debug),
+            5 => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = LogError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            other => Err(LogError::UnknownLevel(other.to_string())),
+        }
+    }
+}
+
+/// A level or off, as written in filters
+pub fn parse_threshold(s: &str) -> Result<u8, LogError> {
+    if s.trim().eq_ignore_ascii_case("off") { return Ok(OFF); }
+    Ok(Level::from_str(s)? as u8)
+}
+
+pub fn threshold_str(threshold: u8) -> &'static str {
+    match Level::from_u8(threshold) {
+        Some(level) => level.as_str(),
+        None => "OFF",
+    }
+}
@@ -1,45 +1,196 @@
+//! Leveled logging kept off the hot path.
+//! A log call checks its level against the filter for its module and, if it gets through, formats the message and
+//! pushes it onto a bounded lock-free queue. A background thread drains the queue into stdout and an optional file,
+//! as text or JSON lines, rolling the file over by size or time. When the queue is full records are dropped rather
+//! than blocking the caller, and the writer logs how many were lost.
+//! The filter can be swapped at runtime with set_filter, e.g. "info,strategy::grid=debug,backend=warn".
+
 #[macro_use]
 extern crate lazy_static;
 
-use crossbeam_channel::{Sender, Receiver, unbounded};
+mod filter;
+mod format;
+mod level;
+mod sink;
+mod writer;
+
+use std::fmt::Arguments;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crossbeam::sync::ShardedLock;
+use crossbeam_channel::bounded;
+use thiserror::Error;
+
+pub use self::filter::*;
+pub use self::format::{Format, Record, timestamp};
+pub use self::level::Level;
+
+use self::sink::Sinks;
+use self::writer::{Control, Writer};
+
+/// Records that can wait on the writer before new ones are dropped
+const QUEUE_CAPACITY: usize = 1 << 16;
+
+/// Highest level any module lets through, checked before the filter so most rejected calls cost one load
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+/// Records dropped on a full queue since the writer last reported
+static DROPPED: AtomicU64 = AtomicU64::new(0);
 
 lazy_static! {
-    static ref LOG_SENDER: Sender<String> = create_logging_thread();
+    static ref FILTER: ShardedLock<Filter> = ShardedLock::new(Filter::default());
+    static ref WRITER: Writer = Writer::spawn(QUEUE_CAPACITY);
 }
 
-fn create_logging_thread() -> Sender<String>{
-    let (logging_tx, logging_rx): (Sender<String>, Receiver<String>) = unbounded();
-    std::thread::spawn(move|| { 
-        loop {
-            match logging_rx.recv() {
-                Ok(message) => println!("{}", message),
-                Err(_) => {},
-            }
-        }
-    });
-    return logging_tx;
+#[derive(Error, Debug)]
+pub enum LogError {
+    #[error("Unknown log level {0}")]
+    UnknownLevel(String),
+    #[error("Unknown log format {0}, expected text or json")]
+    UnknownFormat(String),
+    #[error("Malformed filter directive {0}")]
+    Filter(String),
+    #[error("Failed to open log file: {0}")]
+    Io(#[from] std::io::Error),
 }
 
-pub fn pipe_to_output(message: String) {
-    LOG_SENDER.send(message);
+/// Where logs go and what gets through, see init
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub filter: Filter,
+    pub format: Format,
+    pub stdout: bool,
+    /// Appended to when set
+    pub file: Option<PathBuf>,
+    /// Roll the file over once it would grow past this many bytes
+    pub rotate_bytes: Option<u64>,
+    /// Roll the file over on multiples of this many seconds since the epoch, 86400 for midnight UTC
+    pub rotate_secs: Option<u64>,
+    /// Rolled over files kept next to the live one
+    pub keep: usize,
 }
 
-#[macro_export]
-macro_rules! info{
-    ($($arg:tt)*) => {
-        match false {
-            true => {(logging::pipe_to_output(format!("[{}] [INFO] {}", std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap().as_millis(), format!($($arg)*).to_string())));},
-            false => {println!("{}", format!($($arg)*).to_string())}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            filter: Filter::default(),
+            format: Format::Text,
+            stdout: true,
+            file: None,
+            rotate_bytes: Some(100 * 1024 * 1024),
+            rotate_secs: None,
+            keep: 10,
         }
     }
 }
 
+/// Applies the settings and hooks panics so whatever is queued is written before the process goes down.
+/// Logging works before init with the default settings.
+pub fn init(settings: Settings) -> Result<(), LogError> {
+    let sinks = Sinks::open(&settings)?;
+    let (ack, done) = bounded(1);
+    if WRITER.control.send(Control::Sinks(sinks, ack)).is_ok() {
+        WRITER.thread.unpark();
+        let _ = done.recv_timeout(Duration::from_secs(1));
+    }
+    set(settings.filter);
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic| {
+        log(Level::Error, module_path!(), line!(), format_args!("[PANIC] {}", panic));
+        flush();
+        previous(panic);
+    }));
+    Ok(())
+}
+
+fn set(filter: Filter) {
+    MAX_LEVEL.store(filter.max(), Ordering::Relaxed);
+    match FILTER.write() {
+        Ok(mut current) => *current = filter,
+        Err(poisoned) => *poisoned.into_inner() = filter,
+    }
+}
+
+/// Swaps the filter while running
+pub fn set_filter(spec: &str) -> Result<(), LogError> {
+    set(spec.parse()?);
+    Ok(())
+}
+
+/// The filter in the form set_filter takes
+pub fn filter() -> String {
+    match FILTER.read() {
+        Ok(filter) => filter.to_string(),
+        Err(poisoned) => poisoned.into_inner().to_string(),
+    }
+}
+
+/// Whether a record at level from module would get through, called by the macros before anything is formatted
+#[inline]
+pub fn enabled(level: Level, module: &str) -> bool {
+    if level as u8 > MAX_LEVEL.load(Ordering::Relaxed) { return false; }
+    match FILTER.read() {
+        Ok(filter) => level as u8 <= filter.threshold(module),
+        Err(_) => true,
+    }
+}
+
+/// Queues a record for the writer, use the macros rather than calling this directly
+pub fn log(level: Level, module: &'static str, line: u32, args: Arguments) {
+    let record = Record {
+        time: SystemTime::now(),
+        level,
+        module,
+        line,
+        thread: std::thread::current(),
+        message: std::fmt::format(args),
+    };
+    if WRITER.queue.push(record).is_err() {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Blocks until everything queued so far is written out, or a second has passed
+pub fn flush() {
+    let (ack, done) = bounded(1);
+    if WRITER.control.send(Control::Flush(ack)).is_ok() {
+        WRITER.thread.unpark();
+        let _ = done.recv_timeout(Duration::from_secs(1));
+    }
+}
+
 #[macro_export]
-macro_rules! debug{
-    ($($arg:tt)*) => {
-        match false {
-            true => {(logging::pipe_to_output(format!("[{}] [DBUG] {}", std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap().as_millis(), format!($($arg)*).to_string())));},
-            false => {println!("debug {}", format!($($arg)*).to_string())}
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {{
+        let level = $level;
+        if $crate::enabled(level, module_path!()) {
+            $crate::log(level, module_path!(), line!(), format_args!($($arg)+));
         }
-    }
-}
\ No newline at end of file
+    }};
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::Level::Trace, $($arg)+) };
+}
@@ -0,0 +1,145 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::{file_stamp, Format, Record};
+use crate::Settings;
+
+/// Where the writer thread puts records
+pub struct Sinks {
+    format: Format,
+    stdout: Option<BufWriter<Stdout>>,
+    file: Option<FileSink>,
+    line: String,
+}
+
+impl Sinks {
+    /// Opens the file sink up front so a bad path is reported by init rather than lost on the writer thread
+    pub fn open(settings: &Settings) -> io::Result<Sinks> {
+        Ok(Sinks {
+            format: settings.format,
+            stdout: if settings.stdout { Some(BufWriter::new(io::stdout())) } else { None },
+            file: match &settings.file {
+                Some(path) => Some(FileSink::open(path.clone(), settings.rotate_bytes, settings.rotate_secs, settings.keep)?),
+                None => None,
+            },
+            line: String::with_capacity(512),
+        })
+    }
+
+    pub fn write(&mut self, record: &Record) {
+        self.line.clear();
+        record.write(self.format, &mut self.line);
+        if let Some(stdout) = &mut self.stdout {
+            let _ = stdout.write_all(self.line.as_bytes());
+        }
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.write(self.line.as_bytes()) {
+                eprintln!("[LOG] Failed to write to {}: {}", file.path.display(), e);
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(stdout) = &mut self.stdout {
+            let _ = stdout.flush();
+        }
+        if let Some(file) = &mut self.file {
+            let _ = file.out.flush();
+        }
+    }
+}
+
+/// Appends to path, rolling it over to path.<UTC time> by size, on wall clock boundaries, or both
+struct FileSink {
+    path: PathBuf,
+    out: BufWriter<File>,
+    written: u64,
+    rotate_bytes: Option<u64>,
+    rotate_secs: Option<u64>,
+    /// Seconds since the epoch the next time based rollover is due at
+    next_rotation: Option<u64>,
+    keep: usize,
+}
+
+impl FileSink {
+    fn open(path: PathBuf, rotate_bytes: Option<u64>, rotate_secs: Option<u64>, keep: usize) -> io::Result<FileSink> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        let rotate_secs = rotate_secs.filter(|secs| *secs > 0);
+        Ok(FileSink {
+            path,
+            out: BufWriter::new(file),
+            written,
+            rotate_bytes: rotate_bytes.filter(|bytes| *bytes > 0),
+            rotate_secs,
+            next_rotation: rotate_secs.map(next_boundary),
+            keep,
+        })
+    }
+
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        let size_due = self.rotate_bytes.map_or(false, |limit| self.written > 0 && self.written + line.len() as u64 > limit);
+        let time_due = self.next_rotation.map_or(false, |due| now_secs() >= due);
+        if size_due || time_due {
+            self.rotate()?;
+        }
+        self.out.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        let stamp = file_stamp(SystemTime::now());
+        let mut rolled = suffixed(&self.path, &stamp);
+        let mut n = 1;
+        while rolled.exists() {
+            rolled = suffixed(&self.path, &format!("{}.{}", stamp, n));
+            n += 1;
+        }
+        fs::rename(&self.path, &rolled)?;
+        self.out = BufWriter::new(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        self.written = 0;
+        self.next_rotation = self.rotate_secs.map(next_boundary);
+        self.prune();
+        Ok(())
+    }
+
+    /// Deletes the oldest rolled over files past keep, the stamps sort in time order
+    fn prune(&self) {
+        let (dir, name) = match (self.path.parent(), self.path.file_name().and_then(|name| name.to_str())) {
+            (Some(dir), Some(name)) => (if dir.as_os_str().is_empty() { Path::new(".") } else { dir }, format!("{}.", name)),
+            _ => return,
+        };
+        let mut rolled: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_str().map_or(false, |file| file.starts_with(&name)))
+                .map(|entry| entry.path())
+                .collect(),
+            Err(_) => return,
+        };
+        if rolled.len() <= self.keep { return; }
+        rolled.sort();
+        for old in &rolled[..rolled.len() - self.keep] {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+/// Next multiple of period since the epoch, so daily files roll over at midnight UTC
+fn next_boundary(period: u64) -> u64 {
+    (now_secs() / period + 1) * period
+}
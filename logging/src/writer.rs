@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread::{self, Thread};
+use std::time::{Duration, SystemTime};
+
+use crossbeam::queue::ArrayQueue;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::{Settings, DROPPED};
+use crate::format::Record;
+use crate::level::Level;
+use crate::sink::Sinks;
+
+/// How long the writer sleeps once it's caught up, callers never wake it so logging costs them no syscall
+const IDLE: Duration = Duration::from_millis(2);
+
+pub enum Control {
+    /// Acked once the new sinks are in, anything logged after that goes to them
+    Sinks(Sinks, Sender<()>),
+    /// Acked once everything queued before it has been written out
+    Flush(Sender<()>),
+}
+
+pub struct Writer {
+    pub queue: Arc<ArrayQueue<Record>>,
+    pub control: Sender<Control>,
+    pub thread: Thread,
+}
+
+impl Writer {
+    pub fn spawn(capacity: usize) -> Writer {
+        let queue = Arc::new(ArrayQueue::new(capacity));
+        let (control, control_rx) = unbounded();
+        let sinks = Sinks::open(&Settings::default()).expect("Default log sinks can't fail to open");
+        let handle = {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name("log_writer".to_string())
+                .spawn(move || run(queue, control_rx, sinks))
+                .expect("Failed to spawn log writer")
+        };
+        Writer { queue, control, thread: handle.thread().clone() }
+    }
+}
+
+fn run(queue: Arc<ArrayQueue<Record>>, control: Receiver<Control>, mut sinks: Sinks) {
+    loop {
+        while let Ok(message) = control.try_recv() {
+            match message {
+                Control::Sinks(new, ack) => {
+                    drain(&queue, &mut sinks);
+                    sinks.flush();
+                    sinks = new;
+                    let _ = ack.send(());
+                },
+                Control::Flush(ack) => {
+                    drain(&queue, &mut sinks);
+                    sinks.flush();
+                    let _ = ack.send(());
+                },
+            }
+        }
+        if drain(&queue, &mut sinks) {
+            sinks.flush();
+        } else {
+            thread::park_timeout(IDLE);
+        }
+    }
+}
+
+/// Writes out everything queued, false if there was nothing
+fn drain(queue: &ArrayQueue<Record>, sinks: &mut Sinks) -> bool {
+    let mut wrote = false;
+    while let Some(record) = queue.pop() {
+        sinks.write(&record);
+        wrote = true;
+    }
+    let dropped = DROPPED.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        sinks.write(&Record {
+            time: SystemTime::now(),
+            level: Level::Warn,
+            module: module_path!(),
+            line: line!(),
+            thread: thread::current(),
+            message: format!("[LOG] Queue was full, dropped {} records", dropped),
+        });
+        wrote = true;
+    }
+    wrote
+}
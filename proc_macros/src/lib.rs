@@ -119,7 +119,7 @@ pub fn derive_binance_signable(item: TokenStream) -> TokenStream {
 
         impl #ident {
 
-            pub fn get_signed_data(&mut self, secret: String) -> Result<String, SignRequestError>{
+            pub fn get_signed_data(&self, secret: String) -> Result<String, SignRequestError>{
                 use hmac::{Hmac, Mac};
                 use sha2::{Sha256};
                 use crate::config::CONFIG;
@@ -23,6 +23,10 @@ pub struct BookResult {
     pub best_ask_volatility: D128,
     pub best_bid: (D128, OrderBookValue),
     pub best_ask: (D128, OrderBookValue),
+    /// Taker trades per second over the trade flow window
+    pub arrival_rate: D128,
+    /// Average distance of those trades from the mid, NaN while the window is empty
+    pub trade_depth: D128,
     pub test_timer: Instant,
 }
 
@@ -35,6 +39,8 @@ impl BookResult {
             total_bid_liq: D128::NAN,
             best_bid: (D128::NAN, OrderBookValue::new()),
             best_ask: (D128::NAN, OrderBookValue::new()),
+            arrival_rate: D128::NAN,
+            trade_depth: D128::NAN,
             test_timer: Instant::now(),
         }
     }
@@ -43,6 +49,10 @@ impl BookResult {
 #[derive(Clone, Copy, Debug)]
 pub struct TradeResult {
     pub new_level: (D128, D128),
+    /// Taker buy volume in the update that produced this result
+    pub buy_volume: D128,
+    /// Taker sell volume in the update that produced this result
+    pub sell_volume: D128,
     pub test_timer: Instant,
 }
 
@@ -65,6 +75,8 @@ impl Analysis {
 
         result.best_bid = (best_bid.0.key, *best_bid.1);
         result.best_ask = (best_ask.0.key, *best_ask.1);
+        result.arrival_rate = tradeflow.arrival_rate();
+        result.trade_depth = tradeflow.depth((result.best_bid.0 + result.best_ask.0) / 2);
 
         result
     }
@@ -73,6 +85,8 @@ impl Analysis {
     pub fn new_trade(orderbook: &OrderBook, tradeflow: &TradeFlow) -> TradeResult {
         let mut result = TradeResult {
             new_level: (D128::NAN, D128::NAN),
+            buy_volume: tradeflow.last_buy.volume.sum_dependent,
+            sell_volume: tradeflow.last_sell.volume.sum_dependent,
             test_timer: Instant::now(),
         };
 
@@ -1,35 +1,192 @@
+use std::sync::atomic::Ordering;
 use std::time::{SystemTimeError, Instant, UNIX_EPOCH, SystemTime};
 
 use dec::D128;
+use thiserror::Error;
 use uuid::Uuid;
 
-use crate::{config::CONFIG, backend::{types::{Side}, binance::{types::{OrderType, MarketOrderRequest, OrderResponseType, BinanceSide, BinancePositionSide, OrderResponseWrapper, LimitOrderRequest, BinanceTimeInForce, AccountBalanceRequest, AccountBalance, AccountBalanceWrapper}, broker::BROKER}}, strategy::types::Stage};
+use crate::SignRequestError;
+use crate::{config::CONFIG, backend::{types::{Side}, binance::{types::{OrderType, MarketOrderRequest, OrderResponseType, BinanceSide, BinancePositionSide, OrderResponseWrapper, LimitOrderRequest, BinanceTimeInForce, AccountBalanceRequest, AccountBalance, AccountBalanceWrapper, OpenOrdersRequest, OpenOrdersWrapper, PositionRiskRequest, PositionRiskWrapper, PositionModeRequest, PositionModeWrapper, CommissionRateRequest, CommissionRateWrapper}, broker::BROKER}}, strategy::types::Stage};
 
-use super::Broker;
+use super::{Broker, CalculateServerTimeError};
+
+#[derive(Error, Debug)]
+pub enum AccountInfoError {
+    #[error("Failed to sign request")]
+    SignRequestError(#[from] SignRequestError),
+    #[error("Failed to send request")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("Failed to deserialize response")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Failed to calculate server time")]
+    CalculateServerTimeError(#[from] CalculateServerTimeError),
+}
 
 impl Broker {
     pub async fn account_balance(&self) -> AccountBalanceWrapper {
-        let req = AccountBalanceRequest {
-            receive_window: 5000,
-            timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
-        }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
-
-        let balance_res = self.client
-            .get(format!("{}/fapi/v2/balance?{}", self.auth.url, req))
-            .header("Content-Type", "application/json")
-            .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
-            .send()
-            .await
-            .expect("error recv key response")
-            .text()
-            .await
-            .expect("err");
-        // info!("acc bal {}", balance_res);
-        let balances = serde_json::from_str::<AccountBalanceWrapper>(&balance_res).expect("err deser acc bal");
-        match &balances {
-            AccountBalanceWrapper::Balance(_) => {},
-            AccountBalanceWrapper::Error(e) => BROKER.error(e),
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = AccountBalanceRequest {
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let balance_res = self.client
+                .get(format!("{}/fapi/v2/balance?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            // info!("acc bal {}", balance_res);
+            let balances = serde_json::from_str::<AccountBalanceWrapper>(&balance_res).expect("err deser acc bal");
+            if let AccountBalanceWrapper::Error(e) = &balances {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return balances;
+        }
+    }
+
+    /// Pulls every order the exchange considers open for the symbol
+    pub async fn open_orders(&self, symbol: String) -> Result<OpenOrdersWrapper, AccountInfoError> {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = OpenOrdersRequest {
+                symbol: symbol.clone(),
+                receive_window: 5000,
+                timestamp: self.calculate_server_time()?,
+            }.get_signed_data(self.auth.secret.clone())?;
+
+            let orders_res = self.client
+                .get(format!("{}/fapi/v1/openOrders?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await?
+                .text()
+                .await?;
+            // info!("open orders {}", orders_res);
+            let orders = serde_json::from_str::<OpenOrdersWrapper>(&orders_res)?;
+            if let OpenOrdersWrapper::Error(e) = &orders {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return Ok(orders);
         }
-        balances
     }
-}
\ No newline at end of file
+
+    /// Pulls the exchange's view of the symbol's positions, one per position side in hedge mode
+    pub async fn position_risk(&self, symbol: String) -> Result<PositionRiskWrapper, AccountInfoError> {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = PositionRiskRequest {
+                symbol: symbol.clone(),
+                receive_window: 5000,
+                timestamp: self.calculate_server_time()?,
+            }.get_signed_data(self.auth.secret.clone())?;
+
+            let risk_res = self.client
+                .get(format!("{}/fapi/v2/positionRisk?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await?
+                .text()
+                .await?;
+            // info!("position risk {}", risk_res);
+            let positions = serde_json::from_str::<PositionRiskWrapper>(&risk_res)?;
+            if let PositionRiskWrapper::Error(e) = &positions {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return Ok(positions);
+        }
+    }
+
+    /// Pulls the account's maker and taker rates for the symbol, these follow the account's fee tier
+    pub async fn commission_rate(&self, symbol: String) -> Result<CommissionRateWrapper, AccountInfoError> {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = CommissionRateRequest {
+                symbol: symbol.clone(),
+                receive_window: 5000,
+                timestamp: self.calculate_server_time()?,
+            }.get_signed_data(self.auth.secret.clone())?;
+
+            let rate_res = self.client
+                .get(format!("{}/fapi/v1/commissionRate?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await?
+                .text()
+                .await?;
+            let rate = serde_json::from_str::<CommissionRateWrapper>(&rate_res)?;
+            if let CommissionRateWrapper::Error(e) = &rate {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return Ok(rate);
+        }
+    }
+
+    /// Pulls the account's position mode, true for hedge mode
+    pub async fn position_mode(&self) -> PositionModeWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = PositionModeRequest {
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let mode_res = self.client
+                .get(format!("{}/fapi/v1/positionSide/dual?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            let mode = serde_json::from_str::<PositionModeWrapper>(&mode_res).expect("err deser position mode");
+            if let PositionModeWrapper::Error(e) = &mode {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return mode;
+        }
+    }
+
+    /// Queries and stores the position mode that orders are built for, stays in hedge mode if the query fails
+    pub async fn detect_position_mode(&self) -> bool {
+        match self.position_mode().await {
+            PositionModeWrapper::Mode(mode) => self.hedge_mode.store(mode.dual_side_position, Ordering::SeqCst),
+            PositionModeWrapper::Error(e) => info!("Couldn't query position mode, assuming hedge mode: {}", e.msg),
+        }
+        self.hedge_mode()
+    }
+
+    pub fn hedge_mode(&self) -> bool {
+        self.hedge_mode.load(Ordering::SeqCst)
+    }
+}
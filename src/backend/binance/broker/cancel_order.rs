@@ -1,6 +1,6 @@
 use std::time::Instant;
 use uuid::Uuid;
-use crate::{config::CONFIG, backend::{binance::{types::{CancelRequest, CancelResponseWrapper}, broker::BROKER}}};
+use crate::{config::CONFIG, backend::{binance::{types::{CancelRequest, CancelResponseWrapper}, broker::BROKER, gateway::GATEWAY}}};
 use super::Broker;
 
 impl Broker {
@@ -10,30 +10,42 @@ impl Broker {
         symbol: String,
     ) -> CancelResponseWrapper {
 
-        let req = CancelRequest {
-            symbol,
-            id,
-            receive_window: 5000,
-            timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
-        }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
-        // info!("can req: {}", req);
-        let timer = Instant::now();
-        let cancel_res = self.client
-            .delete(format!("{}/fapi/v1/order?{}", self.auth.url, req))
-            .header("Content-Type", "application/json")
-            .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
-            .send()
-            .await
-            .expect("error recv key response")
-            .text()
-            .await
-            .expect("err");
-        // info!("can res: {}\ncan ping: {}", cancel_res, timer.elapsed().as_millis());
-        let wrapper = serde_json::from_str::<CancelResponseWrapper>(&cancel_res).expect("serde err binance market res");
-        match &wrapper {
-            CancelResponseWrapper::Cancel(_) => {},
-            CancelResponseWrapper::Error(e) => BROKER.error(e),
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = CancelRequest {
+                symbol: symbol.clone(),
+                id,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            };
+            // info!("can req: {:?}", req);
+            let timer = Instant::now();
+            let cancel_res = match GATEWAY.request("order.cancel", &req).await {
+                Some(res) => res,
+                None => {
+                    let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+                    self.client
+                        .delete(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                        .header("Content-Type", "application/json")
+                        .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                        .send()
+                        .await
+                        .expect("error recv key response")
+                        .text()
+                        .await
+                        .expect("err")
+                },
+            };
+            // info!("can res: {}\ncan ping: {}", cancel_res, timer.elapsed().as_millis());
+            let wrapper = serde_json::from_str::<CancelResponseWrapper>(&cancel_res).expect("serde err binance market res");
+            if let CancelResponseWrapper::Error(e) = &wrapper {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return wrapper;
         }
-        wrapper
     }
 }
\ No newline at end of file
@@ -1,11 +1,23 @@
-use std::time::{SystemTimeError, Instant, UNIX_EPOCH, SystemTime};
+use std::time::{SystemTimeError, UNIX_EPOCH, SystemTime};
 
 use dec::D128;
 use uuid::Uuid;
 
-use crate::{config::CONFIG, backend::{types::{Side}, binance::{types::{OrderType, MarketOrderRequest, OrderResponseType, BinanceSide, BinancePositionSide, OrderResponseWrapper, LimitOrderRequest, BinanceTimeInForce}, broker::BROKER}}, strategy::types::Stage};
+use crate::{config::CONFIG, backend::{types::{Side}, binance::{types::{OrderType, MarketOrderRequest, OrderResponseType, BinanceSide, BinancePositionSide, OrderResponseWrapper, LimitOrderRequest, BinanceTimeInForce, ConditionalOrderRequest, WorkingType, ModifyOrderRequest}, broker::BROKER, gateway::GATEWAY}}, strategy::types::Stage};
+use crate::telemetry::{LATENCY, Venue, LatencyStage};
 
-use super::Broker;
+use super::{Broker, Resend};
+
+/// Maps the direction of the order being placed and its stage onto Binance's order fields.
+/// Hedge mode names the position being traded, one-way mode trades BOTH and flags exits reduce only.
+fn order_sides(side: Side, stage: Stage) -> (BinanceSide, BinancePositionSide, Option<bool>) {
+    if BROKER.hedge_mode() {
+        let position_side = match stage { Stage::Entry => side, Stage::Exit => !side, };
+        (BinanceSide::from(side), BinancePositionSide::from(position_side), None)
+    } else {
+        (BinanceSide::from(side), BinancePositionSide::Both, match stage { Stage::Entry => None, Stage::Exit => Some(true), })
+    }
+}
 
 impl Broker {
     pub async fn create_market(
@@ -16,47 +28,55 @@ impl Broker {
         side: Side,
         stage: Stage,
     ) -> OrderResponseWrapper {
-        let ord_side =  match side {
-            Side::Buy => match stage {
-                Stage::Entry => BinanceSide::Buy,
-                Stage::Exit => BinanceSide::Sell,
-            },
-            Side::Sell => match stage {
-                Stage::Entry => BinanceSide::Sell,
-                Stage::Exit => BinanceSide::Buy,
-            },
-        };
+        let (ord_side, position_side, reduce_only) = order_sides(side, stage);
 
-        let req = MarketOrderRequest {
-            symbol,
-            side: ord_side,
-            position_side: BinancePositionSide::from(side),
-            order_type: OrderType::Market,
-            quantity: size,
-            id,
-            receive_window: 5000,
-            timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
-            order_response_type: OrderResponseType::Result,
-        }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
-        // info!("req: {}", req);
-        let timer = Instant::now();
-        let order_res = self.client
-            .post(format!("{}/fapi/v1/order?{}", self.auth.url, req))
-            .header("Content-Type", "application/json")
-            .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
-            .send()
-            .await
-            .expect("error recv key response")
-            .text()
-            .await
-            .expect("err");
-        // info!("{}\n{}", order_res, timer.elapsed().as_millis());
-        let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance market res");
-        match &wrapper {
-            OrderResponseWrapper::Order(_) => {},
-            OrderResponseWrapper::Error(e) => BROKER.error(e),
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = MarketOrderRequest {
+                symbol: symbol.clone(),
+                side: ord_side,
+                position_side,
+                order_type: OrderType::Market,
+                quantity: size,
+                reduce_only,
+                id,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+                order_response_type: OrderResponseType::Result,
+            };
+            // info!("req: {:?}", req);
+            let timer = LATENCY.sending(Venue::Binance, id.to_string());
+            let order_res = match GATEWAY.request("order.place", &req).await {
+                Some(res) => res,
+                None => {
+                    let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+                    self.client
+                        .post(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                        .header("Content-Type", "application/json")
+                        .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                        .send()
+                        .await
+                        .expect("error recv key response")
+                        .text()
+                        .await
+                        .expect("err")
+                },
+            };
+            LATENCY.since(Venue::Binance, LatencyStage::RestAck, timer);
+            let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance market res");
+            if let OrderResponseWrapper::Error(e) = &wrapper {
+                match BROKER.should_resend(BROKER.error(e), attempt, id, &symbol).await {
+                    Resend::Send => {
+                        attempt += 1;
+                        continue;
+                    },
+                    Resend::Landed(order) => return OrderResponseWrapper::Order(order),
+                    Resend::GiveUp => {},
+                }
+            }
+            return wrapper;
         }
-        wrapper
     }
 
     pub async fn create_limit(
@@ -69,52 +89,173 @@ impl Broker {
         side: Side,
         stage: Stage,
     ) -> OrderResponseWrapper {
+        let (ord_side, position_side, reduce_only) = order_sides(side, stage);
+
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = LimitOrderRequest {
+                symbol: symbol.clone(),
+                side: ord_side,
+                position_side,
+                price,
+                order_type: OrderType::Limit,
+                quantity: size,
+                time_in_force: BinanceTimeInForce::GoodTillCrossing,
+                reduce_only,
+                id,
+                order_response_type: OrderResponseType::Result,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            };
+
+            // info!("ord req: {:?}", req);
+            let timer = LATENCY.sending(Venue::Binance, id.to_string());
+            let order_res = match GATEWAY.request("order.place", &req).await {
+                Some(res) => res,
+                None => {
+                    let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+                    self.client
+                        .post(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                        .header("Content-Type", "application/json")
+                        .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                        .send()
+                        .await
+                        .expect("error recv key response")
+                        .text()
+                        .await
+                        .expect("err")
+                },
+            };
+            LATENCY.since(Venue::Binance, LatencyStage::RestAck, timer);
+            // info!("order res: {}", order_res);
+            let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance market res");
+            if let OrderResponseWrapper::Error(e) = &wrapper {
+                match BROKER.should_resend(BROKER.error(e), attempt, id, &symbol).await {
+                    Resend::Send => {
+                        attempt += 1;
+                        continue;
+                    },
+                    Resend::Landed(order) => return OrderResponseWrapper::Order(order),
+                    Resend::GiveUp => {},
+                }
+            }
+            return wrapper;
+        }
+    }
+
+    /// Places a STOP_MARKET or TAKE_PROFIT_MARKET, triggered off the mark price so a wick on the last price doesn't set it off
+    pub async fn create_conditional(
+        &self,
+        id: Uuid,
+        symbol: String,
+        order_type: OrderType,
+        trigger: f64,
+        size: f64,
+        side: Side,
+        stage: Stage,
+    ) -> OrderResponseWrapper {
+        let (ord_side, position_side, reduce_only) = order_sides(side, stage);
 
-        let ord_side =  match side {
-            Side::Buy => match stage {
-                Stage::Entry => BinancePositionSide::Long,
-                Stage::Exit => BinancePositionSide::Short,
-            },
-            Side::Sell => match stage {
-                Stage::Entry => BinancePositionSide::Short,
-                Stage::Exit => BinancePositionSide::Long,
-            },
-        };
-        let hedge_side = BinanceSide::from(side);
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = ConditionalOrderRequest {
+                symbol: symbol.clone(),
+                side: ord_side,
+                position_side,
+                order_type,
+                quantity: size,
+                stop_price: trigger,
+                working_type: WorkingType::MarkPrice,
+                price_protect: true,
+                reduce_only,
+                id,
+                order_response_type: OrderResponseType::Result,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            };
+            let order_res = match GATEWAY.request("order.place", &req).await {
+                Some(res) => res,
+                None => {
+                    let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+                    self.client
+                        .post(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                        .header("Content-Type", "application/json")
+                        .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                        .send()
+                        .await
+                        .expect("error recv key response")
+                        .text()
+                        .await
+                        .expect("err")
+                },
+            };
+            // info!("conditional res: {}", order_res);
+            let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance conditional res");
+            if let OrderResponseWrapper::Error(e) = &wrapper {
+                match BROKER.should_resend(BROKER.error(e), attempt, id, &symbol).await {
+                    Resend::Send => {
+                        attempt += 1;
+                        continue;
+                    },
+                    Resend::Landed(order) => return OrderResponseWrapper::Order(order),
+                    Resend::GiveUp => {},
+                }
+            }
+            return wrapper;
+        }
+    }
 
-        let req = LimitOrderRequest {
-            symbol,
-            side: hedge_side, // The direction of the positionw we're modifying
-            position_side: ord_side, // The direction of the actual order being placed, binance is a mess
-            price,
-            order_type: OrderType::Limit,
-            quantity: size,
-            time_in_force: BinanceTimeInForce::GoodTillCrossing,
-            id,
-            order_response_type: OrderResponseType::Result,
-            receive_window: 5000,
-            timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
-        }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+    /// Moves a resting limit to a new price and size, the order keeps its id
+    pub async fn amend_order(
+        &self,
+        id: Uuid,
+        symbol: String,
+        price: f64,
+        size: f64,
+        side: Side,
+        stage: Stage,
+    ) -> OrderResponseWrapper {
+        let (ord_side, _, _) = order_sides(side, stage);
 
-        // info!("ord req: {}", req);
-        let timer = Instant::now();
-        let order_res = self.client
-            .post(format!("{}/fapi/v1/order?{}", self.auth.url, req))
-            .header("Content-Type", "application/json")
-            .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
-            .send()
-            .await
-            .expect("error recv key response")
-            .text()
-            .await
-            .expect("err");
-        // info!("order ping: {}", timer.elapsed().as_millis());
-        // info!("order res: {}", order_res);
-        let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance market res");
-        match &wrapper {
-            OrderResponseWrapper::Order(_) => {},
-            OrderResponseWrapper::Error(e) => BROKER.error(e),
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = ModifyOrderRequest {
+                symbol: symbol.clone(),
+                side: ord_side,
+                id,
+                price,
+                quantity: size,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            };
+            let order_res = match GATEWAY.request("order.modify", &req).await {
+                Some(res) => res,
+                None => {
+                    let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+                    self.client
+                        .put(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                        .header("Content-Type", "application/json")
+                        .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                        .send()
+                        .await
+                        .expect("error recv key response")
+                        .text()
+                        .await
+                        .expect("err")
+                },
+            };
+            // info!("amend res: {}", order_res);
+            let wrapper = serde_json::from_str::<OrderResponseWrapper>(&order_res).expect("serde err binance amend res");
+            if let OrderResponseWrapper::Error(e) = &wrapper {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return wrapper;
         }
-        wrapper
     }
-}
\ No newline at end of file
+}
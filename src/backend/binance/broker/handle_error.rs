@@ -1,208 +1,329 @@
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::backend::binance::errors::ErrorPolicy;
 use crate::backend::binance::errors::FilterOtherErrors;
 use crate::backend::binance::errors::ProcessingErrors;
 use crate::backend::binance::errors::RequestErrors;
 use crate::backend::binance::errors::ServerNetworkErrors;
-use crate::backend::binance::types::BinanceError;
+use crate::backend::binance::types::{BinanceError, OrderResponse, OrderResponseWrapper};
 use crate::backend::binance::errors::ErrorCode::*;
 use crate::backend::binance::errors::ServerNetworkErrors::*;
 use crate::backend::binance::errors::RequestErrors::*;
 use crate::backend::binance::errors::ProcessingErrors::*;
 use crate::backend::binance::errors::FilterOtherErrors::*;
+use crate::telemetry::{METRICS, Venue};
 
-use super::BROKER;
 use super::Broker;
 
+/// How many times a request gets resent for Retry and RetryAfterResync policies
+pub const MAX_RETRIES: usize = 2;
+/// Seconds a halt holds before orders are let through again, if the cause hasn't cleared the next error halts again
+pub const HALT_PERIOD: u64 = 300;
+
+impl BinanceError {
+    /// Looks the error up in the policy table
+    pub fn policy(&self) -> ErrorPolicy {
+        match self.code {
+            ServerNetworkErrors(sne) => server_network_policy(sne, &self.msg),
+            RequestErrors(re) => request_policy(re),
+            ProcessingErrors(pe) => processing_policy(pe),
+            FilterOtherErrors(foe) => filter_other_policy(foe),
+        }
+    }
+}
+
 impl Broker {
-    pub fn error(&self, error: &BinanceError) {
-        info!("{:?}", error);
-        match error.code {
-            ServerNetworkErrors(sne) => self.server_network_error(sne, &error.msg),
-            RequestErrors(re) => self.request_error(re, &error.msg),
-            ProcessingErrors(pe) => self.processing_error(pe, &error.msg),
-            FilterOtherErrors(foe) => self.filter_other_error(foe, &error.msg),
-        };
+    /// Logs the error, applies any broker wide consequences and hands the policy back to the caller
+    pub fn error(&self, error: &BinanceError) -> ErrorPolicy {
+        let policy = error.policy();
+        info!("{:?} -> {:?}", error, policy);
+        METRICS.error(Venue::Binance, error.code.code().to_string());
+        match policy {
+            ErrorPolicy::Backoff(duration) => {
+                let until = Instant::now() + duration;
+                let mut backoff = self.backoff_until.write().unwrap();
+                if *backoff < until { *backoff = until; }
+            },
+            ErrorPolicy::HaltAll => {
+                info!("[HALT] Broker halted by {:?} for {}s: {}", error.code, HALT_PERIOD, error.msg);
+                *self.halted_at.write().unwrap() = Instant::now();
+                self.halted.store(true, Ordering::SeqCst);
+            },
+            _ => {},
+        }
+        policy
     }
 
-    fn server_network_error(&self, sne: ServerNetworkErrors, msg: &str) {
-        match sne {
-            Unknown => todo!(),
-            Disconnected => todo!(),
-            Unauthorized => todo!(),
-            TooManyRequests => todo!(),
-            DuplicateIp => todo!(),
-            NoSuchIp => todo!(),
-            UnexpectedResponse => todo!(),
-            Timeout => todo!(),
-            ErrorMessageReceived => todo!(),
-            NonWhiteList => todo!(),
-            InvalidMessage => todo!(),
-            UnknownOrderComposition => todo!(),
-            TooManyOrders => todo!(),
-            ServiceShuttingDown => todo!(),
-            UnsupportedOperation => todo!(),
-            InvalidTimestamp => {
-                match msg.contains("1000ms ahead") {
-                    true => BROKER.set_server_offset(-1000).unwrap(),
-                    false => BROKER.set_server_offset(1000).unwrap(),
-                }
+    /// Applies the broker side of a policy, returns true if the request should be sent again.
+    /// Resync isn't resent here, placing an order again could double it up, see should_resend.
+    pub async fn should_retry(&self, policy: ErrorPolicy, attempt: usize) -> bool {
+        if attempt >= MAX_RETRIES { return false; }
+        match policy {
+            ErrorPolicy::Retry => true,
+            ErrorPolicy::RetryAfterResync => {
+                self.resync_server_time().await;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// should_retry for requests that land the same however many times they're sent, reads, cancels, amends and margin settings.
+    /// An unknown outcome is just sent again.
+    pub async fn should_retry_idempotent(&self, policy: ErrorPolicy, attempt: usize) -> bool {
+        match policy {
+            ErrorPolicy::Resync => self.should_retry(ErrorPolicy::Retry, attempt).await,
+            _ => self.should_retry(policy, attempt).await,
+        }
+    }
+
+    /// should_retry for order placements. An order whose outcome is unknown is looked up by its client id first,
+    /// it's only sent again if the exchange has no such order.
+    pub async fn should_resend(&self, policy: ErrorPolicy, attempt: usize, id: Uuid, symbol: &str) -> Resend {
+        if policy != ErrorPolicy::Resync {
+            return match self.should_retry(policy, attempt).await { true => Resend::Send, false => Resend::GiveUp };
+        }
+        if attempt >= MAX_RETRIES { return Resend::GiveUp; }
+        match self.query_order(id, symbol.to_string()).await {
+            OrderResponseWrapper::Order(order) => {
+                info!("[RESYNC] {} landed despite the error, taking it as placed", id);
+                Resend::Landed(order)
+            },
+            OrderResponseWrapper::Error(e) if matches!(e.code, ProcessingErrors(NoSuchOrder)) => {
+                info!("[RESYNC] {} never landed, sending it again", id);
+                Resend::Send
             },
-            InvalidSignature => todo!(),
-            StartTimeGreaterThanEndTime => todo!(),
-        };
+            OrderResponseWrapper::Error(e) => {
+                info!("[RESYNC] Couldn't look {} up, giving up on it: {:?}", id, e);
+                Resend::GiveUp
+            },
+        }
+    }
+
+    /// Holds the caller until any rate limit backoff has passed
+    pub async fn await_backoff(&self) {
+        let until = *self.backoff_until.read().unwrap();
+        let now = Instant::now();
+        if until > now {
+            tokio::time::sleep(until - now).await;
+        }
+    }
+
+    /// True while a HaltAll holds, lifts it once HALT_PERIOD has passed
+    pub fn is_halted(&self) -> bool {
+        if !self.halted.load(Ordering::SeqCst) { return false; }
+        if self.halted_at.read().unwrap().elapsed().as_secs() < HALT_PERIOD { return true; }
+        if self.halted.swap(false, Ordering::SeqCst) {
+            info!("[HALT] Broker halt lifted after {}s", HALT_PERIOD);
+        }
+        false
+    }
+
+    /// Lifts the broker's halt and every portfolio's now, from the console
+    pub fn resume(&self) {
+        self.halted.store(false, Ordering::SeqCst);
+        self.resumes.fetch_add(1, Ordering::SeqCst);
+        info!("[HALT] Resumed, orders are allowed again");
+    }
+
+    pub fn resumes(&self) -> u64 {
+        self.resumes.load(Ordering::SeqCst)
+    }
+}
+
+/// What should_resend decided for an order placement that came back as an error
+#[derive(Debug)]
+pub enum Resend {
+    /// Send the order again with the same client id
+    Send,
+    /// The order made it to the exchange, this is it
+    Landed(OrderResponse),
+    /// Hand the error back to the caller
+    GiveUp,
+}
+
+fn server_network_policy(sne: ServerNetworkErrors, msg: &str) -> ErrorPolicy {
+    match sne {
+        Unknown => ErrorPolicy::Resync,
+        Disconnected => ErrorPolicy::Retry,
+        Unauthorized => ErrorPolicy::HaltAll,
+        TooManyRequests => match msg.contains("banned") {
+            true => ErrorPolicy::Backoff(Duration::from_secs(120)),
+            false => ErrorPolicy::Backoff(Duration::from_secs(5)),
+        },
+        DuplicateIp => ErrorPolicy::Ignore,
+        NoSuchIp => ErrorPolicy::HaltAll,
+        UnexpectedResponse => ErrorPolicy::Resync,
+        Timeout => ErrorPolicy::Resync,
+        ErrorMessageReceived => ErrorPolicy::Retry,
+        NonWhiteList => ErrorPolicy::HaltAll,
+        InvalidMessage => ErrorPolicy::RejectOrder,
+        UnknownOrderComposition => ErrorPolicy::RejectOrder,
+        TooManyOrders => ErrorPolicy::Backoff(Duration::from_secs(10)),
+        ServiceShuttingDown => ErrorPolicy::HaltAll,
+        UnsupportedOperation => ErrorPolicy::RejectOrder,
+        InvalidTimestamp => ErrorPolicy::RetryAfterResync,
+        InvalidSignature => ErrorPolicy::HaltAll,
+        StartTimeGreaterThanEndTime => ErrorPolicy::RejectOrder,
     }
+}
 
-    fn request_error(&self, re: RequestErrors, msg: &str) {
-        match re {
-            IllegalChars => todo!(),
-            TooManyParameters => todo!(),
-            MandatoryParamEmptyOrMalformed => todo!(),
-            UnknownParam => todo!(),
-            UnreadParameters => todo!(),
-            ParamEmpty => todo!(),
-            ParamNotRequired => todo!(),
-            BadAsset => todo!(),
-            BadAccount => todo!(),
-            BadInstrumentType => todo!(),
-            BadPrecision => todo!(),
-            NoDepth => todo!(),
-            WithdrawNotNegative => todo!(),
-            TIFNotRequired => todo!(),
-            InvalidTIF => todo!(),
-            InvalidOrderType => todo!(),
-            InvalidSide => todo!(),
-            EmptyNewClientOrderId => todo!(),
-            EmptyOriginalClientOrderId => todo!(),
-            BadInterval => todo!(),
-            BadSymbol => todo!(),
-            InvalidListenKey => todo!(),
-            LookupIntervalTooBig => todo!(),
-            OptionalParamsBadCombo => todo!(),
-            InvalidParameter => todo!(),
-            InvalidNewOrderResponseType => todo!(),
-        };
+fn request_policy(re: RequestErrors) -> ErrorPolicy {
+    match re {
+        IllegalChars => ErrorPolicy::RejectOrder,
+        TooManyParameters => ErrorPolicy::RejectOrder,
+        MandatoryParamEmptyOrMalformed => ErrorPolicy::RejectOrder,
+        UnknownParam => ErrorPolicy::RejectOrder,
+        UnreadParameters => ErrorPolicy::RejectOrder,
+        ParamEmpty => ErrorPolicy::RejectOrder,
+        ParamNotRequired => ErrorPolicy::RejectOrder,
+        BadAsset => ErrorPolicy::HaltSymbol,
+        BadAccount => ErrorPolicy::HaltAll,
+        BadInstrumentType => ErrorPolicy::HaltSymbol,
+        BadPrecision => ErrorPolicy::RejectOrder,
+        NoDepth => ErrorPolicy::RejectOrder,
+        WithdrawNotNegative => ErrorPolicy::RejectOrder,
+        TIFNotRequired => ErrorPolicy::RejectOrder,
+        InvalidTIF => ErrorPolicy::RejectOrder,
+        InvalidOrderType => ErrorPolicy::RejectOrder,
+        InvalidSide => ErrorPolicy::RejectOrder,
+        EmptyNewClientOrderId => ErrorPolicy::RejectOrder,
+        EmptyOriginalClientOrderId => ErrorPolicy::RejectOrder,
+        BadInterval => ErrorPolicy::RejectOrder,
+        BadSymbol => ErrorPolicy::HaltSymbol,
+        // The user data stream renews its own key
+        InvalidListenKey => ErrorPolicy::Ignore,
+        LookupIntervalTooBig => ErrorPolicy::RejectOrder,
+        OptionalParamsBadCombo => ErrorPolicy::RejectOrder,
+        InvalidParameter => ErrorPolicy::RejectOrder,
+        InvalidNewOrderResponseType => ErrorPolicy::RejectOrder,
     }
+}
 
-    fn processing_error(&self, pe: ProcessingErrors, msg: &str) {
-        match pe {
-            NewOrderRejected => todo!(),
-            CancelRejected => { /*debug!("Cancel Rejected, hopefully this was because of a fill")*/},
-            NoSuchOrder => todo!(),
-            BadApiKeyFormat => todo!(),
-            RejectedMBXKey => todo!(),
-            NoTradingWindow => todo!(),
-            BalanceNotSufficient => todo!(),
-            MarginNotSufficient => todo!(),
-            UnableToFill => todo!(),
-            OrderWouldImmediatelyTrigger => todo!(),
-            ReduceOnlyRejected => {},
-            UserInLiquidation => todo!(),
-            PositionNotSufficient => todo!(),
-            MaxOpenOrderExceeded => todo!(),
-            ReduceOnlyOrderTypeNotSupported => todo!(),
-            MaxLeverageRatio => todo!(),
-            MinLeverageRatio => todo!(),
-        };
+fn processing_policy(pe: ProcessingErrors) -> ErrorPolicy {
+    match pe {
+        NewOrderRejected => ErrorPolicy::RejectOrder,
+        // Hopefully this was because of a fill, reconciliation will tell if not
+        CancelRejected => ErrorPolicy::Ignore,
+        NoSuchOrder => ErrorPolicy::Ignore,
+        BadApiKeyFormat => ErrorPolicy::HaltAll,
+        RejectedMBXKey => ErrorPolicy::HaltAll,
+        NoTradingWindow => ErrorPolicy::HaltSymbol,
+        BalanceNotSufficient => ErrorPolicy::RejectOrder,
+        MarginNotSufficient => ErrorPolicy::RejectOrder,
+        UnableToFill => ErrorPolicy::RejectOrder,
+        OrderWouldImmediatelyTrigger => ErrorPolicy::RejectOrder,
+        ReduceOnlyRejected => ErrorPolicy::RejectOrder,
+        UserInLiquidation => ErrorPolicy::HaltAll,
+        PositionNotSufficient => ErrorPolicy::RejectOrder,
+        MaxOpenOrderExceeded => ErrorPolicy::RejectOrder,
+        ReduceOnlyOrderTypeNotSupported => ErrorPolicy::RejectOrder,
+        MaxLeverageRatio => ErrorPolicy::RejectOrder,
+        MinLeverageRatio => ErrorPolicy::RejectOrder,
     }
+}
 
-    fn filter_other_error(&self, foe: FilterOtherErrors, msg: &str) {
-        match foe {
-            InvalidOrderStatus => todo!(),
-            PriceLessThanZero => todo!(),
-            PriceGreaterThanMax => todo!(),
-            QuantityLessThanZero => todo!(),
-            QuantityLessThanMin => todo!(),
-            QuantityGreaterThanMax => todo!(),
-            StopPriceLessThanZero => todo!(),
-            StopPriceGreaterThanMax => todo!(),
-            TickSizeLessThanZero => todo!(),
-            MaxPriceLessThanMinPrice => todo!(),
-            MaxQuantityLessThanMinQuantity => todo!(),
-            StepSizeLessThanZero => todo!(),
-            MaxNumberOfOrdersLessThanZero => todo!(),
-            PriceLessThanMinPrice => todo!(),
-            PriceNotIncreasedByTickSize => todo!(),
-            InvalidClientOrderIdLength => todo!(),
-            PriceHigherThanMarkMultiplierCap => todo!(),
-            MultiplierUpLessThanZero => todo!(),
-            MultiplierDownLessThanZero => todo!(),
-            CompositeScaleOverflow => todo!(),
-            TargetStrategyInvalid => todo!(),
-            InvalidDepthLimit => todo!(),
-            WrongMarketStatus => todo!(),
-            QuantityNotIncreasedByStepSize => todo!(),
-            PriceLowerThanMarkMultiplierFloor => todo!(),
-            MultiplierDecimalLessThanZero => todo!(),
-            CommissionInvalid => todo!(),
-            InvalidAccountType => todo!(),
-            InvalidLeverage => todo!(),
-            InvalidTickSizePrecision => todo!(),
-            InvalidStepSizePrecision => todo!(),
-            InvalidWorkingType => todo!(),
-            ExceedMaxCancelOrderSize => todo!(),
-            InsuranceAccountNotFound => todo!(),
-            InvalidBalanceType => todo!(),
-            MaxStopOrderExceeded => todo!(),
-            NoNeedToChangeMarginType => todo!(),
-            ThereExistsOpenOrders => todo!(),
-            ThereExistsQuantity => todo!(),
-            AddIsolatedMarginReject => todo!(),
-            CrossBalanceInsufficient => todo!(),
-            IsolatedBalanceInsufficient => todo!(),
-            NoNeedToChangeAutoAddMargin => todo!(),
-            AutoAddCrossedMarginReject => todo!(),
-            AddIsolatedMarginNoPositionReject => todo!(),
-            AmountMustBePosition => todo!(),
-            InvalidApiKeyType => todo!(),
-            InvalidRsaPublicKey => todo!(),
-            MaxPriceTooLarge => todo!(),
-            NoNeedToChangePositionSide => todo!(),
-            InvalidPositionSide => todo!(),
-            PositionSideNotMatch => todo!(),
-            ReduceOnlyConflict => todo!(),
-            InvalidOptionsRequestType => todo!(),
-            InvalidOptionsTimeFrame => todo!(),
-            InvalidOptionsAmount => todo!(),
-            InvalidOptionsEventType => todo!(),
-            PositionSideChangeExistsOpenOrders => todo!(),
-            PositionSideChangeExistsQuantity => todo!(),
-            InvalidOptionsPremiumFee => todo!(),
-            InvalidClientOptionsIdLength => todo!(),
-            InvalidOptionsDirection => todo!(),
-            OptionsPremiumNotUpdated => todo!(),
-            OptionsPremiumInputLessThanZero => todo!(),
-            OptionsAmountBiggerThanUpper => todo!(),
-            OptionsPremiumOutputZero => todo!(),
-            OptionsPremiumTooDiff => todo!(),
-            OptionsPremiumReachLimit => todo!(),
-            OptionsCommonError => todo!(),
-            InvalidOptionsId => todo!(),
-            OptionsUserNotFound => todo!(),
-            OptionsNotFound => todo!(),
-            InvalidBatchPlaceOrderSize => todo!(),
-            PlaceBatchOrdersFail => todo!(),
-            UpcomingMethod => todo!(),
-            InvalidNotionalLimitCoef => todo!(),
-            InvalidPriceSpreadThreshold => todo!(),
-            ReduceOnlyOrderPermission => todo!(),
-            NoPlaceOrderPermission => todo!(),
-            InvalidContractType => todo!(),
-            InvalidClientTranIdLength => todo!(),
-            DuplicatedClientTranId => todo!(),
-            ReduceOnlyMarginCheckFailed => todo!(),
-            MarketOrderReject => todo!(),
-            InvalidActivationPrice => todo!(),
-            QuantityExistsWithClosePosition => todo!(),
-            ReduceOnlyMustBeTrue => todo!(),
-            OrderTypeCannotBeMarket => todo!(),
-            InvalidOpeningPositionStatus => todo!(),
-            SymbolAlreadyClosed => todo!(),
-            StrategyInvalidTriggerPrice => todo!(),
-            InvalidPair => todo!(),
-            IsolatedLeverageRejectWithPosition => todo!(),
-            MinNotional => todo!(),
-            InvalidTimeInterval => todo!(),
-            PriceHigherThanStopMultiplierUp => todo!(),
-            PriceLowerThanStopMultiplierDown => todo!(),
-        };
+fn filter_other_policy(foe: FilterOtherErrors) -> ErrorPolicy {
+    match foe {
+        InvalidOrderStatus => ErrorPolicy::RejectOrder,
+        PriceLessThanZero => ErrorPolicy::RejectOrder,
+        PriceGreaterThanMax => ErrorPolicy::RejectOrder,
+        QuantityLessThanZero => ErrorPolicy::RejectOrder,
+        QuantityLessThanMin => ErrorPolicy::RejectOrder,
+        QuantityGreaterThanMax => ErrorPolicy::RejectOrder,
+        StopPriceLessThanZero => ErrorPolicy::RejectOrder,
+        StopPriceGreaterThanMax => ErrorPolicy::RejectOrder,
+        TickSizeLessThanZero => ErrorPolicy::RejectOrder,
+        MaxPriceLessThanMinPrice => ErrorPolicy::RejectOrder,
+        MaxQuantityLessThanMinQuantity => ErrorPolicy::RejectOrder,
+        StepSizeLessThanZero => ErrorPolicy::RejectOrder,
+        MaxNumberOfOrdersLessThanZero => ErrorPolicy::RejectOrder,
+        PriceLessThanMinPrice => ErrorPolicy::RejectOrder,
+        PriceNotIncreasedByTickSize => ErrorPolicy::RejectOrder,
+        InvalidClientOrderIdLength => ErrorPolicy::RejectOrder,
+        PriceHigherThanMarkMultiplierCap => ErrorPolicy::RejectOrder,
+        MultiplierUpLessThanZero => ErrorPolicy::RejectOrder,
+        MultiplierDownLessThanZero => ErrorPolicy::RejectOrder,
+        CompositeScaleOverflow => ErrorPolicy::RejectOrder,
+        TargetStrategyInvalid => ErrorPolicy::RejectOrder,
+        InvalidDepthLimit => ErrorPolicy::RejectOrder,
+        WrongMarketStatus => ErrorPolicy::HaltSymbol,
+        QuantityNotIncreasedByStepSize => ErrorPolicy::RejectOrder,
+        PriceLowerThanMarkMultiplierFloor => ErrorPolicy::RejectOrder,
+        MultiplierDecimalLessThanZero => ErrorPolicy::RejectOrder,
+        CommissionInvalid => ErrorPolicy::RejectOrder,
+        InvalidAccountType => ErrorPolicy::HaltAll,
+        InvalidLeverage => ErrorPolicy::RejectOrder,
+        InvalidTickSizePrecision => ErrorPolicy::RejectOrder,
+        InvalidStepSizePrecision => ErrorPolicy::RejectOrder,
+        InvalidWorkingType => ErrorPolicy::RejectOrder,
+        ExceedMaxCancelOrderSize => ErrorPolicy::RejectOrder,
+        InsuranceAccountNotFound => ErrorPolicy::HaltAll,
+        InvalidBalanceType => ErrorPolicy::RejectOrder,
+        MaxStopOrderExceeded => ErrorPolicy::RejectOrder,
+        NoNeedToChangeMarginType => ErrorPolicy::Ignore,
+        ThereExistsOpenOrders => ErrorPolicy::RejectOrder,
+        ThereExistsQuantity => ErrorPolicy::RejectOrder,
+        AddIsolatedMarginReject => ErrorPolicy::RejectOrder,
+        CrossBalanceInsufficient => ErrorPolicy::RejectOrder,
+        IsolatedBalanceInsufficient => ErrorPolicy::RejectOrder,
+        NoNeedToChangeAutoAddMargin => ErrorPolicy::Ignore,
+        AutoAddCrossedMarginReject => ErrorPolicy::RejectOrder,
+        AddIsolatedMarginNoPositionReject => ErrorPolicy::RejectOrder,
+        AmountMustBePosition => ErrorPolicy::RejectOrder,
+        InvalidApiKeyType => ErrorPolicy::HaltAll,
+        InvalidRsaPublicKey => ErrorPolicy::HaltAll,
+        MaxPriceTooLarge => ErrorPolicy::RejectOrder,
+        NoNeedToChangePositionSide => ErrorPolicy::Ignore,
+        InvalidPositionSide => ErrorPolicy::RejectOrder,
+        PositionSideNotMatch => ErrorPolicy::RejectOrder,
+        ReduceOnlyConflict => ErrorPolicy::RejectOrder,
+        InvalidOptionsRequestType => ErrorPolicy::RejectOrder,
+        InvalidOptionsTimeFrame => ErrorPolicy::RejectOrder,
+        InvalidOptionsAmount => ErrorPolicy::RejectOrder,
+        InvalidOptionsEventType => ErrorPolicy::RejectOrder,
+        PositionSideChangeExistsOpenOrders => ErrorPolicy::RejectOrder,
+        PositionSideChangeExistsQuantity => ErrorPolicy::RejectOrder,
+        InvalidOptionsPremiumFee => ErrorPolicy::RejectOrder,
+        InvalidClientOptionsIdLength => ErrorPolicy::RejectOrder,
+        InvalidOptionsDirection => ErrorPolicy::RejectOrder,
+        OptionsPremiumNotUpdated => ErrorPolicy::RejectOrder,
+        OptionsPremiumInputLessThanZero => ErrorPolicy::RejectOrder,
+        OptionsAmountBiggerThanUpper => ErrorPolicy::RejectOrder,
+        OptionsPremiumOutputZero => ErrorPolicy::RejectOrder,
+        OptionsPremiumTooDiff => ErrorPolicy::RejectOrder,
+        OptionsPremiumReachLimit => ErrorPolicy::RejectOrder,
+        OptionsCommonError => ErrorPolicy::RejectOrder,
+        InvalidOptionsId => ErrorPolicy::RejectOrder,
+        OptionsUserNotFound => ErrorPolicy::RejectOrder,
+        OptionsNotFound => ErrorPolicy::RejectOrder,
+        InvalidBatchPlaceOrderSize => ErrorPolicy::RejectOrder,
+        PlaceBatchOrdersFail => ErrorPolicy::RejectOrder,
+        UpcomingMethod => ErrorPolicy::RejectOrder,
+        InvalidNotionalLimitCoef => ErrorPolicy::RejectOrder,
+        InvalidPriceSpreadThreshold => ErrorPolicy::RejectOrder,
+        ReduceOnlyOrderPermission => ErrorPolicy::HaltSymbol,
+        NoPlaceOrderPermission => ErrorPolicy::HaltSymbol,
+        InvalidContractType => ErrorPolicy::RejectOrder,
+        InvalidClientTranIdLength => ErrorPolicy::RejectOrder,
+        DuplicatedClientTranId => ErrorPolicy::RejectOrder,
+        ReduceOnlyMarginCheckFailed => ErrorPolicy::RejectOrder,
+        MarketOrderReject => ErrorPolicy::RejectOrder,
+        InvalidActivationPrice => ErrorPolicy::RejectOrder,
+        QuantityExistsWithClosePosition => ErrorPolicy::RejectOrder,
+        ReduceOnlyMustBeTrue => ErrorPolicy::RejectOrder,
+        OrderTypeCannotBeMarket => ErrorPolicy::RejectOrder,
+        InvalidOpeningPositionStatus => ErrorPolicy::RejectOrder,
+        SymbolAlreadyClosed => ErrorPolicy::HaltSymbol,
+        StrategyInvalidTriggerPrice => ErrorPolicy::RejectOrder,
+        InvalidPair => ErrorPolicy::HaltSymbol,
+        IsolatedLeverageRejectWithPosition => ErrorPolicy::RejectOrder,
+        MinNotional => ErrorPolicy::RejectOrder,
+        InvalidTimeInterval => ErrorPolicy::RejectOrder,
+        PriceHigherThanStopMultiplierUp => ErrorPolicy::RejectOrder,
+        PriceLowerThanStopMultiplierDown => ErrorPolicy::RejectOrder,
     }
-}
\ No newline at end of file
+}
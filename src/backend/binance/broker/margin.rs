@@ -0,0 +1,103 @@
+use crate::{config::CONFIG, backend::binance::{types::{MarginType, LeverageRequest, LeverageWrapper, MarginTypeRequest, MarginTypeWrapper, LeverageBracketRequest, LeverageBracketWrapper}, broker::BROKER}};
+
+use super::Broker;
+
+/// Leverage and margin type are per symbol. The current values are read off position risk.
+impl Broker {
+    pub async fn set_leverage(&self, symbol: String, leverage: u32) -> LeverageWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = LeverageRequest {
+                symbol: symbol.clone(),
+                leverage,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let leverage_res = self.client
+                .post(format!("{}/fapi/v1/leverage?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            let leverage = serde_json::from_str::<LeverageWrapper>(&leverage_res).expect("err deser leverage");
+            if let LeverageWrapper::Error(e) = &leverage {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return leverage;
+        }
+    }
+
+    /// Comes back with NoNeedToChangeMarginType if it's already set, which is fine
+    pub async fn set_margin_type(&self, symbol: String, margin_type: MarginType) -> MarginTypeWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = MarginTypeRequest {
+                symbol: symbol.clone(),
+                margin_type,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let margin_res = self.client
+                .post(format!("{}/fapi/v1/marginType?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            let margin = serde_json::from_str::<MarginTypeWrapper>(&margin_res).expect("err deser margin type");
+            if let MarginTypeWrapper::Error(e) = &margin {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return margin;
+        }
+    }
+
+    /// Notional tiers for the symbol, each with its own max leverage and maintenance margin
+    pub async fn leverage_brackets(&self, symbol: String) -> LeverageBracketWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = LeverageBracketRequest {
+                symbol: symbol.clone(),
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            }.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let bracket_res = self.client
+                .get(format!("{}/fapi/v1/leverageBracket?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            let brackets = serde_json::from_str::<LeverageBracketWrapper>(&bracket_res).expect("err deser leverage brackets");
+            if let LeverageBracketWrapper::Error(e) = &brackets {
+                if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return brackets;
+        }
+    }
+}
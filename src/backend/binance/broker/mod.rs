@@ -1,10 +1,12 @@
 mod create_order;
 mod cancel_order;
+mod query_order;
 mod handle_error;
 mod account_info;
 mod info;
+mod margin;
 
-use std::{sync::RwLock, time::{SystemTime, UNIX_EPOCH}};
+use std::{sync::{RwLock, atomic::{AtomicBool, AtomicU64}}, time::{SystemTime, UNIX_EPOCH, Instant}};
 use reqwest::{Client, Error};
 use std::sync::PoisonError;
 use std::time::SystemTimeError;
@@ -26,13 +28,24 @@ pub enum CalculateServerTimeError {
     RwLockPoisonedError
 }
 
+pub use self::account_info::*;
 pub use self::create_order::*;
+pub use self::handle_error::*;
 
 use super::types::BinanceAuth;
 
 #[derive(Debug)]
 pub struct Broker {
     server_timestamp_offset: RwLock<i64>,
+    /// Requests are held until this passes after a rate limit
+    backoff_until: RwLock<Instant>,
+    /// Set by errors with a HaltAll policy, lifts HALT_PERIOD after halted_at or on a resume
+    halted: AtomicBool,
+    halted_at: RwLock<Instant>,
+    /// Counts resumes from the console, portfolios lift their own halts when it moves
+    resumes: AtomicU64,
+    /// Dual side position mode, detected at startup
+    hedge_mode: AtomicBool,
     auth: BinanceAuth,
     client: Client,
 }
@@ -41,6 +54,11 @@ impl Broker {
     pub fn new(url: String, key: String, secret: String) -> Result<Self, Error> {
         Ok(Broker {
             server_timestamp_offset: RwLock::new(-5000),
+            backoff_until: RwLock::new(Instant::now()),
+            halted: AtomicBool::new(false),
+            halted_at: RwLock::new(Instant::now()),
+            resumes: AtomicU64::new(0),
+            hedge_mode: AtomicBool::new(true),
             auth: BinanceAuth { url, key, secret },
             client: reqwest::Client::builder().https_only(true).pool_max_idle_per_host(4).pool_idle_timeout(None).use_rustls_tls().build()?,
         })
@@ -58,6 +76,13 @@ impl Broker {
         Ok(())
     }
 
+    /// Queries the server's time and resets the offset from it
+    pub async fn resync_server_time(&self) {
+        let server_time = self.time().await;
+        let our_time: i64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis().try_into().unwrap();
+        BROKER.set_server_offset(server_time - our_time).unwrap();
+    }
+
     /// Calculates the current time with the server offset accomodated
     pub fn calculate_server_time(&self) -> Result<u64, CalculateServerTimeError> {
         let server_time = match self.server_timestamp_offset.read() {
@@ -0,0 +1,47 @@
+use uuid::Uuid;
+use crate::{config::CONFIG, backend::{binance::{types::{QueryOrderRequest, OrderResponseWrapper}, broker::BROKER}}};
+use super::Broker;
+
+impl Broker {
+    /// Looks an order up by its client id, NoSuchOrder comes back if it never reached the exchange
+    pub async fn query_order(
+        &self,
+        id: Uuid,
+        symbol: String,
+    ) -> OrderResponseWrapper {
+
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = QueryOrderRequest {
+                symbol: symbol.clone(),
+                id,
+                receive_window: 5000,
+                timestamp: self.calculate_server_time().expect("Failed to calculate server time"),
+            };
+            // Always over REST, the lookup usually follows the session failing to answer
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+            let query_res = self.client
+                .get(format!("{}/fapi/v1/order?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", CONFIG.binance_key.clone())
+                .send()
+                .await
+                .expect("error recv key response")
+                .text()
+                .await
+                .expect("err");
+            let mut wrapper = serde_json::from_str::<OrderResponseWrapper>(&query_res).expect("serde err binance query res");
+            match &mut wrapper {
+                OrderResponseWrapper::Order(order) => order.cum_qty = order.executed_qty,
+                OrderResponseWrapper::Error(e) => {
+                    if BROKER.should_retry_idempotent(BROKER.error(e), attempt).await {
+                        attempt += 1;
+                        continue;
+                    }
+                },
+            }
+            return wrapper;
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serde::Deserialize;
 use serde_repr::Deserialize_repr;
 
@@ -359,4 +361,37 @@ pub enum ErrorCode {
     RequestErrors(RequestErrors),
     ProcessingErrors(ProcessingErrors),
     FilterOtherErrors(FilterOtherErrors)
-}
\ No newline at end of file
+}
+
+impl ErrorCode {
+    /// The numeric code Binance sent
+    pub fn code(&self) -> i32 {
+        match self {
+            ErrorCode::ServerNetworkErrors(e) => *e as i32,
+            ErrorCode::RequestErrors(e) => *e as i32,
+            ErrorCode::ProcessingErrors(e) => *e as i32,
+            ErrorCode::FilterOtherErrors(e) => *e as i32,
+        }
+    }
+}
+/// What should be done about an error the exchange handed back.
+/// Produced by the policy table in broker/handle_error.rs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ErrorPolicy {
+    /// Nothing to be done, the request's outcome is fine as is
+    Ignore,
+    /// Transient, the same request can be sent again
+    Retry,
+    /// The request's outcome is unknown, look the order up by client id before sending it again
+    Resync,
+    /// Our clock drifted from the server's, resync the offset and send again
+    RetryAfterResync,
+    /// We're being rate limited, hold all requests for the duration
+    Backoff(Duration),
+    /// The request itself is bad, give up on the order
+    RejectOrder,
+    /// The symbol can't be traded right now
+    HaltSymbol,
+    /// The account can't be traded right now
+    HaltAll,
+}
@@ -0,0 +1,168 @@
+/// Order entry over the futures websocket API.
+/// One authenticated session is kept open and requests are matched back to their callers by id.
+/// Anything that can't go over the session because it's down returns None so the broker sends it over REST instead.
+/// A request the session took but didn't answer in time may still have landed, it comes back as a Timeout error
+/// so the broker's policy decides, orders are looked up by their client id before they're sent again.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use async_tungstenite::tokio::connect_async;
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{SinkExt, StreamExt};
+use hmac::Mac;
+use serde::Serialize;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::HmacSha256;
+use crate::config::CONFIG;
+use crate::telemetry::{METRICS, Venue};
+
+use super::types::WsApiResponse;
+
+lazy_static! {
+    pub static ref GATEWAY: OrderGateway = OrderGateway::new();
+    /// How long a request waits on the session before it's given up as timed out
+    static ref RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+    static ref RECONNECT_DELAY: Duration = Duration::from_secs(1);
+}
+
+pub struct OrderGateway {
+    /// Outgoing frames for the open session, None while it's down
+    session: RwLock<Option<UnboundedSender<Message>>>,
+    /// Callers waiting on a response, by request id
+    pending: Mutex<HashMap<String, oneshot::Sender<String>>>,
+}
+
+impl OrderGateway {
+    fn new() -> OrderGateway {
+        OrderGateway { session: RwLock::new(None), pending: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_up(&self) -> bool {
+        match self.session.read() {
+            Ok(session) => session.is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Signs the request's params with the api key added, sorted the way the websocket API expects them
+    fn sign<T: Serialize>(params: &T) -> Option<serde_json::Map<String, serde_json::Value>> {
+        let mut pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(&serde_urlencoded::to_string(params).ok()?).ok()?;
+        pairs.push(("apiKey".to_string(), CONFIG.binance_key.clone()));
+        pairs.sort();
+        let mut mac = HmacSha256::new_from_slice(CONFIG.binance_secret.as_bytes()).ok()?;
+        mac.update(serde_urlencoded::to_string(&pairs).ok()?.as_bytes());
+        let mut signed = serde_json::Map::new();
+        for (key, value) in pairs {
+            signed.insert(key, serde_json::Value::String(value));
+        }
+        signed.insert("signature".to_string(), serde_json::Value::String(format!("{:x}", mac.finalize().into_bytes())));
+        Some(signed)
+    }
+
+    /// Sends a request over the session and waits on its response.
+    /// Returns the body REST would have, the order or the error, or None if it never went out and has to go over REST.
+    /// Once sent, a request that goes unanswered comes back as a Timeout error rather than None, it may have landed.
+    pub async fn request<T: Serialize>(&self, method: &str, params: &T) -> Option<String> {
+        let session = self.session.read().ok()?.clone()?;
+        let id = Uuid::new_v4().to_string();
+        let frame = serde_json::json!({
+            "id": id,
+            "method": method,
+            "params": OrderGateway::sign(params)?,
+        });
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().ok()?.insert(id.clone(), tx);
+        if session.send(Message::Text(frame.to_string())).is_err() {
+            self.pending.lock().ok()?.remove(&id);
+            return None;
+        }
+        match tokio::time::timeout(*RESPONSE_TIMEOUT, rx).await {
+            Ok(Ok(res)) => Some(res),
+            _ => {
+                info!("[GATEWAY] {} {} went unanswered, its outcome is unknown", method, id);
+                if let Ok(mut pending) = self.pending.lock() { pending.remove(&id); }
+                Some(serde_json::json!({ "code": -1007, "msg": "Websocket API request went unanswered" }).to_string())
+            },
+        }
+    }
+
+    fn respond(&self, txt: &str) {
+        let res = match serde_json::from_str::<WsApiResponse>(txt) {
+            Ok(res) => res,
+            Err(e) => {
+                debug!("[GATEWAY] Unreadable frame {}: {}", txt, e);
+                return;
+            },
+        };
+        let body = match (res.result, res.error) {
+            (Some(result), _) => result.to_string(),
+            (None, Some(error)) => error.to_string(),
+            (None, None) => return,
+        };
+        let waiting = match (res.id, self.pending.lock()) {
+            (Some(id), Ok(mut pending)) => pending.remove(&id),
+            _ => None,
+        };
+        if let Some(waiting) = waiting {
+            waiting.send(body).ok();
+        }
+    }
+
+    /// Marks the session down, dropping the waiting callers sends them over REST
+    fn close(&self) {
+        if let Ok(mut session) = self.session.write() { *session = None; }
+        if let Ok(mut pending) = self.pending.lock() { pending.clear(); }
+    }
+}
+
+/// Keeps the session open for as long as the program runs, reconnecting whenever it drops.
+/// Does nothing when BINANCE_WS_API_URL isn't set.
+pub async fn connect_gateway() {
+    let url = match &CONFIG.binance_ws_api_url {
+        Some(url) => url.clone(),
+        None => {
+            info!("[GATEWAY] No websocket API url set, orders go over REST");
+            return;
+        },
+    };
+    loop {
+        match connect_async(url.clone()).await {
+            Ok((ws_stream, _res)) => {
+                METRICS.connected(Venue::Binance, "gateway");
+                let (mut write, mut read) = ws_stream.split();
+                let (send, mut rec) = unbounded_channel::<Message>();
+                if let Ok(mut session) = GATEWAY.session.write() { *session = Some(send.clone()); }
+                info!("[GATEWAY] Order session open");
+                let writer = tokio::spawn(async move {
+                    while let Some(msg) = rec.recv().await {
+                        if write.send(msg).await.is_err() { break; }
+                    }
+                });
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Text(txt)) => GATEWAY.respond(&txt),
+                        Ok(Message::Ping(payload)) => { send.send(Message::Pong(payload)).ok(); },
+                        Ok(Message::Close(frame)) => {
+                            info!("[GATEWAY] Order session closed by the server: {:?}", frame);
+                            break;
+                        },
+                        Ok(_) => {},
+                        Err(e) => {
+                            info!("[GATEWAY] Order session error: {}", e);
+                            break;
+                        },
+                    }
+                }
+                GATEWAY.close();
+                writer.abort();
+            },
+            Err(e) => info!("[GATEWAY] Failed to open order session: {}", e),
+        }
+        tokio::time::sleep(*RECONNECT_DELAY).await;
+    }
+}
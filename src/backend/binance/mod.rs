@@ -4,6 +4,8 @@ use reqwest::{Client, Error};
 pub mod credentials;
 pub mod stream;
 pub mod broker;
+pub mod gateway;
 pub mod market;
+pub mod spot;
 pub mod types;
 pub mod errors;
\ No newline at end of file
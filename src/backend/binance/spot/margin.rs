@@ -0,0 +1,82 @@
+use crate::backend::binance::types::{MarginLoanRequest, MarginLoanWrapper, MarginAccountRequest, MarginAccountWrapper};
+
+use crate::backend::binance::broker::BROKER;
+
+use super::SpotBroker;
+
+/// Cross margin loans. Orders can borrow and repay on their own through their side effect,
+/// these are for moving the loan independently of trading, like paying it down after a hedge unwinds.
+impl SpotBroker {
+    pub async fn margin_borrow(&self, asset: String, amount: f64) -> MarginLoanWrapper {
+        self.margin_loan("sapi/v1/margin/loan", asset, amount).await
+    }
+
+    pub async fn margin_repay(&self, asset: String, amount: f64) -> MarginLoanWrapper {
+        self.margin_loan("sapi/v1/margin/repay", asset, amount).await
+    }
+
+    async fn margin_loan(&self, path: &str, asset: String, amount: f64) -> MarginLoanWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = MarginLoanRequest {
+                asset: asset.clone(),
+                amount,
+                receive_window: 5000,
+                timestamp: self.timestamp(),
+            };
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let loan_res = self.client
+                .post(format!("{}/{}?{}", self.auth.url, path, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", self.auth.key.clone())
+                .send()
+                .await
+                .expect("error recv margin loan response")
+                .text()
+                .await
+                .expect("err");
+            let loan = serde_json::from_str::<MarginLoanWrapper>(&loan_res).expect("err deser margin loan");
+            if let MarginLoanWrapper::Error(e) = &loan {
+                if self.should_retry(e, attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return loan;
+        }
+    }
+
+    /// Balances, loans and the margin level of the cross margin account
+    pub async fn margin_account(&self) -> MarginAccountWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = MarginAccountRequest {
+                receive_window: 5000,
+                timestamp: self.timestamp(),
+            };
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let account_res = self.client
+                .get(format!("{}/sapi/v1/margin/account?{}", self.auth.url, req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", self.auth.key.clone())
+                .send()
+                .await
+                .expect("error recv margin account response")
+                .text()
+                .await
+                .expect("err");
+            let account = serde_json::from_str::<MarginAccountWrapper>(&account_res).expect("err deser margin account");
+            if let MarginAccountWrapper::Error(e) = &account {
+                if self.should_retry_idempotent(e, attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return account;
+        }
+    }
+}
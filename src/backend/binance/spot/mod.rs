@@ -0,0 +1,61 @@
+/// Spot and cross margin trading on the same account as the futures.
+/// Requests are timed off the futures broker's clock offset, the exchange clock is the same one.
+/// Errors don't go through the futures broker's policies, a rejected spot order has nothing to do with
+/// whether the perps should keep trading.
+
+mod order;
+mod margin;
+pub mod stream;
+
+use reqwest::{Client, Error};
+
+use crate::config::CONFIG;
+
+use super::broker::BROKER;
+use super::types::{BinanceAuth, SpotError};
+
+#[derive(Debug)]
+pub struct SpotBroker {
+    auth: BinanceAuth,
+    client: Client,
+}
+
+impl SpotBroker {
+    pub fn new(url: String, key: String, secret: String) -> Result<Self, Error> {
+        Ok(SpotBroker {
+            auth: BinanceAuth { url, key, secret },
+            client: reqwest::Client::builder().https_only(true).pool_max_idle_per_host(4).pool_idle_timeout(None).use_rustls_tls().build()?,
+        })
+    }
+
+    fn timestamp(&self) -> u64 {
+        BROKER.calculate_server_time().expect("Failed to calculate server time")
+    }
+
+    /// Only errors the futures side also knows get retried, anything spot specific is handed back as is.
+    /// An unknown outcome isn't retried, orders look themselves up first and loans could double up.
+    async fn should_retry(&self, error: &SpotError, attempt: usize) -> bool {
+        info!("[SPOT] {:?}", error);
+        match error.as_futures() {
+            Some(error) => BROKER.should_retry(error.policy(), attempt).await,
+            None => false,
+        }
+    }
+
+    /// should_retry for cancels and reads, an unknown outcome is just sent again
+    async fn should_retry_idempotent(&self, error: &SpotError, attempt: usize) -> bool {
+        info!("[SPOT] {:?}", error);
+        match error.as_futures() {
+            Some(error) => BROKER.should_retry_idempotent(error.policy(), attempt).await,
+            None => false,
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref SPOT: SpotBroker = SpotBroker::new(
+        CONFIG.binance_spot_rest_url.clone().unwrap_or_else(|| "https://api.binance.com".to_string()),
+        CONFIG.binance_key.clone(),
+        CONFIG.binance_secret.clone(),
+    ).expect("Failed to create spot broker due to an issue building the request pool");
+}
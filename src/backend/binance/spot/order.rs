@@ -0,0 +1,185 @@
+use uuid::Uuid;
+
+use crate::backend::types::Side;
+use crate::backend::binance::errors::{ErrorPolicy, ProcessingErrors};
+use crate::backend::binance::types::{SpotOrderRequest, SpotCancelRequest, SpotQueryRequest, SpotOrderWrapper, SpotOrderType, SpotAccount, BinanceSide, BinanceTimeInForce, OrderResponseType};
+use crate::telemetry::{LATENCY, Venue, LatencyStage};
+
+use crate::backend::binance::broker::{BROKER, MAX_RETRIES};
+
+use super::SpotBroker;
+
+/// Spot orders go to api/v3, cross margin orders to sapi/v1/margin and carry a side effect
+fn order_path(account: SpotAccount) -> &'static str {
+    match account {
+        SpotAccount::Spot => "api/v3/order",
+        SpotAccount::CrossMargin(_) => "sapi/v1/margin/order",
+    }
+}
+
+fn isolated(account: SpotAccount) -> Option<String> {
+    match account {
+        SpotAccount::Spot => None,
+        SpotAccount::CrossMargin(_) => Some("FALSE".to_string()),
+    }
+}
+
+impl SpotBroker {
+    pub async fn create_market(&self, id: Uuid, symbol: String, size: f64, side: Side, account: SpotAccount) -> SpotOrderWrapper {
+        self.create_order(id, symbol, SpotOrderType::Market, None, size, side, account).await
+    }
+
+    /// Post only, a limit that would take is rejected
+    pub async fn create_limit(&self, id: Uuid, symbol: String, price: f64, size: f64, side: Side, account: SpotAccount) -> SpotOrderWrapper {
+        self.create_order(id, symbol, SpotOrderType::LimitMaker, Some(price), size, side, account).await
+    }
+
+    /// Takes up to the price and cancels the rest
+    pub async fn create_ioc(&self, id: Uuid, symbol: String, price: f64, size: f64, side: Side, account: SpotAccount) -> SpotOrderWrapper {
+        self.create_order(id, symbol, SpotOrderType::Limit, Some(price), size, side, account).await
+    }
+
+    async fn create_order(
+        &self,
+        id: Uuid,
+        symbol: String,
+        order_type: SpotOrderType,
+        price: Option<f64>,
+        size: f64,
+        side: Side,
+        account: SpotAccount,
+    ) -> SpotOrderWrapper {
+        let time_in_force = match order_type {
+            SpotOrderType::Limit => Some(BinanceTimeInForce::ImmediateOrCancel),
+            _ => None,
+        };
+        let side_effect = match account {
+            SpotAccount::Spot => None,
+            SpotAccount::CrossMargin(side_effect) => Some(side_effect),
+        };
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = SpotOrderRequest {
+                symbol: symbol.clone(),
+                side: BinanceSide::from(side),
+                order_type,
+                quantity: size,
+                price,
+                time_in_force,
+                id,
+                order_response_type: OrderResponseType::Result,
+                side_effect,
+                is_isolated: isolated(account),
+                receive_window: 5000,
+                timestamp: self.timestamp(),
+            };
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let timer = LATENCY.sending(Venue::Binance, id.to_string());
+            let order_res = self.client
+                .post(format!("{}/{}?{}", self.auth.url, order_path(account), req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", self.auth.key.clone())
+                .send()
+                .await
+                .expect("error recv spot order response")
+                .text()
+                .await
+                .expect("err");
+            LATENCY.since(Venue::Binance, LatencyStage::RestAck, timer);
+            let wrapper = serde_json::from_str::<SpotOrderWrapper>(&order_res).expect("serde err binance spot order res");
+            if let SpotOrderWrapper::Error(e) = &wrapper {
+                let unknown = e.as_futures().map_or(false, |e| e.policy() == ErrorPolicy::Resync);
+                if unknown && attempt < MAX_RETRIES {
+                    // The order may have landed, only send it again if the exchange has never seen it
+                    match self.query_order(id, symbol.clone(), account).await {
+                        SpotOrderWrapper::Order(order) => {
+                            info!("[SPOT] {} landed despite {:?}, taking it as placed", id, e);
+                            return SpotOrderWrapper::Order(order);
+                        },
+                        SpotOrderWrapper::Error(q) if q.code == ProcessingErrors::NoSuchOrder as i32 => {
+                            info!("[SPOT] {} never landed, sending it again", id);
+                            attempt += 1;
+                            continue;
+                        },
+                        SpotOrderWrapper::Error(q) => info!("[SPOT] Couldn't look {} up, giving up on it: {:?}", id, q),
+                    }
+                } else if self.should_retry(e, attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return wrapper;
+        }
+    }
+
+    pub async fn cancel_order(&self, id: Uuid, symbol: String, account: SpotAccount) -> SpotOrderWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = SpotCancelRequest {
+                symbol: symbol.clone(),
+                id,
+                is_isolated: isolated(account),
+                receive_window: 5000,
+                timestamp: self.timestamp(),
+            };
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let cancel_res = self.client
+                .delete(format!("{}/{}?{}", self.auth.url, order_path(account), req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", self.auth.key.clone())
+                .send()
+                .await
+                .expect("error recv spot cancel response")
+                .text()
+                .await
+                .expect("err");
+            let wrapper = serde_json::from_str::<SpotOrderWrapper>(&cancel_res).expect("serde err binance spot cancel res");
+            if let SpotOrderWrapper::Error(e) = &wrapper {
+                if self.should_retry_idempotent(e, attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return wrapper;
+        }
+    }
+
+    /// Looks an order up by the client id it was placed with, NoSuchOrder comes back if it never reached the exchange
+    pub async fn query_order(&self, id: Uuid, symbol: String, account: SpotAccount) -> SpotOrderWrapper {
+        let mut attempt = 0;
+        loop {
+            BROKER.await_backoff().await;
+            let req = SpotQueryRequest {
+                symbol: symbol.clone(),
+                id,
+                is_isolated: isolated(account),
+                receive_window: 5000,
+                timestamp: self.timestamp(),
+            };
+            let req = req.get_signed_data(self.auth.secret.clone()).expect("Sign error");
+
+            let query_res = self.client
+                .get(format!("{}/{}?{}", self.auth.url, order_path(account), req))
+                .header("Content-Type", "application/json")
+                .header("X-MBX-APIKEY", self.auth.key.clone())
+                .send()
+                .await
+                .expect("error recv spot query response")
+                .text()
+                .await
+                .expect("err");
+            let wrapper = serde_json::from_str::<SpotOrderWrapper>(&query_res).expect("serde err binance spot query res");
+            if let SpotOrderWrapper::Error(e) = &wrapper {
+                if e.code != ProcessingErrors::NoSuchOrder as i32 && self.should_retry_idempotent(e, attempt).await {
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return wrapper;
+        }
+    }
+}
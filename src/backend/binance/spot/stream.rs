@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use async_tungstenite::tokio::connect_async;
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc::{Sender, unbounded_channel};
+use tokio::time;
+
+use crate::backend::binance::types::{SpotSignal, SpotUserData, SpotAccount, Trades, MarginOrders};
+use crate::config::CONFIG;
+use crate::telemetry::{LATENCY, METRICS, Venue};
+
+use super::SPOT;
+
+fn stream_url() -> String {
+    CONFIG.binance_spot_stream_url.clone().unwrap_or_else(|| "wss://stream.binance.com:9443".to_string())
+}
+
+lazy_static! {
+    static ref RECONNECT_DELAY: Duration = Duration::from_secs(1);
+}
+
+/// Individual spot trades for a symbol
+/// NOTE: Should be called from a new thread to avoid blocking the main thread.
+pub async fn connect_trade(sender: Sender<SpotSignal>, symbol: String) {
+    let url = format!("{}/ws/{}@trade", stream_url(), symbol.to_lowercase());
+    while listen(url.clone(), |txt| serde_json::from_str::<Trades>(txt).ok().map(SpotSignal::Trade), &sender).await {
+        time::sleep(*RECONNECT_DELAY).await;
+    }
+}
+
+/// Depth diffs every 100ms, a local book needs a REST snapshot to apply them to
+pub async fn connect_depth(sender: Sender<SpotSignal>, symbol: String) {
+    let url = format!("{}/ws/{}@depth@100ms", stream_url(), symbol.to_lowercase());
+    while listen(url.clone(), |txt| serde_json::from_str::<MarginOrders>(txt).ok().map(SpotSignal::Depth), &sender).await {
+        time::sleep(*RECONNECT_DELAY).await;
+    }
+}
+
+/// Order, balance and loan updates for the spot or the cross margin account, each has its own listen key.
+/// A fresh key is fetched whenever the stream drops or the key expires.
+pub async fn connect_user_data(sender: Sender<SpotSignal>, account: SpotAccount) {
+    let path = match account {
+        SpotAccount::Spot => "api/v3/userDataStream",
+        SpotAccount::CrossMargin(_) => "sapi/v1/userDataStream",
+    };
+    loop {
+        let key = match listen_key(path).await {
+            Some(key) => key,
+            None => {
+                time::sleep(*RECONNECT_DELAY).await;
+                continue;
+            },
+        };
+        // Keys lapse after an hour without a keepalive
+        let keepalive = {
+            let key = key.clone();
+            tokio::spawn(async move {
+                let mut interval = time::interval(Duration::from_secs(1800));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    keepalive(path, &key).await;
+                }
+            })
+        };
+        let listening = listen(format!("{}/ws/{}", stream_url(), key), |txt| {
+            let ud = serde_json::from_str::<SpotUserData>(txt).ok()?;
+            if let SpotUserData::ExecutionReport(report) = &ud { LATENCY.confirmed(&report.client_order_id); }
+            Some(SpotSignal::UserData(ud))
+        }, &sender).await;
+        keepalive.abort();
+        if !listening { return; }
+        time::sleep(*RECONNECT_DELAY).await;
+    }
+}
+
+/// Runs one connection until it drops or its listen key expires, unreadable frames are skipped.
+/// Returns false once nothing is listening for the signals anymore.
+async fn listen<F>(url: String, parse: F, sender: &Sender<SpotSignal>) -> bool
+where F: Fn(&str) -> Option<SpotSignal> {
+    let ws_stream = match connect_async(url).await {
+        Ok((ws_stream, res)) if res.status().is_informational() || res.status().is_success() => ws_stream,
+        Ok((_, res)) => {
+            info!("[SPOT] Stream refused: {:?}", res);
+            return true;
+        },
+        Err(e) => {
+            info!("[SPOT] Failed to open stream: {}", e);
+            return true;
+        },
+    };
+
+    METRICS.connected(Venue::Binance, "spot");
+    let (mut write, mut read) = ws_stream.split();
+    let (send, mut rec) = unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rec.recv().await {
+            if write.send(msg).await.is_err() { break; }
+        }
+    });
+
+    let mut listening = true;
+    while let Some(msg) = read.next().await {
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                info!("[SPOT] Stream error, reconnecting: {}", e);
+                break;
+            },
+        };
+        match msg {
+            Message::Text(txt) => {
+                let signal = match parse(&txt) {
+                    Some(signal) => signal,
+                    None => {
+                        debug!("[SPOT] Unreadable frame {}", txt);
+                        continue;
+                    },
+                };
+                let expired = matches!(signal, SpotSignal::UserData(SpotUserData::StreamExpired));
+                if sender.send(signal).await.is_err() {
+                    listening = false;
+                    break;
+                }
+                if expired {
+                    info!("[SPOT] Listen key expired, renewing it");
+                    break;
+                }
+            },
+            Message::Ping(payload) => { send.send(Message::Pong(payload)).ok(); },
+            Message::Close(frame) => {
+                info!("[SPOT] The server shut down the stream on us, reconnecting: {:?}", frame);
+                break;
+            },
+            _ => {},
+        }
+    }
+    writer.abort();
+    listening
+}
+
+async fn listen_key(path: &str) -> Option<String> {
+    let res = SPOT.client
+        .post(format!("{}/{}", SPOT.auth.url, path))
+        .header("X-MBX-APIKEY", SPOT.auth.key.clone())
+        .send()
+        .await;
+    let text = match res {
+        Ok(res) => res.text().await,
+        Err(e) => Err(e),
+    };
+    match text.map(|text| serde_json::from_str::<Key>(&text)) {
+        Ok(Ok(key)) => Some(key.listen_key),
+        Ok(Err(e)) => {
+            info!("[SPOT] Unreadable listen key response: {}", e);
+            None
+        },
+        Err(e) => {
+            info!("[SPOT] Failed to get a listen key: {}", e);
+            None
+        },
+    }
+}
+
+async fn keepalive(path: &str, key: &str) {
+    let res = SPOT.client
+        .put(format!("{}/{}?listenKey={}", SPOT.auth.url, path, key))
+        .header("X-MBX-APIKEY", SPOT.auth.key.clone())
+        .send()
+        .await;
+    if let Err(e) = res { info!("[SPOT] listen key keepalive failed: {}", e); }
+}
+
+#[derive(Deserialize)]
+struct Key {
+    #[serde(rename = "listenKey")]
+    pub listen_key: String,
+}
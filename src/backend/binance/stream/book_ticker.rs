@@ -10,6 +10,8 @@ use tokio::time;
 
 use crate::backend::binance::types::{WebsocketSubscribe, Orders, OrderbookResponse, OrderBookSignal, Signal, BestLevel};
 use crate::config::CONFIG;
+use crate::backend::binance::broker::BROKER;
+use crate::telemetry::{LATENCY, METRICS, Venue, LatencyStage};
 
 pub async fn connect_book_ticker(sender: Sender<Signal>, symbol: String) {
     let args = format!("{}@bookTicker", symbol);
@@ -18,6 +20,7 @@ pub async fn connect_book_ticker(sender: Sender<Signal>, symbol: String) {
         .await
         .expect("error building stream");
     if !res.status().is_informational() && !res.status().is_success() { panic!("panic stream: {:?}\nres: {:?}", ws_stream, res); }
+    METRICS.connected(Venue::Binance, "book_ticker");
     let (mut write, mut read) = ws_stream.split();
 
     loop {
@@ -26,6 +29,11 @@ pub async fn connect_book_ticker(sender: Sender<Signal>, symbol: String) {
                 let timer = Instant::now();
                 let mut bt = serde_json::from_str::<BestLevel>(&txt.to_string()).expect("Deser BT went wrong");
                 bt.test_timer = timer;
+                LATENCY.since(Venue::Binance, LatencyStage::Deserialize, timer);
+                METRICS.book_update(Venue::Binance);
+                if let Ok(now) = BROKER.calculate_server_time() {
+                    LATENCY.record(Venue::Binance, LatencyStage::SocketReceive, Duration::from_millis(now.saturating_sub(bt.event_time)));
+                }
                 sender.send(Signal::OrderBook(OrderBookSignal::BestLevels(bt))).await.expect("err sending bt out of ws");
             },
             Message::Binary(_) => todo!(),
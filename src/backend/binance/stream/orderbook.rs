@@ -10,6 +10,8 @@ use tokio::time;
 
 use crate::backend::binance::types::{WebsocketSubscribe, Orders, OrderbookResponse, OrderBookSignal, Signal};
 use crate::config::CONFIG;
+use crate::backend::binance::broker::BROKER;
+use crate::telemetry::{LATENCY, METRICS, Venue, LatencyStage};
 
 pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
     let args = format!("{}@depth", symbol);
@@ -19,6 +21,7 @@ pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
         .expect("error building stream");
     if !res.status().is_informational() && !res.status().is_success() { panic!("panic stream: {:?}\nres: {:?}", ws_stream, res); }
 
+    METRICS.connected(Venue::Binance, "orderbook");
     let (mut write, mut read) = ws_stream.split();
 
     loop {
@@ -29,6 +32,11 @@ pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
                 let timer = Instant::now();
                 let mut ob = serde_json::from_str::<Orders>(&txt.to_string()).expect("Deser OB went wrong");
                 ob.test_timer = timer;
+                LATENCY.since(Venue::Binance, LatencyStage::Deserialize, timer);
+                METRICS.book_update(Venue::Binance);
+                if let Ok(now) = BROKER.calculate_server_time() {
+                    LATENCY.record(Venue::Binance, LatencyStage::SocketReceive, Duration::from_millis(now.saturating_sub(ob.event_time)));
+                }
                 sender.send(Signal::OrderBook(OrderBookSignal::OrderBook(ob))).await.expect("err sending ob out of ws");
             },
             Message::Binary(_) => todo!(),
@@ -10,6 +10,8 @@ use tokio::time;
 
 use crate::backend::binance::types::{WebsocketSubscribe, Signal, TradeFlows, FuturesTrades, StreamWrapper};
 use crate::config::CONFIG;
+use crate::backend::binance::broker::BROKER;
+use crate::telemetry::{LATENCY, METRICS, Venue, LatencyStage};
 
 pub async fn connect_tradeflow(sender: Sender<Signal>, symbol: String) {
     let trade_arg = format!("{}@aggTrade", symbol);
@@ -21,6 +23,7 @@ pub async fn connect_tradeflow(sender: Sender<Signal>, symbol: String) {
         .expect("error building stream");
     if !res.status().is_informational() && !res.status().is_success() { panic!("panic stream: {:?}\nres: {:?}", ws_stream, res); }
 
+    METRICS.connected(Venue::Binance, "trade");
     let (mut write, mut read) = ws_stream.split();
     // let obsub = serde_json::to_string(&WebsocketSubscribe {
     //     method: "SUBSCRIBE".to_string(),
@@ -37,6 +40,11 @@ pub async fn connect_tradeflow(sender: Sender<Signal>, symbol: String) {
                 let timer = Instant::now();
                 let mut tr = serde_json::from_str::<FuturesTrades>(&txt.to_string()).expect("Deser TR went wrong");
                 tr.test_timer = timer;
+                LATENCY.since(Venue::Binance, LatencyStage::Deserialize, timer);
+                METRICS.trade_update(Venue::Binance);
+                if let Ok(now) = BROKER.calculate_server_time() {
+                    LATENCY.record(Venue::Binance, LatencyStage::SocketReceive, Duration::from_millis(now.saturating_sub(tr.event_time)));
+                }
                 sender.send(Signal::TradeFlows(TradeFlows::Trades(tr))).await.expect("err sending ob out of ws");
             },
             Message::Binary(_) => todo!(),
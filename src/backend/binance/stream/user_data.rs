@@ -11,9 +11,10 @@ use tokio::task;
 // use tokio::sync::mpsc::{Sender, Receiver, channel};
 use tokio::time;
 
-use crate::backend::binance::types::{WebsocketSubscribe, Signal, TradeFlows, FuturesTrades, StreamWrapper, UserDataStreams, UserStreamWrapper, OrderUpdateData, PositionUpdateData, StreamExpired, WebsocketMessager};
+use crate::backend::binance::types::{WebsocketSubscribe, Signal, TradeFlows, FuturesTrades, StreamWrapper, UserDataStreams, UserStreamWrapper, OrderUpdateData, PositionUpdateData, StreamExpired, WebsocketMessager, MarginCall};
 use crate::config::CONFIG;
 use crate::strategy::binance::{StrategyMessage, AccountMessage};
+use crate::telemetry::{LATENCY, METRICS, Venue};
 
 pub async fn connect_user_data(sender: crossbeam_channel::Sender<StrategyMessage>) {
     let key = get_key().await;
@@ -23,6 +24,7 @@ pub async fn connect_user_data(sender: crossbeam_channel::Sender<StrategyMessage
         .expect("error building stream");
     if !(res.status().is_informational() || res.status().is_success()) { panic!("panic stream: {:?}\nres: {:?}", ws_stream, res); }
 
+    METRICS.connected(Venue::Binance, "user_data");
     let (mut write, mut read) = ws_stream.split();
 
     let (send, mut rec): (tokio::sync::mpsc::Sender<WebsocketMessager>, tokio::sync::mpsc::Receiver<WebsocketMessager>) = tokio::sync::mpsc::channel(1);
@@ -71,6 +73,7 @@ pub async fn connect_user_data(sender: crossbeam_channel::Sender<StrategyMessage
                     Message::Text(txt) => {
                         if txt.contains("ORDER_TRADE_UPDATE") {
                             let ud = serde_json::from_str::<UserStreamWrapper<OrderUpdateData>>(&txt.to_string()).expect("Deser UD went wrong");
+                            LATENCY.confirmed(&ud.data.id);
                             sender.send(StrategyMessage::AccountMessage(AccountMessage::OrderUpdate(ud.data))).expect("err sending od out of ws");
 
                         } else if txt.contains("ACCOUNT_UPDATE") {
@@ -82,7 +85,8 @@ pub async fn connect_user_data(sender: crossbeam_channel::Sender<StrategyMessage
                             panic!("{:?}", ud);
 
                         } else if txt.contains("MARGIN_CALL") {
-                            todo!();
+                            let mc = serde_json::from_str::<MarginCall>(&txt.to_string()).expect("Deser UD went wrong");
+                            sender.send(StrategyMessage::AccountMessage(AccountMessage::MarginCall(mc))).expect("err sending mc out of ws");
                         } else if txt.contains("ACCOUNT_CONFIG_UPDATE") {
                             todo!();
                         } else {
@@ -38,7 +38,8 @@ pub struct BestLevel {
 }
 pub fn instant_default() -> Instant { Instant::now() }
 
-#[derive(Deserialize, Debug)]
+/// Spot and margin depth diff, the futures one carries a transaction time as well
+#[derive(Deserialize, Clone, Debug)]
 pub struct MarginOrders {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -82,7 +83,8 @@ pub struct Orders {
     pub test_timer: Instant,
 }
 
-#[derive(Deserialize, Debug)]
+/// Spot trade stream, one per fill rather than aggregated
+#[derive(Deserialize, Clone, Debug)]
 pub struct Trades {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -98,9 +100,10 @@ pub struct Trades {
     pub price: D128,
     #[serde(rename = "q")]
     pub quantity: D128,
-    #[serde(rename = "b")]
+    /// No longer sent on spot
+    #[serde(rename = "b", default)]
     pub buyer_order_id: u64,
-    #[serde(rename = "a")]
+    #[serde(rename = "a", default)]
     pub seller_order_id: u64,
     #[serde(rename = "m")]
     pub buyer_maker: bool,
@@ -191,7 +194,7 @@ pub struct StreamExpired {
     pub event_time: u64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct MarginCall {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -203,16 +206,19 @@ pub struct MarginCall {
     pub positions: Vec<MarginCallPosition>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct MarginCallPosition {
     #[serde(rename = "s")]
     pub symbol: String,
     #[serde(rename = "ps")]
     pub position_side: BinanceSide,
+    /// Signed in one-way mode
+    #[serde(rename = "pa")]
+    pub quantity: D128,
     #[serde(rename = "mt")]
     pub margin_type: MarginType,
     #[serde(rename = "iw")]
-    pub isolated_wallet: Option<u32>,
+    pub isolated_wallet: Option<D128>,
     #[serde(rename = "mp")]
     pub mark_price: D128,
     #[serde(rename = "up")]
@@ -351,6 +357,9 @@ pub struct MarketOrderRequest {
     #[serde(rename = "type")]
     pub order_type: OrderType,
     pub quantity: f64,
+    /// Only sent in one-way mode, hedge mode rejects it
+    #[serde(rename = "reduceOnly", skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
     #[serde(rename = "newClientOrderId")]
     pub id: Uuid,
     #[serde(rename = "newOrderRespType")]
@@ -373,6 +382,37 @@ pub struct LimitOrderRequest {
     pub quantity: f64,
     #[serde(rename = "timeInForce")]
     pub time_in_force: BinanceTimeInForce,
+    /// Only sent in one-way mode, hedge mode rejects it
+    #[serde(rename = "reduceOnly", skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(rename = "newClientOrderId")]
+    pub id: Uuid,
+    #[serde(rename = "newOrderRespType")]
+    pub order_response_type: OrderResponseType,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// STOP_MARKET and TAKE_PROFIT_MARKET, the trigger goes in stopPrice and fills at market
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct ConditionalOrderRequest {
+    pub symbol: String,
+    pub side: BinanceSide,
+    #[serde(rename = "positionSide")]
+    pub position_side: BinancePositionSide,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub quantity: f64,
+    #[serde(rename = "stopPrice")]
+    pub stop_price: f64,
+    #[serde(rename = "workingType")]
+    pub working_type: WorkingType,
+    #[serde(rename = "priceProtect")]
+    pub price_protect: bool,
+    /// Only sent in one-way mode, hedge mode rejects it
+    #[serde(rename = "reduceOnly", skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
     #[serde(rename = "newClientOrderId")]
     pub id: Uuid,
     #[serde(rename = "newOrderRespType")]
@@ -436,6 +476,8 @@ pub struct OrderRequest {
 pub struct OrderResponse {
     #[serde(alias = "clientOrderId")]
     pub id: Uuid,
+    /// Missing when the order is looked up rather than placed, query_order fills it from executed_qty
+    #[serde(default)]
     pub cum_qty: D128,
     pub cum_quote: D128,
     pub executed_qty: D128,
@@ -462,6 +504,31 @@ pub struct OrderResponse {
     pub price_protect: Option<bool>,
 }
 
+/// Reprices or resizes a resting limit, the exchange keeps its place in the book only if the price is unchanged
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct ModifyOrderRequest {
+    pub symbol: String,
+    pub side: BinanceSide,
+    #[serde(rename = "origClientOrderId")]
+    pub id: Uuid,
+    pub price: f64,
+    pub quantity: f64,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// Looks an order up by the client id it was placed with
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct QueryOrderRequest {
+    pub symbol: String,
+    #[serde(rename = "origClientOrderId")]
+    pub id: Uuid,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
 #[derive(Serialize, BinanceSignable, Debug)]
 pub struct CancelRequest {
     pub symbol: String,
@@ -523,6 +590,168 @@ pub struct AccountBalance {
     pub update_time: i64,
 }
 
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct OpenOrdersRequest {
+    pub symbol: String,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// An order as reported by the open orders endpoint.
+/// The client id is kept as a string since orders placed outside the trader won't carry a UUID.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenOrder {
+    #[serde(rename = "clientOrderId")]
+    pub id: String,
+    pub avg_price: D128,
+    pub cum_quote: D128,
+    pub executed_qty: D128,
+    #[serde(rename = "orderId")]
+    pub auto_id: u64,
+    pub orig_qty: D128,
+    pub orig_type: OrderType,
+    pub price: D128,
+    pub reduce_only: bool,
+    pub side: BinanceSide,
+    pub position_side: BinanceSide,
+    pub status: OrderStatus,
+    pub stop_price: Option<D128>,
+    pub close_position: Option<bool>,
+    pub symbol: String,
+    pub time: u64,
+    pub time_in_force: TimeInForce,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
+    pub update_time: u64,
+    pub working_type: WorkingType,
+    pub price_protect: Option<bool>,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct PositionRiskRequest {
+    pub symbol: String,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct LeverageRequest {
+    pub symbol: String,
+    pub leverage: u32,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageResponse {
+    pub symbol: String,
+    pub leverage: u32,
+    /// Largest position notional allowed at this leverage
+    pub max_notional_value: D128,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct MarginTypeRequest {
+    pub symbol: String,
+    #[serde(rename = "marginType")]
+    pub margin_type: MarginType,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// Binance answers a margin type change with a bare code 200
+#[derive(Deserialize, Clone, Debug)]
+pub struct MarginTypeResponse {
+    pub code: i32,
+    pub msg: String,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct LeverageBracketRequest {
+    pub symbol: String,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SymbolBrackets {
+    pub symbol: String,
+    pub brackets: Vec<LeverageBracket>,
+}
+
+/// Notional tier with its own leverage cap and maintenance margin
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageBracket {
+    pub bracket: u32,
+    pub initial_leverage: u32,
+    pub notional_cap: D128,
+    pub notional_floor: D128,
+    pub maint_margin_ratio: D128,
+    /// Maintenance amount deducted at this tier
+    pub cum: D128,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct CommissionRateRequest {
+    pub symbol: String,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// The account's current maker and taker rates for a symbol, as fractions of notional
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommissionRate {
+    pub symbol: String,
+    pub maker_commission_rate: D128,
+    pub taker_commission_rate: D128,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct PositionModeRequest {
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// Hedge mode when true, one-way when false
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct PositionMode {
+    #[serde(rename = "dualSidePosition")]
+    pub dual_side_position: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionRisk {
+    pub symbol: String,
+    #[serde(rename = "positionAmt")]
+    pub quantity: D128,
+    pub entry_price: D128,
+    pub mark_price: D128,
+    #[serde(rename = "unRealizedProfit")]
+    pub unrealized_pnl: D128,
+    pub liquidation_price: D128,
+    pub leverage: D128,
+    pub max_notional_value: D128,
+    pub margin_type: MarginType,
+    pub isolated_margin: D128,
+    pub is_auto_add_margin: String,
+    pub position_side: BinanceSide,
+    pub notional: D128,
+    pub isolated_wallet: D128,
+    pub update_time: u64,
+}
+
 #[derive(Serialize, Debug)]
 pub struct WebsocketSubscribe {
     pub method: String,
@@ -565,6 +794,222 @@ pub struct ServerTimeResponse {
 }
 
 
+/// Spot and cross margin orders share a request, margin adds the side effect and the isolated flag
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct SpotOrderRequest {
+    pub symbol: String,
+    pub side: BinanceSide,
+    #[serde(rename = "type")]
+    pub order_type: SpotOrderType,
+    pub quantity: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    #[serde(rename = "timeInForce", skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<BinanceTimeInForce>,
+    #[serde(rename = "newClientOrderId")]
+    pub id: Uuid,
+    #[serde(rename = "newOrderRespType")]
+    pub order_response_type: OrderResponseType,
+    #[serde(rename = "sideEffectType", skip_serializing_if = "Option::is_none")]
+    pub side_effect: Option<SideEffectType>,
+    #[serde(rename = "isIsolated", skip_serializing_if = "Option::is_none")]
+    pub is_isolated: Option<String>,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct SpotCancelRequest {
+    pub symbol: String,
+    #[serde(rename = "origClientOrderId")]
+    pub id: Uuid,
+    #[serde(rename = "isIsolated", skip_serializing_if = "Option::is_none")]
+    pub is_isolated: Option<String>,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// Looks a spot or margin order up by the client id it was placed with
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct SpotQueryRequest {
+    pub symbol: String,
+    #[serde(rename = "origClientOrderId")]
+    pub id: Uuid,
+    #[serde(rename = "isIsolated", skip_serializing_if = "Option::is_none")]
+    pub is_isolated: Option<String>,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+/// Creates, cancels and lookups all come back in this shape
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotOrderResponse {
+    pub symbol: String,
+    #[serde(rename = "orderId")]
+    pub auto_id: u64,
+    #[serde(alias = "origClientOrderId")]
+    pub client_order_id: String,
+    #[serde(default)]
+    pub transact_time: u64,
+    pub price: D128,
+    pub orig_qty: D128,
+    pub executed_qty: D128,
+    pub cummulative_quote_qty: D128,
+    pub status: SpotOrderStatus,
+    #[serde(rename = "type")]
+    pub order_type: SpotOrderType,
+    pub side: Side,
+    #[serde(default)]
+    pub fills: Vec<SpotFill>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotFill {
+    pub price: D128,
+    pub qty: D128,
+    pub commission: D128,
+    pub commission_asset: String,
+}
+
+/// Borrows and repays against the cross margin account
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct MarginLoanRequest {
+    pub asset: String,
+    pub amount: f64,
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginLoanResponse {
+    pub tran_id: u64,
+}
+
+#[derive(Serialize, BinanceSignable, Debug)]
+pub struct MarginAccountRequest {
+    #[serde(rename = "recvWindow")]
+    pub receive_window: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginAccount {
+    pub borrow_enabled: bool,
+    pub trade_enabled: bool,
+    /// Assets over liabilities, Binance margin calls at 1.1 and liquidates at 1.05
+    pub margin_level: D128,
+    pub total_asset_of_btc: D128,
+    pub total_liability_of_btc: D128,
+    pub total_net_asset_of_btc: D128,
+    pub user_assets: Vec<MarginAsset>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginAsset {
+    pub asset: String,
+    pub free: D128,
+    pub locked: D128,
+    pub borrowed: D128,
+    pub interest: D128,
+    pub net_asset: D128,
+}
+
+/// Spot and margin error codes go beyond the futures ones, so the code is kept raw
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpotError {
+    pub code: i32,
+    pub msg: String,
+}
+
+impl SpotError {
+    /// The futures error it matches, if it's one of theirs
+    pub fn as_futures(&self) -> Option<BinanceError> {
+        serde_json::from_value::<ErrorCode>(serde_json::Value::from(self.code)).ok()
+            .map(|code| BinanceError { code, msg: self.msg.clone() })
+    }
+}
+
+/// executionReport off the spot and margin user data streams
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExecutionReport {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    /// Holds the original client id on cancels, c is a new one then
+    #[serde(rename = "C")]
+    pub orig_client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: Side,
+    #[serde(rename = "o")]
+    pub order_type: SpotOrderType,
+    #[serde(rename = "q")]
+    pub quantity: D128,
+    #[serde(rename = "p")]
+    pub price: D128,
+    #[serde(rename = "x")]
+    pub execution_type: SpotExecutionType,
+    #[serde(rename = "X")]
+    pub status: SpotOrderStatus,
+    #[serde(rename = "i")]
+    pub auto_id: u64,
+    #[serde(rename = "l")]
+    pub last_filled_qty: D128,
+    #[serde(rename = "z")]
+    pub cum_filled_qty: D128,
+    #[serde(rename = "L")]
+    pub last_filled_price: D128,
+    #[serde(rename = "n")]
+    pub commission: D128,
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>,
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+    #[serde(rename = "m")]
+    pub maker: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AccountPosition {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "B")]
+    pub balances: Vec<AccountPositionBalance>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AccountPositionBalance {
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "f")]
+    pub free: D128,
+    #[serde(rename = "l")]
+    pub locked: D128,
+}
+
+/// Deposits, withdrawals, transfers and margin borrows or repays
+#[derive(Deserialize, Clone, Debug)]
+pub struct BalanceUpdate {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "d")]
+    pub delta: D128,
+}
+
+
 
 /// TYPE ENUMS
 
@@ -661,7 +1106,7 @@ impl From<Side> for BinanceSide {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
 pub enum BinancePositionSide {
     #[serde(alias="BUY")]
     #[serde(alias="buy")]
@@ -691,16 +1136,32 @@ impl From<Side> for BinancePositionSide {
     }
 }
 
-#[derive(Deserialize, Clone, Copy, Debug)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
 pub enum MarginType {
     #[serde(alias = "ISOLATED")]
     #[serde(alias = "isolated")]
+    #[serde(rename(serialize = "ISOLATED"))]
     Isolated,
     #[serde(alias = "CROSS")]
     #[serde(alias = "cross")]
+    #[serde(alias = "CROSSED")]
+    #[serde(alias = "crossed")]
+    #[serde(rename(serialize = "CROSSED"))]
     Cross
 }
 
+impl TryFrom<String> for MarginType {
+    type Error = &'static str;
+
+    fn try_from(margin_type: String) -> Result<Self, Self::Error> {
+        match margin_type.to_lowercase().as_str() {
+            "isolated" => Ok(MarginType::Isolated),
+            "cross" | "crossed" => Ok(MarginType::Cross),
+            _ => Err("Invalid Data: margin type must be isolated or cross"),
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub enum DepthLimit {
     Five = 5,
@@ -731,6 +1192,7 @@ pub enum OrderType {
     Market,
     Limit,
     Stop,
+    StopMarket,
     TakeProfit,
     TakeProfitMarket,
     Liquidation,
@@ -743,6 +1205,7 @@ impl Display for OrderType {
             OrderType::Market => "MARKET",
             OrderType::Limit => "LIMIT",
             OrderType::Stop => "STOP",
+            OrderType::StopMarket => "STOP_MARKET",
             OrderType::TakeProfit => "TAKE_PROFIT",
             OrderType::Liquidation => "LIQUIDATION",
             OrderType::TakeProfitMarket => "TAKE_PROFIT_MARKET",
@@ -770,6 +1233,63 @@ pub enum WorkingType {
 }
 
 
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpotOrderType {
+    Market,
+    Limit,
+    /// Post only, rejected if it would take
+    LimitMaker,
+    StopLoss,
+    StopLossLimit,
+    TakeProfit,
+    TakeProfitLimit,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpotOrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    #[serde(rename = "CANCELED")]
+    Cancelled,
+    PendingCancel,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpotExecutionType {
+    New,
+    #[serde(rename = "CANCELED")]
+    Cancelled,
+    Replaced,
+    Rejected,
+    Trade,
+    Expired,
+    TradePrevention,
+}
+
+/// What a margin order does with the loan, borrowing whatever it's short of or repaying out of the proceeds
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SideEffectType {
+    NoSideEffect,
+    MarginBuy,
+    AutoRepay,
+}
+
+/// Which account a spot order goes against
+#[derive(Clone, Copy, Debug)]
+pub enum SpotAccount {
+    Spot,
+    CrossMargin(SideEffectType),
+}
+
+
 /// ROUTING ENUMS
 
 
@@ -781,6 +1301,16 @@ pub enum AccountBalanceWrapper {
 }
 
 
+/// Response frame from the websocket API, the id ties it back to the request.
+/// Result holds the same body the REST endpoint would have returned.
+#[derive(Deserialize, Debug)]
+pub struct WsApiResponse {
+    pub id: Option<String>,
+    pub status: u16,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<serde_json::Value>,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum OrderResponseWrapper {
@@ -795,6 +1325,56 @@ pub enum CancelResponseWrapper {
     Error(BinanceError)
 }
 
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum OpenOrdersWrapper {
+    Orders(Vec<OpenOrder>),
+    Error(BinanceError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PositionRiskWrapper {
+    Positions(Vec<PositionRisk>),
+    Error(BinanceError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum LeverageWrapper {
+    Leverage(LeverageResponse),
+    Error(BinanceError)
+}
+
+/// Error goes first, a successful response would deserialize as either
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MarginTypeWrapper {
+    Error(BinanceError),
+    Success(MarginTypeResponse),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum LeverageBracketWrapper {
+    Brackets(Vec<SymbolBrackets>),
+    Error(BinanceError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum CommissionRateWrapper {
+    Rate(CommissionRate),
+    Error(BinanceError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PositionModeWrapper {
+    Mode(PositionMode),
+    Error(BinanceError)
+}
+
 pub enum WebsocketMessager {
     Message(Message),
     Ping(),
@@ -839,3 +1419,47 @@ pub enum OrderbookResponse {
     Order(Orders)
 }
 
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum SpotOrderWrapper {
+    Order(SpotOrderResponse),
+    Error(SpotError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MarginLoanWrapper {
+    Loan(MarginLoanResponse),
+    Error(SpotError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MarginAccountWrapper {
+    Account(MarginAccount),
+    Error(SpotError)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "e")]
+pub enum SpotUserData {
+    #[serde(rename = "executionReport")]
+    ExecutionReport(ExecutionReport),
+    #[serde(rename = "outboundAccountPosition")]
+    AccountPosition(AccountPosition),
+    #[serde(rename = "balanceUpdate")]
+    BalanceUpdate(BalanceUpdate),
+    #[serde(rename = "listenKeyExpired")]
+    StreamExpired,
+    /// OCO list updates, we don't place any
+    #[serde(other)]
+    Other,
+}
+
+/// Everything the spot streams emit, kept apart from the futures signals
+#[derive(Clone, Debug)]
+pub enum SpotSignal {
+    Trade(Trades),
+    Depth(MarginOrders),
+    UserData(SpotUserData),
+}
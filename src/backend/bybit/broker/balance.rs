@@ -3,6 +3,7 @@ use hmac::{Mac, digest::InvalidLength};
 use thiserror::Error;
 
 use crate::{config::CONFIG, HmacSha256};
+use crate::backend::bybit::V5;
 
 use super::{Broker, Balance, RestResponse, CalculateServerTimeError};
 
@@ -25,6 +26,7 @@ impl Broker {
 
     /// Gets the current balance of the user for a given symbol
     pub async fn get_balance(&self, symbol: String) -> Result<RestResponse<HashMap<String, Balance>>, GetBalanceError> {
+        if *V5 { return self.v5_balance(Some(symbol)).await; }
         let timestamp = self.calculate_server_time()?;
         let mut mac = HmacSha256::new_from_slice(self.auth.secret.as_bytes())?;
         mac.update(format!(
@@ -48,6 +50,7 @@ impl Broker {
     }
 
     pub async fn get_all_balances(&self) -> Result<RestResponse<HashMap<String, Balance>>, GetBalanceError> {
+        if *V5 { return self.v5_balance(None).await; }
         let timestamp = self.calculate_server_time()?;
         let mut mac = HmacSha256::new_from_slice(self.auth.secret.as_bytes())?;
         mac.update(format!("api_key={}&timestamp={}", self.auth.key, timestamp).as_bytes());
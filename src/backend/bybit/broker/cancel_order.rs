@@ -4,11 +4,16 @@ use hmac::digest::InvalidLength;
 use uuid::Uuid;
 use thiserror::Error;
 
+use dec::D128;
+
 use crate::{config::CONFIG};
 use crate::SignRequestError;
+use crate::backend::bybit::errors::PerpetualStatus;
+use crate::backend::bybit::{V5, CONTRACT};
+use crate::backend::bybit::gateway::GATEWAY;
 
 use super::CalculateServerTimeError;
-use super::{Broker, types::{RestResponse, CancelResult, CancelJSON}};
+use super::{Broker, types::{RestResponse, CancelResult, CancelJSON, ReplaceResult, ReplaceJSON, TradeAck}};
 
 #[derive(Error, Debug)]
 pub enum CancelOrderError {
@@ -29,6 +34,26 @@ pub enum CancelOrderError {
 impl Broker {
     pub async fn cancel_order(&self, symbol: String, order_link_id: Uuid, auto_report_id: Uuid) -> Result<RestResponse<CancelResult>, CancelOrderError> {
         // info!("cancelling: {}, {}", order_link_id.to_string(), auto_report_id);
+        let args = serde_json::json!({
+            "category": CONTRACT.category(),
+            "symbol": symbol,
+            "orderLinkId": order_link_id.to_string(),
+        });
+        if let Some(res) = GATEWAY.request("order.cancel", args.clone()).await {
+            let result = match serde_json::from_value::<TradeAck>(res.data) {
+                Ok(ack) if res.ret_code == 0 => Some(CancelResult { order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(RestResponse::from_trade(PerpetualStatus::from_v5(res.ret_code), res.ret_msg, result));
+        }
+        if *V5 {
+            let res = self.v5_post::<CancelOrderError>("/v5/order/cancel", &args).await?;
+            let result = match serde_json::from_value::<TradeAck>(res.result.clone()) {
+                Ok(ack) if res.ret_code == 0 => Some(CancelResult { order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(res.into_rest(result));
+        }
         let timestamp = self.calculate_server_time()?;
         let can = CancelJSON {
             api_key: self.auth.key.clone(),
@@ -38,7 +63,7 @@ impl Broker {
             sign: String::default(),
         }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
         let cancel_res = self.client
-            .post(format!("{}/private/linear/order/cancel", CONFIG.bybit_rest_url))
+            .post(format!("{}{}/order/cancel", CONFIG.bybit_rest_url, CONTRACT.private_path()))
             .header("Content-Type", "application/json")
             .body(can)
             .send()
@@ -49,5 +74,50 @@ impl Broker {
         let ret = serde_json::from_str::<RestResponse<CancelResult>>(&cancel_res)?;
         return Ok(ret);
     }
-}
 
+    /// Moves a resting order's price and size in place, keeping its spot in the queue when only the size shrinks
+    pub async fn amend_order(&self, symbol: String, order_link_id: Uuid, price: D128, size: f64) -> Result<RestResponse<ReplaceResult>, CancelOrderError> {
+        let args = serde_json::json!({
+            "category": CONTRACT.category(),
+            "symbol": symbol,
+            "orderLinkId": order_link_id.to_string(),
+            "price": price.to_string(),
+            "qty": size.to_string(),
+        });
+        if let Some(res) = GATEWAY.request("order.amend", args.clone()).await {
+            let result = match serde_json::from_value::<TradeAck>(res.data) {
+                Ok(ack) if res.ret_code == 0 => Some(ReplaceResult { order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(RestResponse::from_trade(PerpetualStatus::from_v5(res.ret_code), res.ret_msg, result));
+        }
+        if *V5 {
+            let res = self.v5_post::<CancelOrderError>("/v5/order/amend", &args).await?;
+            let result = match serde_json::from_value::<TradeAck>(res.result.clone()) {
+                Ok(ack) if res.ret_code == 0 => Some(ReplaceResult { order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(res.into_rest(result));
+        }
+        let timestamp = self.calculate_server_time()?;
+        let rep = ReplaceJSON {
+            api_key: self.auth.key.clone(),
+            order_link_id: order_link_id.to_string(),
+            symbol,
+            p_r_price: price.to_string(),
+            p_r_qty: size,
+            timestamp,
+            sign: String::default(),
+        }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
+        let replace_res = self.client
+            .post(format!("{}{}/order/replace", CONFIG.bybit_rest_url, CONTRACT.private_path()))
+            .header("Content-Type", "application/json")
+            .body(rep)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<ReplaceResult>>(&replace_res)?;
+        return Ok(ret);
+    }
+}
@@ -0,0 +1,115 @@
+use dec::D128;
+use uuid::Uuid;
+
+use crate::backend::bybit::{V5, CONTRACT};
+use crate::config::CONFIG;
+
+use super::{Broker, RestResponse, Side, StopOrderResult, ConditionalOrderJSON, CancelConditionalJSON, CreateOrderError, CancelOrderError, TradeAck};
+
+/// Conditional orders go through the stop order endpoints, they rest untriggered until the mark price crosses stop_px
+/// and then become a regular active order under the same order_link_id.
+/// Position level trading stops are avoided, the order they close with carries no link id for us to match.
+/// On v5 conditionals are regular orders with a trigger price, their updates come in on the order topic.
+impl Broker {
+    /// Side is the side of the order itself, so the opposite of the position it protects
+    pub async fn create_conditional(
+        &self,
+        id: Uuid,
+        symbol: String,
+        trigger: D128,
+        base_price: D128,
+        size: f64,
+        side: Side,
+    ) -> Result<RestResponse<StopOrderResult>, CreateOrderError> {
+        let timestamp = self.calculate_server_time()?;
+        // Conditionals always close, so they belong to the position opposite the order's side
+        let position_idx = self.position_idx(!side);
+        let side = match side {
+            Side::Buy => "Buy",
+            Side::Sell => "Sell",
+        };
+        if *V5 {
+            let res = self.v5_post::<CreateOrderError>("/v5/order/create", &serde_json::json!({
+                "category": CONTRACT.category(),
+                "symbol": symbol,
+                "side": side,
+                "orderType": "Market",
+                "qty": size.to_string(),
+                "triggerPrice": trigger.to_string(),
+                // 1 triggers on a rise through the price, 2 on a fall
+                "triggerDirection": if trigger > base_price { 1 } else { 2 },
+                "triggerBy": "MarkPrice",
+                "orderLinkId": id.to_string(),
+                "reduceOnly": true,
+                "closeOnTrigger": true,
+                "timeInForce": "GTC",
+                "positionIdx": position_idx,
+            })).await?;
+            let result = match serde_json::from_value::<TradeAck>(res.result.clone()) {
+                Ok(ack) if res.ret_code == 0 => Some(StopOrderResult { stop_order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(res.into_rest(result));
+        }
+        let ord = ConditionalOrderJSON {
+            api_key: self.auth.key.clone(),
+            order_link_id: id.to_string(),
+            order_type: "Market".to_string(),
+            qty: size,
+            side: side.to_string(),
+            symbol,
+            stop_px: trigger.to_string(),
+            base_price: base_price.to_string(),
+            trigger_by: "MarkPrice".to_string(),
+            reduce_only: true,
+            close_on_trigger: true,
+            time_in_force: "GoodTillCancel".to_string(),
+            timestamp,
+            sign: String::default(),
+        }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
+        let order_res = self.client
+            .post(format!("{}{}/stop-order/create", CONFIG.bybit_rest_url, CONTRACT.private_path()))
+            .header("Content-Type", "application/json")
+            .body(ord)
+            .send()
+            .await?
+            .text()
+            .await?;
+        // info!("conditional res: {}", order_res);
+        let ret = serde_json::from_str::<RestResponse<StopOrderResult>>(&order_res)?;
+        return Ok(ret);
+    }
+
+    pub async fn cancel_conditional(&self, symbol: String, order_link_id: Uuid) -> Result<RestResponse<StopOrderResult>, CancelOrderError> {
+        if *V5 {
+            let res = self.v5_post::<CancelOrderError>("/v5/order/cancel", &serde_json::json!({
+                "category": CONTRACT.category(),
+                "symbol": symbol,
+                "orderLinkId": order_link_id.to_string(),
+            })).await?;
+            let result = match serde_json::from_value::<TradeAck>(res.result.clone()) {
+                Ok(ack) if res.ret_code == 0 => Some(StopOrderResult { stop_order_id: ack.order_id }),
+                _ => None,
+            };
+            return Ok(res.into_rest(result));
+        }
+        let timestamp = self.calculate_server_time()?;
+        let can = CancelConditionalJSON {
+            api_key: self.auth.key.clone(),
+            order_link_id: order_link_id.to_string(),
+            symbol,
+            timestamp,
+            sign: String::default(),
+        }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
+        let cancel_res = self.client
+            .post(format!("{}{}/stop-order/cancel", CONFIG.bybit_rest_url, CONTRACT.private_path()))
+            .header("Content-Type", "application/json")
+            .body(can)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<StopOrderResult>>(&cancel_res)?;
+        return Ok(ret);
+    }
+}
@@ -8,8 +8,13 @@ use thiserror::Error;
 use crate::{backend::bybit::broker::MarketOrderJSON, strategy::types::Stage};
 use crate::config::CONFIG;
 use crate::SignRequestError;
+use crate::telemetry::{LATENCY, Venue, LatencyStage};
 
-use super::{Broker, OrderResult, LimitOrderJSON, RestResponse, CalculateServerTimeError, Side};
+use crate::backend::bybit::{V5, CONTRACT};
+use crate::backend::bybit::gateway::GATEWAY;
+use crate::backend::types::TimeInForce;
+
+use super::{Broker, OrderResult, OrderType, LimitOrderJSON, RestResponse, CalculateServerTimeError, Side, order_ack};
 
 #[derive(Error, Debug)]
 pub enum CreateOrderError {
@@ -41,6 +46,7 @@ impl Broker {
     ) -> Result<(RestResponse<OrderResult>, String), CreateOrderError> {
         // println!("[DEBUG] Sending limit order");
         let timestamp = self.calculate_server_time()?;
+        let position_idx = self.position_idx(match stage { Stage::Entry => side, Stage::Exit => !side });
         let side = match side {
             Side::Buy => "Buy",
             Side::Sell => "Sell",
@@ -50,6 +56,29 @@ impl Broker {
             Stage::Exit => true,
         };
         let time_in_force = "PostOnly".to_string();
+        let timer = LATENCY.sending(Venue::Bybit, id.to_string());
+        let args = serde_json::json!({
+            "category": CONTRACT.category(),
+            "symbol": symbol,
+            "side": side,
+            "orderType": "Limit",
+            "qty": size.to_string(),
+            "price": price.to_string(),
+            "timeInForce": time_in_force,
+            "orderLinkId": id.to_string(),
+            "reduceOnly": stage,
+            "positionIdx": position_idx,
+        });
+        if let Some(ret) = self.trade_create(&args, id, symbol.clone(), side, OrderType::Limit, Some(price), size, TimeInForce::PostOnly, stage).await {
+            LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
+            return Ok((ret, args.to_string()));
+        }
+        if *V5 {
+            let res = self.v5_post::<CreateOrderError>("/v5/order/create", &args).await?;
+            LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
+            let ret = order_ack(res.ret_code, res.ret_msg, res.result, id, symbol, side, OrderType::Limit, Some(price), size, TimeInForce::PostOnly, stage);
+            return Ok((ret, args.to_string()));
+        }
         let ord = LimitOrderJSON {
             api_key: self.auth.key.clone(),
             close_on_trigger: false,
@@ -66,13 +95,14 @@ impl Broker {
         }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
         // info!("limit ord: {:?}", ord);
         let order_res = self.client
-            .post(format!("{}/private/linear/order/create", CONFIG.bybit_rest_url))
+            .post(format!("{}{}/order/create", CONFIG.bybit_rest_url, CONTRACT.private_path()))
             .header("Content-Type", "application/json")
             .body(ord.clone())
             .send()
             .await?
             .text()
             .await?;
+        LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
         // info!("{} {} limit res: {}", side, stage, order_res);
         let ret =  serde_json::from_str::<RestResponse<OrderResult>>(&order_res)?;
         return Ok((ret, ord));
@@ -88,6 +118,7 @@ impl Broker {
     ) -> Result<RestResponse<OrderResult>, CreateOrderError> {
         // println!("[DEBUG] Sending market order");
         let timestamp = self.calculate_server_time()?;
+        let position_idx = self.position_idx(match stage { Stage::Entry => side, Stage::Exit => !side });
         let side = match side {
             Side::Buy => "Buy",
             Side::Sell => "Sell",
@@ -96,6 +127,27 @@ impl Broker {
             Stage::Entry => false,
             Stage::Exit => true,
         };
+        let timer = LATENCY.sending(Venue::Bybit, id.to_string());
+        let args = serde_json::json!({
+            "category": CONTRACT.category(),
+            "symbol": symbol,
+            "side": side,
+            "orderType": "Market",
+            "qty": size.to_string(),
+            "timeInForce": "GTC",
+            "orderLinkId": id.to_string(),
+            "reduceOnly": close,
+            "positionIdx": position_idx,
+        });
+        if let Some(ret) = self.trade_create(&args, id, symbol.clone(), side, OrderType::Market, None, size, TimeInForce::GoodTillCancel, close).await {
+            LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
+            return Ok(ret);
+        }
+        if *V5 {
+            let res = self.v5_post::<CreateOrderError>("/v5/order/create", &args).await?;
+            LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
+            return Ok(order_ack(res.ret_code, res.ret_msg, res.result, id, symbol, side, OrderType::Market, None, size, TimeInForce::GoodTillCancel, close));
+        }
         let ord = MarketOrderJSON {
             api_key: self.auth.key.clone(),
             close_on_trigger: false,
@@ -110,16 +162,33 @@ impl Broker {
             sign: String::default(),
         }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
         let order_res = self.client
-            .post(format!("{}/private/linear/order/create", CONFIG.bybit_rest_url))
+            .post(format!("{}{}/order/create", CONFIG.bybit_rest_url, CONTRACT.private_path()))
             .header("Content-Type", "application/json")
             .body(ord)
             .send()
             .await?
             .text()
             .await?;
+        LATENCY.since(Venue::Bybit, LatencyStage::RestAck, timer);
         // info!("market res: {}", order_res);
         let ret = serde_json::from_str::<RestResponse<OrderResult>>(&order_res)?;
         return Ok(ret);
     }
-}
 
+    /// Places an order over the trade websocket, None if it has to go over REST
+    async fn trade_create(
+        &self,
+        args: &serde_json::Value,
+        id: Uuid,
+        symbol: String,
+        side: &str,
+        order_type: OrderType,
+        price: Option<D128>,
+        size: f64,
+        time_in_force: TimeInForce,
+        reduce_only: bool,
+    ) -> Option<RestResponse<OrderResult>> {
+        let res = GATEWAY.request("order.create", args.clone()).await?;
+        Some(order_ack(res.ret_code, res.ret_msg, res.data, id, symbol, side, order_type, price, size, time_in_force, reduce_only))
+    }
+}
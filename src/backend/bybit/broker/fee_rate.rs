@@ -0,0 +1,47 @@
+use std::time::SystemTimeError;
+
+use hmac::{Mac, digest::InvalidLength};
+use thiserror::Error;
+
+use crate::{config::CONFIG, HmacSha256};
+use crate::backend::bybit::V5;
+
+use super::{Broker, FeeRate, RestResponse, CalculateServerTimeError};
+
+#[derive(Error, Debug)]
+pub enum FeeRateError {
+    #[error("Invalid mac key length")]
+    MacLengthError(#[from] InvalidLength),
+    #[error("Failed to send request")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("Failed to serialize response")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Failed to get system time")]
+    SystemTimeError(#[from] SystemTimeError),
+    #[error("Failed to calculate server time")]
+    CalculateServerTimeError(#[from] CalculateServerTimeError)
+}
+
+impl Broker {
+    /// The account's current rates for the symbol, these follow its VIP tier.
+    /// v2 only serves the rates of inverse contracts.
+    pub async fn fee_rate(&self, symbol: String) -> Result<RestResponse<FeeRate>, FeeRateError> {
+        if *V5 { return self.v5_fee_rate(symbol).await; }
+        let timestamp = self.calculate_server_time()?;
+        let mut mac = HmacSha256::new_from_slice(self.auth.secret.as_bytes())?;
+        mac.update(format!("api_key={}&symbol={}&timestamp={}", self.auth.key, symbol, timestamp).as_bytes());
+        let signature = format!("{:x}", mac.finalize().into_bytes());
+        let rate_res = self.client
+            .get(&format!(
+                    "{}/v2/private/position/fee-rate?api_key={}&symbol={}&timestamp={}&sign={}",
+                    CONFIG.bybit_rest_url, self.auth.key, symbol, timestamp, signature
+                ),
+            )
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<FeeRate>>(&rate_res)?;
+        return Ok(ret);
+    }
+}
@@ -6,6 +6,7 @@ use thiserror::Error;
 
 use crate::{config::CONFIG, HmacSha256};
 use crate::SignRequestError;
+use crate::backend::bybit::{CONTRACT, ContractType};
 
 use super::{RestResponse, QueryAllActiveOrdersResult, Broker, CalculateServerTimeError};
 
@@ -41,10 +42,14 @@ impl Broker {
             .as_bytes(),
         );
         let signature = format!("{:x}", mac.finalize().into_bytes());
+        let path = match *CONTRACT {
+            ContractType::Linear => "/private/linear/order/search",
+            ContractType::Inverse => "/v2/private/order",
+        };
         let orders_res = self.client
             .get(&format!(
-                        "{}/private/linear/order/search?api_key={}&symbol={}&timestamp={}&sign={}",
-                        CONFIG.bybit_rest_url, self.auth.key, symbol, timestamp, signature
+                        "{}{}?api_key={}&symbol={}&timestamp={}&sign={}",
+                        CONFIG.bybit_rest_url, path, self.auth.key, symbol, timestamp, signature
                 ),
             )
             .send()
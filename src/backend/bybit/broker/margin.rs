@@ -0,0 +1,147 @@
+use std::time::SystemTimeError;
+
+use hmac::Mac;
+use hmac::digest::InvalidLength;
+use thiserror::Error;
+
+use crate::{config::CONFIG, HmacSha256};
+use crate::SignRequestError;
+use crate::backend::bybit::{V5, CONTRACT, ContractType};
+
+use super::{Broker, RestResponse, RiskLimit, PositionListResult, SetLeverageJSON, InverseLeverageJSON, InversePositionResult, SwitchIsolatedJSON, CalculateServerTimeError};
+
+#[derive(Error, Debug)]
+pub enum MarginError {
+    #[error("Invalid mac key length")]
+    MacLengthError(#[from] InvalidLength),
+    #[error("Failed to send request")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("Failed to serialize response")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Failed to sign the request")]
+    SignRequestError(#[from] SignRequestError),
+    #[error("Failed to get system time")]
+    SystemTimeError(#[from] SystemTimeError),
+    #[error("Failed to calculate server time")]
+    CalculateServerTimeError(#[from] CalculateServerTimeError)
+}
+
+/// Leverage is set per position side, we always keep both sides the same
+impl Broker {
+    pub async fn set_leverage(&self, symbol: String, leverage: f64) -> Result<RestResponse<serde_json::Value>, MarginError> {
+        if *V5 {
+            let res = self.v5_post::<MarginError>("/v5/position/set-leverage", &serde_json::json!({
+                "category": CONTRACT.category(),
+                "symbol": symbol,
+                "buyLeverage": leverage.to_string(),
+                "sellLeverage": leverage.to_string(),
+            })).await?;
+            let result = Some(res.result.clone());
+            return Ok(res.into_rest(result));
+        }
+        let timestamp = self.calculate_server_time()?;
+        let (req, path) = match *CONTRACT {
+            ContractType::Linear => (SetLeverageJSON {
+                api_key: self.auth.key.clone(),
+                buy_leverage: leverage,
+                sell_leverage: leverage,
+                symbol,
+                timestamp,
+                sign: String::default(),
+            }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?, "/private/linear/position/set-leverage"),
+            ContractType::Inverse => (InverseLeverageJSON {
+                api_key: self.auth.key.clone(),
+                leverage,
+                symbol,
+                timestamp,
+                sign: String::default(),
+            }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?, "/v2/private/position/leverage/save"),
+        };
+        let leverage_res = self.client
+            .post(format!("{}{}", CONFIG.bybit_rest_url, path))
+            .header("Content-Type", "application/json")
+            .body(req)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<serde_json::Value>>(&leverage_res)?;
+        return Ok(ret);
+    }
+
+    /// Switching margin mode resets leverage, so it's sent along
+    pub async fn switch_isolated(&self, symbol: String, is_isolated: bool, leverage: f64) -> Result<RestResponse<serde_json::Value>, MarginError> {
+        if *V5 {
+            let res = self.v5_post::<MarginError>("/v5/position/switch-isolated", &serde_json::json!({
+                "category": CONTRACT.category(),
+                "symbol": symbol,
+                "tradeMode": if is_isolated { 1 } else { 0 },
+                "buyLeverage": leverage.to_string(),
+                "sellLeverage": leverage.to_string(),
+            })).await?;
+            let result = Some(res.result.clone());
+            return Ok(res.into_rest(result));
+        }
+        let timestamp = self.calculate_server_time()?;
+        let req = SwitchIsolatedJSON {
+            api_key: self.auth.key.clone(),
+            buy_leverage: leverage,
+            is_isolated,
+            sell_leverage: leverage,
+            symbol,
+            timestamp,
+            sign: String::default(),
+        }.get_signed_data(self.auth.secret.clone(), self.auth.key.clone())?;
+        let switch_res = self.client
+            .post(format!("{}{}/position/switch-isolated", CONFIG.bybit_rest_url, CONTRACT.private_path()))
+            .header("Content-Type", "application/json")
+            .body(req)
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<serde_json::Value>>(&switch_res)?;
+        return Ok(ret);
+    }
+
+    /// Current leverage and margin mode live on the position, one entry per side
+    pub async fn position_list(&self, symbol: String) -> Result<RestResponse<Vec<PositionListResult>>, MarginError> {
+        if *V5 { return self.v5_position_list(symbol).await; }
+        let timestamp = self.calculate_server_time()?;
+        let mut mac = HmacSha256::new_from_slice(self.auth.secret.as_bytes())?;
+        mac.update(format!("api_key={}&symbol={}&timestamp={}", self.auth.key, symbol, timestamp).as_bytes());
+        let signature = format!("{:x}", mac.finalize().into_bytes());
+        let position_res = self.client
+            .get(&format!(
+                    "{}{}/position/list?api_key={}&symbol={}&timestamp={}&sign={}",
+                    CONFIG.bybit_rest_url, CONTRACT.private_path(), self.auth.key, symbol, timestamp, signature
+                ),
+            )
+            .send()
+            .await?
+            .text()
+            .await?;
+        if *CONTRACT == ContractType::Inverse {
+            let ret = serde_json::from_str::<RestResponse<InversePositionResult>>(&position_res)?;
+            return Ok(ret.map(|position| vec![PositionListResult::from(position)]));
+        }
+        let ret = serde_json::from_str::<RestResponse<Vec<PositionListResult>>>(&position_res)?;
+        return Ok(ret);
+    }
+
+    /// Risk limit tiers, Bybit's equivalent of leverage brackets
+    pub async fn risk_limits(&self, symbol: String) -> Result<RestResponse<Vec<RiskLimit>>, MarginError> {
+        if *V5 { return self.v5_risk_limits(symbol).await; }
+        let risk_res = self.client
+            .get(&format!("{}{}?symbol={}", CONFIG.bybit_rest_url, match *CONTRACT {
+                ContractType::Linear => "/public/linear/risk-limit",
+                ContractType::Inverse => "/v2/public/risk-limit/list",
+            }, symbol))
+            .send()
+            .await?
+            .text()
+            .await?;
+        let ret = serde_json::from_str::<RestResponse<Vec<RiskLimit>>>(&risk_res)?;
+        return Ok(ret);
+    }
+}
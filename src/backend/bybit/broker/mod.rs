@@ -1,24 +1,35 @@
 mod types;
 mod balance;
 mod cancel_order;
+mod conditional;
 mod create_order;
 mod get_order;
+mod margin;
+mod fee_rate;
 pub mod ping;
+mod v5;
 
 use reqwest::{Client, Error};
 use std::sync::PoisonError;
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::SystemTime;
 use std::time::SystemTimeError;
 use std::time::UNIX_EPOCH;
 use thiserror::Error;
 
+use crate::backend::bybit::{V5, CONTRACT, ContractType};
+
 pub use self::types::*;
 pub use self::balance::*;
 pub use self::cancel_order::*;
+pub use self::conditional::*;
 pub use self::create_order::*;
 pub use self::get_order::*;
+pub use self::margin::*;
+pub use self::fee_rate::*;
 pub use self::ping::*;
+pub use self::v5::*;
 
 #[derive(Error, Debug)]
 pub enum SetServerOffsetError {
@@ -47,6 +58,8 @@ pub struct Broker {
     client: Client,
     /// The timestamp offset from the current time
     server_timestamp_offset: RwLock<i128>,
+    /// Whether the account holds a position per side, v2 linear is always hedged and inverse always one-way
+    hedge_mode: AtomicBool,
 }
 
 impl Broker {
@@ -54,6 +67,7 @@ impl Broker {
     pub fn new(url: String, key: String, secret: String) -> Result<Self, Error>{
         Ok(Broker {
             server_timestamp_offset: RwLock::new(0),
+            hedge_mode: AtomicBool::new(*CONTRACT == ContractType::Linear),
             auth: BybitAuth { url, key, secret },
             client: reqwest::Client::builder().https_only(true).pool_max_idle_per_host(4).pool_idle_timeout(None).use_rustls_tls().build()?,
         })
@@ -73,6 +87,30 @@ impl Broker {
         }?;
         return Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis().saturating_add_signed(server_time));
     }
+
+    /// Picks the hedge mode up from the v5 position list, v2 keeps the contract type's mode
+    pub async fn detect_position_mode(&self, symbol: String) -> bool {
+        if *V5 {
+            match self.v5_position_mode(symbol).await {
+                Some(hedge) => self.hedge_mode.store(hedge, Ordering::SeqCst),
+                None => info!("Couldn't query position mode, assuming {} mode", if self.hedge_mode() { "hedge" } else { "one-way" }),
+            }
+        }
+        self.hedge_mode()
+    }
+
+    pub fn hedge_mode(&self) -> bool {
+        self.hedge_mode.load(Ordering::SeqCst)
+    }
+
+    /// v5 positionIdx of the position an order trades, 1 and 2 are the buy and sell sides in hedge mode and 0 is one-way
+    pub fn position_idx(&self, position_side: Side) -> u8 {
+        match (self.hedge_mode(), position_side) {
+            (false, _) => 0,
+            (true, Side::Buy) => 1,
+            (true, Side::Sell) => 2,
+        }
+    }
 }
 
 lazy_static! {
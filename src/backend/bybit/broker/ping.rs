@@ -1,3 +1,5 @@
+use crate::backend::bybit::V5;
+
 use super::Broker;
 
 
@@ -5,7 +7,8 @@ use super::Broker;
 
 impl Broker {
     pub async fn ping(&self) -> Result<(), reqwest::Error> {
-        self.client.get(&format!("{}/v2/public/time", self.auth.url)).send().await?;
+        let path = if *V5 { "/v5/market/time" } else { "/v2/public/time" };
+        self.client.get(&format!("{}{}", self.auth.url, path)).send().await?;
         return Ok(());
     }
 }
\ No newline at end of file
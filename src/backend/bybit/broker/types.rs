@@ -7,7 +7,7 @@ use proc_macros::BybitSignable;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::backend::bybit::errors::PerpetualStatus;
+use crate::backend::bybit::errors::{PerpetualStatus, deserialize_status};
 use crate::SignRequestError;
 use crate::backend::types::TimeInForce;
 
@@ -73,6 +73,17 @@ pub struct CancelJSON {
     pub sign: String,
 }
 
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct ReplaceJSON {
+    pub api_key: String,
+    pub order_link_id: String,
+    pub symbol: String,
+    pub p_r_price: String,
+    pub p_r_qty: f64,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
 #[derive(Serialize, Debug, BybitSignable)]
 pub struct LimitOrderJSON {
     pub api_key: String,
@@ -104,14 +115,134 @@ pub struct MarketOrderJSON {
     pub sign: String,
 }
 
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct ConditionalOrderJSON {
+    pub api_key: String,
+    pub order_link_id: String,
+    pub order_type: String,
+    pub qty: f64,
+    pub side: String,
+    pub symbol: String,
+    pub stop_px: String,
+    pub base_price: String,
+    pub trigger_by: String,
+    pub reduce_only: bool,
+    pub close_on_trigger: bool,
+    pub time_in_force: String,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct CancelConditionalJSON {
+    pub api_key: String,
+    pub order_link_id: String,
+    pub symbol: String,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct SetLeverageJSON {
+    pub api_key: String,
+    pub buy_leverage: f64,
+    pub sell_leverage: f64,
+    pub symbol: String,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
+/// Inverse positions are one-way, so there's a single leverage
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct InverseLeverageJSON {
+    pub api_key: String,
+    pub leverage: f64,
+    pub symbol: String,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
+#[derive(Serialize, Debug, BybitSignable)]
+pub struct SwitchIsolatedJSON {
+    pub api_key: String,
+    pub buy_leverage: f64,
+    pub is_isolated: bool,
+    pub sell_leverage: f64,
+    pub symbol: String,
+    pub timestamp: u128,
+    pub sign: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PositionListResult {
+    pub symbol: String,
+    pub side: Side,
+    pub size: D128,
+    pub entry_price: D128,
+    pub leverage: D128,
+    pub is_isolated: bool,
+    pub position_value: D128,
+    pub liq_price: D128,
+}
+
+/// The inverse position list is a single one-way entry, its side is None when flat
+#[derive(Deserialize, Debug, Clone)]
+pub struct InversePositionResult {
+    pub symbol: String,
+    pub side: String,
+    pub size: D128,
+    pub entry_price: D128,
+    pub leverage: D128,
+    pub is_isolated: bool,
+    pub position_value: D128,
+    pub liq_price: D128,
+}
+
+impl From<InversePositionResult> for PositionListResult {
+    fn from(position: InversePositionResult) -> Self {
+        PositionListResult {
+            symbol: position.symbol,
+            side: if position.side == "Sell" { Side::Sell } else { Side::Buy },
+            size: position.size,
+            entry_price: position.entry_price,
+            leverage: position.leverage,
+            is_isolated: position.is_isolated,
+            position_value: position.position_value,
+            liq_price: position.liq_price,
+        }
+    }
+}
+
+/// One risk limit tier, limit is the largest position value it covers
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct RiskLimit {
+    pub id: i64,
+    pub limit: D128,
+    pub maintain_margin: D128,
+    pub starting_margin: D128,
+    pub max_leverage: D128,
+}
+
+/// The account's maker and taker rates, fractions of position value and negative when they're rebates
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct FeeRate {
+    pub taker_fee_rate: D128,
+    pub maker_fee_rate: D128,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopOrderResult {
+    pub stop_order_id: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct CancelResult {
-    order_id: String,
+    pub order_id: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ReplaceResult {
-    order_id: String,
+    pub order_id: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -132,14 +263,23 @@ pub struct OrderResult {
     pub cum_exec_qty: D128,
     pub cum_exec_value: D128,
     pub cum_exec_fee: D128,
+    /// Inverse results leave out the close flags and trading stops
+    #[serde(default)]
     pub reduce_only: bool,
+    #[serde(default)]
     pub close_on_trigger: bool,
     pub order_link_id: Uuid,
+    #[serde(alias = "created_at")]
     pub created_time: String,
+    #[serde(alias = "updated_at")]
     pub updated_time: String,
+    #[serde(default)]
     pub take_profit: D128,
+    #[serde(default)]
     pub stop_loss: D128,
+    #[serde(default)]
     pub tp_trigger_by: String,
+    #[serde(default)]
     pub sl_trigger_by: String,
 }
 
@@ -220,8 +360,28 @@ pub struct ExtFields {
     pub xreq_type: String,
 }
 
+/// Response frame from the v5 trade websocket, the req_id ties it back to the request
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeResponse {
+    pub req_id: Option<String>,
+    pub ret_code: u32,
+    pub ret_msg: String,
+    pub op: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeAck {
+    pub order_id: String,
+    pub order_link_id: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct RestResponse<T> {
+    #[serde(deserialize_with = "deserialize_status")]
     pub ret_code: PerpetualStatus,
     pub ret_msg: String,
     pub ext_code: String,
@@ -233,6 +393,70 @@ pub struct RestResponse<T> {
     pub rate_limit: Option<i32>,
 }
 
+impl<T> RestResponse<T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> RestResponse<U> {
+        RestResponse {
+            ret_code: self.ret_code,
+            ret_msg: self.ret_msg,
+            ext_code: self.ext_code,
+            ext_info: self.ext_info,
+            result: self.result.map(f),
+            time_now: self.time_now,
+            rate_limit_status: self.rate_limit_status,
+            rate_limit_reset_ms: self.rate_limit_reset_ms,
+            rate_limit: self.rate_limit,
+        }
+    }
+
+    /// Shapes a trade websocket response like the REST one, so nothing past the broker cares which way it went
+    pub fn from_trade(ret_code: PerpetualStatus, ret_msg: String, result: Option<T>) -> RestResponse<T> {
+        RestResponse {
+            ret_code,
+            ret_msg,
+            ext_code: String::default(),
+            ext_info: String::default(),
+            result,
+            time_now: String::default(),
+            rate_limit_status: None,
+            rate_limit_reset_ms: None,
+            rate_limit: None,
+        }
+    }
+}
+
+impl OrderResult {
+    /// What an accepted order looks like before anything happens to it.
+    /// The trade websocket only acks with ids, the rest is what we sent.
+    pub fn accepted(order_id: Uuid, order_link_id: Uuid, symbol: String, side: Side, order_type: OrderType, price: Option<D128>, qty: D128, time_in_force: TimeInForce, reduce_only: bool) -> OrderResult {
+        OrderResult {
+            order_id,
+            user_id: 0,
+            symbol,
+            side,
+            order_type,
+            price,
+            qty,
+            time_in_force,
+            order_status: CreateOrderStatus::Created,
+            last_exec_price: D128::ZERO,
+            leaves_qty: qty,
+            reject_reason: None,
+            cum_exec_qty: D128::ZERO,
+            cum_exec_value: D128::ZERO,
+            cum_exec_fee: D128::ZERO,
+            reduce_only,
+            close_on_trigger: false,
+            order_link_id,
+            created_time: String::default(),
+            updated_time: String::default(),
+            take_profit: D128::ZERO,
+            stop_loss: D128::ZERO,
+            tp_trigger_by: String::default(),
+            sl_trigger_by: String::default(),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
 pub enum Side {
     #[serde(alias="BUY")]
@@ -280,6 +504,15 @@ impl TryFrom<String> for Side {
     }
 }
 
+impl From<Side> for crate::backend::types::Side {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => crate::backend::types::Side::Buy,
+            Side::Sell => crate::backend::types::Side::Sell,
+        }
+    }
+}
+
 impl Side {
     /// Helps you deside
     pub fn deside<T>(&self, buy: T, sell: T) -> T {
@@ -0,0 +1,278 @@
+/// Requests against the v5 unified API.
+/// v5 signs headers instead of body fields, sends every number as a string and wraps results differently,
+/// so responses are reshaped into the v2 types here and nothing past the broker knows which API it talked to.
+/// Selected with BYBIT_API=v5.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use dec::D128;
+use hmac::Mac;
+use hmac::digest::InvalidLength;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::HmacSha256;
+use crate::backend::bybit::CONTRACT;
+use crate::backend::bybit::errors::PerpetualStatus;
+use crate::backend::types::TimeInForce;
+use crate::config::CONFIG;
+
+use super::{Broker, Balance, CalculateServerTimeError, MarginError, FeeRate, OrderResult, OrderType, PositionListResult, RestResponse, RiskLimit, Side, TradeAck};
+
+lazy_static! {
+    static ref RECV_WINDOW: String = "5000".to_string();
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5Response {
+    pub ret_code: u32,
+    pub ret_msg: String,
+    #[serde(default)]
+    pub result: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct V5List<T> {
+    pub list: Vec<T>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5Position {
+    pub symbol: String,
+    pub side: String,
+    pub size: String,
+    pub avg_price: String,
+    pub leverage: String,
+    /// 0 cross, 1 isolated
+    pub trade_mode: i32,
+    pub position_value: String,
+    pub liq_price: String,
+    /// 0 one-way, 1 hedge buy side, 2 hedge sell side
+    pub position_idx: i32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5RiskLimit {
+    pub id: i64,
+    pub risk_limit_value: String,
+    pub maintenance_margin: String,
+    pub initial_margin: String,
+    pub max_leverage: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5Wallet {
+    pub coin: Vec<V5Coin>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5Coin {
+    pub coin: String,
+    pub equity: String,
+    pub wallet_balance: String,
+    pub available_to_withdraw: String,
+    pub total_order_i_m: String,
+    pub total_position_i_m: String,
+    pub unrealised_pnl: String,
+    pub cum_realised_pnl: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5FeeRate {
+    pub symbol: String,
+    pub taker_fee_rate: String,
+    pub maker_fee_rate: String,
+}
+
+/// v5 leaves fields that don't apply as empty strings
+pub fn num(value: &str) -> D128 {
+    D128::from_str(value).unwrap_or(D128::ZERO)
+}
+
+impl V5Response {
+    pub fn into_rest<T>(self, result: Option<T>) -> RestResponse<T> {
+        RestResponse::from_trade(PerpetualStatus::from_v5(self.ret_code), self.ret_msg, result)
+    }
+
+    fn list<T: for<'de> Deserialize<'de>>(&self) -> Option<Vec<T>> {
+        if self.ret_code != 0 { return None; }
+        serde_json::from_value::<V5List<T>>(self.result.clone()).ok().map(|l| l.list)
+    }
+}
+
+impl From<V5Position> for PositionListResult {
+    fn from(position: V5Position) -> Self {
+        let side = match (position.side.as_str(), position.position_idx) {
+            ("Sell", _) | ("", 2) => Side::Sell,
+            _ => Side::Buy,
+        };
+        PositionListResult {
+            symbol: position.symbol,
+            side,
+            size: num(&position.size),
+            entry_price: num(&position.avg_price),
+            leverage: num(&position.leverage),
+            is_isolated: position.trade_mode == 1,
+            position_value: num(&position.position_value),
+            liq_price: num(&position.liq_price),
+        }
+    }
+}
+
+impl From<V5RiskLimit> for RiskLimit {
+    fn from(limit: V5RiskLimit) -> Self {
+        RiskLimit {
+            id: limit.id,
+            limit: num(&limit.risk_limit_value),
+            maintain_margin: num(&limit.maintenance_margin),
+            starting_margin: num(&limit.initial_margin),
+            max_leverage: num(&limit.max_leverage),
+        }
+    }
+}
+
+impl From<V5FeeRate> for FeeRate {
+    fn from(rate: V5FeeRate) -> Self {
+        FeeRate {
+            taker_fee_rate: num(&rate.taker_fee_rate),
+            maker_fee_rate: num(&rate.maker_fee_rate),
+        }
+    }
+}
+
+impl From<V5Coin> for Balance {
+    fn from(coin: V5Coin) -> Self {
+        let order_margin = num(&coin.total_order_i_m);
+        let position_margin = num(&coin.total_position_i_m);
+        let mut balance = Balance::new();
+        balance.equity = num(&coin.equity);
+        balance.available_balance = num(&coin.available_to_withdraw);
+        balance.used_margin = order_margin + position_margin;
+        balance.order_margin = order_margin;
+        balance.position_margin = position_margin;
+        balance.wallet_balance = num(&coin.wallet_balance);
+        balance.unrealised_pnl = num(&coin.unrealised_pnl);
+        balance.cum_realised_pnl = num(&coin.cum_realised_pnl);
+        balance
+    }
+}
+
+/// The trade websocket and v5 REST only ack orders with ids, the rest of the result is what we sent
+pub fn order_ack(
+    ret_code: u32,
+    ret_msg: String,
+    data: serde_json::Value,
+    id: Uuid,
+    symbol: String,
+    side: &str,
+    order_type: OrderType,
+    price: Option<D128>,
+    size: f64,
+    time_in_force: TimeInForce,
+    reduce_only: bool,
+) -> RestResponse<OrderResult> {
+    let result = match (serde_json::from_value::<TradeAck>(data), Side::try_from(side.to_string())) {
+        (Ok(ack), Ok(side)) if ret_code == 0 => Some(OrderResult::accepted(
+            Uuid::parse_str(&ack.order_id).unwrap_or(Uuid::nil()), id, symbol,
+            side, order_type, price, D128::from(size), time_in_force, reduce_only,
+        )),
+        _ => None,
+    };
+    RestResponse::from_trade(PerpetualStatus::from_v5(ret_code), ret_msg, result)
+}
+
+impl Broker {
+    /// Signature over timestamp, key, recv window and the payload, the json body for POST and the query string for GET
+    fn v5_sign(&self, timestamp: u128, payload: &str) -> Result<String, InvalidLength> {
+        let mut mac = HmacSha256::new_from_slice(self.auth.secret.as_bytes())?;
+        mac.update(format!("{}{}{}{}", timestamp, self.auth.key, *RECV_WINDOW, payload).as_bytes());
+        Ok(format!("{:x}", mac.finalize().into_bytes()))
+    }
+
+    pub async fn v5_post<E>(&self, path: &str, body: &serde_json::Value) -> Result<V5Response, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let timestamp = self.calculate_server_time()?;
+        let body = body.to_string();
+        let signature = self.v5_sign(timestamp, &body)?;
+        let res = self.client
+            .post(format!("{}{}", CONFIG.bybit_rest_url, path))
+            .header("Content-Type", "application/json")
+            .header("X-BAPI-API-KEY", self.auth.key.clone())
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", RECV_WINDOW.clone())
+            .header("X-BAPI-SIGN", signature)
+            .body(body)
+            .send()
+            .await?
+            .text()
+            .await?;
+        Ok(serde_json::from_str::<V5Response>(&res)?)
+    }
+
+    pub async fn v5_get<E>(&self, path: &str, query: &str) -> Result<V5Response, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let timestamp = self.calculate_server_time()?;
+        let signature = self.v5_sign(timestamp, query)?;
+        let res = self.client
+            .get(format!("{}{}?{}", CONFIG.bybit_rest_url, path, query))
+            .header("X-BAPI-API-KEY", self.auth.key.clone())
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", RECV_WINDOW.clone())
+            .header("X-BAPI-SIGN", signature)
+            .send()
+            .await?
+            .text()
+            .await?;
+        Ok(serde_json::from_str::<V5Response>(&res)?)
+    }
+
+    pub async fn v5_position_list<E>(&self, symbol: String) -> Result<RestResponse<Vec<PositionListResult>>, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let res = self.v5_get::<E>("/v5/position/list", &format!("category={}&symbol={}", CONTRACT.category(), symbol)).await?;
+        let positions = res.list::<V5Position>().map(|l| l.into_iter().map(PositionListResult::from).collect());
+        Ok(res.into_rest(positions))
+    }
+
+    /// Hedge mode lists a position per side under positionIdx 1 and 2, None when the list can't be read
+    pub async fn v5_position_mode(&self, symbol: String) -> Option<bool> {
+        let res = self.v5_get::<MarginError>("/v5/position/list", &format!("category={}&symbol={}", CONTRACT.category(), symbol)).await.ok()?;
+        let positions = res.list::<V5Position>()?;
+        Some(positions.iter().any(|position| position.position_idx != 0))
+    }
+
+    pub async fn v5_risk_limits<E>(&self, symbol: String) -> Result<RestResponse<Vec<RiskLimit>>, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let res = self.v5_get::<E>("/v5/market/risk-limit", &format!("category={}&symbol={}", CONTRACT.category(), symbol)).await?;
+        let limits = res.list::<V5RiskLimit>().map(|l| l.into_iter().map(RiskLimit::from).collect());
+        Ok(res.into_rest(limits))
+    }
+
+    pub async fn v5_fee_rate<E>(&self, symbol: String) -> Result<RestResponse<FeeRate>, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let res = self.v5_get::<E>("/v5/account/fee-rate", &format!("category={}&symbol={}", CONTRACT.category(), symbol)).await?;
+        let rate = res.list::<V5FeeRate>().and_then(|l| l.into_iter().find(|r| r.symbol == symbol)).map(FeeRate::from);
+        Ok(res.into_rest(rate))
+    }
+
+    /// Unified account balances by coin, same shape as the v2 wallet
+    pub async fn v5_balance<E>(&self, coin: Option<String>) -> Result<RestResponse<HashMap<String, Balance>>, E>
+    where E: From<InvalidLength> + From<reqwest::Error> + From<serde_json::Error> + From<CalculateServerTimeError> {
+        let query = match coin {
+            Some(coin) => format!("accountType=UNIFIED&coin={}", coin),
+            None => "accountType=UNIFIED".to_string(),
+        };
+        let res = self.v5_get::<E>("/v5/account/wallet-balance", &query).await?;
+        let balances = res.list::<V5Wallet>().map(|wallets| wallets.into_iter()
+            .flat_map(|w| w.coin.into_iter())
+            .map(|c| (c.coin.clone(), Balance::from(c)))
+            .collect());
+        Ok(res.into_rest(balances))
+    }
+}
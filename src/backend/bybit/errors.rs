@@ -1,8 +1,11 @@
 
+use serde::{Deserialize, Deserializer};
 use serde_repr::Deserialize_repr;
 
 
-/** I used the wrong market type */
+/// Inverse perpetual codes, mapped onto the closest linear status when they come back
+#[derive(Debug, Deserialize_repr, PartialEq, Clone, Copy)]
+#[repr(u32)]
 pub enum InversePerpetualErrors {
     RequestFailed = 10001,
     RequestUnauthorized = 10002,
@@ -175,4 +178,222 @@ pub enum PerpetualStatus {
     MaxTwentySlTpOrdersUnderPartialPosMode = 130159,
     RiskAdjustmentFailedSizeExceedsLimit = 132011,
     RiskLimitNotChanged = 134026,
-}
\ No newline at end of file
+}
+/// Number of times a Retry or Resync outcome gets to resend before the order is dropped
+pub const MAX_RETRIES: usize = 2;
+
+/// What the strategy should do with an order or cancel that came back with a given status
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StatusOutcome {
+    /// Accepted, or nothing to do about it
+    Ok,
+    /// Transient, send it again
+    Retry,
+    /// The request may have landed. Orders aren't resent, a resend goes out under a new order_link_id
+    /// and could double up, so they're left for the reconcile to find by their link id. Cancels are sent again.
+    Unknown,
+    /// Rejected for something about the order itself, release it
+    DropOrder,
+    /// Timestamp drift, fix the server offset then send it again
+    Resync,
+    /// Account or key level problem, stop opening anything new
+    Halt,
+}
+
+impl PerpetualStatus {
+    /// Maps v5 codes, what the trade websocket answers with, onto the closest v2 status
+    pub fn from_v5(code: u32) -> PerpetualStatus {
+        match code {
+            0 => PerpetualStatus::Ok,
+            10000 => PerpetualStatus::BackendResponseTimeout,
+            10001 => PerpetualStatus::ParamsError,
+            10002 => PerpetualStatus::RequestNotAuthorized,
+            10003 | 10005 => PerpetualStatus::ApiKeyPermDenied,
+            10004 => PerpetualStatus::InvalidSign,
+            10006 => PerpetualStatus::TooManyRequests,
+            10016 => PerpetualStatus::ServiceNotAvailable,
+            10018 => PerpetualStatus::ExceededIpRateLimit,
+            110001 => PerpetualStatus::NoSuchOrderOrTooLate,
+            110003 => PerpetualStatus::OrderPriceOutOfRange,
+            110004 | 110007 | 110012 => PerpetualStatus::InsufficientAvailableBalance,
+            110008 => PerpetualStatus::OrderAlreadyCancelled,
+            110017 => PerpetualStatus::CloseOrderSideLargerThanPosLeavingQty,
+            110020 => PerpetualStatus::OverOrderLimit,
+            _ => PerpetualStatus::ParamsError,
+        }
+    }
+
+    /// Maps codes only the inverse endpoints send onto the closest linear status
+    pub fn from_inverse(error: InversePerpetualErrors) -> PerpetualStatus {
+        match error {
+            InversePerpetualErrors::RequestFailed => PerpetualStatus::ParamsError,
+            InversePerpetualErrors::RequestUnauthorized => PerpetualStatus::RequestNotAuthorized,
+            InversePerpetualErrors::TooManyRequests => PerpetualStatus::TooManyRequests,
+            InversePerpetualErrors::InvalidSignature => PerpetualStatus::InvalidSign,
+            InversePerpetualErrors::ApiKeyPermDenied
+            | InversePerpetualErrors::InvalidApiKeyFormat
+            | InversePerpetualErrors::InvalidApiKeyOrIp => PerpetualStatus::ApiKeyPermDenied,
+            InversePerpetualErrors::SystemNotRespondingContactSupport => PerpetualStatus::SystemNotRespondingContactSupport,
+            InversePerpetualErrors::BackendResponseTimeout => PerpetualStatus::BackendResponseTimeout,
+            InversePerpetualErrors::RequestIpMismatch => PerpetualStatus::RequestIpMismatch,
+            InversePerpetualErrors::ServiceNotAvailable => PerpetualStatus::ServiceNotAvailable,
+            InversePerpetualErrors::PathOrMethodInvalid => PerpetualStatus::PathOrMethodInvalid,
+            InversePerpetualErrors::ExceededIpRateLimit => PerpetualStatus::ExceededIpRateLimit,
+            InversePerpetualErrors::OrderNotExistsSic
+            | InversePerpetualErrors::OrderDoesNotExist
+            | InversePerpetualErrors::CancelationRejected => PerpetualStatus::NoSuchOrderOrTooLate,
+            InversePerpetualErrors::NeedQtyBetweenZeroAndMillion
+            | InversePerpetualErrors::QtyMustBeMoreThanMin
+            | InversePerpetualErrors::QtyMustBeLessThanMax => PerpetualStatus::OrderQtyOutOfRange,
+            InversePerpetualErrors::PriceExceedsMax
+            | InversePerpetualErrors::PriceExceedsMin => PerpetualStatus::OrderPriceOutOfRange,
+            InversePerpetualErrors::InvalidOrderType => PerpetualStatus::OrderTypeInvalid,
+            InversePerpetualErrors::NoPositionFound => PerpetualStatus::PositionSizeZero,
+            InversePerpetualErrors::InsufficientWalletBalance => PerpetualStatus::InsufficientAvailableBalance,
+            InversePerpetualErrors::OperationNotAllowedPositionUndergoingLiquidation
+            | InversePerpetualErrors::PositionInLiquidationOrADL => PerpetualStatus::ActionDeniedPositionInLiquidation,
+            InversePerpetualErrors::OperationNotAllowedPositionUndergoingADL => PerpetualStatus::ActionDeniedPositionInAdl,
+            InversePerpetualErrors::InvalidCloseQtyCantBeGreaterThanSize => PerpetualStatus::CloseOrderSideLargerThanPosLeavingQty,
+            InversePerpetualErrors::EstimatedFillCantBeLowerThanCurrentBuyLiqPrice
+            | InversePerpetualErrors::EstimatedFillCantBeHigherThanCurrentSellLiqPrice => PerpetualStatus::WillBeTriggeredLiqAfterOrderComplete,
+            _ => PerpetualStatus::ParamsError,
+        }
+    }
+
+    pub fn outcome(&self) -> StatusOutcome {
+        match self {
+            PerpetualStatus::Ok
+            | PerpetualStatus::OrderAlreadyCancelled
+            | PerpetualStatus::OrderSuccessfulButSlOrTpFailed
+            | PerpetualStatus::DepositReqHandled
+            | PerpetualStatus::WithdrawReqHandled
+            | PerpetualStatus::RotateReqHandled => StatusOutcome::Ok,
+
+            PerpetualStatus::RequestNotAuthorized => StatusOutcome::Resync,
+
+            PerpetualStatus::BackendResponseTimeout
+            | PerpetualStatus::UnknownCreateOrderRequest
+            | PerpetualStatus::UnknownPreCreateOrderRequest => StatusOutcome::Unknown,
+
+            PerpetualStatus::ServiceNotAvailable
+            | PerpetualStatus::NotGetPosition
+            | PerpetualStatus::TooFreqToCancelTryLater
+            | PerpetualStatus::TryAgainLater
+            | PerpetualStatus::UknownCancelOrderRequest
+            | PerpetualStatus::UnknownCancelAllRequest
+            | PerpetualStatus::UnknownLiqExecuteReqRequestParamNotMatch
+            | PerpetualStatus::UnknownQueryOrderRequest
+            | PerpetualStatus::UnmatchedTriggerToActiveImplRequest
+            | PerpetualStatus::UnknownAddMarginRequest
+            | PerpetualStatus::UnknownCalculatePositionPnlRequest
+            | PerpetualStatus::UnknownQueryAssetImplRequest
+            | PerpetualStatus::UnknownQueryPositionListRequest
+            | PerpetualStatus::UnknownSetAutoAddMarginRequest
+            | PerpetualStatus::UnknownSetFeeRateRequest
+            | PerpetualStatus::UnknownSetLeverageRequest
+            | PerpetualStatus::UnknownSetMarginRequest
+            | PerpetualStatus::UnknownSetOpenLimitRequest
+            | PerpetualStatus::UnknownSetTpSITRequest
+            | PerpetualStatus::UnknownSettleFundingFeeReqRequest
+            | PerpetualStatus::UnknownSetPositionModeRequest
+            | PerpetualStatus::UnknownWalletDepositRequest
+            | PerpetualStatus::UnknownWalletWithdrawRequest
+            | PerpetualStatus::UnknownRotateRealisedPnlRequest
+            | PerpetualStatus::UnknownAdlExecuteRequest
+            | PerpetualStatus::UnknownAdlCleanReqRequest => StatusOutcome::Retry,
+
+            // Retrying into a rate limit just burns more of it, the strategy will replace the order next tick
+            PerpetualStatus::TooManyRequests
+            | PerpetualStatus::ExceededIpRateLimit
+            | PerpetualStatus::ParamsError
+            | PerpetualStatus::OrderDoesntExistOrTooLateToCancel
+            | PerpetualStatus::OverOrderLimit
+            | PerpetualStatus::OrderQtyOutOfRangeDuplicate
+            | PerpetualStatus::PzStatusNotNormal
+            | PerpetualStatus::OrderNumberOutOfRange
+            | PerpetualStatus::OrderPriceOutOfRange
+            | PerpetualStatus::OrderQtyOutOfRange
+            | PerpetualStatus::OrderPriceOutOfRangeDuplicate
+            | PerpetualStatus::OrderTypeInvalid
+            | PerpetualStatus::ContractsBelowMinLimitSurpassed
+            | PerpetualStatus::NoSuchOrderOrTooLate
+            | PerpetualStatus::StopOrderTrailValueInvalid
+            | PerpetualStatus::StopOrderTriggerPriceInvalid
+            | PerpetualStatus::StopOrderDirectionOrPriceInvalid
+            | PerpetualStatus::StopOrderTypeInvalidCantReplacePrice
+            | PerpetualStatus::StopOrderTypeInvalidCantReplaceQty
+            | PerpetualStatus::InvalidTrailValue
+            | PerpetualStatus::StopOrderTypeInvalidCantReplaceTriggerPrice
+            | PerpetualStatus::StopOrderTypeInvalidCantReplaceTrailValue
+            | PerpetualStatus::OrderCostNotAvailable
+            | PerpetualStatus::WillBeTriggeredLiqAfterOrderComplete
+            | PerpetualStatus::CantSetTpSlTsForZeroPosition
+            | PerpetualStatus::BelowTenPctOfBasePrice
+            | PerpetualStatus::PriceTooHigh
+            | PerpetualStatus::BuyPriceShouldBeHigherThanBasePrice
+            | PerpetualStatus::SellPriceShouldBeBetweenBaseAndLiqPrice
+            | PerpetualStatus::BuyPriceShouldBeBetweenLiqAndBasePrice
+            | PerpetualStatus::SellPriceShouldBeLowerThanBasePrice
+            | PerpetualStatus::InvalidOrderStatusCantCancelOrExecuteTrigger
+            | PerpetualStatus::StopOrderCountAtOrExceedingTen
+            | PerpetualStatus::CantReplaceStopOrder
+            | PerpetualStatus::PositionWillBeLiq
+            | PerpetualStatus::InsufficientAvailableBalance
+            | PerpetualStatus::AdjustmentsWouldTriggerLiq
+            | PerpetualStatus::CantSetLeverageBeyondRiskLimit
+            | PerpetualStatus::CantSetLeverageBelowLowerRiskLimit
+            | PerpetualStatus::PositionInCrossMargin
+            | PerpetualStatus::PositionSizeZero
+            | PerpetualStatus::CantSetMarginBelowMinPositionCost
+            | PerpetualStatus::CantSetPosOpenLimitBeyondSymbolLimit
+            | PerpetualStatus::AutoAddMarginNotChanged
+            | PerpetualStatus::NotChangeFeeInvalidReq
+            | PerpetualStatus::CantSetPosOpenLimitBelowCurrentBuyPosValue
+            | PerpetualStatus::CantSetPosOpenLimitBelowCurrentSellPosValue
+            | PerpetualStatus::OnlySupportsUsdt
+            | PerpetualStatus::ExpectRisingTriggerPriceBelowCurrent
+            | PerpetualStatus::ExpectFallingTriggerPriceAboveCurrent
+            | PerpetualStatus::ReplaceParamsInvalid
+            | PerpetualStatus::RiskLimitInvalid
+            | PerpetualStatus::NoChangeMadeForTpSlPrice
+            | PerpetualStatus::NoOrders
+            | PerpetualStatus::TakeProfitStopLossAndTrailingStopLossNotModified
+            | PerpetualStatus::CloseOrderSideLargerThanPosLeavingQty
+            | PerpetualStatus::SwitchFailedPleaseCancelSlTpSetting
+            | PerpetualStatus::SwitchFailedPleaseCancelSlTpSettingTwo
+            | PerpetualStatus::SwitchFailedPleaseCancelSlTpSettingThree
+            | PerpetualStatus::SwitchFailedPleaseCancelSlTpSettingOfActiveOrders
+            | PerpetualStatus::InsufficientQtyForTpSl
+            | PerpetualStatus::ReplacingActiveOrderPriceAndQtySimultaneouslyIsForbidden
+            | PerpetualStatus::SlTpPriceCantBeAmendedWhenOrderPartiallyFilled
+            | PerpetualStatus::SlTpPriceCantBeAmendedUnderFullPosMode
+            | PerpetualStatus::MaxTwentySlTpOrdersUnderPartialPosMode
+            | PerpetualStatus::RiskAdjustmentFailedSizeExceedsLimit
+            | PerpetualStatus::RiskLimitNotChanged => StatusOutcome::DropOrder,
+
+            PerpetualStatus::InvalidSign
+            | PerpetualStatus::ApiKeyPermDenied
+            | PerpetualStatus::SystemNotRespondingContactSupport
+            | PerpetualStatus::RequestIpMismatch
+            | PerpetualStatus::PathOrMethodInvalid
+            | PerpetualStatus::ApiKeyExpired
+            | PerpetualStatus::WalletIsNil
+            | PerpetualStatus::ActionDeniedPositionInLiquidation
+            | PerpetualStatus::ActionDeniedPositionInAdl
+            | PerpetualStatus::AvailableBalanceLessThanZero => StatusOutcome::Halt,
+        }
+    }
+}
+
+/// Reads a v2 ret_code off either the linear or the inverse endpoints, unknown codes are treated as bad params
+pub fn deserialize_status<'de, D>(deserializer: D) -> Result<PerpetualStatus, D::Error>
+where D: Deserializer<'de> {
+    let code = u32::deserialize(deserializer)?;
+    let value = serde_json::Value::from(code);
+    Ok(match serde_json::from_value::<PerpetualStatus>(value.clone()) {
+        Ok(status) => status,
+        Err(_) => serde_json::from_value::<InversePerpetualErrors>(value)
+            .map(PerpetualStatus::from_inverse)
+            .unwrap_or(PerpetualStatus::ParamsError),
+    })
+}
@@ -0,0 +1,185 @@
+/// Order entry over the v5 trade websocket.
+/// One authenticated session is kept open and responses are matched back to their requests by req_id.
+/// Anything that can't go over the session because it's down returns None so the broker sends it over REST instead.
+/// A request the session took but didn't answer in time may still have landed, it comes back as a server timeout
+/// so orders are left for the reconcile to find by their link id rather than sent again.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_tungstenite::tokio::connect_async;
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{SinkExt, StreamExt};
+use hmac::Mac;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::HmacSha256;
+use crate::config::CONFIG;
+use crate::telemetry::{METRICS, Venue};
+
+use super::broker::{BROKER, TradeResponse};
+
+lazy_static! {
+    pub static ref GATEWAY: OrderGateway = OrderGateway::new();
+    /// How long a request waits on the session before it's given up as timed out
+    static ref RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+    static ref RECONNECT_DELAY: Duration = Duration::from_secs(1);
+    /// Bybit drops sessions that go quiet for longer than this
+    static ref PING_INTERVAL: Duration = Duration::from_secs(20);
+}
+
+pub struct OrderGateway {
+    /// Outgoing frames for the open session, None until it's authenticated and while it's down
+    session: RwLock<Option<UnboundedSender<Message>>>,
+    /// Callers waiting on a response, by req_id
+    pending: Mutex<HashMap<String, oneshot::Sender<TradeResponse>>>,
+}
+
+impl OrderGateway {
+    fn new() -> OrderGateway {
+        OrderGateway { session: RwLock::new(None), pending: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_up(&self) -> bool {
+        match self.session.read() {
+            Ok(session) => session.is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// Sends an op (order.create, order.amend, order.cancel) over the session and waits on its response.
+    /// Returns None if it never went out and has to go over REST, a sent op that goes unanswered comes back as a server timeout.
+    pub async fn request(&self, op: &str, args: serde_json::Value) -> Option<TradeResponse> {
+        let session = self.session.read().ok()?.clone()?;
+        let id = Uuid::new_v4().to_string();
+        let frame = serde_json::json!({
+            "reqId": id,
+            "header": {
+                "X-BAPI-TIMESTAMP": BROKER.calculate_server_time().ok()?.to_string(),
+                "X-BAPI-RECV-WINDOW": "5000",
+            },
+            "op": op,
+            "args": [args],
+        });
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().ok()?.insert(id.clone(), tx);
+        if session.send(Message::Text(frame.to_string())).is_err() {
+            self.pending.lock().ok()?.remove(&id);
+            return None;
+        }
+        match tokio::time::timeout(*RESPONSE_TIMEOUT, rx).await {
+            Ok(Ok(res)) => Some(res),
+            _ => {
+                info!("[GATEWAY] {} {} went unanswered, its outcome is unknown", op, id);
+                if let Ok(mut pending) = self.pending.lock() { pending.remove(&id); }
+                Some(TradeResponse {
+                    req_id: Some(id),
+                    ret_code: 10000,
+                    ret_msg: "Trade websocket request went unanswered".to_string(),
+                    op: op.to_string(),
+                    data: serde_json::Value::Null,
+                })
+            },
+        }
+    }
+
+    fn respond(&self, res: TradeResponse) {
+        let waiting = match (&res.req_id, self.pending.lock()) {
+            (Some(id), Ok(mut pending)) => pending.remove(id),
+            _ => None,
+        };
+        if let Some(waiting) = waiting {
+            waiting.send(res).ok();
+        }
+    }
+
+    /// Marks the session down, dropping the waiting callers sends them over REST
+    fn close(&self) {
+        if let Ok(mut session) = self.session.write() { *session = None; }
+        if let Ok(mut pending) = self.pending.lock() { pending.clear(); }
+    }
+}
+
+fn auth_frame() -> Option<String> {
+    let expires = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_millis() + 10000;
+    let mut mac = HmacSha256::new_from_slice(CONFIG.bybit_secret.as_bytes()).ok()?;
+    mac.update(format!("GET/realtime{}", expires).as_bytes());
+    let signature = format!("{:x}", mac.finalize().into_bytes());
+    Some(serde_json::json!({ "op": "auth", "args": [CONFIG.bybit_key.clone(), expires, signature] }).to_string())
+}
+
+/// Keeps the session open for as long as the program runs, reconnecting whenever it drops.
+/// Does nothing when BYBIT_TRADE_WS_URL isn't set.
+pub async fn connect_gateway() {
+    let url = match &CONFIG.bybit_trade_ws_url {
+        Some(url) => url.clone(),
+        None => {
+            info!("[GATEWAY] No trade websocket url set, orders go over REST");
+            return;
+        },
+    };
+    loop {
+        match connect_async(url.clone()).await {
+            Ok((ws_stream, _res)) => {
+                METRICS.connected(Venue::Bybit, "gateway");
+                let (mut write, mut read) = ws_stream.split();
+                let (send, mut rec) = unbounded_channel::<Message>();
+                let writer = tokio::spawn(async move {
+                    while let Some(msg) = rec.recv().await {
+                        if write.send(msg).await.is_err() { break; }
+                    }
+                });
+                let pinger = {
+                    let send = send.clone();
+                    tokio::spawn(async move {
+                        let mut interval = tokio::time::interval(*PING_INTERVAL);
+                        loop {
+                            interval.tick().await;
+                            if send.send(Message::Text(serde_json::json!({ "op": "ping" }).to_string())).is_err() { break; }
+                        }
+                    })
+                };
+                match auth_frame() {
+                    Some(auth) => { send.send(Message::Text(auth)).ok(); },
+                    None => info!("[GATEWAY] Failed to sign the trade session auth"),
+                }
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Text(txt)) => match serde_json::from_str::<TradeResponse>(&txt) {
+                            Ok(res) if res.op == "auth" => {
+                                if res.ret_code == 0 {
+                                    if let Ok(mut session) = GATEWAY.session.write() { *session = Some(send.clone()); }
+                                    info!("[GATEWAY] Order session open");
+                                } else {
+                                    info!("[GATEWAY] Trade session auth failed: {}", res.ret_msg);
+                                    break;
+                                }
+                            },
+                            Ok(res) => GATEWAY.respond(res),
+                            // Pongs don't carry a ret_code
+                            Err(_) => {},
+                        },
+                        Ok(Message::Ping(payload)) => { send.send(Message::Pong(payload)).ok(); },
+                        Ok(Message::Close(frame)) => {
+                            info!("[GATEWAY] Order session closed by the server: {:?}", frame);
+                            break;
+                        },
+                        Ok(_) => {},
+                        Err(e) => {
+                            info!("[GATEWAY] Order session error: {}", e);
+                            break;
+                        },
+                    }
+                }
+                GATEWAY.close();
+                pinger.abort();
+                writer.abort();
+            },
+            Err(e) => info!("[GATEWAY] Failed to open order session: {}", e),
+        }
+        tokio::time::sleep(*RECONNECT_DELAY).await;
+    }
+}
@@ -1,4 +1,115 @@
 pub mod errors;
 pub mod stream;
 pub mod broker;
-pub mod credentials;
\ No newline at end of file
+pub mod credentials;
+pub mod gateway;
+
+use dec::D128;
+
+use crate::config::CONFIG;
+use self::broker::Side;
+
+lazy_static! {
+    /// Whether the broker and streams talk to the v5 unified API instead of v2
+    pub static ref V5: bool = CONFIG.bybit_api.as_deref().map(|api| api.eq_ignore_ascii_case("v5")).unwrap_or(false);
+    pub static ref CONTRACT: ContractType = match CONFIG.bybit_contract.as_deref() {
+        Some(contract) if contract.eq_ignore_ascii_case("inverse") => ContractType::Inverse,
+        _ => ContractType::Linear,
+    };
+}
+
+/// Linear contracts are sized in the coin and settle in USDT.
+/// Inverse contracts are sized in USD and settle in the coin, so a contract's value moves with 1/price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContractType {
+    Linear,
+    Inverse,
+}
+
+impl ContractType {
+    /// v5 category
+    pub fn category(&self) -> &'static str {
+        match self {
+            ContractType::Linear => "linear",
+            ContractType::Inverse => "inverse",
+        }
+    }
+
+    /// Root of the v2 private endpoints, order, stop-order and position paths hang off it the same way for both
+    pub fn private_path(&self) -> &'static str {
+        match self {
+            ContractType::Linear => "/private/linear",
+            ContractType::Inverse => "/v2/private",
+        }
+    }
+
+    /// BYBIT_SYMBOL, or the BTC perpetual of the contract type when unset
+    pub fn symbol(&self) -> &'static str {
+        if let Some(symbol) = CONFIG.bybit_symbol.as_deref() { return symbol; }
+        match self {
+            ContractType::Linear => "BTCUSDT",
+            ContractType::Inverse => "BTCUSD",
+        }
+    }
+
+    /// Coin the wallet, margin and pnl are held in
+    pub fn settle_coin(&self, symbol: &str) -> String {
+        match self {
+            ContractType::Linear => "USDT".to_string(),
+            ContractType::Inverse => symbol.trim_end_matches("USD").to_string(),
+        }
+    }
+
+    /// Value of size contracts at price, in the settle coin
+    pub fn value(&self, size: D128, price: D128) -> D128 {
+        match self {
+            ContractType::Linear => size * price,
+            ContractType::Inverse => size / price,
+        }
+    }
+
+    /// Value of size contracts at price in USD, inverse contracts are a dollar each
+    pub fn notional(&self, size: D128, price: D128) -> D128 {
+        match self {
+            ContractType::Linear => size * price,
+            ContractType::Inverse => size,
+        }
+    }
+
+    /// Average price of inv contracts worth liq in the settle coin, harmonic for inverse
+    pub fn cost_basis(&self, inv: D128, liq: D128) -> D128 {
+        match self {
+            ContractType::Linear => liq / inv,
+            ContractType::Inverse => inv / liq,
+        }
+    }
+
+    /// Whether a side makes money as the settle coin value of its contracts rises
+    pub fn rising(&self, side: Side) -> bool {
+        matches!((self, side), (ContractType::Linear, Side::Buy) | (ContractType::Inverse, Side::Sell))
+    }
+
+    /// Profit in the settle coin of a position opened for open and closed for close, both values in the settle coin
+    pub fn pnl(&self, side: Side, open: D128, close: D128) -> D128 {
+        match (self, side) {
+            (ContractType::Linear, Side::Buy) | (ContractType::Inverse, Side::Sell) => close - open,
+            (ContractType::Linear, Side::Sell) | (ContractType::Inverse, Side::Buy) => open - close,
+        }
+    }
+
+    /// Order quantity, linear trades in thousandths of a coin and inverse in whole USD contracts
+    pub fn qty(&self, size: D128) -> f64 {
+        match self {
+            ContractType::Linear => (size.to_float() * 1000.).round() / 1000.,
+            ContractType::Inverse => size.to_float().round(),
+        }
+    }
+
+    /// Notional in contracts of a settle coin amount at price
+    pub fn contracts(&self, amount: D128, price: D128) -> D128 {
+        match self {
+            ContractType::Linear => amount / price,
+            ContractType::Inverse => amount * price,
+        }
+    }
+}
@@ -2,9 +2,12 @@
 pub mod orderbook;
 pub mod private;
 pub mod trade;
+pub mod v5;
 
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use std::str::FromStr;
 use std::collections::HashMap;
 use std::time::Instant;
 // use tokio::sync::mpsc::Sender;
@@ -15,12 +18,39 @@ use serde;
 use super::broker::OrderStatus;
 
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Lenient<T> {
+    Number(T),
+    Text(String),
+}
+
+/// Linear topics send numbers as numbers, inverse ones send most of them as strings
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where D: Deserializer<'de>, T: Deserialize<'de> + FromStr + Default {
+    Ok(match Lenient::<T>::deserialize(deserializer)? {
+        Lenient::Number(number) => number,
+        Lenient::Text(text) => text.parse().unwrap_or_default(),
+    })
+}
+
+/// Ids, sequences and timestamps, strings on linear and numbers on inverse
+fn text<'de, D>(deserializer: D) -> Result<String, D::Error>
+where D: Deserializer<'de> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(text) => text,
+        value => value.to_string(),
+    })
+}
+
 #[derive(Deserialize, Debug)]
 pub struct TickLevel {
     pub price: String,
     pub symbol: String,
+    #[serde(deserialize_with = "text")]
     pub id: String,
     pub side: String,
+    #[serde(deserialize_with = "number")]
     pub size: f32,
 }
 
@@ -28,6 +58,7 @@ pub struct TickLevel {
 pub struct TickDelete {
     pub price: String,
     pub symbol: String,
+    #[serde(deserialize_with = "text")]
     pub id: String,
     pub side: String,
 }
@@ -54,10 +85,16 @@ pub struct OBTick {
     #[serde(rename = "topic")]
     pub channel: String,
     #[serde(rename = "timestamp_e6")]
+    #[serde(deserialize_with = "text")]
     pub timestamp: String,
+    #[serde(deserialize_with = "text")]
     pub cross_seq: String,
     pub data: OBTickData,
+    #[serde(skip)]
+    #[serde(default = "instant_default")]
+    pub test_timer: Instant,
 }
+pub fn instant_default() -> Instant { Instant::now() }
 
 #[derive(Deserialize, Debug)]
 pub struct InitBook {
@@ -68,9 +105,13 @@ pub struct InitBook {
 pub struct TradeData {
     pub symbol: String,
     pub tick_direction: String,
+    #[serde(deserialize_with = "text")]
     pub price: String,
+    #[serde(deserialize_with = "number")]
     pub size: f64,
+    #[serde(deserialize_with = "text")]
     pub timestamp: String,
+    #[serde(deserialize_with = "text")]
     pub trade_time_ms: String,
     pub side: String,
     pub trade_id: String,
@@ -89,7 +130,9 @@ pub struct BybitOBInit {
     #[serde(rename = "topic")]
     pub channel: String,
     #[serde(rename = "timestamp_e6")]
+    #[serde(deserialize_with = "text")]
     pub timestamp: String,
+    #[serde(deserialize_with = "text")]
     pub cross_seq: String,
     pub data: InitBook,
 }
@@ -102,7 +145,9 @@ pub struct BybitWalletTick {
 
 #[derive(Deserialize, Debug)]
 pub struct BybitWalletData {
+    #[serde(deserialize_with = "number")]
     pub wallet_balance: f64,
+    #[serde(deserialize_with = "number")]
     pub available_balance: f64,
 }
 
@@ -115,27 +160,49 @@ pub struct BybitPositionTick {
 
 #[derive(Deserialize, Debug)]
 pub struct BybitPositionData {
+    #[serde(deserialize_with = "text")]
     pub user_id: String,
     pub symbol: String,
+    #[serde(deserialize_with = "number")]
     pub size: f64,
     pub side: String,
+    #[serde(deserialize_with = "number")]
     pub position_value: f64,
+    #[serde(deserialize_with = "number")]
     pub entry_price: f64,
+    #[serde(deserialize_with = "number")]
     pub bust_price: f64,
+    /// Zero when the stream doesn't send it, bust price is the fallback
+    #[serde(default)]
+    #[serde(deserialize_with = "number")]
+    pub liq_price: f64,
+    #[serde(deserialize_with = "number")]
     pub leverage: f64,
+    #[serde(deserialize_with = "number")]
     pub order_margin: f64,
+    #[serde(deserialize_with = "number")]
     pub position_margin: f64,
+    /// Maintenance margin, only v5 sends it
+    #[serde(default)]
+    #[serde(deserialize_with = "number")]
+    pub maint_margin: f64,
+    #[serde(deserialize_with = "number")]
     pub occ_closing_fee: f64,
+    #[serde(deserialize_with = "number")]
     pub take_profit: f64,
     #[serde(default)]
     pub tp_trigger_by: String,
+    #[serde(deserialize_with = "number")]
     pub stop_loss: f64,
     #[serde(default)]
     pub s1_trigger_by: String,
     #[serde(default)]
+    #[serde(deserialize_with = "number")]
     pub realised_pnl: f64,
     #[serde(default)]
+    #[serde(deserialize_with = "number")]
     pub cum_realized_pnl: f64,
+    #[serde(deserialize_with = "text")]
     pub position_seq: String,
 }
 
@@ -149,11 +216,14 @@ pub struct BybitStopOrderTick {
 pub struct BybitStopOrderData {
     pub stop_order_id: String,
     pub order_link_id: String,
+    #[serde(deserialize_with = "text")]
     pub user_id: String,
     pub symbol: String,
     pub side: String,
     pub order_type: String,
+    #[serde(deserialize_with = "number")]
     pub price: f64,
+    #[serde(deserialize_with = "number")]
     pub qty: f64,
     pub time_in_force: String,
     pub order_status: String,
@@ -161,6 +231,7 @@ pub struct BybitStopOrderData {
     #[serde(default)]
     pub trigger_by: String,
     #[serde(default)]
+    #[serde(deserialize_with = "number")]
     pub trigger_price: f64,
     pub reduce_only: bool,
     pub close_on_trigger: bool,
@@ -182,23 +253,35 @@ pub struct BybitOrderData {
     pub symbol: String,
     pub side: String,
     pub order_type: String,
+    #[serde(deserialize_with = "number")]
     pub price: f64,
+    #[serde(deserialize_with = "number")]
     pub qty: f64,
+    #[serde(deserialize_with = "number")]
     pub leaves_qty: f64,
+    #[serde(deserialize_with = "number")]
     pub last_exec_price: f64,
+    #[serde(deserialize_with = "number")]
     pub cum_exec_qty: f64,
+    #[serde(deserialize_with = "number")]
     pub cum_exec_value: f64,
+    #[serde(deserialize_with = "number")]
     pub cum_exec_fee: f64,
     pub time_in_force: String,
     pub create_type: String,
     pub cancel_type: String,
     pub order_status: OrderStatus,
+    #[serde(deserialize_with = "number")]
     pub take_profit: f64,
+    #[serde(deserialize_with = "number")]
     pub stop_loss: f64,
+    #[serde(deserialize_with = "number")]
     pub trailing_stop: f64,
     pub reduce_only: bool,
     pub close_on_trigger: bool,
+    #[serde(default)]
     pub create_time: String,
+    #[serde(default)]
     pub update_time: String,
 }
 
@@ -215,11 +298,16 @@ pub struct BybitExecutionData {
     pub order_id: String,
     pub exec_id: String,
     pub order_link_id: String,
+    #[serde(deserialize_with = "number")]
     pub price: f64,
+    #[serde(deserialize_with = "number")]
     pub order_qty: f64,
     pub exec_type: String,
+    #[serde(deserialize_with = "number")]
     pub exec_fee: f64,
+    #[serde(deserialize_with = "number")]
     pub exec_qty: f64,
+    #[serde(deserialize_with = "number")]
     pub leaves_qty: f64,
     pub is_maker: bool,
     pub trade_time: String,
@@ -13,7 +13,9 @@ use crate::backend::bybit::stream::ArgType;
 use crate::backend::bybit::stream::WebsocketMessager;
 use crate::backend::bybit::stream::WebsocketSubscribe;
 
+use crate::backend::bybit::broker::BROKER;
 use crate::config::CONFIG;
+use crate::telemetry::{LATENCY, METRICS, Venue, LatencyStage};
 
 
 /// Connects the stream to a orderbook type websocket and emits signals to the provided sender.
@@ -25,6 +27,7 @@ pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
         .await
         .expect("error building stream");
 
+    METRICS.connected(Venue::Bybit, "orderbook");
     let (mut write, mut read) = ws_stream.split();
     // CHANNEL SETUP
     // COMPOSE AND STRINGIFY THE REQUEST
@@ -75,11 +78,18 @@ pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
                 WebsocketMessager::Message(msg) => {
                     match msg {
                         Message::Text(txt) => {
+                            let timer = Instant::now();
                             let ob = serde_json::from_str::<OrderBookTicks>(&txt.to_string())
                                 .expect("Deserializing OB went wrong");
+                            LATENCY.since(Venue::Bybit, LatencyStage::Deserialize, timer);
                             match ob {
-                                OrderBookTicks::OBTick(delta) => {
+                                OrderBookTicks::OBTick(mut delta) => {
                                     if stream.orderbook_activated {
+                                        delta.test_timer = timer;
+                                        METRICS.book_update(Venue::Bybit);
+                                        if let (Ok(now), Ok(event_time)) = (BROKER.calculate_server_time(), delta.timestamp.parse::<u128>()) {
+                                            LATENCY.record(Venue::Bybit, LatencyStage::SocketReceive, Duration::from_millis(now.saturating_sub(event_time / 1000) as u64));
+                                        }
                                         sender.send(Signal::Orderbook(delta)).await;
                                     }
                                 }
@@ -96,6 +106,7 @@ pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
                                                 update: vec![],
                                                 insert: snapshot.data.order_book,
                                             },
+                                            test_timer: timer,
                                         };
                                         // SEND SNAPSHOT UPDATE TO MODEL THREAD
                                         sender
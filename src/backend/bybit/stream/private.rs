@@ -9,13 +9,14 @@ use futures::{SinkExt, StreamExt};
 use tokio::runtime::Runtime;
 
 use crate::HmacSha256;
-use crate::backend::bybit::stream::{BybitStream, BybitTimeTick, WebsocketPing, WSPrivateTicks, RestWallet};
+use crate::backend::bybit::stream::{BybitStream, BybitTimeTick, WebsocketPing, WSPrivateTicks, PrivateTicks, RestWallet};
 use crate::backend::bybit::stream::Signal;
 use crate::backend::bybit::stream::ArgType;
 use crate::backend::bybit::stream::WebsocketMessager;
 use crate::backend::bybit::stream::WebsocketSubscribe;
 
 use crate::config::CONFIG;
+use crate::telemetry::{LATENCY, METRICS, Venue};
 
 /// Connects the stream to a private type websocket and emits signals to the provided sender.
 /// NOTE: Should be called from a new thread to avoid blocking the main thread.
@@ -26,6 +27,7 @@ pub async fn connect_private(sender: Sender<Signal>) {
     let (ws_stream, _res) = connect_async(CONFIG.bybit_perpetuals_private_url.clone())
         .await
         .expect("error building stream");
+    METRICS.connected(Venue::Bybit, "private");
     let (mut write, mut read) = ws_stream.split();
     //AUTHORIZE
     let expires = (SystemTime::now()
@@ -152,6 +154,9 @@ pub async fn connect_private(sender: Sender<Signal>) {
 
                                 match tick {
                                     WSPrivateTicks::PrivateTicks(pt) => {
+                                        if let PrivateTicks::OrderTick(ot) = &pt {
+                                            for order in ot.data.iter() { LATENCY.confirmed(&order.order_link_id); }
+                                        }
                                         if sender.send(Signal::PrivateTicks(pt)).await.is_err() {
                                             panic!("something went wrong sending private tick");
                                         }
@@ -12,19 +12,21 @@ use crate::backend::bybit::stream::WebsocketMessager;
 use crate::backend::bybit::stream::WebsocketPing;
 use crate::backend::bybit::stream::WebsocketSubscribe;
 use crate::config::CONFIG;
+use crate::telemetry::{METRICS, Venue};
 use tokio::time;
 
-pub async fn connect_trade(sender: Sender<Signal>) {
+pub async fn connect_trade(sender: Sender<Signal>, symbol: String) {
     let stream =  BybitStream::new();
     info!("[INIT] Bybit trade socket connecting...");
     let (ws_stream, _res) = connect_async(CONFIG.bybit_perpetuals_url.clone())
         .await
         .expect("error building stream");
     info!("[INIT] Connect trade async done");
+    METRICS.connected(Venue::Bybit, "trade");
     let (mut write, mut read) = ws_stream.split();
     let obsub = serde_json::to_string(&WebsocketSubscribe {
         message_type: "subscribe".to_string(),
-        args: vec![ArgType::String("trade.BTCUSDT".to_string())],
+        args: vec![ArgType::String(format!("trade.{}", symbol))],
     })
     .expect("something went wrong stringifying query json");
     write
@@ -77,6 +79,7 @@ pub async fn connect_trade(sender: Sender<Signal>) {
                                 TradeTicks::TradeTick(trade) => {
                                     // println!("parsed: {}, {}", parsed_txt.data[0].side, parsed_txt.data[0].size);
                                     if stream.trades_activated {
+                                        METRICS.trade_update(Venue::Bybit);
                                         sender
                                             .send(Tradeflow(trade)).await
                                             .expect("problem sending tradeflow from ws");
@@ -0,0 +1,370 @@
+/// Streams off the v5 unified API.
+/// Every v5 tick is turned into its v2 counterpart before it's emitted, so the signal handler and strategy
+/// can't tell the two apart. Numbers come in as strings and fields that don't apply are left empty.
+
+pub mod public;
+pub mod private;
+
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use super::{
+    OBTick, OBTickData, TickLevel, TickDelete, TradeTick, TradeData, PrivateTicks,
+    BybitOrderTick, BybitOrderData, BybitStopOrderTick, BybitStopOrderData, BybitExecutionTick, BybitExecutionData,
+    BybitPositionTick, BybitPositionData, BybitWalletTick, BybitWalletData,
+};
+use super::super::broker::OrderStatus;
+use super::super::CONTRACT;
+
+fn float(value: &str) -> f64 {
+    value.parse().unwrap_or(0.0)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct V5OrderbookTick {
+    pub topic: String,
+    /// snapshot or delta
+    #[serde(rename = "type")]
+    pub message_type: String,
+    /// Milliseconds
+    pub ts: u64,
+    pub data: V5OrderbookData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct V5OrderbookData {
+    pub s: String,
+    /// [price, size], size 0 removes the level
+    pub b: Vec<[String; 2]>,
+    pub a: Vec<[String; 2]>,
+    pub u: u64,
+    pub seq: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct V5TradeTick {
+    pub topic: String,
+    pub ts: u64,
+    pub data: Vec<V5TradeData>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct V5TradeData {
+    #[serde(rename = "T")]
+    pub time: u64,
+    pub s: String,
+    #[serde(rename = "S")]
+    pub side: String,
+    pub v: String,
+    pub p: String,
+    #[serde(rename = "L")]
+    #[serde(default)]
+    pub tick_direction: String,
+    pub i: String,
+}
+
+/// Replies to auth, subscribe and ping
+#[derive(Deserialize, Debug)]
+pub struct V5OpResponse {
+    #[serde(default)]
+    pub success: bool,
+    #[serde(default)]
+    pub ret_msg: String,
+    pub op: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum V5PublicTicks {
+    Orderbook(V5OrderbookTick),
+    Trade(V5TradeTick),
+    OpResponse(V5OpResponse),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5OrderData {
+    pub symbol: String,
+    pub order_id: String,
+    pub order_link_id: String,
+    pub side: String,
+    pub order_type: String,
+    pub price: String,
+    pub qty: String,
+    pub leaves_qty: String,
+    pub avg_price: String,
+    pub cum_exec_qty: String,
+    pub cum_exec_value: String,
+    pub cum_exec_fee: String,
+    pub time_in_force: String,
+    pub order_status: String,
+    pub create_type: String,
+    pub cancel_type: String,
+    pub take_profit: String,
+    pub stop_loss: String,
+    pub trigger_price: String,
+    pub trigger_by: String,
+    /// Empty for regular orders
+    pub stop_order_type: String,
+    pub reduce_only: bool,
+    pub close_on_trigger: bool,
+    pub created_time: String,
+    pub updated_time: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5ExecutionData {
+    pub symbol: String,
+    pub side: String,
+    pub order_id: String,
+    pub exec_id: String,
+    pub order_link_id: String,
+    pub exec_price: String,
+    pub order_qty: String,
+    pub exec_type: String,
+    pub exec_fee: String,
+    pub exec_qty: String,
+    pub leaves_qty: String,
+    pub is_maker: bool,
+    pub exec_time: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5PositionData {
+    pub symbol: String,
+    pub side: String,
+    pub size: String,
+    pub position_value: String,
+    pub entry_price: String,
+    pub bust_price: String,
+    #[serde(default)]
+    pub liq_price: String,
+    pub leverage: String,
+    #[serde(rename = "positionIM")]
+    pub position_im: String,
+    #[serde(rename = "positionMM")]
+    #[serde(default)]
+    pub position_mm: String,
+    pub take_profit: String,
+    pub tp_trigger_by: String,
+    pub stop_loss: String,
+    pub sl_trigger_by: String,
+    pub cum_realised_pnl: String,
+    pub seq: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5WalletData {
+    pub coin: Vec<V5WalletCoin>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct V5WalletCoin {
+    pub coin: String,
+    pub wallet_balance: String,
+    pub available_to_withdraw: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "topic")]
+pub enum V5PrivateTicks {
+    #[serde(rename = "order")]
+    Order { data: Vec<V5OrderData> },
+    #[serde(rename = "execution")]
+    Execution { data: Vec<V5ExecutionData> },
+    #[serde(rename = "position")]
+    Position { data: Vec<V5PositionData> },
+    #[serde(rename = "wallet")]
+    Wallet { data: Vec<V5WalletData> },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum V5WSPrivateTicks {
+    PrivateTicks(V5PrivateTicks),
+    OpResponse(V5OpResponse),
+}
+
+impl V5OrderbookTick {
+    /// A snapshot inserts every level, a delta inserts over or deletes them.
+    /// The snapshot type is kept so the handler clears the book before a snapshot goes in.
+    pub fn into_tick(self, received: Instant) -> OBTick {
+        let symbol = self.data.s;
+        let mut data = OBTickData { delete: vec![], update: vec![], insert: vec![] };
+        for (side, levels) in [("Buy", self.data.b), ("Sell", self.data.a)] {
+            for [price, size] in levels {
+                let size = float(&size);
+                if size == 0.0 {
+                    data.delete.push(TickDelete { id: price.clone(), price, symbol: symbol.clone(), side: side.to_string() });
+                } else {
+                    data.insert.push(TickLevel { id: price.clone(), price, symbol: symbol.clone(), side: side.to_string(), size: size as f32 });
+                }
+            }
+        }
+        OBTick {
+            message_type: self.message_type,
+            channel: self.topic,
+            // v2 sent microseconds
+            timestamp: format!("{}000", self.ts),
+            cross_seq: self.data.seq.to_string(),
+            data,
+            test_timer: received,
+        }
+    }
+}
+
+impl From<V5TradeTick> for TradeTick {
+    fn from(tick: V5TradeTick) -> Self {
+        TradeTick {
+            topic: tick.topic,
+            data: tick.data.into_iter().map(|t| TradeData {
+                symbol: t.s,
+                tick_direction: t.tick_direction,
+                price: t.p,
+                size: float(&t.v),
+                timestamp: t.time.to_string(),
+                trade_time_ms: t.time.to_string(),
+                side: t.side,
+                trade_id: t.i,
+            }).collect(),
+        }
+    }
+}
+
+fn order_status(status: &str) -> OrderStatus {
+    match status {
+        "Created" => OrderStatus::Created,
+        "New" => OrderStatus::New,
+        "PartiallyFilled" => OrderStatus::PartiallyFilled,
+        "Filled" => OrderStatus::Filled,
+        "Cancelled" | "PartiallyFilledCanceled" | "Deactivated" => OrderStatus::Cancelled,
+        _ => OrderStatus::Rejected,
+    }
+}
+
+impl V5PrivateTicks {
+    /// v5 puts conditional orders on the order topic, they're split back out into stop order ticks.
+    /// One v5 tick can become a regular and a stop order tick.
+    pub fn into_ticks(self) -> Vec<PrivateTicks> {
+        match self {
+            V5PrivateTicks::Order { data } => {
+                let (stops, orders): (Vec<V5OrderData>, Vec<V5OrderData>) = data.into_iter().partition(|o| !o.stop_order_type.is_empty() && o.order_status != "Filled");
+                let mut ticks = vec![];
+                if !orders.is_empty() {
+                    ticks.push(PrivateTicks::OrderTick(BybitOrderTick {
+                        topic: "order".to_string(),
+                        action: "update".to_string(),
+                        data: orders.into_iter().map(|o| BybitOrderData {
+                            order_status: order_status(&o.order_status),
+                            order_id: o.order_id,
+                            order_link_id: o.order_link_id,
+                            symbol: o.symbol,
+                            side: o.side,
+                            order_type: o.order_type,
+                            price: float(&o.price),
+                            qty: float(&o.qty),
+                            leaves_qty: float(&o.leaves_qty),
+                            last_exec_price: float(&o.avg_price),
+                            cum_exec_qty: float(&o.cum_exec_qty),
+                            cum_exec_value: float(&o.cum_exec_value),
+                            cum_exec_fee: float(&o.cum_exec_fee),
+                            time_in_force: o.time_in_force,
+                            create_type: o.create_type,
+                            cancel_type: o.cancel_type,
+                            take_profit: float(&o.take_profit),
+                            stop_loss: float(&o.stop_loss),
+                            trailing_stop: 0.0,
+                            reduce_only: o.reduce_only,
+                            close_on_trigger: o.close_on_trigger,
+                            create_time: o.created_time,
+                            update_time: o.updated_time,
+                        }).collect(),
+                    }));
+                }
+                if !stops.is_empty() {
+                    ticks.push(PrivateTicks::StopOrderTick(BybitStopOrderTick {
+                        topic: "stop_order".to_string(),
+                        data: stops.into_iter().map(|o| BybitStopOrderData {
+                            stop_order_id: o.order_id,
+                            order_link_id: o.order_link_id,
+                            user_id: String::default(),
+                            symbol: o.symbol,
+                            side: o.side,
+                            order_type: o.order_type,
+                            price: float(&o.price),
+                            qty: float(&o.qty),
+                            time_in_force: o.time_in_force,
+                            order_status: o.order_status,
+                            stop_order_type: o.stop_order_type,
+                            trigger_by: o.trigger_by,
+                            trigger_price: float(&o.trigger_price),
+                            reduce_only: o.reduce_only,
+                            close_on_trigger: o.close_on_trigger,
+                            create_time: o.created_time,
+                            update_time: o.updated_time,
+                        }).collect(),
+                    }));
+                }
+                ticks
+            },
+            V5PrivateTicks::Execution { data } => vec![PrivateTicks::ExecutionTick(BybitExecutionTick {
+                topic: "execution".to_string(),
+                data: data.into_iter().map(|e| BybitExecutionData {
+                    symbol: e.symbol,
+                    side: e.side,
+                    order_id: e.order_id,
+                    exec_id: e.exec_id,
+                    order_link_id: e.order_link_id,
+                    price: float(&e.exec_price),
+                    order_qty: float(&e.order_qty),
+                    exec_type: e.exec_type,
+                    exec_fee: float(&e.exec_fee),
+                    exec_qty: float(&e.exec_qty),
+                    leaves_qty: float(&e.leaves_qty),
+                    is_maker: e.is_maker,
+                    trade_time: e.exec_time,
+                }).collect(),
+            })],
+            V5PrivateTicks::Position { data } => vec![PrivateTicks::PositionTick(BybitPositionTick {
+                topic: "position".to_string(),
+                action: "update".to_string(),
+                data: data.into_iter().map(|p| BybitPositionData {
+                    user_id: String::default(),
+                    symbol: p.symbol,
+                    // v2 sent None for a flat position
+                    side: if p.side.is_empty() { "None".to_string() } else { p.side },
+                    size: float(&p.size),
+                    position_value: float(&p.position_value),
+                    entry_price: float(&p.entry_price),
+                    bust_price: float(&p.bust_price),
+                    liq_price: float(&p.liq_price),
+                    leverage: float(&p.leverage),
+                    order_margin: 0.0,
+                    position_margin: float(&p.position_im),
+                    maint_margin: float(&p.position_mm),
+                    occ_closing_fee: 0.0,
+                    take_profit: float(&p.take_profit),
+                    tp_trigger_by: p.tp_trigger_by,
+                    stop_loss: float(&p.stop_loss),
+                    s1_trigger_by: p.sl_trigger_by,
+                    realised_pnl: 0.0,
+                    cum_realized_pnl: float(&p.cum_realised_pnl),
+                    position_seq: p.seq.to_string(),
+                }).collect(),
+            })],
+            V5PrivateTicks::Wallet { data } => vec![PrivateTicks::WalletTick(BybitWalletTick {
+                topic: "wallet".to_string(),
+                data: data.into_iter()
+                    .flat_map(|w| w.coin.into_iter())
+                    .filter(|c| c.coin == CONTRACT.settle_coin(CONTRACT.symbol()))
+                    .map(|c| BybitWalletData { wallet_balance: float(&c.wallet_balance), available_balance: float(&c.available_to_withdraw) })
+                    .collect(),
+            })],
+        }
+    }
+}
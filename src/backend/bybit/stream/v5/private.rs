@@ -0,0 +1,131 @@
+use std::time::{Duration, SystemTime};
+
+use async_tungstenite::WebSocketStream;
+use async_tungstenite::tokio::{connect_async, ConnectStream};
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{SinkExt, StreamExt};
+use hmac::Mac;
+use tokio::sync::mpsc::{Sender, unbounded_channel};
+use tokio::time;
+
+use crate::HmacSha256;
+use crate::backend::bybit::stream::{Signal, PrivateTicks};
+use crate::config::CONFIG;
+use crate::telemetry::{LATENCY, METRICS, Venue};
+
+use super::V5WSPrivateTicks;
+
+lazy_static! {
+    static ref RECONNECT_DELAY: Duration = Duration::from_secs(1);
+}
+
+/// Connects to the private order, execution, position and wallet topics and emits them as v2 private ticks.
+/// Reconnects and authenticates again whenever the socket drops, returns once nothing is listening for the signals anymore.
+/// NOTE: Should be called from a new thread to avoid blocking the main thread.
+pub async fn connect_private(sender: Sender<Signal>) {
+    let url = CONFIG.bybit_v5_private_url.clone().unwrap_or_else(|| "wss://stream.bybit.com/v5/private".to_string());
+    loop {
+        match connect_async(url.clone()).await {
+            Ok((ws_stream, _res)) => {
+                METRICS.connected(Venue::Bybit, "private");
+                if !session(ws_stream, &sender).await { return; }
+            },
+            Err(e) => info!("[STREAM] Failed to open the v5 private stream: {}", e),
+        }
+        time::sleep(*RECONNECT_DELAY).await;
+    }
+}
+
+/// Runs one connection until it drops, false if the signal receiver is gone
+async fn session(ws_stream: WebSocketStream<ConnectStream>, sender: &Sender<Signal>) -> bool {
+    let (mut write, mut read) = ws_stream.split();
+
+    let expires = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|now| now.as_millis())
+        .unwrap_or(0)
+        + 30000;
+    let mut mac = match HmacSha256::new_from_slice(CONFIG.bybit_secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(e) => {
+            info!("[STREAM] Can't sign the v5 private stream auth: {}", e);
+            return true;
+        },
+    };
+    mac.update(format!("GET/realtime{}", expires).as_bytes());
+    let signature = format!("{:x}", mac.finalize().into_bytes());
+    let auth = serde_json::json!({ "op": "auth", "args": [CONFIG.bybit_key.clone(), expires, signature] }).to_string();
+    let sub = serde_json::json!({ "op": "subscribe", "args": ["order", "execution", "position", "wallet"] }).to_string();
+    for frame in [auth, sub] {
+        if let Err(e) = write.send(Message::Text(frame)).await {
+            info!("[STREAM] Authenticating the v5 private stream failed: {}", e);
+            return true;
+        }
+    }
+
+    let (send, mut rec) = unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rec.recv().await {
+            if write.send(msg).await.is_err() { break; }
+        }
+    });
+    let pinger = {
+        let send = send.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(20));
+            loop {
+                interval.tick().await;
+                if send.send(Message::Text(serde_json::json!({ "op": "ping" }).to_string())).is_err() { break; }
+            }
+        })
+    };
+
+    let mut listening = true;
+    'read: while let Some(msg) = read.next().await {
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                info!("[STREAM] v5 private stream error, reconnecting: {}", e);
+                break;
+            },
+        };
+        match msg {
+            Message::Text(txt) => {
+                // println!("[DEBUG] [PRIVATE_STREAM] {:?}", txt);
+                let tick = match serde_json::from_str::<V5WSPrivateTicks>(&txt) {
+                    Ok(tick) => tick,
+                    Err(e) => {
+                        debug!("[STREAM] Unreadable v5 private frame {}: {}", txt, e);
+                        continue;
+                    },
+                };
+                match tick {
+                    V5WSPrivateTicks::PrivateTicks(pt) => {
+                        for pt in pt.into_ticks() {
+                            if let PrivateTicks::OrderTick(ot) = &pt {
+                                for order in ot.data.iter() { LATENCY.confirmed(&order.order_link_id); }
+                            }
+                            if sender.send(Signal::PrivateTicks(pt)).await.is_err() {
+                                listening = false;
+                                break 'read;
+                            }
+                        }
+                    }
+                    V5WSPrivateTicks::OpResponse(res) => {
+                        // Private pongs come back as op pong without a success flag
+                        if !res.success && res.op != "pong" { info!("[STREAM] v5 private {} failed: {}", res.op, res.ret_msg); }
+                    }
+                }
+            }
+            Message::Ping(payload) => { send.send(Message::Pong(payload)).ok(); }
+            Message::Close(frame) => {
+                info!("[STREAM] The server shut down the private stream on us, reconnecting: {:?}", frame);
+                break;
+            }
+            _ => {}
+        }
+    }
+    pinger.abort();
+    writer.abort();
+    listening
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use async_tungstenite::WebSocketStream;
+use async_tungstenite::tokio::{connect_async, ConnectStream};
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc::{Sender, unbounded_channel};
+use tokio::time;
+
+use crate::backend::bybit::CONTRACT;
+use crate::backend::bybit::broker::BROKER;
+use crate::backend::bybit::stream::Signal;
+use crate::config::CONFIG;
+use crate::telemetry::{LATENCY, METRICS, Venue, LatencyStage};
+
+use super::V5PublicTicks;
+
+lazy_static! {
+    static ref RECONNECT_DELAY: Duration = Duration::from_secs(1);
+}
+
+/// Depth of the orderbook topic, v5 only offers 1, 50, 200 and 500 for linear
+const ORDERBOOK_DEPTH: u32 = 200;
+
+/// Connects to the orderbook.N topic, emitting the snapshot and deltas as v2 orderbook ticks.
+/// NOTE: Should be called from a new thread to avoid blocking the main thread.
+pub async fn connect_orderbook(sender: Sender<Signal>, symbol: String) {
+    listen(sender, "orderbook", format!("orderbook.{}.{}", ORDERBOOK_DEPTH, symbol)).await;
+}
+
+/// Connects to the publicTrade topic, emitting trades as v2 trade ticks
+pub async fn connect_trade(sender: Sender<Signal>, symbol: String) {
+    listen(sender, "trade", format!("publicTrade.{}", symbol)).await;
+}
+
+/// stream names the connection in the metrics. Reconnects whenever the socket drops, the fresh snapshot resets the book.
+/// Returns once nothing is listening for the signals anymore.
+async fn listen(sender: Sender<Signal>, stream: &'static str, topic: String) {
+    let url = CONFIG.bybit_v5_public_url.clone()
+        .unwrap_or_else(|| format!("wss://stream.bybit.com/v5/public/{}", CONTRACT.category()));
+    loop {
+        info!("[INIT] Bybit v5 {} socket connecting...", topic);
+        match connect_async(url.clone()).await {
+            Ok((ws_stream, _res)) => {
+                METRICS.connected(Venue::Bybit, stream);
+                if !session(ws_stream, &sender, &topic).await { return; }
+            },
+            Err(e) => info!("[STREAM] Failed to open v5 {} socket: {}", topic, e),
+        }
+        time::sleep(*RECONNECT_DELAY).await;
+    }
+}
+
+/// Runs one connection until it drops, false if the signal receiver is gone
+async fn session(ws_stream: WebSocketStream<ConnectStream>, sender: &Sender<Signal>, topic: &str) -> bool {
+    let (mut write, mut read) = ws_stream.split();
+    let sub = serde_json::json!({ "op": "subscribe", "args": [topic] }).to_string();
+    if let Err(e) = write.send(Message::Text(sub)).await {
+        info!("[STREAM] Subscribing to v5 {} failed: {}", topic, e);
+        return true;
+    }
+
+    let (send, mut rec) = unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rec.recv().await {
+            if write.send(msg).await.is_err() { break; }
+        }
+    });
+    let pinger = {
+        let send = send.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(20));
+            loop {
+                interval.tick().await;
+                if send.send(Message::Text(serde_json::json!({ "op": "ping" }).to_string())).is_err() { break; }
+            }
+        })
+    };
+
+    let mut listening = true;
+    while let Some(msg) = read.next().await {
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                info!("[STREAM] v5 {} socket error, reconnecting: {}", topic, e);
+                break;
+            },
+        };
+        match msg {
+            Message::Text(txt) => {
+                let timer = Instant::now();
+                let tick = match serde_json::from_str::<V5PublicTicks>(&txt) {
+                    Ok(tick) => tick,
+                    Err(e) => {
+                        debug!("[STREAM] Unreadable v5 {} frame {}: {}", topic, txt, e);
+                        continue;
+                    },
+                };
+                let sent = match tick {
+                    V5PublicTicks::Orderbook(ob) => {
+                        LATENCY.since(Venue::Bybit, LatencyStage::Deserialize, timer);
+                        METRICS.book_update(Venue::Bybit);
+                        if let Ok(now) = BROKER.calculate_server_time() {
+                            LATENCY.record(Venue::Bybit, LatencyStage::SocketReceive, Duration::from_millis(now.saturating_sub(ob.ts as u128) as u64));
+                        }
+                        sender.send(Signal::Orderbook(ob.into_tick(timer))).await.is_ok()
+                    }
+                    V5PublicTicks::Trade(trade) => {
+                        METRICS.trade_update(Venue::Bybit);
+                        sender.send(Signal::Tradeflow(trade.into())).await.is_ok()
+                    }
+                    V5PublicTicks::OpResponse(res) => {
+                        if !res.success { info!("[STREAM] v5 {} failed: {}", res.op, res.ret_msg); }
+                        true
+                    }
+                };
+                if !sent {
+                    listening = false;
+                    break;
+                }
+            }
+            Message::Ping(payload) => { send.send(Message::Pong(payload)).ok(); }
+            Message::Close(frame) => {
+                info!("[STREAM] The server shut down {} on us, reconnecting: {:?}", topic, frame);
+                break;
+            }
+            _ => {}
+        }
+    }
+    pinger.abort();
+    writer.abort();
+    listening
+}
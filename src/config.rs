@@ -4,6 +4,7 @@
 /// variable from the system by that name.
 /// NOTE: The name of the environment variable it loads will be in all uppercase,
 /// so 'example_key' becomes 'EXAMPLE_KEY' 
+use dec::D128;
 use serde::{Deserialize};
 
 
@@ -21,6 +22,20 @@ pub struct Config {
     pub bybit_perpetuals_private_url: String,
     /// URL for the rest API
     pub bybit_rest_url: String,
+    /// Bybit API generation, v5 for the unified API, the legacy v2 endpoints when unset.
+    /// v5 streams connect to the BYBIT_V5_* urls rather than the perpetuals ones
+    pub bybit_api: Option<String>,
+    /// inverse for the coin margined perpetuals, linear USDT perpetuals when unset.
+    /// On v2 the stream urls have to point at the inverse realtime endpoint
+    pub bybit_contract: Option<String>,
+    /// Symbol traded on bybit, BTCUSDT or BTCUSD for inverse contracts when unset
+    pub bybit_symbol: Option<String>,
+    /// URL for the v5 public stream, stream.bybit.com/v5/public/<category> when unset
+    pub bybit_v5_public_url: Option<String>,
+    /// URL for the v5 private stream, stream.bybit.com/v5/private when unset
+    pub bybit_v5_private_url: Option<String>,
+    /// URL for the v5 trade websocket, orders only go over REST when unset
+    pub bybit_trade_ws_url: Option<String>,
     /// Authentication key for binance
     pub binance_key: String,
     /// Authentication secret for binance
@@ -29,8 +44,60 @@ pub struct Config {
     pub binance_perpetuals_url: String,
     /// URL for the rest API
     pub binance_rest_url: String,
+    /// URL for the spot and margin rest API, api.binance.com when unset
+    pub binance_spot_rest_url: Option<String>,
+    /// URL for the spot and margin streams, stream.binance.com:9443 when unset
+    pub binance_spot_stream_url: Option<String>,
+    /// Symbol for the spot trade, depth and user data streams, the spot streams stay off when unset
+    pub binance_spot_symbol: Option<String>,
+    /// Follow the cross margin user data stream instead of the spot one, spot when unset
+    pub binance_spot_margin: Option<bool>,
+    /// URL for the futures websocket API, orders only go over REST when unset
+    pub binance_ws_api_url: Option<String>,
     /// The style of running code
-    pub execution_mode: Option<String>
+    pub execution_mode: Option<String>,
+    /// Leverage applied to the traded symbol at startup, left as is on the exchange when unset
+    pub leverage: Option<u32>,
+    /// ISOLATED or CROSS margin applied at startup, left as is on the exchange when unset
+    pub margin_type: Option<String>,
+    /// Seconds between latency summaries in the log, 60 when unset
+    pub latency_summary_secs: Option<u64>,
+    /// JSON file with the pre-trade risk limits, reloaded when it changes. Orders are only sanity checked when unset
+    pub risk_config: Option<String>,
+    /// File the tripped kill switch is persisted to, kill_switch.json when unset
+    pub kill_switch_path: Option<String>,
+    /// File the day's losses are persisted to so a restart keeps counting toward the daily loss limits, daily_loss.json when unset
+    pub daily_loss_path: Option<String>,
+    /// Name of the strategy the runtime hosts, ladder when unset
+    pub strategy: Option<String>,
+    /// File parameter changes are appended to, params_audit.log when unset
+    pub params_audit_path: Option<String>,
+    /// JSON file with the market maker's settings, see market_maker.sample.json. Defaults when unset
+    pub market_maker_config: Option<String>,
+    /// JSON file with the grid's settings, see grid.sample.json. Defaults when unset
+    pub grid_config: Option<String>,
+    /// Log filter, a default level and module overrides e.g. info,strategy::grid=debug. info when unset
+    pub log_level: Option<String>,
+    /// text or json, text when unset
+    pub log_format: Option<String>,
+    /// File logs are also appended to, stdout only when unset
+    pub log_file: Option<String>,
+    /// Size in MB the log file rolls over at, 100 when unset and 0 to never
+    pub log_rotate_mb: Option<u64>,
+    /// Seconds between time based rollovers on wall clock boundaries, e.g. 86400 for midnight UTC. Off when unset
+    pub log_rotate_secs: Option<u64>,
+    /// Rolled over log files kept, 10 when unset
+    pub log_keep: Option<usize>,
+    /// Stop loss distance attached to inventory as it opens, a fraction of cost basis. Off when unset
+    pub protect_stop_loss: Option<D128>,
+    /// Take profit distance attached to inventory as it opens, a fraction of cost basis. Off when unset
+    pub protect_take_profit: Option<D128>,
+    /// Trailing stop distance watched locally, a fraction of the best exit price. Off when unset
+    pub protect_trailing: Option<D128>,
+    /// Price increment of the traded symbol, protective triggers are put on it. 0.1 when unset
+    pub price_tick: Option<D128>,
+    /// Address to serve Prometheus metrics on at /metrics, e.g. 127.0.0.1:9184. Not served when unset
+    pub metrics_addr: Option<String>,
 }
 
 lazy_static! {
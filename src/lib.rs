@@ -17,6 +17,8 @@ pub mod tradeflow;
 pub mod strategy;
 pub mod signal_handler;
 pub mod config;
+pub mod telemetry;
+pub mod risk;
 
 // Generally useful type aliases
 pub type HmacSha256 = Hmac<Sha256>;
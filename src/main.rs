@@ -15,6 +15,8 @@ use crossbeam_channel::{Sender, Receiver, unbounded};
 
 use trader::{backend::{bybit, types::Side, binance::{types::{AccountBalanceWrapper, BinanceError}, errors::{ErrorCode, ServerNetworkErrors}}}, strategy::{types::Stage, binance::{StrategyMessage, AccountMessage}}};
 use trader::strategy;
+use trader::telemetry;
+use trader::risk;
 use trader::signal_handler::{bybit_handler, binance_handler};
 use trader::backend::binance;
 use trader::config::CONFIG;
@@ -26,6 +28,7 @@ use trader::backend::binance::types::{OrderBookSignal, Signal, DepthLimit};
 const THREADS_PER_SYMBOL: usize = 3;
 
 fn main() {
+    init_logging();
     if &CONFIG.env == "PRODUCTION" {
         info!("PRODUCTION ENVIRONMENT\nWE ARE LIVE");
     } else if &CONFIG.env == "TEST" {
@@ -46,6 +49,26 @@ fn main() {
     }
 }
 
+/// Applies the LOG_* settings, the defaults are kept for anything unset
+fn init_logging() {
+    let mut settings = logging::Settings::default();
+    if let Some(spec) = &CONFIG.log_level {
+        settings.filter = spec.parse().expect("Failed to parse LOG_LEVEL");
+    }
+    if let Some(format) = &CONFIG.log_format {
+        settings.format = format.parse().expect("Failed to parse LOG_FORMAT");
+    }
+    settings.file = CONFIG.log_file.as_ref().map(|path| path.into());
+    if let Some(mb) = CONFIG.log_rotate_mb {
+        settings.rotate_bytes = Some(mb * 1024 * 1024);
+    }
+    settings.rotate_secs = CONFIG.log_rotate_secs;
+    if let Some(keep) = CONFIG.log_keep {
+        settings.keep = keep;
+    }
+    logging::init(settings).expect("Failed to set up logging");
+}
+
 fn automated_entrypoint_binance() {
     info!("Binance entrypoint");
     let accounts = vec![
@@ -64,6 +87,12 @@ fn automated_entrypoint_binance() {
     .enable_time()
     .build()
     .expect("Failed to build async runtime for bybit order");
+    // Orders are built for the account's position mode, so it has to be known before anything trades
+    info!("[INIT] Querying server time");
+    pool.block_on(binance::broker::BROKER.resync_server_time());
+    info!("[INIT] Queried server time");
+    let hedge_mode = pool.block_on(binance::broker::BROKER.detect_position_mode());
+    info!("[INIT] Account is in {} mode", if hedge_mode { "hedge" } else { "one-way" });
     for account in accounts {
         let symbol = "btcbusd".to_string();
 
@@ -75,14 +104,9 @@ fn automated_entrypoint_binance() {
             let stratshot = strat_tx.clone();
             let signal_tx = signal_tx.clone();
             let symbol = symbol.clone();
-            info!("[INIT] Querying server time");
             pool.spawn( async move {
-                let server_time = binance::broker::BROKER.time().await;
-                let our_time: i64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis().try_into().unwrap();
-                binance::broker::BROKER.set_server_offset(server_time - our_time).unwrap();
-                info!("[INIT] Queried server time");
                 info!("[INIT] Snapshots");
-                let snap = binance::market::MARKET.orderbook_snapshot(symbol, DepthLimit::Thousand).await;
+                let snap = binance::market::MARKET.orderbook_snapshot(symbol.clone(), DepthLimit::Thousand).await;
                 signal_tx.send(Signal::OrderBook(OrderBookSignal::OrderBookSnap(snap))).await.expect("ob snap main");
                 loop {
                     match binance::broker::BROKER.account_balance().await {
@@ -93,6 +117,10 @@ fn automated_entrypoint_binance() {
                         AccountBalanceWrapper::Error(_) => {},
                     }
                 }
+                info!("[INIT] Applying leverage and margin settings");
+                strategy::binance::send_margin(symbol.clone(), stratshot.clone()).await;
+                info!("[INIT] Reconciling against exchange state");
+                strategy::binance::send_reconcile(symbol, stratshot).await;
             });
         }
         {
@@ -122,6 +150,46 @@ fn automated_entrypoint_binance() {
             pool.spawn(async move { binance::stream::user_data::connect_user_data(strat_tx).await; });
             info!("[INIT] Spawned user data stream");
         }
+        if let Some(spot_symbol) = CONFIG.binance_spot_symbol.clone() {
+            let (spot_tx, mut spot_rx) = tokio::sync::mpsc::channel::<binance::types::SpotSignal>(100);
+            let account = strategy::binance::configured_account();
+            info!("[INIT] Spawning spot streams for {}", spot_symbol);
+            {
+                let spot_tx = spot_tx.clone();
+                let spot_symbol = spot_symbol.clone();
+                pool.spawn(async move { binance::spot::stream::connect_trade(spot_tx, spot_symbol).await; });
+            }
+            {
+                let spot_tx = spot_tx.clone();
+                pool.spawn(async move { binance::spot::stream::connect_depth(spot_tx, spot_symbol).await; });
+            }
+            pool.spawn(async move { binance::spot::stream::connect_user_data(spot_tx, account).await; });
+            let strat_tx = strat_tx.clone();
+            pool.spawn(async move {
+                while let Some(signal) = spot_rx.recv().await {
+                    if strat_tx.send(StrategyMessage::SpotMessage(signal)).is_err() { break; }
+                }
+            });
+            info!("[INIT] Spawned spot streams");
+        }
+        {
+            info!("[INIT] Spawning order gateway");
+            pool.spawn(async move { binance::gateway::connect_gateway().await; });
+            info!("[INIT] Spawned order gateway");
+        }
+        {
+            let strat_tx = strat_tx.clone();
+            let symbol = symbol.clone();
+            info!("[INIT] Spawning reconciliation timer");
+            pool.spawn(async move { strategy::binance::reconcile_timer(symbol, strat_tx).await; });
+            info!("[INIT] Spawned reconciliation timer");
+        }
+        {
+            let symbol = symbol.clone();
+            info!("[INIT] Spawning fee rate timer");
+            pool.spawn(async move { strategy::fees::fee_timer(telemetry::Venue::Binance, symbol).await; });
+            info!("[INIT] Spawned fee rate timer");
+        }
 
         // Spawn the main event loop threada
         thread::spawn(move || {
@@ -134,10 +202,16 @@ fn automated_entrypoint_binance() {
             let symbol = symbol.clone();
             // Spawn the strategy thread
             thread::spawn(move || {
-                let strategy = strategy::binance::strategy::Strategy::new(symbol, strat_tx, strat_rx);
-                if let Ok(mut strategy) = strategy {
+                let runtime = strategy::binance::Runtime::new(symbol, strat_tx, strat_rx);
+                if let Err(e) = &runtime {
+                    info!("[INIT] Failed to start strategy: {}", e);
+                    // Nothing trades without a strategy, so don't leave the streams running
+                    logging::flush();
+                    std::process::exit(1);
+                }
+                if let Ok(mut runtime) = runtime {
                     info!("[INIT] Starting strategy loop");
-                    strategy.listen();
+                    runtime.listen();
                     // match strategy.listen() {
                     //     Ok(_) => info!("[SHUTDOWN] Strategy exited gracefully"),
                     //     Err(reason) => {
@@ -154,9 +228,16 @@ fn automated_entrypoint_binance() {
     }
 
 
+    info!("[INIT] Spawning latency summary timer");
+    pool.spawn(async move { telemetry::summary_timer().await; });
+    pool.spawn(async move { telemetry::metrics_server().await; });
+    info!("[INIT] Spawning risk limits reload timer");
+    pool.spawn(async move { risk::reload_timer().await; });
     info!("[INIT] Initialization complete. Blocking main thread");
-    // Block the main thread to prevent the program from ending
-    loop{}
+    // Block the main thread on the console, "latency" dumps the latency histograms and "risk" the risk state
+    telemetry::console();
+    // Keep blocking once stdin closes to prevent the program from ending
+    loop {}
 }
 
 /// Called if the program is supposed to be running as an automated trader
@@ -182,7 +263,9 @@ fn automated_entrypoint_bybit() {
         .expect("Failed to build async runtime for bybit order");
     for account in accounts {
 
-        let symbol = "BTCUSDT".to_string();
+        let symbol = bybit::CONTRACT.symbol().to_string();
+        let hedge_mode = pool.block_on(bybit::broker::BROKER.detect_position_mode(symbol.clone()));
+        info!("[INIT] Account is in {} mode", if hedge_mode { "hedge" } else { "one-way" });
 
         let (signal_tx, signal_rx): (tokio::sync::mpsc::Sender<bybit::stream::Signal>, tokio::sync::mpsc::Receiver<bybit::stream::Signal>) = tokio::sync::mpsc::channel(1);
         let (strat_tx, strat_rx): (Sender<strategy::bybit::StrategyMessage>, Receiver<strategy::bybit::StrategyMessage>) = unbounded();
@@ -193,21 +276,63 @@ fn automated_entrypoint_bybit() {
             let signal_tx = signal_tx.clone();
             let symbol = symbol.clone();
             info!("[INIT] Spawning orderbook stream");
-            pool.spawn(async move { bybit::stream::orderbook::connect_orderbook(signal_tx, symbol).await; });
+            if *bybit::V5 {
+                pool.spawn(async move { bybit::stream::v5::public::connect_orderbook(signal_tx, symbol).await; });
+            } else {
+                pool.spawn(async move { bybit::stream::orderbook::connect_orderbook(signal_tx, symbol).await; });
+            }
             info!("[INIT] Spawned orderbook stream");
         }
         {
             let signal_tx = signal_tx.clone();
+            let symbol = symbol.clone();
             info!("[INIT] Spawning connect_trade stream");
-            pool.spawn(async move { bybit::stream::trade::connect_trade(signal_tx.clone()).await; });
+            if *bybit::V5 {
+                pool.spawn(async move { bybit::stream::v5::public::connect_trade(signal_tx, symbol).await; });
+            } else {
+                pool.spawn(async move { bybit::stream::trade::connect_trade(signal_tx.clone(), symbol).await; });
+            }
             info!("[INIT] Spawned connect_trade stream");
         }
         {
             let signal_tx = signal_tx.clone();
             info!("[INIT] Spawning connect_private stream");
-            pool.spawn(async move { bybit::stream::private::connect_private(signal_tx.clone()).await; });
+            if *bybit::V5 {
+                pool.spawn(async move { bybit::stream::v5::private::connect_private(signal_tx).await; });
+            } else {
+                pool.spawn(async move { bybit::stream::private::connect_private(signal_tx.clone()).await; });
+            }
             info!("[INIT] Spawned connect_private stream");
         }
+        {
+            info!("[INIT] Spawning order gateway");
+            pool.spawn(async move { bybit::gateway::connect_gateway().await; });
+            info!("[INIT] Spawned order gateway");
+        }
+
+        {
+            let strat_tx = strat_tx.clone();
+            let symbol = symbol.clone();
+            info!("[INIT] Applying leverage and margin settings");
+            pool.spawn(async move {
+                strategy::bybit::send_margin(symbol.clone(), strat_tx.clone()).await;
+                info!("[INIT] Reconciling against exchange state");
+                strategy::bybit::send_reconcile(symbol, strat_tx).await;
+            });
+        }
+        {
+            let strat_tx = strat_tx.clone();
+            let symbol = symbol.clone();
+            info!("[INIT] Spawning reconciliation timer");
+            pool.spawn(async move { strategy::bybit::reconcile_timer(symbol, strat_tx).await; });
+            info!("[INIT] Spawned reconciliation timer");
+        }
+        {
+            let symbol = symbol.clone();
+            info!("[INIT] Spawning fee rate timer");
+            pool.spawn(async move { strategy::fees::fee_timer(telemetry::Venue::Bybit, symbol).await; });
+            info!("[INIT] Spawned fee rate timer");
+        }
 
         // Create a new signal handler, passing in channels for receiving events and updating the strategy
         let mut sig_handler = bybit_handler::SignalHandler::new(strat_tx.clone(), signal_rx);
@@ -229,10 +354,16 @@ fn automated_entrypoint_bybit() {
             let symbol = symbol.clone();
             // Spawn the strategy thread
             thread::spawn(move || {
-                let strategy = strategy::bybit::strategy::Strategy::new(symbol, strat_tx, strat_rx);
-                if let Ok(mut strategy) = strategy {
+                let runtime = strategy::bybit::Runtime::new(symbol, strat_tx, strat_rx);
+                if let Err(e) = &runtime {
+                    info!("[INIT] Failed to start strategy: {}", e);
+                    // Nothing trades without a strategy, so don't leave the streams running
+                    logging::flush();
+                    std::process::exit(1);
+                }
+                if let Ok(mut runtime) = runtime {
                     info!("[INIT] Starting strategy loop");
-                    match strategy.listen() {
+                    match runtime.listen() {
                         Ok(_) => info!("[SHUTDOWN] Strategy exited gracefully"),
                         Err(reason) => {
                             match reason {
@@ -246,8 +377,15 @@ fn automated_entrypoint_bybit() {
             });
         }
     }
+    info!("[INIT] Spawning latency summary timer");
+    pool.spawn(async move { telemetry::summary_timer().await; });
+    pool.spawn(async move { telemetry::metrics_server().await; });
+    info!("[INIT] Spawning risk limits reload timer");
+    pool.spawn(async move { risk::reload_timer().await; });
     info!("[INIT] Initialization complete. Blocking main thread");
-    // Block the main thread to prevent the program from ending
+    // Block the main thread on the console, "latency" dumps the latency histograms and "risk" the risk state
+    telemetry::console();
+    // Keep blocking once stdin closes to prevent the program from ending
     loop {}
 }
 
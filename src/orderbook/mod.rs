@@ -219,8 +219,6 @@ pub struct OrderBook {
     pub internal_time: Instant,
     pub exchange: Exchange,
     pub last_sequence: u64,
-    pub maker_commission: f32,
-    pub taker_commission: f32,
     pub highest_jump: D128,
     pub initialized: bool,
     pub tops: Tops,
@@ -238,8 +236,6 @@ impl OrderBook {
             internal_time: Instant::now(),
             exchange: Exchange::None,
             last_sequence: 0,
-            maker_commission: 0.0,
-            taker_commission: 0.0,
             tops: Tops::new(),
             highest_jump: D128::ZERO,
             initialized: false,
@@ -247,6 +243,15 @@ impl OrderBook {
         }
     }
 
+    /// Drops every level ahead of a snapshot, so levels the snapshot no longer has don't linger
+    pub fn clear(&mut self) {
+        self.bids = OrderBookSide::new();
+        self.asks = OrderBookSide::new();
+        self.bid_total_liq = D128::ZERO;
+        self.last_sequence = 0;
+        self.tops = Tops::new();
+    }
+
     pub fn find_best_ask(&self) -> Option<(&OrderBookKey, &OrderBookValue)> {
         return match self.asks.book.iter().next() {
             None => None,
@@ -621,6 +626,7 @@ impl OrderBook {
             }
         }
 
+        self.initialized = true;
         self.last_sequence = sequence;
 
         // println!(
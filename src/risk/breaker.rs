@@ -0,0 +1,271 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use dec::D128;
+use serde::{Deserialize, Serialize};
+
+use crate::config::CONFIG;
+use crate::telemetry::Venue;
+
+use super::{RISK, RiskLimits};
+
+lazy_static! {
+    pub static ref BREAKER: KillSwitch = KillSwitch::new();
+}
+
+/// Where the tripped state is kept when KILL_SWITCH_PATH isn't set
+const DEFAULT_PATH: &str = "kill_switch.json";
+/// Where the day's losses are kept when DAILY_LOSS_PATH isn't set
+const DEFAULT_DAILY_PATH: &str = "daily_loss.json";
+/// Rolling drawdown window when the limits file doesn't say
+const DEFAULT_WINDOW_SECS: u64 = 3600;
+const SECS_PER_DAY: u64 = 86400;
+
+/// A breached loss limit, symbol is None when the whole account on the venue tripped
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trip {
+    pub venue: String,
+    pub symbol: Option<String>,
+    pub reason: String,
+    pub pnl: D128,
+    /// Unix seconds
+    pub at: u64,
+}
+
+impl Trip {
+    fn covers(&self, venue: Venue, symbol: &str) -> bool {
+        self.venue == format!("{:?}", venue) && self.symbol.as_deref().map_or(true, |tripped| tripped == symbol)
+    }
+}
+
+/// A book's loss so far on a UTC day, carried over a restart so the daily limit doesn't start over
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct DailyLoss {
+    venue: String,
+    /// Empty for the whole account
+    symbol: String,
+    day: u64,
+    loss: D128,
+}
+
+/// PnL history of a symbol or a whole account
+struct PnlBook {
+    pnl: D128,
+    day: u64,
+    day_start: D128,
+    /// Decreasing PnL samples inside the window, the front is always the window's peak
+    peaks: VecDeque<(Instant, D128)>,
+}
+
+impl PnlBook {
+    /// carried is the loss already taken today before a restart
+    fn new(pnl: D128, day: u64, carried: D128) -> PnlBook {
+        PnlBook { pnl, day, day_start: pnl + carried, peaks: VecDeque::new() }
+    }
+
+    /// Takes the latest PnL and returns the loss since the start of the UTC day and the drop from the window's peak
+    fn update(&mut self, pnl: D128, day: u64, window: Duration) -> (D128, D128) {
+        let now = Instant::now();
+        if day != self.day {
+            self.day = day;
+            self.day_start = pnl;
+        }
+        self.pnl = pnl;
+        // Anything at or under the new sample can never be the peak again
+        while self.peaks.back().map_or(false, |(_, sample)| *sample <= pnl) {
+            self.peaks.pop_back();
+        }
+        self.peaks.push_back((now, pnl));
+        while self.peaks.front().map_or(false, |(at, _)| now.duration_since(*at) > window) {
+            self.peaks.pop_front();
+        }
+        let peak = self.peaks.front().map_or(pnl, |(_, sample)| *sample);
+        (self.day_start - pnl, peak - pnl)
+    }
+
+    /// Starts the day and the window over from the current PnL
+    fn rebase(&mut self) {
+        self.day_start = self.pnl;
+        self.peaks.clear();
+    }
+}
+
+/// Drawdown and daily loss circuit breaker.
+/// Portfolios report their realized plus unrealized PnL, a breach of the limits in the risk file trips the symbol
+/// or the whole account, and tripped entries stay blocked across restarts until "risk reset" is typed into the console.
+pub struct KillSwitch {
+    books: Mutex<HashMap<(Venue, String), PnlBook>>,
+    trips: Mutex<Vec<Trip>>,
+    /// Losses read back at startup, each is taken up by its book's first update
+    carried: Mutex<Vec<DailyLoss>>,
+}
+
+impl KillSwitch {
+    fn new() -> KillSwitch {
+        let trips = match std::fs::read_to_string(path()) {
+            Ok(raw) => match serde_json::from_str::<Vec<Trip>>(&raw) {
+                Ok(trips) => trips,
+                Err(e) => panic!("Kill switch state in {} is unreadable, fix or remove it before trading: {}", path(), e),
+            },
+            Err(_) => vec![],
+        };
+        for trip in trips.iter() {
+            info!("[KILL SWITCH] Still tripped from a previous run: {:?}", trip);
+        }
+        let day = unix_secs() / SECS_PER_DAY;
+        let carried = match std::fs::read_to_string(daily_path()) {
+            Ok(raw) => match serde_json::from_str::<Vec<DailyLoss>>(&raw) {
+                Ok(losses) => losses.into_iter().filter(|loss| loss.day == day).collect(),
+                Err(e) => {
+                    info!("[KILL SWITCH] Daily losses in {} are unreadable, the day starts over: {}", daily_path(), e);
+                    vec![]
+                },
+            },
+            Err(_) => vec![],
+        };
+        for loss in carried.iter() {
+            info!("[KILL SWITCH] Carrying today's loss from a previous run: {:?}", loss);
+        }
+        KillSwitch {
+            books: Mutex::new(HashMap::new()),
+            trips: Mutex::new(trips),
+            carried: Mutex::new(carried),
+        }
+    }
+
+    /// Loss a book already took today before the restart, handed out once
+    fn take_carried(&self, venue: Venue, symbol: &str, day: u64) -> D128 {
+        let venue = format!("{:?}", venue);
+        let mut carried = self.carried.lock().unwrap();
+        match carried.iter().position(|loss| loss.venue == venue && loss.symbol == symbol && loss.day == day) {
+            Some(i) => carried.remove(i).loss,
+            None => D128::ZERO,
+        }
+    }
+
+    pub fn is_tripped(&self, venue: Venue, symbol: &str) -> bool {
+        self.trips.lock().unwrap().iter().any(|trip| trip.covers(venue, symbol))
+    }
+
+    /// Records a symbol's PnL and checks it and its account against the limits, returns the trip if this update caused one
+    pub fn update(&self, venue: Venue, symbol: &str, pnl: D128) -> Option<Trip> {
+        if pnl.is_nan() { return None; }
+        let limits = RISK.limits();
+        let window = Duration::from_secs(limits.drawdown_window_secs.unwrap_or(DEFAULT_WINDOW_SECS));
+        let day = unix_secs() / SECS_PER_DAY;
+
+        let mut books = self.books.lock().unwrap();
+        let (daily, drawdown) = books.entry((venue, symbol.to_string()))
+            .or_insert_with(|| PnlBook::new(pnl, day, self.take_carried(venue, symbol, day)))
+            .update(pnl, day, window);
+        let account_pnl = books.iter()
+            .filter(|((book_venue, book_symbol), _)| *book_venue == venue && !book_symbol.is_empty())
+            .fold(D128::ZERO, |sum, (_, book)| sum + book.pnl);
+        let (account_daily, account_drawdown) = books.entry((venue, String::new()))
+            .or_insert_with(|| PnlBook::new(account_pnl, day, self.take_carried(venue, "", day)))
+            .update(account_pnl, day, window);
+        drop(books);
+
+        let breach = breached(&limits.max_daily_loss, daily, "daily loss")
+            .or_else(|| breached(&limits.max_drawdown, drawdown, "drawdown"))
+            .map(|reason| (Some(symbol.to_string()), reason, pnl))
+            .or_else(|| breached(&limits.account_max_daily_loss, account_daily, "account daily loss")
+                .or_else(|| breached(&limits.account_max_drawdown, account_drawdown, "account drawdown"))
+                .map(|reason| (None, reason, account_pnl)));
+        let (symbol, reason, pnl) = breach?;
+
+        let trip = Trip { venue: format!("{:?}", venue), symbol, reason, pnl, at: unix_secs() };
+        let mut trips = self.trips.lock().unwrap();
+        // Already covered by this trip or the account's
+        if trips.iter().any(|tripped| tripped.venue == trip.venue && (tripped.symbol.is_none() || tripped.symbol == trip.symbol)) { return None; }
+        trips.push(trip.clone());
+        persist(&trips);
+        info!("[KILL SWITCH] Tripped: {:?}", trip);
+        Some(trip)
+    }
+
+    /// Clears every trip and starts the loss tracking over from the current PnL
+    pub fn reset(&self) {
+        let mut trips = self.trips.lock().unwrap();
+        trips.clear();
+        persist(&trips);
+        for book in self.books.lock().unwrap().values_mut() {
+            book.rebase();
+        }
+        self.carried.lock().unwrap().clear();
+        self.save_daily();
+        info!("[KILL SWITCH] Reset, entries are allowed again");
+    }
+
+    /// Writes each book's loss so far today to DAILY_LOSS_PATH, on a timer rather than every update
+    pub fn save_daily(&self) {
+        let day = unix_secs() / SECS_PER_DAY;
+        let mut losses: Vec<DailyLoss> = self.books.lock().unwrap().iter()
+            .filter(|(_, book)| book.day == day)
+            .map(|((venue, symbol), book)| DailyLoss { venue: format!("{:?}", venue), symbol: symbol.clone(), day, loss: book.day_start - book.pnl })
+            .collect();
+        // Books that haven't had an update since the restart keep what they carried
+        losses.extend(self.carried.lock().unwrap().iter().filter(|loss| loss.day == day).cloned());
+        let result = serde_json::to_string_pretty(&losses).map_err(|e| e.to_string())
+            .and_then(|raw| std::fs::write(daily_path(), raw).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            info!("[KILL SWITCH] Failed to write the daily losses to {}: {}", daily_path(), e);
+        }
+    }
+
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for trip in self.trips.lock().unwrap().iter() {
+            writeln!(out, "tripped: {:?}", trip).unwrap();
+        }
+        for ((venue, symbol), book) in self.books.lock().unwrap().iter() {
+            let name = if symbol.is_empty() { "account" } else { symbol.as_str() };
+            writeln!(out, "{:?} {}: pnl: {}, today: {}", venue, name, book.pnl, book.pnl - book.day_start).unwrap();
+        }
+        out
+    }
+}
+
+fn breached(limit: &Option<D128>, loss: D128, what: &str) -> Option<String> {
+    match limit {
+        Some(max) if loss > *max => Some(format!("{} of {} over the {} limit", what, loss, max)),
+        _ => None,
+    }
+}
+
+fn path() -> &'static str {
+    CONFIG.kill_switch_path.as_deref().unwrap_or(DEFAULT_PATH)
+}
+
+fn daily_path() -> &'static str {
+    CONFIG.daily_loss_path.as_deref().unwrap_or(DEFAULT_DAILY_PATH)
+}
+
+/// The file only exists while something is tripped
+fn persist(trips: &[Trip]) {
+    let result = if trips.is_empty() {
+        match std::fs::remove_file(path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    } else {
+        serde_json::to_string_pretty(trips).map_err(|e| e.to_string())
+            .and_then(|raw| std::fs::write(path(), raw).map_err(|e| e.to_string()))
+    };
+    if let Err(e) = result {
+        info!("[KILL SWITCH] Failed to write the tripped state to {}: {}", path(), e);
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+impl RiskLimits {
+    /// Whether a tripped kill switch should also close out the open position
+    pub fn flatten_on_trip(&self) -> bool {
+        self.flatten_on_trip.unwrap_or(false)
+    }
+}
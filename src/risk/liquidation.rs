@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use dec::D128;
+
+use super::RiskLimits;
+
+/// Share of a position cut when it gets too close to liquidation and the limits file doesn't say
+const DEFAULT_REDUCE_FRACTION: f64 = 0.25;
+/// Minimum time between de-risks of a position that stays inside the threshold, gives the last one time to fill
+pub const DERISK_COOLDOWN: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LiquidationLevel {
+    Safe,
+    /// Inside the warning threshold, logged but left alone
+    Warn,
+    /// Inside the de-risk threshold, part of the position gets closed
+    Reduce,
+}
+
+/// What the exchange last told us about how close a position is to being liquidated, zero while unknown
+#[derive(Debug, Clone, Copy)]
+pub struct LiquidationState {
+    pub liq_price: D128,
+    /// Maintenance margin the position requires
+    pub maint_margin: D128,
+    /// Margin backing the position, unrealized PnL included
+    pub margin_balance: D128,
+    /// Level the position was at on the last check, so transitions are only acted on once
+    pub level: LiquidationLevel,
+}
+
+impl LiquidationState {
+    pub fn new() -> LiquidationState {
+        LiquidationState {
+            liq_price: D128::ZERO,
+            maint_margin: D128::ZERO,
+            margin_balance: D128::ZERO,
+            level: LiquidationLevel::Safe,
+        }
+    }
+
+    /// How far price can move against the position before it's liquidated, as a fraction of mark
+    pub fn distance(&self, mark: D128) -> Option<D128> {
+        if !self.liq_price.is_positive() || !mark.is_positive() { return None; }
+        Some((mark - self.liq_price).abs() / mark)
+    }
+
+    /// Maintenance margin over margin balance, liquidation happens at 1
+    pub fn margin_ratio(&self) -> Option<D128> {
+        if !self.maint_margin.is_positive() || self.margin_balance.is_nan() { return None; }
+        if !self.margin_balance.is_positive() { return Some(D128::from(u32::MAX)); }
+        Some(self.maint_margin / self.margin_balance)
+    }
+
+    /// Worst of the distance and margin ratio levels at mark
+    pub fn assess(&self, mark: D128, limits: &RiskLimits) -> LiquidationLevel {
+        let distance = self.distance(mark);
+        let ratio = self.margin_ratio();
+        if below(distance, limits.liq_reduce_distance) || above(ratio, limits.margin_ratio_reduce) {
+            LiquidationLevel::Reduce
+        } else if below(distance, limits.liq_warn_distance) || above(ratio, limits.margin_ratio_warn) {
+            LiquidationLevel::Warn
+        } else {
+            LiquidationLevel::Safe
+        }
+    }
+}
+
+fn below(value: Option<D128>, limit: Option<D128>) -> bool {
+    matches!((value, limit), (Some(value), Some(limit)) if value < limit)
+}
+
+fn above(value: Option<D128>, limit: Option<D128>) -> bool {
+    matches!((value, limit), (Some(value), Some(limit)) if value > limit)
+}
+
+impl RiskLimits {
+    /// Share of the position closed each time it's de-risked
+    pub fn liq_reduce_fraction(&self) -> D128 {
+        self.liq_reduce_fraction.unwrap_or_else(|| D128::from(DEFAULT_REDUCE_FRACTION))
+    }
+}
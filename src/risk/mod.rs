@@ -0,0 +1,324 @@
+/// Pre-trade risk checks every order goes through before it reaches a broker.
+/// Limits come from the JSON file RISK_CONFIG points at and are picked up again whenever the file changes,
+/// any limit left out of the file is not enforced. Sizes and positions are in the venue's order units,
+/// notionals in the quote currency.
+
+mod breaker;
+mod liquidation;
+
+pub use self::breaker::*;
+pub use self::liquidation::*;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use dec::D128;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::config::CONFIG;
+use crate::telemetry::Venue;
+
+lazy_static! {
+    pub static ref RISK: RiskEngine = RiskEngine::new();
+    /// How often the limits file is checked for changes
+    static ref RELOAD_PERIOD: Duration = Duration::from_secs(5);
+    static ref RATE_WINDOW: Duration = Duration::from_secs(1);
+}
+
+/// Cancels needed before the cancel/fill ratio is enforced when the file doesn't say
+const DEFAULT_RATIO_MIN_CANCELS: u64 = 50;
+/// Seconds cancels and fills are counted over for the ratio when the file doesn't say
+const DEFAULT_RATIO_WINDOW_SECS: u64 = 300;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RiskLimits {
+    /// Largest notional a single order may carry
+    pub max_order_notional: Option<D128>,
+    /// Largest exposure either side may reach, filled plus resting entries
+    pub max_position: Option<D128>,
+    /// Largest absolute difference between the buy and sell exposure
+    pub max_net_position: Option<D128>,
+    /// Furthest an order price may sit from the reference price, as a fraction of it
+    pub price_band: Option<D128>,
+    /// Orders per second per venue and symbol
+    pub max_orders_per_sec: Option<u32>,
+    /// Cancels sent per fill received per venue and symbol
+    pub max_cancel_fill_ratio: Option<D128>,
+    /// Cancels sent inside the ratio window before the ratio is enforced, keeps a quiet stretch from tripping it
+    pub ratio_min_cancels: Option<u64>,
+    /// Seconds cancels and fills are counted over for the ratio, five minutes when unset
+    pub ratio_window_secs: Option<u64>,
+    /// Symbols that may be traded, any when unset
+    pub symbols: Option<Vec<String>>,
+    /// Largest loss a symbol may take since the start of the UTC day, realized plus unrealized
+    pub max_daily_loss: Option<D128>,
+    /// Largest drop a symbol's PnL may take from its peak inside the drawdown window
+    pub max_drawdown: Option<D128>,
+    /// Daily loss limit summed over every symbol on a venue
+    pub account_max_daily_loss: Option<D128>,
+    /// Drawdown limit summed over every symbol on a venue
+    pub account_max_drawdown: Option<D128>,
+    /// Seconds the drawdown peak is looked for in, an hour when unset
+    pub drawdown_window_secs: Option<u64>,
+    /// Close out open positions when the kill switch trips instead of only cancelling orders
+    pub flatten_on_trip: Option<bool>,
+    /// Distance to the liquidation price, as a fraction of mark, under which a warning is logged
+    pub liq_warn_distance: Option<D128>,
+    /// Distance to the liquidation price under which part of the position is closed
+    pub liq_reduce_distance: Option<D128>,
+    /// Maintenance margin over margin balance above which a warning is logged
+    pub margin_ratio_warn: Option<D128>,
+    /// Maintenance margin over margin balance above which part of the position is closed
+    pub margin_ratio_reduce: Option<D128>,
+    /// Share of the position closed per de-risk and per margin call, a quarter when unset
+    pub liq_reduce_fraction: Option<D128>,
+    /// Net filled delta past which the heavier side is trimmed with reduce-only exits, off when unset
+    pub hedge_threshold: Option<D128>,
+}
+
+/// Why an order was stopped, the code is what shows up in the logs
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum RiskReason {
+    #[error("Symbol is not on the allowlist")]
+    SymbolNotAllowed = 1001,
+    #[error("Order notional over the per order limit")]
+    OrderNotional = 1002,
+    #[error("Order would take the side's position over its limit")]
+    SidePosition = 1003,
+    #[error("Order would take the net position over its limit")]
+    NetPosition = 1004,
+    #[error("Order price outside the band around the reference price")]
+    PriceBand = 1005,
+    #[error("Too many orders sent in the last second")]
+    OrderRate = 1006,
+    #[error("Too many cancels sent per fill")]
+    CancelFillRatio = 1007,
+    #[error("Order size or price is not a positive number")]
+    InvalidOrder = 1008,
+    #[error("Kill switch tripped, entries are locked until it's reset")]
+    KillSwitch = 1009,
+}
+
+impl RiskReason {
+    pub fn code(&self) -> u16 {
+        *self as u16
+    }
+}
+
+/// Everything the checks need to know about an order about to be sent
+#[derive(Debug, Clone)]
+pub struct OrderCheck<'a> {
+    pub venue: Venue,
+    pub symbol: &'a str,
+    pub size: D128,
+    /// Limit price, or the expected fill price of a market order
+    pub price: D128,
+    /// Mid or mark the price band is measured from, the band is skipped while this is unknown
+    pub reference: D128,
+    pub notional: D128,
+    /// Exits only ever shrink the position, so they skip the notional, position and band checks, and are never held
+    /// back by the order rate or cancel/fill ratio since they're how the position gets out
+    pub reduces: bool,
+    /// The side's exposure once this order is added
+    pub position: D128,
+    /// Worst case net once this order is added
+    pub net: D128,
+}
+
+impl<'a> OrderCheck<'a> {
+    /// An order that can only shrink the position, e.g. a protective conditional, the position checks don't apply
+    pub fn reducing(venue: Venue, symbol: &'a str, size: D128, price: D128, notional: D128) -> OrderCheck<'a> {
+        OrderCheck {
+            venue,
+            symbol,
+            size,
+            price,
+            reference: D128::ZERO,
+            notional,
+            reduces: true,
+            position: D128::ZERO,
+            net: D128::ZERO,
+        }
+    }
+}
+
+/// Recent orders, cancels and fills, each trimmed to its window whenever it's looked at
+#[derive(Default)]
+struct Activity {
+    sent: VecDeque<Instant>,
+    cancels: VecDeque<Instant>,
+    fills: VecDeque<Instant>,
+}
+
+impl Activity {
+    fn trim(&mut self, now: Instant, ratio_window: Duration) {
+        for (events, window) in [(&mut self.sent, *RATE_WINDOW), (&mut self.cancels, ratio_window), (&mut self.fills, ratio_window)] {
+            while events.front().map_or(false, |at| now.duration_since(*at) > window) {
+                events.pop_front();
+            }
+        }
+    }
+}
+
+pub struct RiskEngine {
+    limits: RwLock<RiskLimits>,
+    activity: Mutex<HashMap<(Venue, String), Activity>>,
+    rejects: Mutex<HashMap<RiskReason, u64>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl RiskEngine {
+    fn new() -> RiskEngine {
+        let engine = RiskEngine {
+            limits: RwLock::new(RiskLimits::default()),
+            activity: Mutex::new(HashMap::new()),
+            rejects: Mutex::new(HashMap::new()),
+            modified: Mutex::new(None),
+        };
+        engine.reload();
+        engine
+    }
+
+    pub fn limits(&self) -> RiskLimits {
+        self.limits.read().unwrap().clone()
+    }
+
+    /// Reads the limits file again, the old limits stay in place if it can't be read or parsed
+    pub fn reload(&self) {
+        let path = match &CONFIG.risk_config {
+            Some(path) => path,
+            None => return,
+        };
+        let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        let limits = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| serde_json::from_str::<RiskLimits>(&raw).map_err(|e| e.to_string()));
+        *self.modified.lock().unwrap() = modified;
+        match limits {
+            Ok(limits) => {
+                info!("[RISK] Loaded limits from {}: {:?}", path, limits);
+                *self.limits.write().unwrap() = limits;
+            },
+            Err(e) => info!("[RISK] Failed to load limits from {}, keeping the old ones: {}", path, e),
+        }
+    }
+
+    /// Reloads only when the limits file has changed since the last load
+    fn reload_if_changed(&self) {
+        let path = match &CONFIG.risk_config {
+            Some(path) => path,
+            None => return,
+        };
+        let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        if modified != *self.modified.lock().unwrap() {
+            self.reload();
+        }
+    }
+
+    /// Runs every check against the order, counting it toward the order rate when it passes
+    pub fn check(&self, order: &OrderCheck) -> Result<(), RiskReason> {
+        let result = self.evaluate(order);
+        if let Err(reason) = result {
+            *self.rejects.lock().unwrap().entry(reason).or_insert(0) += 1;
+            debug!("[RISK] {:?} {} order rejected ({}): {}, size: {}, price: {}, reference: {}, notional: {}, position: {}, net: {}",
+                order.venue, order.symbol, reason.code(), reason, order.size, order.price, order.reference, order.notional, order.position, order.net);
+        }
+        result
+    }
+
+    fn evaluate(&self, order: &OrderCheck) -> Result<(), RiskReason> {
+        if order.size.is_nan() || !order.size.is_positive() || order.price.is_nan() || !order.price.is_positive() {
+            return Err(RiskReason::InvalidOrder);
+        }
+        let limits = self.limits.read().unwrap();
+        if let Some(symbols) = &limits.symbols {
+            if !symbols.iter().any(|symbol| symbol == order.symbol) {
+                return Err(RiskReason::SymbolNotAllowed);
+            }
+        }
+        if !order.reduces {
+            if BREAKER.is_tripped(order.venue, order.symbol) { return Err(RiskReason::KillSwitch); }
+            if let Some(max) = limits.max_order_notional {
+                if order.notional > max { return Err(RiskReason::OrderNotional); }
+            }
+            if let Some(max) = limits.max_position {
+                if order.position > max { return Err(RiskReason::SidePosition); }
+            }
+            if let Some(max) = limits.max_net_position {
+                if order.net.abs() > max { return Err(RiskReason::NetPosition); }
+            }
+            if let Some(band) = limits.price_band {
+                if order.reference.is_positive() && (order.price - order.reference).abs() > order.reference * band {
+                    return Err(RiskReason::PriceBand);
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let mut activity = self.activity.lock().unwrap();
+        let activity = activity.entry((order.venue, order.symbol.to_string())).or_default();
+        activity.trim(now, ratio_window(&limits));
+        if !order.reduces {
+            if let Some(max) = limits.max_orders_per_sec {
+                if activity.sent.len() >= max as usize { return Err(RiskReason::OrderRate); }
+            }
+            if let Some(max) = limits.max_cancel_fill_ratio {
+                let cancels = activity.cancels.len() as u64;
+                if cancels >= limits.ratio_min_cancels.unwrap_or(DEFAULT_RATIO_MIN_CANCELS)
+                    && D128::from(cancels) > max * D128::from(activity.fills.len().max(1) as u64) {
+                    return Err(RiskReason::CancelFillRatio);
+                }
+            }
+        }
+        activity.sent.push_back(now);
+        Ok(())
+    }
+
+    pub fn on_cancel(&self, venue: Venue, symbol: &str) {
+        self.record(venue, symbol, |activity| &mut activity.cancels);
+    }
+
+    pub fn on_fill(&self, venue: Venue, symbol: &str) {
+        self.record(venue, symbol, |activity| &mut activity.fills);
+    }
+
+    fn record(&self, venue: Venue, symbol: &str, events: fn(&mut Activity) -> &mut VecDeque<Instant>) {
+        let now = Instant::now();
+        let window = ratio_window(&self.limits.read().unwrap());
+        let mut activity = self.activity.lock().unwrap();
+        let activity = activity.entry((venue, symbol.to_string())).or_default();
+        activity.trim(now, window);
+        events(activity).push_back(now);
+    }
+
+    /// Current limits, per symbol activity and reject counts by reason
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "limits: {:?}", self.limits()).unwrap();
+        for ((venue, symbol), activity) in self.activity.lock().unwrap().iter() {
+            writeln!(out, "{:?} {}: cancels: {}, fills: {} in the ratio window, sent last second: {}", venue, symbol, activity.cancels.len(), activity.fills.len(), activity.sent.len()).unwrap();
+        }
+        for (reason, count) in self.rejects.lock().unwrap().iter() {
+            writeln!(out, "rejected ({}) {}: {}", reason.code(), reason, count).unwrap();
+        }
+        out
+    }
+}
+
+fn ratio_window(limits: &RiskLimits) -> Duration {
+    Duration::from_secs(limits.ratio_window_secs.unwrap_or(DEFAULT_RATIO_WINDOW_SECS))
+}
+
+/// Watches the limits file and swaps the new limits in when it changes, saving the day's losses on the same beat
+pub async fn reload_timer() {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + *RELOAD_PERIOD, *RELOAD_PERIOD);
+    loop {
+        interval.tick().await;
+        RISK.reload_if_changed();
+        BREAKER.save_daily();
+    }
+}
@@ -6,6 +6,7 @@ use crate::analysis::Analysis;
 use crate::tradeflow::TradeFlow;
 use crate::orderbook::OrderBook;
 use crate::strategy::binance::{StrategyMessage, ModelMessage};
+use crate::telemetry::{LATENCY, Venue, LatencyStage};
 use crossbeam_channel::Sender;
 use thiserror::Error;
 
@@ -48,10 +49,11 @@ impl SignalHandler {
     fn handle_orderbook_signal(&mut self, ob: Orders) {
         let timer = ob.test_timer;
         self.ob_model.binance_update(ob);
-        // info!("{}", timer.elapsed().as_nanos());
+        LATENCY.since(Venue::Binance, LatencyStage::ModelUpdate, timer);
 
         if self.ob_model.initialized {
             let mut analysis = Analysis::new_orderbook(&self.ob_model, &self.tr_model);
+            LATENCY.since(Venue::Binance, LatencyStage::Analysis, timer);
             analysis.test_timer = timer;
             self.strat_tx.send(StrategyMessage::ModelMessage(ModelMessage::OrderBookMessage(analysis))).unwrap();
         }
@@ -62,10 +64,11 @@ impl SignalHandler {
     fn handle_tradeflow_signal(&mut self, ft: FuturesTrades) {
         let timer = ft.test_timer;
         self.tr_model.binance_update(ft);
-        // info!("{}", timer.elapsed().as_nanos());
+        LATENCY.since(Venue::Binance, LatencyStage::ModelUpdate, timer);
 
         if self.ob_model.initialized {
             let mut analysis = Analysis::new_trade(&self.ob_model, &self.tr_model);
+            LATENCY.since(Venue::Binance, LatencyStage::Analysis, timer);
             analysis.test_timer = timer;
             self.strat_tx.send(StrategyMessage::ModelMessage(ModelMessage::TradeFlowMessage(analysis))).unwrap();
         }
@@ -75,7 +78,7 @@ impl SignalHandler {
     fn handle_book_ticker(&mut self, bt: BestLevel) {
         self.ob_model.tops.test_timer = bt.test_timer;
         self.ob_model.binance_update_best_ticker(bt);
-        // info!("{}", self.ob_model.tops.test_timer.elapsed().as_nanos());
+        LATENCY.since(Venue::Binance, LatencyStage::ModelUpdate, self.ob_model.tops.test_timer);
         if self.ob_model.initialized {
             let tops = self.ob_model.tops.clone();
             self.strat_tx.send(StrategyMessage::ModelMessage(ModelMessage::TopsMessage(tops))).unwrap();
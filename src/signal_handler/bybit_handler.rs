@@ -4,8 +4,9 @@
 use crate::backend::bybit::stream::{Signal, PrivateTicks, OBTick, TradeTick};
 use crate::analysis::Analysis;
 use crate::tradeflow::TradeFlow;
-use crate::strategy::bybit::{StrategyMessage, ModelMessage, OrderBookMessage, AccountMessage, PositionMessage, OrderMessage, BybitOrderTickSignal};
+use crate::strategy::bybit::{StrategyMessage, ModelMessage, OrderBookMessage, TradeFlowMessage, AccountMessage, PositionMessage, OrderMessage, WalletMessage, BybitOrderTickSignal};
 use crate::orderbook::OrderBook;
+use crate::telemetry::{LATENCY, Venue, LatencyStage};
 use crossbeam_channel::Sender;
 use thiserror::Error;
 
@@ -109,16 +110,21 @@ impl SignalHandler {
 
     /// Handles signals that are meant for orderbook updates
     fn handle_orderbook_signal(&mut self, ob: OBTick) {
-        
+        let timer = ob.test_timer;
         let timestamp = ob.timestamp.split_at(ob.timestamp.len() - 3);
-        
+        if ob.message_type == "snapshot" {
+            self.ob_model.clear();
+        }
         self.ob_model.bybit_update(
             ob.data,
             ob.cross_seq.parse().expect("problem parsing cross_seq"),
             timestamp.0.parse().expect("problem parsing timestamp"),
         );
+        LATENCY.since(Venue::Bybit, LatencyStage::ModelUpdate, timer);
 
-        let analysis_result = Analysis::new_orderbook(&self.ob_model, &self.tr_model);
+        let mut analysis_result = Analysis::new_orderbook(&self.ob_model, &self.tr_model);
+        LATENCY.since(Venue::Bybit, LatencyStage::Analysis, timer);
+        analysis_result.test_timer = timer;
         self.strat_tx.send(StrategyMessage::ModelMessage(
             ModelMessage::OrderBookMessage(OrderBookMessage {
                 orderbook_analysis: analysis_result})
@@ -127,9 +133,15 @@ impl SignalHandler {
 
     /// Handles tradeflow related signals
     fn handle_tradeflow_signal(&mut self, tr: TradeTick) {
+        let timestamp = tr.data.last().and_then(|t| t.trade_time_ms.parse().ok()).unwrap_or(0);
         self.tr_model.bybit_update(tr.data);
-        // Comming to a future update near u
-        //let _analysis_result = Analysis::new_trade(&self.ob_model, &self.tr_model);
+
+        if self.ob_model.initialized {
+            let trade = Analysis::new_trade(&self.ob_model, &self.tr_model);
+            self.strat_tx.send(StrategyMessage::ModelMessage(
+                ModelMessage::TradeFlowMessage(TradeFlowMessage { timestamp, trade })
+            )).expect("something went wrong sending trades to strat");
+        }
     }
 
     /// Handles private tick signals 
@@ -165,7 +177,12 @@ impl SignalHandler {
                 ))
                 .expect("msg");
             }
-            PrivateTicks::WalletTick(_) => {},
+            PrivateTicks::WalletTick(wt) => {
+                self.strat_tx.send(StrategyMessage::AccountMessage(
+                    AccountMessage::WalletMessage(WalletMessage::WalletUpdate(wt)),
+                ))
+                .expect("msg");
+            }
         };
     }
 
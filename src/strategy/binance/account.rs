@@ -1,13 +1,13 @@
 /// One day we'll use this
 use std::{collections::HashMap};
 
-use super::{Portfolio, strategy::Strategy};
+use super::{Portfolio, Runtime};
 
 use thiserror::Error;
 // #[derive(Debug)]
 pub struct Account {
     /// A mapping of asset pair names to the current state of the asset
-    pub asset_pairs: HashMap<String, Strategy>,
+    pub asset_pairs: HashMap<String, Runtime>,
 }
 
 
@@ -0,0 +1,263 @@
+/// Parent order algorithms for working larger inventory in and out.
+/// A parent is sliced into child limits that go through the portfolio like any other order,
+/// so children are held to the same margin and count limits, and simply wait a tick when they're refused.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dec::D128;
+use uuid::Uuid;
+
+use crate::analysis::TradeResult;
+use crate::backend::binance::types::OrderUpdateData;
+use crate::backend::types::Side;
+use crate::orderbook::Tops;
+use crate::strategy::types::{Stage, OrderClassification};
+
+use super::{Portfolio, StrategyMessage, AccountMessage};
+
+lazy_static! {
+    /// Smallest child the exchange will take, anything under it is left for the next slice
+    pub static ref MIN_CHILD_SIZE: D128 = D128::from(0.001);
+    static ref BPS: D128 = D128::from(10000);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecAlgo {
+    /// Releases the parent in even slices across the duration, each slice rests at the touch until it fills
+    Twap { slices: u32, duration: Duration },
+    /// Releases a fraction of the taker volume hitting our side of the book since the parent started
+    Pov { rate: D128 },
+    /// Only ever shows the display size, pegged to the top of book
+    Iceberg { display: D128 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParentProgress {
+    Working,
+    Done,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChildOrder {
+    price: D128,
+    size: D128,
+    filled: D128,
+    cancelling: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParentOrder {
+    pub id: Uuid,
+    pub side: Side,
+    pub stage: Stage,
+    pub algo: ExecAlgo,
+    pub size: D128,
+    pub filled: D128,
+    pub filled_liq: D128,
+    /// Mid at the time the parent was started, slippage is measured against it
+    pub arrival_price: D128,
+    /// Taker volume on our side of the book since the parent started, drives POV
+    pub market_volume: D128,
+    pub started: Instant,
+    pub progress: ParentProgress,
+    children: HashMap<Uuid, ChildOrder>,
+}
+
+/// Parent level progress, sent to the strategy on every child fill and when the parent finishes
+#[derive(Debug, Clone, Copy)]
+pub struct ExecutionReport {
+    pub id: Uuid,
+    pub side: Side,
+    pub stage: Stage,
+    pub algo: ExecAlgo,
+    pub size: D128,
+    pub filled: D128,
+    pub avg_price: D128,
+    pub arrival_price: D128,
+    /// Against arrival in basis points, positive is worse for us
+    pub slippage: D128,
+    pub progress: ParentProgress,
+}
+
+impl ParentOrder {
+    /// The side actually sent to the exchange, exits trade against their position
+    fn broker_side(&self) -> Side {
+        match self.stage { Stage::Entry => self.side, Stage::Exit => !self.side, }
+    }
+
+    /// Passive price on our side of the book
+    fn peg(&self, tops: &Tops) -> D128 {
+        self.broker_side().deside(&tops.best_bid, &tops.best_ask).0
+    }
+
+    /// Cumulative size the algorithm allows to be filled or working right now
+    fn released(&self) -> D128 {
+        let released = match self.algo {
+            ExecAlgo::Twap { slices, duration } => {
+                let slices = slices.max(1);
+                let due = ((self.started.elapsed().as_millis() * slices as u128) / duration.as_millis().max(1)) as u32 + 1;
+                self.size * D128::from(due.min(slices)) / D128::from(slices)
+            },
+            ExecAlgo::Pov { rate } => self.market_volume * rate,
+            ExecAlgo::Iceberg { display } => self.filled + display,
+        };
+        if released > self.size { self.size } else { released }
+    }
+
+    fn outstanding(&self) -> D128 {
+        let mut outstanding = D128::ZERO;
+        for (_, child) in self.children.iter() {
+            outstanding += child.size - child.filled;
+        }
+        outstanding
+    }
+
+    pub fn avg_price(&self) -> D128 {
+        if self.filled.is_zero() { D128::NAN } else { self.filled_liq / self.filled }
+    }
+
+    pub fn report(&self) -> ExecutionReport {
+        let avg_price = self.avg_price();
+        let slippage = if self.filled.is_zero() { D128::ZERO } else {
+            let raw = (avg_price - self.arrival_price) / self.arrival_price * *BPS;
+            match self.broker_side() { Side::Buy => raw, Side::Sell => raw * -1, }
+        };
+        ExecutionReport {
+            id: self.id,
+            side: self.side,
+            stage: self.stage,
+            algo: self.algo,
+            size: self.size,
+            filled: self.filled,
+            avg_price,
+            arrival_price: self.arrival_price,
+            slippage,
+            progress: self.progress,
+        }
+    }
+}
+
+pub struct Executor {
+    pub parents: HashMap<Uuid, ParentOrder>,
+    tops: Option<Tops>,
+    strat_tx: Sender<StrategyMessage>,
+}
+
+impl Executor {
+    pub fn new(strat_tx: Sender<StrategyMessage>) -> Executor {
+        Executor { parents: HashMap::new(), tops: None, strat_tx }
+    }
+
+    /// Starts a parent order, None until the first tops have come in since there's nothing to price against
+    pub fn start(&mut self, algo: ExecAlgo, side: Side, stage: Stage, size: D128) -> Option<Uuid> {
+        let tops = self.tops?;
+        let id = Uuid::new_v4();
+        let arrival_price = (tops.best_bid.0 + tops.best_ask.0) / 2;
+        info!("[EXEC] {} {:?} {:?} parent {} started for {} at {}", side, stage, algo, id, size, arrival_price);
+        self.parents.insert(id, ParentOrder {
+            id, side, stage, algo, size,
+            filled: D128::ZERO,
+            filled_liq: D128::ZERO,
+            arrival_price,
+            market_volume: D128::ZERO,
+            started: Instant::now(),
+            progress: ParentProgress::Working,
+            children: HashMap::new(),
+        });
+        Some(id)
+    }
+
+    /// Pulls a parent's children and reports it as cancelled, fills so far stay in the portfolio
+    pub fn cancel(&mut self, id: Uuid, portfolio: &mut Portfolio) {
+        if let Some(mut parent) = self.parents.remove(&id) {
+            Executor::cancel_children(&mut parent, portfolio);
+            parent.progress = ParentProgress::Cancelled;
+            self.send_report(&parent);
+        }
+    }
+
+    /// Re-pegs working children and releases new slices
+    pub fn tops(&mut self, tops: Tops, portfolio: &mut Portfolio) {
+        self.tops = Some(tops);
+        let mut finished = vec![];
+        for (id, parent) in self.parents.iter_mut() {
+            let (side, stage) = (parent.side, parent.stage);
+            // Children the portfolio has written off, filled or failed, stop counting as outstanding
+            parent.children.retain(|child_id, _| portfolio.order_working(*child_id, side, stage));
+            if parent.filled >= parent.size {
+                finished.push(*id);
+                continue;
+            }
+
+            let peg = parent.peg(&tops);
+            for (child_id, child) in parent.children.iter_mut() {
+                if child.price != peg && !child.cancelling && portfolio.order_cancellable(*child_id, side, stage) {
+                    child.cancelling = portfolio.cancel_order(*child_id, side, stage);
+                }
+            }
+
+            let want = (parent.released() - parent.filled - parent.outstanding()).round_down(-3);
+            if want < *MIN_CHILD_SIZE { continue; }
+            let child_id = Uuid::new_v4();
+            if portfolio.new_limit(Some(child_id), peg, want, side, stage, OrderClassification::Algo) {
+                parent.children.insert(child_id, ChildOrder { price: peg, size: want, filled: D128::ZERO, cancelling: false });
+            }
+        }
+        for id in finished {
+            if let Some(mut parent) = self.parents.remove(&id) {
+                Executor::cancel_children(&mut parent, portfolio);
+                parent.progress = ParentProgress::Done;
+                info!("[EXEC] parent {} done, {} at {} ({} bps)", id, parent.filled, parent.avg_price(), parent.report().slippage);
+                self.send_report(&parent);
+            }
+        }
+    }
+
+    /// Feeds POV parents the taker volume that would have traded against them
+    pub fn trades(&mut self, tr: TradeResult) {
+        for (_, parent) in self.parents.iter_mut() {
+            // Resting bids are filled by taker sells and vice versa
+            parent.market_volume += match parent.broker_side() {
+                Side::Buy => tr.sell_volume,
+                Side::Sell => tr.buy_volume,
+            };
+        }
+    }
+
+    /// Books child fills against their parent, must see updates before the portfolio cleans finished orders
+    pub fn order_update(&mut self, order: &OrderUpdateData) {
+        if order.last_filled_qty.is_zero() { return; }
+        let child_id = match Uuid::parse_str(&order.id) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+        let mut report = None;
+        for (_, parent) in self.parents.iter_mut() {
+            if let Some(child) = parent.children.get_mut(&child_id) {
+                child.filled += order.last_filled_qty;
+                parent.filled += order.last_filled_qty;
+                parent.filled_liq += order.last_filled_qty * order.filled_price;
+                report = Some(parent.report());
+                break;
+            }
+        }
+        if let Some(report) = report {
+            self.strat_tx.send(StrategyMessage::AccountMessage(AccountMessage::ExecutionReport(report))).unwrap();
+        }
+    }
+
+    fn cancel_children(parent: &mut ParentOrder, portfolio: &mut Portfolio) {
+        for (child_id, child) in parent.children.iter_mut() {
+            if !child.cancelling && portfolio.order_cancellable(*child_id, parent.side, parent.stage) {
+                child.cancelling = portfolio.cancel_order(*child_id, parent.side, parent.stage);
+            }
+        }
+    }
+
+    fn send_report(&self, parent: &ParentOrder) {
+        self.strat_tx.send(StrategyMessage::AccountMessage(AccountMessage::ExecutionReport(parent.report()))).unwrap();
+    }
+}
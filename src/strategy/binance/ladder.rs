@@ -0,0 +1,251 @@
+/**
+ * THIS FILE HAS HAD CONTENT REDACTED
+ */
+
+/// The ladder strategy: a top of book order on each side at all times, with rebase entries laddered behind it at the
+/// cost basis that would leave the position flat after fees.
+
+use dec::D128;
+
+use crate::analysis::BookResult;
+use crate::backend::types::Side;
+use crate::orderbook::Tops;
+use crate::strategy::types::{OrderClassification, Stage};
+use crate::telemetry::Venue;
+
+use super::{FindCancelRes, StratBranch};
+use super::order_list::OrderData;
+use super::strategy::{Strategy, Context, CHIRP, CHIRP_ON_FLIP, CHIRP_INCLUDES_DATA};
+
+pub struct Ladder {
+    last_buy_branch: StratBranch,
+    last_sell_branch: StratBranch,
+}
+
+impl Strategy for Ladder {
+    fn name(&self) -> &'static str {
+        "ladder"
+    }
+
+    fn on_book(&mut self, ctx: &mut Context, book: &BookResult) {
+        self.orderbook(ctx, Side::Buy, *book);
+        self.orderbook(ctx, Side::Sell, *book);
+    }
+
+    fn on_tops(&mut self, ctx: &mut Context, tops: Tops) {
+        self.tops(ctx, Side::Buy, tops);
+        self.tops(ctx, Side::Sell, tops);
+    }
+}
+
+impl Ladder {
+    pub fn new() -> Ladder {
+        Ladder {
+            last_buy_branch: StratBranch::SSS,
+            last_sell_branch: StratBranch::SSS,
+        }
+    }
+
+    /** Controls the logic for responding to new best levels on the orderbook.
+     * The general rule is to always have a top level order in play, either entry or exit.
+     * IE: Always seek entry if inventory is 0, always seek exit if inventory > 0.
+     */
+    fn tops(&mut self, ctx: &mut Context, side: Side, tops: Tops) {
+        let entry_price = side.deside(&tops.best_bid, &tops.best_ask).0;
+        let exit_price = side.deside(&tops.best_ask, &tops.best_bid).0;
+
+        match self.resolve_strat_branch(ctx, side) {
+            StratBranch::NNN => {
+                ctx.portfolio.new_limit(
+                    None,
+                    entry_price,
+                    ctx.portfolio.init_size,
+                    side,
+                    Stage::Entry,
+                    OrderClassification::Top,
+                );
+            },
+            StratBranch::NNS => {
+                let exit_size = side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_position.inv;
+                ctx.portfolio.new_limit(
+                    None,
+                    exit_price,
+                    exit_size,
+                    side,
+                    Stage::Exit,
+                    OrderClassification::Top,
+                );
+            },
+            StratBranch::NSS => {
+                ctx.portfolio.cancel_non_tops(exit_price, side, Stage::Exit);
+            },
+            StratBranch::SNN => {
+                match ctx.portfolio.cancel_non_tops(entry_price, side, Stage::Entry) {
+                    FindCancelRes::Found => {},
+                    FindCancelRes::Cancelled => {},
+                    FindCancelRes::NotFound => {
+                        // Just because there are opens does not mean they are Top classified, so let's add one that is
+                        ctx.portfolio.new_limit(
+                            None,
+                            entry_price,
+                            ctx.portfolio.init_size,
+                            side,
+                            Stage::Entry,
+                            OrderClassification::Top,
+                        );
+                    },
+                };
+            },
+            StratBranch::SNS => {
+                let exit_size = side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_position.inv;
+                ctx.portfolio.new_limit(
+                    None,
+                    exit_price,
+                    exit_size,
+                    side,
+                    Stage::Exit,
+                    OrderClassification::Top,
+                );
+            },
+            StratBranch::SSS => {
+                ctx.portfolio.cancel_non_tops(exit_price, side, Stage::Exit);
+            },
+            StratBranch::NSN | StratBranch::SSN => self.desync(ctx, side),
+        }
+    }
+
+    /** The strategy's response to order book updates.
+     * Commissions mean entering with a cost basis better than the entry price
+     * This permits neutralizing the cost basis during a sweep
+     * The main goal of this function is to demonstrate a simple ladder of orders which maintain a pnl >= 0
+     */
+    fn orderbook(&mut self, ctx: &mut Context, side: Side, ob: BookResult) {
+        let entry_price = side.deside(&ob.best_bid, &ob.best_ask).0;
+        let exit_price = side.deside(&ob.best_ask, &ob.best_bid).0;
+        let maker_rebate = ctx.params.rebate(Venue::Binance, &ctx.portfolio.symbol);
+        let rebate = match side {
+            Side::Buy => D128::ONE - maker_rebate,
+            Side::Sell => D128::ONE + maker_rebate,
+        };
+
+        match self.resolve_strat_branch(ctx, side) {
+            StratBranch::NNN => {
+                // Wait for tops
+            },
+            StratBranch::NNS => {
+                // Wait for tops
+            },
+            StratBranch::NSS => {
+                // Wait for tops
+            },
+            StratBranch::SNN => {
+                match ctx.portfolio.get_top_data(side, Stage::Entry) {
+                    Some(top) => {
+                        match ctx.portfolio.cancel_distant_rebases(top.neutral_cb(rebate), side, Stage::Entry) {
+                            FindCancelRes::Found => {},
+                            FindCancelRes::Cancelled => {},
+                            FindCancelRes::NotFound => {
+                                while ctx.portfolio.new_limit(
+                                    None,
+                                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell)
+                                        .neutral_cb(rebate, side),
+                                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell)
+                                        .open_liqs.total_outstanding.inv,
+                                    side,
+                                    Stage::Entry,
+                                    OrderClassification::Rebase,
+                                ) {}
+                            },
+                        }
+                    },
+                    None => {
+                        let mut od = OrderData::new();
+                        od.update(
+                            ctx.portfolio.init_size,
+                            ctx.portfolio.init_size * entry_price,
+                            ctx.portfolio.init_size * entry_price * maker_rebate
+                        );
+                        ctx.portfolio.cancel_distant_rebases(od.neutral_cb(rebate), side, Stage::Entry);
+                    },
+                }
+            },
+            StratBranch::SNS => {
+                while ctx.portfolio.new_limit(
+                    None,
+                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).neutral_cb(rebate, side),
+                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_liqs.total_outstanding.inv,
+                    side,
+                    Stage::Entry,
+                    OrderClassification::Rebase,
+                ) {}
+            },
+            StratBranch::SSS => {
+                while ctx.portfolio.new_limit(
+                    None,
+                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).neutral_cb(rebate, side),
+                    side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_liqs.total_outstanding.inv,
+                    side,
+                    Stage::Entry,
+                    OrderClassification::Rebase,
+                ) {}
+            },
+            StratBranch::NSN | StratBranch::SSN => self.desync(ctx, side),
+        }
+    }
+
+    /// Probable error case: active closes with no inventory.
+    /// Closes can't do anything without inventory so pull them, then ask the exchange what's really going on.
+    fn desync(&mut self, ctx: &mut Context, side: Side) {
+        ctx.portfolio.cancel_all(side, Stage::Exit);
+        if ctx.portfolio.request_reconcile() {
+            info!("POSSIBLE DESYNC: CLOSING ORDERS RESTING WITH EMPTY POSITION, reconciling\n{}",
+            side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell));
+        }
+    }
+
+    /// Quick and dirty debug outputs
+    fn chirp(&mut self, ctx: &Context, branch: StratBranch, side: Side) -> bool {
+        match CHIRP {
+            true => match CHIRP_ON_FLIP {
+                true => match (match side { Side::Buy => self.last_buy_branch, Side::Sell => self.last_sell_branch }) != branch {
+                        true => {
+                            match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                            info!("{} {:?}", side, branch);
+                            if CHIRP_INCLUDES_DATA { info!("data: {}", ctx.portfolio.data); }
+                            true
+                        },
+                        false => {
+                            match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                            false
+                        },
+                    },
+                false => {
+                    match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                    info!("{} {:?}", side, branch);
+                    if CHIRP_INCLUDES_DATA { info!("data: {}", ctx.portfolio.data); }
+                    true
+                },
+            },
+            false => {
+                match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                false
+            },
+        }
+    }
+
+    /** Breaks down the position into one of nine states, for whether or not open orders and close orders are active, and
+        whether or not a position is open. StratBranch represents these states in Option style: N for None, S for Some.
+    */
+    fn resolve_strat_branch(&mut self, ctx: &Context, side: Side) -> StratBranch {
+        // 
+        if side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_liqs.total_reserved.count < D128::ZERO ||
+        side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).close_liqs.total_reserved.count < D128::ZERO {
+            panic!("reserve count dropped below 0");
+        }
+        let branch = StratBranch::from((side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_liqs.total_reserved.count == D128::ZERO,
+            side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).close_liqs.total_reserved.count == D128::ZERO,
+            side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_liqs.filled.liq <= D128::ZERO));
+        self.chirp(ctx, branch, side);
+        branch
+    }
+}
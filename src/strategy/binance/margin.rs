@@ -0,0 +1,98 @@
+/// Margin requirements the portfolio sizes itself against.
+/// Leverage and margin type are applied from config at startup, then read back along with the symbol's leverage brackets.
+
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use dec::D128;
+
+use crate::backend::binance::broker::BROKER;
+use crate::backend::binance::types::{MarginType, LeverageBracket, LeverageWrapper, MarginTypeWrapper, LeverageBracketWrapper, PositionRiskWrapper};
+use crate::config::CONFIG;
+
+use super::{StrategyMessage, AccountMessage, MARGIN_USAGE};
+
+/// Seconds between fetches while the margin settings are still unknown
+pub const MARGIN_RETRY_PERIOD: u64 = 30;
+
+#[derive(Clone, Debug)]
+pub struct MarginSnapshot {
+    pub leverage: D128,
+    pub margin_type: MarginType,
+    /// Sorted by notional floor
+    pub brackets: Vec<LeverageBracket>,
+}
+
+impl MarginSnapshot {
+    /// Applies the configured leverage and margin type, then reads back what the exchange actually has.
+    /// Returns None if the read back fails, sizing off a guess is how accounts get liquidated.
+    pub async fn fetch(symbol: String) -> Option<MarginSnapshot> {
+        let symbol = symbol.to_uppercase();
+        if let Some(margin_type) = &CONFIG.margin_type {
+            match MarginType::try_from(margin_type.clone()) {
+                Ok(margin_type) => match BROKER.set_margin_type(symbol.clone(), margin_type).await {
+                    MarginTypeWrapper::Success(_) => info!("[MARGIN] {} set to {:?} margin", symbol, margin_type),
+                    MarginTypeWrapper::Error(e) => debug!("[MARGIN] {} margin type left as is: {}", symbol, e.msg),
+                },
+                Err(e) => info!("[MARGIN] Ignoring configured margin type {}: {}", margin_type, e),
+            }
+        }
+        if let Some(leverage) = CONFIG.leverage {
+            match BROKER.set_leverage(symbol.clone(), leverage).await {
+                LeverageWrapper::Leverage(res) => info!("[MARGIN] {} set to {}x, max notional {}", symbol, res.leverage, res.max_notional_value),
+                LeverageWrapper::Error(e) => info!("[MARGIN] Failed to set {} leverage to {}x: {}", symbol, leverage, e.msg),
+            }
+        }
+        let position = match BROKER.position_risk(symbol.clone()).await {
+            Ok(PositionRiskWrapper::Positions(positions)) => positions.into_iter().next()?,
+            Ok(PositionRiskWrapper::Error(_)) => return None,
+            Err(e) => {
+                info!("[MARGIN] Failed to pull {} position risk: {}", symbol, e);
+                return None;
+            },
+        };
+        let mut brackets = match BROKER.leverage_brackets(symbol.clone()).await {
+            LeverageBracketWrapper::Brackets(symbols) => symbols.into_iter().find(|s| s.symbol == symbol)?.brackets,
+            LeverageBracketWrapper::Error(_) => return None,
+        };
+        brackets.sort_by(|a, b| a.notional_floor.partial_cmp(&b.notional_floor).unwrap());
+        Some(MarginSnapshot { leverage: position.leverage, margin_type: position.margin_type, brackets })
+    }
+
+    /// Largest notional the balance can carry, counting initial margin and the maintenance margin of the tier it lands in.
+    /// Tiers that don't allow our leverage cap it at the last one that does.
+    pub fn max_notional(&self, balance: D128) -> D128 {
+        let budget = balance * *MARGIN_USAGE;
+        if !budget.is_positive() || !self.leverage.is_positive() { return D128::ZERO; }
+        if self.brackets.is_empty() { return budget * self.leverage; }
+        let initial = D128::ONE / self.leverage;
+        let mut max = D128::ZERO;
+        for bracket in self.brackets.iter() {
+            if D128::from(bracket.initial_leverage) < self.leverage { break; }
+            // initial + maintenance = notional / leverage + notional * mmr - cum
+            let notional = (budget + bracket.cum) / (initial + bracket.maint_margin_ratio);
+            if notional <= bracket.notional_cap { return notional; }
+            max = bracket.notional_cap;
+        }
+        max
+    }
+
+    /// Maintenance margin a position of notional requires, from the tier it lands in
+    pub fn maint_margin(&self, notional: D128) -> D128 {
+        match self.brackets.iter().find(|bracket| notional <= bracket.notional_cap).or(self.brackets.last()) {
+            Some(bracket) => notional * bracket.maint_margin_ratio - bracket.cum,
+            None => D128::ZERO,
+        }
+    }
+}
+
+pub async fn send_margin(symbol: String, sender: Sender<StrategyMessage>) {
+    let snapshot = MarginSnapshot::fetch(symbol).await;
+    sender.send(StrategyMessage::AccountMessage(AccountMessage::MarginRefresh(snapshot))).unwrap();
+}
+
+/// Fetches again once the retry period is up, entries stay off until one lands
+pub async fn retry_margin(symbol: String, sender: Sender<StrategyMessage>) {
+    tokio::time::sleep(Duration::from_secs(MARGIN_RETRY_PERIOD)).await;
+    send_margin(symbol, sender).await;
+}
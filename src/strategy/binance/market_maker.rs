@@ -0,0 +1,129 @@
+/// Avellaneda–Stoikov market maker, see strategy/market_maker.rs for the model.
+/// Keeps one post-only quote either side. A side holding inventory quotes an exit for it, the other side quotes
+/// an entry, so the bid and ask work the net position rather than growing both legs.
+
+use dec::D128;
+use uuid::Uuid;
+
+use crate::analysis::BookResult;
+use crate::backend::types::Side;
+use crate::strategy::market_maker::{MarketMakerSettings, QuoteModel};
+use crate::strategy::types::{OrderClassification, Stage};
+
+use super::strategy::{Strategy, Context};
+
+/// An order this strategy has resting or on its way
+#[derive(Debug, Clone, Copy)]
+struct RestingQuote {
+    id: Uuid,
+    side: Side,
+    stage: Stage,
+    price: D128,
+}
+
+pub struct MarketMaker {
+    model: QuoteModel,
+    bid: Option<RestingQuote>,
+    ask: Option<RestingQuote>,
+    last_book: Option<BookResult>,
+    refresh_timer: Option<u64>,
+}
+
+impl Strategy for MarketMaker {
+    fn name(&self) -> &'static str {
+        "market_maker"
+    }
+
+    fn on_init(&mut self, ctx: &mut Context) {
+        let period = std::time::Duration::from_secs(self.model.settings.refresh_secs);
+        self.refresh_timer = Some(ctx.timers.every(period));
+    }
+
+    fn on_book(&mut self, ctx: &mut Context, book: &BookResult) {
+        self.model.observe(book);
+        self.last_book = Some(*book);
+        self.requote(ctx, book);
+    }
+
+    fn on_timer(&mut self, ctx: &mut Context, id: u64) {
+        if self.refresh_timer != Some(id) { return; }
+        if let Some(book) = self.last_book {
+            self.requote(ctx, &book);
+        }
+    }
+
+    fn on_shutdown(&mut self, ctx: &mut Context) {
+        for quote in [self.bid.take(), self.ask.take()].iter().flatten() {
+            ctx.portfolio.cancel_order(quote.id, quote.side, quote.stage);
+        }
+    }
+}
+
+impl MarketMaker {
+    pub fn new() -> MarketMaker {
+        MarketMaker {
+            model: QuoteModel::new(MarketMakerSettings::load()),
+            bid: None,
+            ask: None,
+            last_book: None,
+            refresh_timer: None,
+        }
+    }
+
+    fn requote(&mut self, ctx: &mut Context, book: &BookResult) {
+        let long = ctx.portfolio.data.buy.open_position.inv;
+        let short = ctx.portfolio.data.sell.open_position.inv;
+        let quotes = match self.model.quote(book, long - short) {
+            Some(quotes) => quotes,
+            None => return,
+        };
+        debug!("[MM] r {} spread {} bid {} ask {} intensity {}", quotes.reservation, quotes.spread, quotes.bid, quotes.ask, quotes.intensity);
+        let settings = self.model.settings;
+        let lots = (long - short) / settings.order_size;
+        // Bids close shorts before they open longs, asks the other way around
+        let (bid_side, bid_stage, bid_size) = if short.is_positive() {
+            (Side::Sell, Stage::Exit, if short < settings.order_size { short } else { settings.order_size })
+        } else {
+            (Side::Buy, Stage::Entry, settings.order_size)
+        };
+        let (ask_side, ask_stage, ask_size) = if long.is_positive() {
+            (Side::Buy, Stage::Exit, if long < settings.order_size { long } else { settings.order_size })
+        } else {
+            (Side::Sell, Stage::Entry, settings.order_size)
+        };
+        let bid_price = if bid_stage == Stage::Entry && lots >= settings.max_inventory { None } else { Some(quotes.bid) };
+        let ask_price = if ask_stage == Stage::Entry && -lots >= settings.max_inventory { None } else { Some(quotes.ask) };
+        MarketMaker::work(ctx, &self.model, &mut self.bid, bid_side, bid_stage, bid_price, bid_size);
+        MarketMaker::work(ctx, &self.model, &mut self.ask, ask_side, ask_stage, ask_price, ask_size);
+    }
+
+    /// Moves one side's quote toward the target. A stale quote that keeps its side and stage is amended in place,
+    /// otherwise it's cancelled and replaced once the cancel lands.
+    fn work(ctx: &mut Context, model: &QuoteModel, slot: &mut Option<RestingQuote>, side: Side, stage: Stage, price: Option<D128>, size: D128) {
+        if let Some(quote) = slot.as_mut() {
+            if !ctx.portfolio.order_working(quote.id, quote.side, quote.stage) {
+                *slot = None;
+            } else {
+                let same_leg = quote.side == side && quote.stage == stage;
+                if same_leg && price.map_or(false, |price| model.within_requote(quote.price, price)) { return; }
+                if let Some(price) = price.filter(|_| same_leg && size.is_positive()) {
+                    if ctx.portfolio.amend_order(quote.id, price, size, side, stage) {
+                        quote.price = price;
+                        return;
+                    }
+                }
+                if ctx.portfolio.order_cancellable(quote.id, quote.side, quote.stage) {
+                    ctx.portfolio.cancel_order(quote.id, quote.side, quote.stage);
+                }
+                return;
+            }
+        }
+        if let Some(price) = price {
+            if !size.is_positive() { return; }
+            let id = Uuid::new_v4();
+            if ctx.portfolio.new_limit(Some(id), price, size, side, stage, OrderClassification::Top) {
+                *slot = Some(RestingQuote { id, side, stage, price });
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use crate::{backend::{binance::types::{PositionUpdateData, OrderUpdateData, OrderResponse, AccountBalance}, bybit::broker::Balance}, analysis::{BookResult, TradeResult}, orderbook::Tops};
+use crate::{backend::{binance::types::{PositionUpdateData, OrderUpdateData, OrderResponse, AccountBalance, MarginCall, SpotSignal}, bybit::broker::Balance}, analysis::{BookResult, TradeResult}, orderbook::Tops};
+use crate::strategy::params::ParamUpdate;
 
-use super::{OrderResponseContext, CancelResponseContext};
+use super::{SpotResponse, OrderResponseContext, CancelResponseContext, ReconcileSnapshot, ExecutionReport, MarginSnapshot};
 
 #[derive(Clone, Debug)]
 pub enum AccountMessage {
@@ -8,7 +9,12 @@ pub enum AccountMessage {
     OrderUpdate(OrderUpdateData),
     OrderResponse(OrderResponseContext),
     CancelResponse(CancelResponseContext),
+    AmendResponse(OrderResponseContext),
     BalanceRefresh(Vec<AccountBalance>),
+    Reconcile(Option<ReconcileSnapshot>),
+    ExecutionReport(ExecutionReport),
+    MarginRefresh(Option<MarginSnapshot>),
+    MarginCall(MarginCall),
 }
 
 #[derive(Clone, Debug)]
@@ -18,8 +24,22 @@ pub enum ModelMessage {
     TopsMessage(Tops),
 }
 
+/// Messages the runtime sends itself, Init carries the start time in ms and Timer a scheduler timer id.
+/// Params are parameter updates from CONTROL.
+#[derive(Clone, Debug)]
+pub enum OpMessage {
+    Init(u128),
+    Timer(u64),
+    Params(ParamUpdate),
+}
+
 #[derive(Clone, Debug)]
 pub enum StrategyMessage {
     ModelMessage(ModelMessage),
-    AccountMessage(AccountMessage)
+    AccountMessage(AccountMessage),
+    OpMessage(OpMessage),
+    /// Trades, depth and user data from the spot streams, only sent when BINANCE_SPOT_SYMBOL is set
+    SpotMessage(SpotSignal),
+    /// Answers to the orders and loans a strategy sent through ctx.spot
+    SpotResponse(SpotResponse),
 }
\ No newline at end of file
@@ -1,23 +1,37 @@
 mod account;
+mod execution;
+mod grid;
+mod ladder;
+mod market_maker;
+mod margin;
 mod portfolio;
 mod order_list;
 mod message;
 mod order;
 mod position;
+mod reconcile;
+mod runtime;
+mod spot;
 pub mod strategy;
 
 use dec::D128;
 
 pub use self::account::*;
+pub use self::execution::*;
+pub use self::margin::*;
 pub use self::order::*;
 pub use self::position::*;
 pub use self::message::*;
 pub use self::portfolio::*;
+pub use self::reconcile::*;
+pub use self::runtime::*;
+pub use self::spot::*;
 
 lazy_static! {
-    pub static ref REBATE: D128 = D128::from(0.00025);
     pub static ref MAX_OPEN_DIST: D128 = D128::from(30);
     pub static ref TOP_OPEN_DIST: D128 = D128::from(6);
+    /// Share of the balance that can be committed as margin
+    pub static ref MARGIN_USAGE: D128 = D128::from(0.8);
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
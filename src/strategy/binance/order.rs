@@ -8,11 +8,13 @@ use std::str::FromStr;
 // use std::time::Instant;
 use uuid::Uuid;
 
+use crate::backend::binance::errors::ErrorPolicy;
 use crate::backend::binance::types::{OrderResponse, OrderType, OrderStatus, CreateOrderStatus, OrderResponseWrapper, OrderUpdateData, CancelResponseWrapper, CancelResponse, BinanceError};
 use crate::backend::types::{TimeInForce, Side};
+use crate::strategy::protection::ConditionalKind;
 use crate::strategy::types::{Stage, OrderClassification};
-
-use super::REBATE;
+use crate::strategy::fees::{FEES, FeeRates};
+use crate::telemetry::Venue;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OrderProgress {
@@ -48,16 +50,23 @@ pub struct OrderResponseContext {
     pub stage: Stage,
     pub class: OrderClassification,
     pub rest_response: OrderResponseWrapper,
+    /// What the broker's policy table says to do about an error response
+    pub policy: ErrorPolicy,
 }
 
 impl OrderResponseContext {
     pub fn new(id: Uuid, side: Side, stage: Stage, class: OrderClassification, rest_response: OrderResponseWrapper) -> OrderResponseContext {
+        let policy = match &rest_response {
+            OrderResponseWrapper::Order(_) => ErrorPolicy::Ignore,
+            OrderResponseWrapper::Error(e) => e.policy(),
+        };
         OrderResponseContext {
             id,
             side,
             stage,
             class,
-            rest_response
+            rest_response,
+            policy,
         }
     }
 }
@@ -69,16 +78,23 @@ pub struct CancelResponseContext {
     pub stage: Stage,
     pub class: OrderClassification,
     pub rest_response: CancelResponseWrapper,
+    /// What the broker's policy table says to do about an error response
+    pub policy: ErrorPolicy,
 }
 
 impl CancelResponseContext {
     pub fn new(id: Uuid, side: Side, stage: Stage, class: OrderClassification, rest_response: CancelResponseWrapper) -> CancelResponseContext {
+        let policy = match &rest_response {
+            CancelResponseWrapper::Cancel(_) => ErrorPolicy::Ignore,
+            CancelResponseWrapper::Error(e) => e.policy(),
+        };
         CancelResponseContext {
             id,
             side,
             stage,
             class,
             rest_response,
+            policy,
         }
     }
 }
@@ -104,6 +120,8 @@ pub struct Order {
     pub order_class: OrderClassification,
     pub progress: OrderProgress,
     pub unknown_cancel_counter: usize,
+    /// Consecutive reconciles this order has been missing from the exchange
+    pub reconcile_misses: usize,
 }
 
 impl From<OrderUpdateData> for Order {
@@ -113,13 +131,21 @@ impl From<OrderUpdateData> for Order {
                 Some(Uuid::from_u128(incoming.auto_id as u128)),
                 Some(incoming.original_price),
                 incoming.original_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
             ),
             OrderType::Market => Order::new_orphan(
                 Some(Uuid::from_u128(incoming.auto_id as u128)),
                 Some(incoming.original_price),
                 incoming.original_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
+            ),
+            // Conditionals fill at market once triggered
+            _ => Order::new_orphan(
+                Some(Uuid::from_u128(incoming.auto_id as u128)),
+                None,
+                incoming.original_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
             ),
-            _ => todo!(),
         };
         order.id = Uuid::from_u128(incoming.auto_id as u128);
         order.auto_gen = true;
@@ -139,13 +165,20 @@ impl From<OrderResponse> for Order {
                 Some(incoming.id),
                 Some(incoming.price),
                 incoming.orig_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
             ),
             OrderType::Market => Order::new_orphan(
                 Some(incoming.id),
                 None,
                 incoming.orig_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
+            ),
+            _ => Order::new_orphan(
+                Some(incoming.id),
+                None,
+                incoming.orig_qty,
+                FEES.rates(Venue::Binance, &incoming.symbol),
             ),
-            _ => todo!(),
         };
         order.id = incoming.id;
         order.auto_id = incoming.auto_id;
@@ -160,9 +193,9 @@ impl From<OrderResponse> for Order {
 impl From<CancelResponse> for Order {
     fn from(incoming: CancelResponse) -> Self {
         let mut order = match incoming.order_type {
-            OrderType::Market => Order::new_orphan(Some(incoming.id), None, incoming.orig_qty),
-            OrderType::Limit => Order::new_orphan(Some(incoming.id), Some(incoming.price), incoming.orig_qty),
-            _ => todo!(),
+            OrderType::Market => Order::new_orphan(Some(incoming.id), None, incoming.orig_qty, FEES.rates(Venue::Binance, &incoming.symbol)),
+            OrderType::Limit => Order::new_orphan(Some(incoming.id), Some(incoming.price), incoming.orig_qty, FEES.rates(Venue::Binance, &incoming.symbol)),
+            _ => Order::new_orphan(Some(incoming.id), None, incoming.orig_qty, FEES.rates(Venue::Binance, &incoming.symbol)),
         };
         order.id = incoming.id;
         order.auto_id = incoming.auto_id;
@@ -201,10 +234,13 @@ impl Order {
 
     fn patch_ws(&mut self, order: &OrderUpdateData) {
         self.filled_size = order.accumulated_filled_qty;
-        self.filled_liq = order.accumulated_filled_qty * order.filled_price;
+        self.filled_liq = order.accumulated_filled_qty * order.average_price;
         self.unfilled_size = order.original_qty - order.accumulated_filled_qty;
         self.unfilled_liq = self.unfilled_size * order.original_price;
-        self.cum_fee = match order.commission { Some(c) => c, None => D128::ZERO, };
+        // Commission is per trade, not cumulative
+        if order.last_filled_qty.is_positive() {
+            self.cum_fee += order.commission.unwrap_or(D128::ZERO);
+        }
     }
 
     pub fn order_update(&mut self, order: OrderUpdateData) {
@@ -317,21 +353,47 @@ impl Order {
         }
     }
 
+    /// Only resting limits can be moved, and not while anything else is in flight for them
+    pub fn can_amend(&self) -> bool {
+        matches!(self.order_type, OrderType::Limit)
+            && (self.progress == OrderProgress::Resting || self.progress == OrderProgress::PartiallyFilled)
+            && !self.in_flight && !self.cancel_in_flight
+    }
+
+    pub fn pre_amend(&mut self) {
+        self.in_flight = true;
+    }
+
+    pub fn amend_response(&mut self, order: OrderResponse) {
+        self.in_flight = false;
+        self.orig_price = order.price;
+        self.orig_size = order.orig_qty;
+        self.patch_rest(&order);
+    }
+
+    /// The order is still resting where it was
+    pub fn fail_amend_response(&mut self) {
+        self.in_flight = false;
+    }
+
     pub fn cancel_response(&mut self) {
         self.cancel_in_flight = false;
         self.progress = OrderProgress::Cancelled;
     }
 
-    pub fn fail_cancel_response(&mut self, error: BinanceError) {
+    /// Returns true if the failures point to a desync with the exchange
+    pub fn fail_cancel_response(&mut self, error: BinanceError) -> bool {
         self.cancel_in_flight = false;
         if error.msg.contains("Unknown order sent") {
             self.unknown_cancel_counter += 1;
             debug!("unknown cancel");
             if self.unknown_cancel_counter > 3 {
-                panic!("possible desync: too many failed cancels, crashing.");
-                // self.progress = OrderProgress::Failed
+                // possible desync: too many failed cancels, write it off and let reconciliation find out the truth
+                self.progress = OrderProgress::Failed;
+                return true;
             }
         }
+        false
     }
 
     pub fn new_taker(
@@ -339,6 +401,7 @@ impl Order {
         expected_price: D128,
         size: D128,
         class: OrderClassification,
+        fees: FeeRates,
     ) -> Order {
         Order {
             id: match id {
@@ -350,7 +413,7 @@ impl Order {
             filled_price: D128::ZERO,
             orig_price: expected_price,
             orig_size: size,
-            expected_fee: expected_price * size * 0.00075,
+            expected_fee: fees.taker_fee(expected_price * size),
             in_flight: false,
             cancel_in_flight: false,
             unfilled_size: size,
@@ -363,6 +426,7 @@ impl Order {
             progress: OrderProgress::Init,
             order_class: class,
             unknown_cancel_counter: 0,
+            reconcile_misses: 0,
         }
     }
 
@@ -371,9 +435,10 @@ impl Order {
         price: D128,
         size: D128,
         class: OrderClassification,
+        fees: FeeRates,
     ) -> Order {
-        let mut ord = Order::new_taker(id, price, size, class);
-        ord.expected_fee = price * size * *REBATE * -1;
+        let mut ord = Order::new_taker(id, price, size, class, fees);
+        ord.expected_fee = fees.maker_fee(price * size);
         ord.time_in_force = TimeInForce::PostOnly;
         ord.order_type = OrderType::Limit;
         ord.unfilled_liq = ord.orig_price * ord.orig_size;
@@ -385,12 +450,42 @@ impl Order {
         id: Option<Uuid>,
         price: Option<D128>,
         size: D128,
+        fees: FeeRates,
     ) -> Order {
         let mut ord = match price {
-            Some(price) => Order::new_rebate(id, price, size, OrderClassification::None),
-            None => Order::new_taker(id, D128::NAN, size, OrderClassification::None),
+            Some(price) => Order::new_rebate(id, price, size, OrderClassification::None, fees),
+            None => Order::new_taker(id, D128::NAN, size, OrderClassification::None, fees),
         };
         ord.progress = OrderProgress::Untracked;
         ord
     }
+
+    /// Stop loss or take profit, the trigger price is held in orig_price
+    pub fn new_conditional(id: Option<Uuid>, kind: ConditionalKind, trigger: D128, size: D128, fees: FeeRates) -> Order {
+        let mut ord = Order::new_taker(id, trigger, size, OrderClassification::Protect, fees);
+        ord.order_type = match kind {
+            ConditionalKind::StopLoss => OrderType::StopMarket,
+            ConditionalKind::TakeProfit => OrderType::TakeProfitMarket,
+        };
+        ord
+    }
+
+    pub fn conditional_kind(&self) -> Option<ConditionalKind> {
+        match self.order_type {
+            OrderType::StopMarket => Some(ConditionalKind::StopLoss),
+            OrderType::TakeProfitMarket => Some(ConditionalKind::TakeProfit),
+            _ => None,
+        }
+    }
+
+    /// A synthetic fill used by reconciliation to bring our inventory in line with the exchange's
+    pub fn new_adjustment(price: D128, size: D128) -> Order {
+        let mut ord = Order::new_orphan(None, Some(price), size, FeeRates::NONE);
+        ord.filled_size = size;
+        ord.filled_liq = size * price;
+        ord.unfilled_size = D128::ZERO;
+        ord.unfilled_liq = D128::ZERO;
+        ord.progress = OrderProgress::Filled;
+        ord
+    }
 }
\ No newline at end of file
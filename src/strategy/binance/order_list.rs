@@ -13,6 +13,7 @@ use uuid::Uuid;
 use crate::backend::binance::types::CancelResponseWrapper;
 use crate::backend::binance::types::OrderResponseWrapper;
 use crate::backend::binance::types::OrderUpdateData;
+use crate::strategy::fees::FeeRates;
 use crate::strategy::types::OrderClassification;
 
 use super::Order;
@@ -241,58 +242,78 @@ impl OrderList {
                     occ.fail_response();
                 },
                 Vacant(vac) => { // If this was an existing orphan there's no way to get a link to it,
-                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO)); // But not the end of the world
+                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO, FeeRates::NONE)); // But not the end of the world
                     debug!("REST response's context didn't match to a known order, making orphan");
                 },
             },
         }
     }
 
-    pub fn rest_cancel(&mut self, id: Uuid, cancel: CancelResponseWrapper) {
+    pub fn rest_amend(&mut self, id: Uuid, order: OrderResponseWrapper) {
+        match (self.order_map.get_mut(&id), order) {
+            (Some(occ), OrderResponseWrapper::Order(ord)) => occ.amend_response(ord),
+            (Some(occ), OrderResponseWrapper::Error(e)) => {
+                debug!("Amend of {} failed, leaving it where it was: {}", id, e.msg);
+                occ.fail_amend_response();
+            },
+            (None, _) => debug!("REST amend response's context didn't match to a known order"),
+        }
+    }
+
+    /// Returns true if the cancel points to a desync with the exchange
+    pub fn rest_cancel(&mut self, id: Uuid, cancel: CancelResponseWrapper) -> bool {
         match cancel {
             CancelResponseWrapper::Cancel(can) => match self.order_map.entry(id) {
-                Occupied(mut occ) => occ.get_mut().cancel_response(),
+                Occupied(mut occ) => { occ.get_mut().cancel_response(); false },
                 Vacant(vac) => match self.order_map.entry(Uuid::from_u128(can.auto_id as u128)) {
-                    Occupied(mut occ) => occ.get_mut().cancel_response(),
+                    Occupied(mut occ) => { occ.get_mut().cancel_response(); false },
                     Vacant(vac) => {
                         vac.insert(Order::from(can));
                         info!("REST cancel response's context didn't match to a known order, making orphan");
+                        false
                     },
                 },
             },
             CancelResponseWrapper::Error(err) => match self.order_map.entry(id) {
                 Occupied(mut occ) => occ.get_mut().fail_cancel_response(err),
                 Vacant(vac) => {
-                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO));
+                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO, FeeRates::NONE));
                     info!("REST cancel response's context didn't match to a known order, making orphan");
+                    false
                 },
             },
         }
     }
 
-    pub fn ws_order(&mut self, order: OrderUpdateData) {
+    /// Returns true if the update didn't match a known order, meaning we've lost track of something
+    pub fn ws_order(&mut self, order: OrderUpdateData) -> bool {
         match Uuid::from_str(&order.id) {
             Ok(id) => match self.order_map.entry(id) {
                 Occupied(mut occ) => {
                     occ.get_mut().order_update(order);
+                    false
                 },
                 Vacant(_) => match self.order_map.entry(Uuid::from_u128(order.auto_id as u128)) {
                     Occupied(mut occ) => {
                         occ.get_mut().order_update(order);
+                        false
                     },
                     Vacant(vac) => {
                         vac.insert(Order::from(order));
-                        panic!("WS update didn't match a known order, making orphan");
+                        info!("WS update didn't match a known order, making orphan");
+                        true
                     },
                 },
             },
             Err(_) => match self.order_map.entry(Uuid::from_u128(order.auto_id as u128)) {
                 Occupied(mut occ) => {
                     occ.get_mut().order_update(order);
+                    false
                 },
                 Vacant(vac) => {
                     vac.insert(Order::from(order));
-                    panic!("UUID ERROR WS update didn't match a known order, making orphan");
+                    info!("UUID ERROR WS update didn't match a known order, making orphan");
+                    true
                 },
             },
         }
@@ -307,6 +328,19 @@ impl OrderList {
         }
     }
 
+    /// Finds the local id for an exchange order, by client id first and exchange id second
+    pub fn find_id(&self, client_id: &str, auto_id: u64) -> Option<Uuid> {
+        if let Ok(id) = Uuid::from_str(client_id) {
+            if self.order_map.contains_key(&id) { return Some(id); }
+        }
+        let orphan_id = Uuid::from_u128(auto_id as u128);
+        if self.order_map.contains_key(&orphan_id) { return Some(orphan_id); }
+        match self.order_map.iter().find(|(_, order)| order.auto_id == auto_id) {
+            Some((id, _)) => Some(*id),
+            None => None,
+        }
+    }
+
     pub fn clean(&mut self) {
         self.order_map.retain(|_, ord| ord.progress.incomplete_unfailed());
     }
@@ -1,18 +1,28 @@
 use std::fmt::{Display, Formatter};
+use std::time::Instant;
 
 use crossbeam_channel::Sender;
 /// Bybit Account --> account interface --> position interface --> position --> orders
 
 use dec::D128;
-use tokio::runtime::{Runtime, Builder};
+use tokio::runtime::{Runtime, Builder, Handle};
 use uuid::Uuid;
 
-use crate::backend::binance::types::{PositionUpdateData, BinanceSide, OrderUpdateData, OrderResponseWrapper, CancelResponseWrapper, AccountBalance, PositionUpdatePosition, PositionUpdateBalance};
+use crate::backend::binance::broker::{BROKER, HALT_PERIOD};
+use crate::backend::binance::errors::ErrorPolicy;
+use crate::backend::binance::types::{PositionUpdateData, BinanceSide, OrderUpdateData, OrderResponseWrapper, CancelResponseWrapper, AccountBalance, PositionUpdatePosition, PositionUpdateBalance, MarginCall, MarginType};
 use crate::backend::types::Side;
+use crate::orderbook::Tops;
+use crate::risk::{RISK, BREAKER, OrderCheck, LiquidationLevel, DERISK_COOLDOWN};
+use crate::strategy::protection::{ConditionalKind, ProtectionSettings};
+use crate::strategy::exposure::{Exposure, SideExposure};
+use crate::strategy::ledger::PNL;
+use crate::strategy::params::Params;
 use crate::strategy::types::{Stage, OrderClassification};
+use crate::telemetry::{PortfolioMetrics, Venue};
 
 use super::order_list::OrderData;
-use super::{StrategyMessage, Position, PositionData, FinData, FindCancelRes, Order, OrderResponseContext};
+use super::{StrategyMessage, Position, MarginSnapshot, PositionData, FinData, FindCancelRes, Order, OrderResponseContext, ReconcileSnapshot, send_reconcile, retry_margin, RECONCILE_COOLDOWN, MARGIN_RETRY_PERIOD};
 
 #[derive(Clone, Copy)]
 pub struct Limits {
@@ -27,14 +37,13 @@ pub struct PortfolioData {
     pub sell: PositionData,
     pub remaining_margin: D128,
     pub remaining_count: D128,
-    // Someday lmao
-    // pub delta: PositionData,
+    pub exposure: Exposure,
 }
 
 impl Display for PortfolioData {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        write!(f, "Portfolio Data: \n{{\n    buys: {},\n    sells: {},\n    remaining margin: {}, remaining count: {}\n}}",
-        self.buy, self.sell, self.remaining_margin, self.remaining_count)
+        write!(f, "Portfolio Data: \n{{\n    buys: {},\n    sells: {},\n    remaining margin: {}, remaining count: {}\n    {}\n}}",
+        self.buy, self.sell, self.remaining_margin, self.remaining_count, self.exposure)
     }
 }
 
@@ -45,6 +54,7 @@ impl PortfolioData {
             sell: PositionData::new(),
             remaining_margin: D128::ZERO,
             remaining_count: D128::ZERO,
+            exposure: Exposure::new(),
         }
     }
 }
@@ -70,10 +80,29 @@ pub struct Portfolio {
     pub max_size: D128,
     pub balance: D128,
     pub available_balance: D128,
+    /// Leverage and brackets max_size is worked out from, None until the startup fetch lands
+    pub margin: Option<MarginSnapshot>,
+    /// Mid price, converts the notional the margin allows into size
+    pub price: D128,
     ///
     pub rebase_distance_limit: D128,
     /// Channel for sending updates back to the main strategy
     pub strat_tx: Sender<StrategyMessage>,
+    /// Set when an error's policy says the symbol can't be traded, blocks all new orders until HALT_PERIOD passes or a resume
+    pub halted: bool,
+    halted_at: Instant,
+    /// The broker's resume count when the halt was set
+    halt_resumes: u64,
+    /// Set while the kill switch covers this symbol, orders were cancelled when it tripped
+    pub tripped: bool,
+    /// Entries on a disabled side are refused, exits still go out
+    pub buy_enabled: bool,
+    pub sell_enabled: bool,
+    /// Set while a desync triggered reconcile is waiting on the exchange
+    reconcile_in_flight: bool,
+    last_reconcile: Instant,
+    /// When part of a position was last closed for being too close to liquidation
+    last_derisk: Option<Instant>,
     pool: Runtime
 }
 
@@ -96,11 +125,26 @@ impl Portfolio {
             max_size: D128::ZERO,
             balance: D128::ZERO,
             available_balance: D128::ZERO,
+            margin: None,
+            price: D128::ZERO,
             symbol: symbol,
             strat_tx,
+            halted: false,
+            halted_at: Instant::now(),
+            halt_resumes: 0,
+            tripped: false,
+            buy_enabled: true,
+            sell_enabled: true,
+            reconcile_in_flight: false,
+            last_reconcile: Instant::now(),
+            last_derisk: None,
             pool,
             data: PortfolioData::new(),
         };
+        let settings = ProtectionSettings::load();
+        if !settings.is_none() {
+            app.set_protection(settings);
+        }
         app.data_refresh();
         Ok(app)
     }
@@ -113,11 +157,30 @@ impl Portfolio {
             sell: sell,
             remaining_margin: self.max_size - (buy.open_liqs.total_reserved.inv + sell.open_liqs.total_reserved.inv),
             remaining_count: self.max_open_orders - (buy.open_liqs.total_reserved.count + sell.open_liqs.total_reserved.count),
+            exposure: Exposure::from_sides(
+                Portfolio::side_exposure(&buy),
+                Portfolio::side_exposure(&sell),
+                |size| size * self.price,
+                self.margin.as_ref().map_or(D128::ZERO, |margin| margin.max_notional(self.balance)),
+            ),
         };
         // info!("pd {}", pd);
         pd
     }
 
+    fn side_exposure(data: &PositionData) -> SideExposure {
+        SideExposure {
+            filled: data.open_position.inv,
+            entries: data.open_liqs.total_reserved.inv,
+            exits: data.close_liqs.total_reserved.inv,
+        }
+    }
+
+    /// The pool orders are sent from, for requests the portfolio doesn't track
+    pub fn pool(&self) -> Handle {
+        self.pool.handle().clone()
+    }
+
     pub fn data_refresh(&mut self) {
         self.data = self.data_generate();
         // info!("data: {}", self.data);
@@ -133,21 +196,24 @@ impl Portfolio {
         class: OrderClassification,
     ) -> bool {
         // self.data_refresh();
-        if stage == Stage::Entry && class == OrderClassification::Rebase && (size > (self.data.remaining_margin / price) || D128::ONE > self.data.remaining_count) {
+        if self.is_halted() { return false; }
+        if stage == Stage::Entry && !self.side_enabled(side) { return false; }
+        if stage == Stage::Entry && (class == OrderClassification::Rebase || class == OrderClassification::Algo) && (size > self.data.remaining_margin || D128::ONE > self.data.remaining_count) {
             // debug!("portrej {} rem: {}, count: {}", side, self.data.remaining_margin, self.data.remaining_count);
             return false;
         }
         if size.is_nan() { panic!("size is nan"); }
         else if size.is_zero() { panic!("size is zero, dump: {}\n{:?}\n{:?}", self.data, self.buy, self.sell); }
+        if !self.risk_check(price, size, side, stage) { return false; }
         // info!("{:?} {:?} order up for {}", side, stage, size);
         match side {
             Side::Buy => {
-                let r = self.buy.new_limit(id, price, size, stage, class, self.data.buy.remaining_margin / price, self.data.buy.remaining_count);
+                let r = self.buy.new_limit(id, price, size, stage, class, self.data.buy.remaining_margin, self.data.buy.remaining_count);
                 self.data_refresh();
                 r
             },
             Side::Sell => {
-                let r = self.sell.new_limit(id, price, size, stage, class, self.data.sell.remaining_margin / price, self.data.sell.remaining_count);
+                let r = self.sell.new_limit(id, price, size, stage, class, self.data.sell.remaining_margin, self.data.sell.remaining_count);
                 self.data_refresh();
                 r
             },
@@ -164,23 +230,49 @@ impl Portfolio {
         class: OrderClassification,
     ) -> bool {
         // self.data_refresh();
-        if stage == Stage::Entry && class == OrderClassification::Rebase && (size > (self.data.remaining_margin / expected_price) || D128::ONE > self.data.remaining_count) { info!("failed portfolio\n{}", self.data); return false; }
+        if self.is_halted() { return false; }
+        if stage == Stage::Entry && !self.side_enabled(side) { return false; }
+        if stage == Stage::Entry && (class == OrderClassification::Rebase || class == OrderClassification::Algo) && (size > self.data.remaining_margin || D128::ONE > self.data.remaining_count) { info!("failed portfolio\n{}", self.data); return false; }
         if size.is_nan() { panic!("size is nan, dump: {}\n{:?}\n{:?}", self.data, self.buy, self.sell); }
         else if size.is_zero() { panic!("size is zero, dump: {}\n{:?}\n{:?}", self.data, self.buy, self.sell); }
+        if !self.risk_check(expected_price, size, side, stage) { return false; }
         match side {
             Side::Buy => {
-                let r = self.buy.new_market(id, expected_price, size, stage, class, self.data.buy.remaining_margin / expected_price, self.data.buy.remaining_count);
+                let r = self.buy.new_market(id, expected_price, size, stage, class, self.data.buy.remaining_margin, self.data.buy.remaining_count);
                 self.data_refresh();
                 r
             },
             Side::Sell => {
-                let r = self.sell.new_market(id, expected_price, size, stage, class, self.data.sell.remaining_margin / expected_price, self.data.sell.remaining_count);
+                let r = self.sell.new_market(id, expected_price, size, stage, class, self.data.sell.remaining_margin, self.data.sell.remaining_count);
                 self.data_refresh();
                 r
             },
         }
     }
 
+    /// Runs the order past the pre-trade risk checks, entries count against their side's filled and working inventory
+    fn risk_check(&self, price: D128, size: D128, side: Side, stage: Stage) -> bool {
+        self.risk_check_replacing(price, size, D128::ZERO, side, stage)
+    }
+
+    /// Risk checks for an order taking the place of a resting one of size replaced, only the growth is added to the side
+    fn risk_check_replacing(&self, price: D128, size: D128, replaced: D128, side: Side, stage: Stage) -> bool {
+        let reduces = stage == Stage::Exit;
+        let added = if reduces || size <= replaced { D128::ZERO } else { size - replaced };
+        let data = match side { Side::Buy => &self.data.buy, Side::Sell => &self.data.sell };
+        RISK.check(&OrderCheck {
+            venue: Venue::Binance,
+            symbol: &self.symbol,
+            size,
+            price,
+            reference: self.price,
+            notional: size * price,
+            reduces,
+            position: data.open_position.inv + data.open_liqs.total_reserved.inv + added,
+            net: self.data.exposure.worst_net_with(side == Side::Buy, added),
+        }).is_ok()
+    }
+
     pub fn cancel_order(&mut self, id: Uuid, side: Side, stage: Stage) -> bool {
         match side {
             Side::Buy => {
@@ -196,40 +288,258 @@ impl Portfolio {
         }
     }
 
-    pub fn order_rest_response(&mut self, id: Uuid, side: Side, stage: Stage, order: OrderResponseWrapper) {
-        match side {
+    pub fn order_rest_response(&mut self, id: Uuid, side: Side, stage: Stage, order: OrderResponseWrapper, policy: ErrorPolicy) {
+        let desync = match side {
             Side::Buy => self.buy.order_rest_response(id, stage, order),
             Side::Sell => self.sell.order_rest_response(id, stage, order),
         };
         self.data_refresh();
+        self.apply_policy(policy);
+        if desync && self.request_reconcile() {
+            info!("POSSIBLE DESYNC: {} {:?} order {} was rejected, reconciling", side, stage, id);
+        }
+    }
+
+    /// Reacts to the portfolio wide part of an error policy, order level reactions happen in the positions
+    fn apply_policy(&mut self, policy: ErrorPolicy) {
+        match policy {
+            ErrorPolicy::HaltSymbol | ErrorPolicy::HaltAll => self.halt(policy),
+            _ => {},
+        }
+    }
+
+    /// Stops all new orders and pulls resting entries.
+    /// Exits are left working so any inventory can still unwind.
+    pub fn halt(&mut self, policy: ErrorPolicy) {
+        if self.halted { return; }
+        info!("[HALT] {} halted by {:?} for {}s\n{}", self.symbol, policy, HALT_PERIOD, self.data);
+        self.halted = true;
+        self.halted_at = Instant::now();
+        self.halt_resumes = BROKER.resumes();
+        self.buy.cancel_all(Stage::Entry);
+        self.sell.cancel_all(Stage::Entry);
+        self.data_refresh();
+    }
+
+    /// True while this symbol or the whole broker is halted, lifts the symbol's halt after HALT_PERIOD or a resume.
+    /// If the cause hasn't cleared the next order's error halts it again.
+    fn is_halted(&mut self) -> bool {
+        if self.halted && (self.halted_at.elapsed().as_secs() >= HALT_PERIOD || BROKER.resumes() != self.halt_resumes) {
+            self.halted = false;
+            info!("[HALT] {} lifted, orders are allowed again", self.symbol);
+        }
+        self.halted || BROKER.is_halted()
+    }
+
+    /// Reprices a resting limit in place, false if it isn't ours or can't be moved right now
+    pub fn amend_order(&mut self, id: Uuid, price: D128, size: D128, side: Side, stage: Stage) -> bool {
+        if self.is_halted() { return false; }
+        let position = side.deside(&self.buy, &self.sell);
+        let resting = match stage.aggress(&position.opens, &position.closes).order_map.get(&id) {
+            Some(order) => order.orig_size,
+            None => return false,
+        };
+        if !self.risk_check_replacing(price, size, resting, side, stage) { return false; }
+        let r = match side {
+            Side::Buy => self.buy.amend_order(id, stage, price, size),
+            Side::Sell => self.sell.amend_order(id, stage, price, size),
+        };
+        self.data_refresh();
+        r
     }
 
-    pub fn cancel_response(&mut self, id: Uuid, side: Side, stage: Stage, cancel: CancelResponseWrapper) {
+    pub fn amend_response(&mut self, id: Uuid, side: Side, stage: Stage, order: OrderResponseWrapper, policy: ErrorPolicy) {
+        match side {
+            Side::Buy => self.buy.rest_amend(stage, id, order),
+            Side::Sell => self.sell.rest_amend(stage, id, order),
+        };
+        self.data_refresh();
+        self.apply_policy(policy);
+    }
+
+    pub fn cancel_response(&mut self, id: Uuid, side: Side, stage: Stage, cancel: CancelResponseWrapper, policy: ErrorPolicy) {
         match cancel {
             CancelResponseWrapper::Cancel(_) => {},
             CancelResponseWrapper::Error(_) => {/*debug!("cancel err\n{}", self.data);*/},
         };
-        match side {
+        let desync = match side {
             Side::Buy => self.buy.rest_cancel(stage, id, cancel),
             Side::Sell => self.sell.rest_cancel(stage, id, cancel),
+        };
+        self.protect();
+        self.apply_policy(policy);
+        if desync && self.request_reconcile() {
+            info!("POSSIBLE DESYNC: too many failed cancels on {} {:?} order {}, reconciling", side, stage, id);
         }
-        self.data_refresh();
     }
 
     pub fn order_update(&mut self, order: OrderUpdateData) {
-        match order.position_side {
-                BinanceSide::Buy => self.buy.order_update(order),
-                BinanceSide::Sell => self.sell.order_update(order),
-                BinanceSide::Both => todo!(),
+        let one_way_entry = matches!(order.position_side, BinanceSide::Both) && !order.reduce_only;
+        let (price, size) = (order.filled_price, order.last_filled_qty);
+        if size.is_positive() { RISK.on_fill(Venue::Binance, &self.symbol); }
+        let side = Portfolio::position_of(order.side, order.position_side, order.reduce_only);
+        let desync = match side {
+                Side::Buy => self.buy.order_update(order),
+                Side::Sell => self.sell.order_update(order),
             };
+        if one_way_entry && size.is_positive() { self.net(side, price, size); }
+        self.protect();
+        self.check_breaker();
+        self.hedge();
+        if desync && self.request_reconcile() {
+            info!("POSSIBLE DESYNC: order update for an unknown order, reconciling");
+        }
+    }
+
+    /// The position an order belongs to, one-way orders trade BOTH so exits are told apart by reduce only
+    fn position_of(side: BinanceSide, position_side: BinanceSide, reduce_only: bool) -> Side {
+        match position_side {
+            BinanceSide::Buy => Side::Buy,
+            BinanceSide::Sell => Side::Sell,
+            BinanceSide::Both => match (side, reduce_only) {
+                (BinanceSide::Buy, false) | (BinanceSide::Sell, true) => Side::Buy,
+                _ => Side::Sell,
+            },
+        }
+    }
+
+    /// One-way mode holds a single net position, so an entry filling while the other side holds inventory closes that first.
+    /// The overlap is booked as a close on both sides, leaving each with what the exchange actually holds.
+    fn net(&mut self, side: Side, price: D128, size: D128) {
+        let (entered, opposite) = match side {
+            Side::Buy => (&mut self.buy, &mut self.sell),
+            Side::Sell => (&mut self.sell, &mut self.buy),
+        };
+        let held = opposite.data_refresh().open_position.inv;
+        let overlap = (if held < size { held } else { size }).round_down(-3);
+        if !overlap.is_positive() { return; }
+        info!("{} entry of {} at {} nets out {} of the {} inventory", side, size, price, overlap, !side);
+        opposite.net_out(price, overlap);
+        entered.net_out(price, overlap);
         self.data_refresh();
     }
 
+    /// Whether entries are allowed on the side, set through the buy_enabled and sell_enabled params
+    pub fn side_enabled(&self, side: Side) -> bool {
+        *side.deside(&self.buy_enabled, &self.sell_enabled)
+    }
+
+    /// Takes on a runtime parameter change, entries resting on a side that was just disabled are pulled
+    pub fn apply_params(&mut self, params: &Params) {
+        self.init_size = params.init_size;
+        self.max_open_orders = params.max_open_orders;
+        self.rebase_distance_limit = params.rebase_distance;
+        self.buy.pos_max_orders = params.max_open_orders / 2;
+        self.sell.pos_max_orders = params.max_open_orders / 2;
+        if self.buy_enabled && !params.buy_enabled {
+            self.cancel_all(Side::Buy, Stage::Entry);
+        }
+        if self.sell_enabled && !params.sell_enabled {
+            self.cancel_all(Side::Sell, Stage::Entry);
+        }
+        self.buy_enabled = params.buy_enabled;
+        self.sell_enabled = params.sell_enabled;
+        self.data_refresh();
+    }
+
+    /// Protection applied to inventory as it opens, see protection.rs
+    pub fn set_protection(&mut self, settings: ProtectionSettings) {
+        self.buy.protection = settings;
+        self.sell.protection = settings;
+        self.protect();
+    }
+
+    /// Brings both sides' stop loss and take profit in line with their inventory
+    pub fn protect(&mut self) {
+        self.buy.protect();
+        self.sell.protect();
+        self.data_refresh();
+    }
+
+    /// Longs exit into the bid and shorts into the ask, so that's what their trailing stops follow
+    pub fn trail(&mut self, tops: &Tops) {
+        self.buy.trail(tops.best_bid.0);
+        self.sell.trail(tops.best_ask.0);
+        self.data_refresh();
+    }
+
+    pub fn new_conditional(&mut self, kind: ConditionalKind, trigger: D128, size: D128, side: Side) -> bool {
+        if size.is_nan() || size.is_zero() { return false; }
+        match side {
+            Side::Buy => self.buy.new_conditional(kind, trigger, size),
+            Side::Sell => self.sell.new_conditional(kind, trigger, size),
+        }
+    }
+
+    /// Kicks off an out of cycle reconcile, returns false if one is already running or ran too recently
+    pub fn request_reconcile(&mut self) -> bool {
+        if self.reconcile_in_flight || self.last_reconcile.elapsed().as_millis() < RECONCILE_COOLDOWN {
+            return false;
+        }
+        self.reconcile_in_flight = true;
+        let symbol = self.symbol.clone();
+        let sender = self.strat_tx.clone();
+        self.pool.spawn(async move { send_reconcile(symbol, sender).await; });
+        true
+    }
+
+    /// Brings both positions in line with the exchange's snapshot
+    pub fn reconcile(&mut self, snapshot: Option<ReconcileSnapshot>) {
+        self.reconcile_in_flight = false;
+        self.last_reconcile = Instant::now();
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                info!("[RECONCILE] Failed to pull exchange state, waiting for the next cycle");
+                return;
+            },
+        };
+        let mut buys = vec![];
+        let mut sells = vec![];
+        for order in snapshot.orders {
+            match Portfolio::position_of(order.side, order.position_side, order.reduce_only) {
+                Side::Buy => buys.push(order),
+                Side::Sell => sells.push(order),
+            }
+        }
+        let (buy_position, sell_position) = match snapshot.positions.iter().find(|pos| matches!(pos.position_side, BinanceSide::Both)) {
+            // One-way mode reports one signed position, the side not holding it is flat
+            Some(net) => {
+                let mut flat = net.clone();
+                flat.quantity = D128::ZERO;
+                match net.quantity.is_negative() {
+                    true => (Some(flat), Some(net.clone())),
+                    false => (Some(net.clone()), Some(flat)),
+                }
+            },
+            None => (
+                snapshot.positions.iter().find(|pos| matches!(pos.position_side, BinanceSide::Buy)).cloned(),
+                snapshot.positions.iter().find(|pos| matches!(pos.position_side, BinanceSide::Sell)).cloned(),
+            ),
+        };
+        let corrections = self.buy.reconcile(&buys, buy_position.as_ref()) + self.sell.reconcile(&sells, sell_position.as_ref());
+        self.protect();
+        self.watch_liquidation();
+        if corrections > 0 {
+            info!("[RECONCILE] Made {} corrections\n{}", corrections, self.data);
+        }
+    }
+
     pub fn position_update(&mut self, position: PositionUpdatePosition) {
         match position.side {
             BinanceSide::Buy => self.buy.position_update(position),
             BinanceSide::Sell => self.sell.position_update(position),
-            BinanceSide::Both => todo!(),
+            BinanceSide::Both => {
+                let mut flat = position.clone();
+                flat.quantity = D128::ZERO;
+                if position.quantity.is_negative() {
+                    self.buy.position_update(flat);
+                    self.sell.position_update(position);
+                } else {
+                    self.sell.position_update(flat);
+                    self.buy.position_update(position);
+                }
+            },
         }
         self.data_refresh();
     }
@@ -237,21 +547,224 @@ impl Portfolio {
     pub fn balance_update(&mut self, balance: &PositionUpdateBalance) {
         self.balance += balance.balance_change;
         self.available_balance = balance.wallet_balance;
-        self.max_size = self.available_balance * 0.8;
-        self.buy.balance_update(balance.wallet_balance);
-        self.sell.balance_update(balance.wallet_balance);
-        self.data_refresh();
+        self.buy.balance_update();
+        self.sell.balance_update();
+        self.limits_refresh();
     }
 
     pub fn balance_refresh(&mut self, balance: AccountBalance) {
         self.balance = balance.balance;
         self.available_balance = balance.available_balance;
-        self.max_size = balance.balance * 0.8;
-        self.buy.balance_refresh(balance.balance);
-        self.sell.balance_refresh(balance.balance);
+        self.buy.balance_update();
+        self.sell.balance_update();
+        self.limits_refresh();
+    }
+
+    pub fn margin_refresh(&mut self, margin: Option<MarginSnapshot>) {
+        match margin {
+            Some(margin) => {
+                info!("[MARGIN] {} at {}x {:?}, {} brackets", self.symbol, margin.leverage, margin.margin_type, margin.brackets.len());
+                self.margin = Some(margin);
+            },
+            None if self.margin.is_none() => {
+                info!("[MARGIN] Failed to read margin settings for {}, entries stay off until they're known, retrying in {}s", self.symbol, MARGIN_RETRY_PERIOD);
+                let symbol = self.symbol.clone();
+                let sender = self.strat_tx.clone();
+                self.pool.spawn(async move { retry_margin(symbol, sender).await; });
+            },
+            None => info!("[MARGIN] Failed to refresh margin settings for {}, keeping the last ones", self.symbol),
+        }
+        self.limits_refresh();
+    }
+
+    /// Keeps the price current, max_size only follows it on the portfolio timer except for the first price
+    pub fn mark(&mut self, tops: &Tops) {
+        let price: D128 = (tops.best_bid.0 + tops.best_ask.0) / 2;
+        if price.is_nan() || !price.is_positive() || price == self.price { return; }
+        let first = !self.price.is_positive();
+        self.price = price;
+        if first { self.limits_refresh(); }
+        self.check_breaker();
+        self.watch_liquidation();
+    }
+
+    /// Warns as either side nears liquidation and closes part of it while it's inside the de-risk threshold
+    fn watch_liquidation(&mut self) {
+        if !self.price.is_positive() { return; }
+        let limits = RISK.limits();
+        let cross_balance = self.balance + self.buy.known_prebate_unrealized + self.sell.known_prebate_unrealized;
+        for side in [Side::Buy, Side::Sell] {
+            let inv = side.deside(&self.data.buy, &self.data.sell).open_position.inv;
+            let position = match side { Side::Buy => &mut self.buy, Side::Sell => &mut self.sell };
+            if let Some(margin) = &self.margin {
+                position.liquidation.maint_margin = margin.maint_margin(inv * self.price);
+                if margin.margin_type == MarginType::Cross { position.liquidation.margin_balance = cross_balance; }
+            }
+            let level = if inv.is_positive() { position.liquidation.assess(self.price, &limits) } else { LiquidationLevel::Safe };
+            let previous = std::mem::replace(&mut position.liquidation.level, level);
+            if level != previous {
+                info!("[LIQUIDATION] {} {} {:?} -> {:?}, liq price: {}, mark: {}, distance: {:?}, margin ratio: {:?}",
+                    self.symbol, side, previous, level, position.liquidation.liq_price, self.price,
+                    position.liquidation.distance(self.price), position.liquidation.margin_ratio());
+            }
+            let due = self.last_derisk.map_or(true, |at| at.elapsed() > DERISK_COOLDOWN);
+            if level == LiquidationLevel::Reduce && due {
+                self.derisk(side, limits.liq_reduce_fraction());
+            }
+        }
+    }
+
+    /// Stops adding to a side and closes fraction of what it holds at market
+    fn derisk(&mut self, side: Side, fraction: D128) {
+        self.last_derisk = Some(Instant::now());
+        let inv = side.deside(&self.data.buy, &self.data.sell).open_position.inv;
+        if !inv.is_positive() { return; }
+        self.cancel_all(side, Stage::Entry);
+        let mut size = (inv * fraction).round_down(-3);
+        // Too small to trade closes the lot
+        if size > inv || size.is_zero() { size = inv; }
+        info!("[LIQUIDATION] De-risking {} {}, closing {} of {}", self.symbol, side, size, inv);
+        self.new_market(None, self.price, size, side, Stage::Exit, OrderClassification::Exit);
+    }
+
+    /// The exchange says a position is close to liquidation, cut it without waiting on our own thresholds
+    pub fn margin_call(&mut self, call: MarginCall) {
+        let mut called = Vec::new();
+        for position in call.positions.iter().filter(|position| position.symbol.eq_ignore_ascii_case(&self.symbol)) {
+            let side = match position.position_side {
+                BinanceSide::Buy => Side::Buy,
+                BinanceSide::Sell => Side::Sell,
+                BinanceSide::Both => if position.quantity.is_negative() { Side::Sell } else { Side::Buy },
+            };
+            info!("[LIQUIDATION] Margin call on {} {}, mark: {}, unrealized: {}, maintenance margin: {}",
+                self.symbol, side, position.mark_price, position.unrealized_pnl, position.maintenance_margin);
+            called.push((side, position.maintenance_margin));
+        }
+        for (side, maint_margin) in called {
+            match side {
+                Side::Buy => self.buy.liquidation.maint_margin = maint_margin,
+                Side::Sell => self.sell.liquidation.maint_margin = maint_margin,
+            }
+            self.derisk(side, RISK.limits().liq_reduce_fraction());
+        }
+    }
+
+    /// Trims the heavier side with reduce-only exits while filled net delta is past the hedge threshold.
+    /// Exits already working on that side count toward the trim so repeated fills don't stack hedges.
+    fn hedge(&mut self) {
+        let threshold = match RISK.limits().hedge_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let excess = self.data.exposure.hedge_excess(threshold);
+        if excess.is_zero() || !self.price.is_positive() { return; }
+        let side = if excess.is_positive() { Side::Buy } else { Side::Sell };
+        let working = side.deside(&self.data.buy, &self.data.sell).close_liqs.total_reserved.inv;
+        let size = excess.abs() - working;
+        let size = size.round_down(-3);
+        if !size.is_positive() { return; }
+        info!("[HEDGE] {} net {} past {}, trimming {} {}", self.symbol, self.data.exposure.net, threshold, side, size);
+        self.new_market(None, self.price, size, side, Stage::Exit, OrderClassification::Exit);
+    }
+
+    /// Realized plus unrealized PnL net of fees since startup, at the mid
+    pub fn pnl(&self) -> D128 {
+        (self.buy.pnl(self.price) + self.sell.pnl(self.price)).net()
+    }
+
+    /// What the metrics endpoint shows for this symbol
+    pub fn metrics(&self) -> PortfolioMetrics {
+        let mut metrics = PortfolioMetrics {
+            buy_inventory: self.data.buy.open_position.inv.to_float(),
+            sell_inventory: self.data.sell.open_position.inv.to_float(),
+            pnl: self.pnl().to_float(),
+            remaining_margin: self.data.remaining_margin.to_float(),
+            remaining_count: self.data.remaining_count.to_float(),
+            ..PortfolioMetrics::default()
+        };
+        for (side, position) in [("buy", &self.buy), ("sell", &self.sell)] {
+            for (stage, list) in [("entry", &position.opens), ("exit", &position.closes)] {
+                for order in list.order_map.values() {
+                    *metrics.orders.entry((side, stage, format!("{:?}", order.progress).to_lowercase())).or_insert(0) += 1;
+                }
+            }
+        }
+        metrics
+    }
+
+    /// Reports PnL to the kill switch, standing down once it covers this symbol and standing back up after a reset
+    fn check_breaker(&mut self) {
+        if !self.price.is_positive() { return; }
+        PNL.publish(Venue::Binance, &self.symbol, self.buy.pnl(self.price), self.sell.pnl(self.price));
+        BREAKER.update(Venue::Binance, &self.symbol, self.pnl());
+        match (self.tripped, BREAKER.is_tripped(Venue::Binance, &self.symbol)) {
+            (false, true) => {
+                self.tripped = true;
+                self.stand_down();
+            },
+            (true, false) => {
+                self.tripped = false;
+                info!("[KILL SWITCH] {} cleared, entries back on", self.symbol);
+            },
+            _ => {},
+        }
+    }
+
+    /// Pulls every order and, if the limits say so, closes out what's held at market
+    fn stand_down(&mut self) {
+        info!("[KILL SWITCH] {} tripped, cancelling all orders", self.symbol);
+        for side in [Side::Buy, Side::Sell] {
+            self.cancel_all(side, Stage::Entry);
+            self.cancel_all(side, Stage::Exit);
+        }
+        if !RISK.limits().flatten_on_trip() { return; }
+        for side in [Side::Buy, Side::Sell] {
+            let inv = side.deside(&self.data.buy, &self.data.sell).open_position.inv;
+            if inv.is_positive() {
+                info!("[KILL SWITCH] Flattening {} {} {}", self.symbol, side, inv);
+                self.new_market(None, self.price, inv, side, Stage::Exit, OrderClassification::Exit);
+            }
+        }
+    }
+
+    /// Max size is whatever the margin allows at the current price, split evenly between the sides
+    pub fn limits_refresh(&mut self) {
+        let notional = match &self.margin {
+            Some(margin) => margin.max_notional(self.balance),
+            None => D128::ZERO,
+        };
+        self.max_size = if self.price.is_positive() { (notional / self.price).round_down(-3) } else { D128::ZERO };
+        self.buy.set_max_size(self.max_size / 2);
+        self.sell.set_max_size(self.max_size / 2);
         self.data_refresh();
     }
 
+    pub fn cancel_all(&mut self, side: Side, stage: Stage) {
+        match side {
+            Side::Buy => self.buy.cancel_all(stage),
+            Side::Sell => self.sell.cancel_all(stage),
+        }
+        self.data_refresh();
+    }
+
+    /// Whether an order is still on its way to or resting on the book
+    pub fn order_working(&self, id: Uuid, side: Side, stage: Stage) -> bool {
+        let position = side.deside(&self.buy, &self.sell);
+        match stage.aggress(&position.opens, &position.closes).order_map.get(&id) {
+            Some(order) => order.progress.incomplete_unfailed(),
+            None => false,
+        }
+    }
+
+    /// Whether an order can take a cancel right now, false while one's already in flight
+    pub fn order_cancellable(&self, id: Uuid, side: Side, stage: Stage) -> bool {
+        let position = side.deside(&self.buy, &self.sell);
+        match stage.aggress(&position.opens, &position.closes).order_map.get(&id) {
+            Some(order) => order.can_cancel(),
+            None => false,
+        }
+    }
+
     pub fn get_top(&self, side: Side, stage: Stage) -> Option<&Order> {
         match side {
             Side::Buy => self.buy.get_top(stage),
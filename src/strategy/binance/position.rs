@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Instant;
 
 use crossbeam_channel::{SendError, Sender};
 use dec::{D128, Context};
@@ -8,13 +10,22 @@ use uuid::Uuid;
 use thiserror::Error;
 
 use crate::backend::binance::broker::BROKER;
-use crate::backend::binance::types::{OrderType, PositionUpdateData, PositionUpdatePosition, PositionUpdateBalance, OrderUpdateData, CancelResponse, OrderResponseWrapper, CancelResponseWrapper};
+use crate::backend::binance::errors::{ErrorCode, ProcessingErrors};
+use crate::backend::binance::types::{OrderType, PositionUpdateData, PositionUpdatePosition, PositionUpdateBalance, OrderUpdateData, CancelResponse, OrderResponseWrapper, CancelResponseWrapper, OpenOrder, PositionRisk, MarginType};
 use crate::backend::types::Side;
+use crate::risk::{RISK, OrderCheck, LiquidationState};
+use crate::strategy::fees::FEES;
+use crate::strategy::ledger::{Ledger, LedgerReport};
+use crate::telemetry::Venue;
+use crate::strategy::protection::{ConditionalKind, ProtectionSettings, TrailingStop};
 use crate::strategy::types::{Stage, OrderClassification};
 
 use super::order_list::{OrderList, OrderListError, AllLiqs, OrderData};
 use super::{StrategyMessage, Order, AccountMessage, OrderResponseContext, CancelResponseContext, message, OrderProgress};
 
+/// Seconds the count stays capped after the exchange refuses an order for its open order limit
+pub const ORDER_CAP_PERIOD: u64 = 60;
+
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum FindCancelRes {
@@ -127,6 +138,8 @@ pub struct Position {
     pub closes: OrderList,
     pub side: Side,
     pub pos_max_orders: D128,
+    /// Count the exchange's open order limit was hit at and when, holds below pos_max_orders for ORDER_CAP_PERIOD
+    pub order_cap: Option<(D128, Instant)>,
     pub pos_max_size: D128,
     pub known_size: D128,
     pub known_price: D128,
@@ -134,6 +147,17 @@ pub struct Position {
     pub known_prebate_pnl: D128,
     pub known_prebate_unrealized: D128,
     pub sequence: D128,
+    /// Average cost inventory and PnL
+    pub ledger: Ledger,
+    pub liquidation: LiquidationState,
+    /// Inventory drift seen on the last reconcile, only corrected once it's seen twice in a row
+    pub reconcile_drift: D128,
+    /// Set when the exchange rejects an entry for margin, cleared by the next balance update
+    pub margin_exhausted: bool,
+    /// Conditional orders, kept apart from closes so they don't count as working exits
+    pub protective: OrderList,
+    pub protection: ProtectionSettings,
+    pub trailing: Option<TrailingStop>,
     pub pool: Handle,
     pub strat_tx: Sender<StrategyMessage>,
 }
@@ -146,13 +170,21 @@ impl Position {
             opens: OrderList::new(),
             closes: OrderList::new(),
             sequence: D128::ZERO,
+            ledger: Ledger::new(open_side == Side::Buy),
+            liquidation: LiquidationState::new(),
             pos_max_size: max_size,
             pos_max_orders: max_count,
+            order_cap: None,
             known_prebate_pnl: D128::ZERO,
             known_prebate_unrealized: D128::ZERO,
             known_size: D128::ZERO,
             known_price: D128::ZERO,
             known_liq: D128::ZERO,
+            reconcile_drift: D128::ZERO,
+            margin_exhausted: false,
+            protective: OrderList::new(),
+            protection: ProtectionSettings::none(),
+            trailing: None,
             pool,
             strat_tx: sender,
         }
@@ -248,6 +280,28 @@ impl Position {
         }
     }
 
+    /// Moves a resting limit instead of cancelling and replacing it, false if the order can't be moved right now
+    pub fn amend_order(&mut self, id: Uuid, stage: Stage, price: D128, size: D128) -> bool {
+        let list = match stage {
+            Stage::Entry => &mut self.opens,
+            Stage::Exit => &mut self.closes,
+        };
+        match list.order_map.get_mut(&id) {
+            Some(order) if order.can_amend() => {
+                Position::send_amend(self.pool.clone(), order, price, size, self.side, stage, self.symbol.clone(), self.strat_tx.clone());
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn rest_amend(&mut self, stage: Stage, id: Uuid, order: OrderResponseWrapper) {
+        match stage {
+            Stage::Entry => self.opens.rest_amend(id, order),
+            Stage::Exit => self.closes.rest_amend(id, order),
+        }
+    }
+
     pub fn get_best_rebase_price(&self, stage: Stage) -> Option<D128> {
         /*
          * X closer to best than Y is X > Y for buy opens and sell closes, vice versa for vice versa.
@@ -269,6 +323,14 @@ impl Position {
         }
     }
 
+    /// The configured order count, or the exchange's limit while it's capped
+    pub fn max_orders(&self) -> D128 {
+        match self.order_cap {
+            Some((cap, at)) if at.elapsed().as_secs() < ORDER_CAP_PERIOD && cap < self.pos_max_orders => cap,
+            _ => self.pos_max_orders,
+        }
+    }
+
     pub fn data_refresh(&self) -> PositionData {
         let open_liqs = self.opens.all_liqs(false);
         let close_liqs = self.closes.all_liqs(true);
@@ -277,50 +339,213 @@ impl Position {
             close_liqs: close_liqs,
             open_position: FinData::from(open_liqs.filled - close_liqs.filled),
             total_count: open_liqs.total_count + close_liqs.total_count,
-            remaining_count: self.pos_max_orders - open_liqs.total_reserved.count,
+            remaining_count: self.max_orders() - open_liqs.total_reserved.count,
             remaining_margin: self.pos_max_size - open_liqs.total_outstanding.inv,
             // active_delta: todo!(),
             // total_delta: todo!(),
         }
     }
 
+    /// Returns true if the update points to a desync with the exchange
     pub fn order_update(
         &mut self,
         order: OrderUpdateData
-    ) {
+    ) -> bool {
         let price = order.filled_price;
-        match Stage::from_binance_side(order.side, order.position_side) {
+        if self.protective_update(&order) {
+            self.check_closed_out(price);
+            return false;
+        }
+        let stage = Stage::from_binance_side(order.side, order.position_side, order.reduce_only);
+        self.book_fill(stage, &order);
+        match stage {
             Stage::Entry => self.opens.ws_order(order),
             Stage::Exit => {
-                self.closes.ws_order(order);
-                let pd = self.data_refresh();
-                if pd.open_position.inv <= D128::ZERO {
-                    let prebate = pd.open_liqs.filled.liq - pd.close_liqs.filled.liq;
-                    let rebate = pd.open_liqs.filled.rebate + pd.close_liqs.filled.rebate;
-                    let pnl = prebate - rebate;
-                    // info!("{}side CLOSED OUT: prebate pnl: {}, fee/rebates: {}, pnl: {}\n",
-                    // self.side, prebate, rebate, pnl);
-                    self.cancel_distant_rebases(price, D128::ZERO, Stage::Entry);
-                    self.opens.clean();
-                    self.closes.clean();
-                    // info!("post clean: {}", self.data_refresh());
-                }
+                let desync = self.closes.ws_order(order);
+                self.check_closed_out(price);
+                desync
             },
+        }
+    }
+
+    /// Books inventory netted away in one-way mode as a close at the fill price
+    pub fn net_out(&mut self, price: D128, size: D128) {
+        self.closes.add_order(Order::new_adjustment(price, size)).ok();
+        self.ledger.exit(size, size * price, D128::ZERO);
+        self.check_closed_out(price);
+    }
+
+    /// Ledger snapshot with the open inventory marked at price
+    pub fn pnl(&self, price: D128) -> LedgerReport {
+        self.ledger.report(self.ledger.inv * price)
+    }
+
+    /// Books a trade from the user stream, commission in anything but the quote asset (BNB) isn't counted
+    fn book_fill(&mut self, stage: Stage, order: &OrderUpdateData) {
+        let qty = order.last_filled_qty;
+        if !qty.is_positive() { return; }
+        let fee = match (&order.commission, &order.commission_asset) {
+            (Some(fee), Some(asset)) if order.symbol.ends_with(asset.as_str()) => *fee,
+            (Some(fee), Some(asset)) => {
+                debug!("{} fill paid {} {} in fees, left out of the ledger", self.side, fee, asset);
+                D128::ZERO
+            },
+            _ => D128::ZERO,
         };
+        match stage {
+            Stage::Entry => self.ledger.entry(qty, qty * order.filled_price, fee),
+            Stage::Exit => { self.ledger.exit(qty, qty * order.filled_price, fee); },
+        }
+    }
+
+    fn check_closed_out(&mut self, price: D128) {
+        let pd = self.data_refresh();
+        if pd.open_position.inv <= D128::ZERO {
+            let prebate = pd.open_liqs.filled.liq - pd.close_liqs.filled.liq;
+            let rebate = pd.open_liqs.filled.rebate + pd.close_liqs.filled.rebate;
+            let pnl = prebate - rebate;
+            // info!("{}side CLOSED OUT: prebate pnl: {}, fee/rebates: {}, pnl: {}\n",
+            // self.side, prebate, rebate, pnl);
+            self.cancel_distant_rebases(price, D128::ZERO, Stage::Entry);
+            self.opens.clean();
+            self.closes.clean();
+            self.protective.clean();
+            self.trailing = None;
+            // info!("post clean: {}", self.data_refresh());
+        }
+    }
+
+    /// Takes updates for our conditionals, booking their fills against closes since that's where inventory is reduced.
+    /// Returns false if the update wasn't for a conditional.
+    fn protective_update(&mut self, order: &OrderUpdateData) -> bool {
+        if self.protective.find_id(&order.id, order.auto_id).is_none() { return false; }
+        let (qty, price) = (order.last_filled_qty, order.filled_price);
+        self.book_fill(Stage::Exit, order);
+        self.protective.ws_order(order.clone());
+        if qty.is_positive() {
+            info!("{} conditional {} filled {} at {}", self.side, order.id, qty, price);
+            self.closes.add_order(Order::new_adjustment(price, qty)).ok();
+        }
+        true
+    }
+
+    /// Keeps the automatic stop loss and take profit sized to current inventory, arming them as inventory opens
+    /// and pulling them once it's gone. Resizing is a cancel then replace, the replacement goes out on the call after the cancel lands.
+    pub fn protect(&mut self) {
+        let position = self.data_refresh().open_position;
+        let size = position.inv.round_down(-3);
+        for kind in [ConditionalKind::StopLoss, ConditionalKind::TakeProfit] {
+            let trigger = if size.is_positive() { self.protection.trigger(kind, self.side, position.cb) } else { None };
+            let live = self.protective.order_map.iter_mut()
+                .find(|(_, ord)| ord.order_class == OrderClassification::Protect && ord.conditional_kind() == Some(kind) && ord.progress.incomplete_unfailed());
+            match (live, trigger) {
+                (Some((_, order)), Some(_)) => {
+                    if order.orig_size != size && order.can_cancel() {
+                        Position::send_cancel(self.pool.clone(), order, self.side, Stage::Exit, self.symbol.clone(), self.strat_tx.clone());
+                    }
+                },
+                (Some((_, order)), None) => {
+                    if order.can_cancel() {
+                        Position::send_cancel(self.pool.clone(), order, self.side, Stage::Exit, self.symbol.clone(), self.strat_tx.clone());
+                    }
+                },
+                (None, Some(trigger)) => {
+                    // Don't keep hammering a trigger the exchange already refused at this size
+                    if self.protective.order_map.values().any(|ord|
+                        ord.order_class == OrderClassification::Protect && ord.conditional_kind() == Some(kind) &&
+                        ord.progress == OrderProgress::Failed && ord.orig_size == size) { continue; }
+                    if !self.conditional_allowed(trigger, size) { continue; }
+                    if let Ok(order) = self.protective.add_order(Order::new_conditional(None, kind, trigger, size, FEES.rates(Venue::Binance, &self.symbol))) {
+                        info!("{} attaching {:?} for {} at {}", self.side, kind, size, trigger);
+                        Position::send_order(self.pool.clone(), order, self.side, Stage::Exit, self.symbol.clone(), self.strat_tx.clone());
+                    }
+                },
+                (None, None) => {},
+            }
+        }
+    }
+
+    /// Conditionals only ever close, but they still go past the allowlist, sanity and rate checks
+    fn conditional_allowed(&self, trigger: D128, size: D128) -> bool {
+        RISK.check(&OrderCheck::reducing(Venue::Binance, &self.symbol, size, trigger, size * trigger)).is_ok()
+    }
+
+    /// Places a conditional by hand, these are left alone by the automatic protection
+    pub fn new_conditional(&mut self, kind: ConditionalKind, trigger: D128, size: D128) -> bool {
+        if !self.conditional_allowed(trigger, size) { return false; }
+        let mut ord = Order::new_conditional(None, kind, trigger, size, FEES.rates(Venue::Binance, &self.symbol));
+        ord.order_class = OrderClassification::Exit;
+        match self.protective.add_order(ord) {
+            Ok(order) => {
+                Position::send_order(self.pool.clone(), order, self.side, Stage::Exit, self.symbol.clone(), self.strat_tx.clone());
+                true
+            },
+            Err(_) => false,
+        }
     }
 
-    pub fn rest_cancel(&mut self, stage: Stage, id: Uuid, cancel: CancelResponseWrapper) {
+    /// Moves the local trailing stop along with the exit price, dumping inventory at market once it's hit
+    pub fn trail(&mut self, price: D128) {
+        let distance = match self.protection.trailing { Some(distance) => distance, None => return, };
+        let size = self.data_refresh().open_position.inv.round_down(-3);
+        if !size.is_positive() {
+            self.trailing = None;
+            return;
+        }
+        let side = self.side;
+        let trailing = self.trailing.get_or_insert(TrailingStop::new(distance, price));
+        if trailing.update(price, side) {
+            info!("{} trailing stop hit at {}, best was {}, closing {} at market", side, price, trailing.extreme, size);
+            self.cancel_all(Stage::Exit);
+            self.new_market(None, price, size, Stage::Exit, OrderClassification::Protect, D128::ZERO, D128::ZERO);
+        }
+    }
+
+    /// Returns true if the cancel points to a desync with the exchange
+    pub fn rest_cancel(&mut self, stage: Stage, id: Uuid, cancel: CancelResponseWrapper) -> bool {
+        if self.protective.order_map.contains_key(&id) { return self.protective.rest_cancel(id, cancel); }
         match stage {
             Stage::Entry => self.opens.rest_cancel(id, cancel),
             Stage::Exit => self.closes.rest_cancel(id, cancel),
         }
     }
 
-    pub fn order_rest_response(&mut self, id: Uuid, stage: Stage, order: OrderResponseWrapper) {
+    /// Returns true if the response points to a desync with the exchange
+    pub fn order_rest_response(&mut self, id: Uuid, stage: Stage, order: OrderResponseWrapper) -> bool {
+        if self.protective.order_map.contains_key(&id) {
+            if let OrderResponseWrapper::Error(e) = &order {
+                info!("{} conditional {} rejected: {}", self.side, id, e.msg);
+            }
+            self.protective.rest_order(id, order);
+            return false;
+        }
+        let rejection = match &order {
+            OrderResponseWrapper::Order(_) => None,
+            OrderResponseWrapper::Error(e) => Some(e.code),
+        };
         match stage {
             Stage::Entry => self.opens.rest_order(id, order),
             Stage::Exit => self.closes.rest_order(id, order),
         }
+        match rejection {
+            Some(ErrorCode::ProcessingErrors(ProcessingErrors::MarginNotSufficient)) => {
+                info!("{} margin exhausted, holding entries until the next balance update", self.side);
+                self.margin_exhausted = true;
+                false
+            },
+            Some(ErrorCode::ProcessingErrors(ProcessingErrors::ReduceOnlyRejected)) => {
+                // A close bigger than the exchange's position means our inventory is off
+                info!("{} {:?} order {} rejected as reduce only", self.side, stage, id);
+                true
+            },
+            Some(ErrorCode::ProcessingErrors(ProcessingErrors::MaxOpenOrderExceeded)) => {
+                let cap = self.data_refresh().open_liqs.total_reserved.count;
+                self.order_cap = Some((cap, Instant::now()));
+                info!("{} hit the exchange's open order limit, capping at {} orders for {}s", self.side, cap, ORDER_CAP_PERIOD);
+                false
+            },
+            _ => false,
+        }
     }
 
     pub fn position_update(&mut self, position: PositionUpdatePosition) {
@@ -331,12 +556,138 @@ impl Position {
         self.known_prebate_pnl = position.accumulated_realized;
     }
 
-    pub fn balance_update(&mut self, balance: D128) {
-        self.pos_max_size = balance * 0.8 / 2;
+    /// Diffs the exchange's open orders and position for this side against our own, correcting whatever drifted.
+    /// Returns the number of corrections made.
+    pub fn reconcile(&mut self, orders: &[OpenOrder], position: Option<&PositionRisk>) -> usize {
+        let mut corrections = 0;
+        let mut seen: Vec<Uuid> = vec![];
+
+        for open in orders.iter() {
+            // Conditionals are tracked apart from opens and closes
+            if self.protective.find_id(&open.id, open.auto_id).is_some() { continue; }
+            let stage = Stage::from_binance_side(open.side, open.position_side, open.reduce_only);
+            // Orders without a UUID weren't placed by us, we'll track them but they aren't ours to cancel
+            let foreign = Uuid::from_str(&open.id).is_err();
+            let list = stage.aggress_mut(&mut self.opens, &mut self.closes);
+            match list.find_id(&open.id, open.auto_id) {
+                Some(id) => {
+                    seen.push(id);
+                    let order = list.order_map.get_mut(&id).unwrap();
+                    order.reconcile_misses = 0;
+                    if order.progress == OrderProgress::Untracked {
+                        if !foreign && !order.cancel_in_flight {
+                            info!("[RECONCILE] {} {:?} stray {} is still open, cancelling again", self.side, stage, id);
+                            Position::send_cancel(self.pool.clone(), order, self.side, stage, self.symbol.clone(), self.strat_tx.clone());
+                            corrections += 1;
+                        }
+                    } else if !order.progress.incomplete_unfailed() {
+                        info!("[RECONCILE] {} {:?} order {} is {:?} here but open on the exchange, cancelling", self.side, stage, id, order.progress);
+                        order.progress = OrderProgress::Untracked;
+                        if !order.cancel_in_flight {
+                            Position::send_cancel(self.pool.clone(), order, self.side, stage, self.symbol.clone(), self.strat_tx.clone());
+                        }
+                        corrections += 1;
+                    } else if open.executed_qty > order.filled_size {
+                        info!("[RECONCILE] {} {:?} order {} missed fills, filled {} here vs {} on the exchange", self.side, stage, id, order.filled_size, open.executed_qty);
+                        order.in_flight = false;
+                        order.progress = OrderProgress::PartiallyFilled;
+                        order.filled_size = open.executed_qty;
+                        order.filled_liq = open.executed_qty * open.avg_price;
+                        order.unfilled_size = open.orig_qty - open.executed_qty;
+                        order.unfilled_liq = order.unfilled_size * open.price;
+                        corrections += 1;
+                    }
+                },
+                None => {
+                    let id = match Uuid::from_str(&open.id) {
+                        Ok(id) => id,
+                        Err(_) => Uuid::from_u128(open.auto_id as u128),
+                    };
+                    let mut orphan = Order::new_orphan(Some(id), Some(open.price), open.orig_qty, FEES.rates(Venue::Binance, &self.symbol));
+                    orphan.auto_id = open.auto_id;
+                    orphan.auto_gen = true;
+                    orphan.filled_size = open.executed_qty;
+                    orphan.filled_liq = open.executed_qty * open.avg_price;
+                    orphan.unfilled_size = open.orig_qty - open.executed_qty;
+                    orphan.unfilled_liq = orphan.unfilled_size * open.price;
+                    seen.push(id);
+                    if let Ok(order) = list.add_order(orphan) {
+                        if foreign {
+                            info!("[RECONCILE] Adopted foreign {} {:?} order {} for {} at {}, leaving it be", self.side, stage, open.id, open.orig_qty, open.price);
+                        } else {
+                            info!("[RECONCILE] Adopted stray {} {:?} order {} for {} at {}, cancelling", self.side, stage, id, open.orig_qty, open.price);
+                            Position::send_cancel(self.pool.clone(), order, self.side, stage, self.symbol.clone(), self.strat_tx.clone());
+                        }
+                    }
+                    corrections += 1;
+                },
+            }
+        }
+
+        // Anything we think is working that the exchange doesn't know about missed its final update.
+        // Takes two misses since an order can rest between the snapshot being taken and it being read.
+        for stage in [Stage::Entry, Stage::Exit] {
+            let side = self.side;
+            for (id, order) in stage.aggress_mut(&mut self.opens, &mut self.closes).order_map.iter_mut()
+            .filter(|(id, ord)|
+            (ord.progress == OrderProgress::Resting || ord.progress == OrderProgress::PartiallyFilled) &&
+            !ord.in_flight && !seen.contains(*id)) {
+                order.reconcile_misses += 1;
+                if order.reconcile_misses > 1 {
+                    info!("[RECONCILE] {} {:?} order {} is {:?} here but gone from the exchange, marking cancelled", side, stage, id, order.progress);
+                    order.cancel_in_flight = false;
+                    order.progress = OrderProgress::Cancelled;
+                    corrections += 1;
+                }
+            }
+        }
+
+        if let Some(position) = position {
+            self.known_size = position.quantity.abs();
+            self.known_price = position.entry_price;
+            self.known_liq = self.known_size * position.entry_price;
+            self.known_prebate_unrealized = position.unrealized_pnl;
+            // The flat side of a one-way position would otherwise pick up the held side's liquidation price
+            self.liquidation.liq_price = if position.quantity.is_zero() { D128::ZERO } else { position.liquidation_price };
+            self.liquidation.margin_balance = match position.margin_type {
+                MarginType::Isolated => position.isolated_margin,
+                MarginType::Cross => D128::ZERO,
+            };
+
+            let drift = (self.known_size - self.data_refresh().open_position.inv).round_down(-3);
+            if drift.is_zero() {
+                self.reconcile_drift = D128::ZERO;
+            } else if drift != self.reconcile_drift {
+                // Fills can land between the snapshot and now, so wait to see the same drift twice
+                info!("[RECONCILE] {} inventory drift of {} awaiting confirmation", self.side, drift);
+                self.reconcile_drift = drift;
+            } else {
+                info!("[RECONCILE] {} inventory is {} here vs {} on the exchange, booking the difference", self.side, self.known_size - drift, self.known_size);
+                self.reconcile_drift = D128::ZERO;
+                if drift.is_positive() {
+                    self.opens.add_order(Order::new_adjustment(position.entry_price, drift)).ok();
+                    self.ledger.entry(drift, drift * position.entry_price, D128::ZERO);
+                } else {
+                    self.closes.add_order(Order::new_adjustment(position.mark_price, drift.abs())).ok();
+                    self.ledger.exit(drift.abs(), drift.abs() * position.mark_price, D128::ZERO);
+                }
+                if self.known_size.is_zero() {
+                    self.opens.clean();
+                    self.closes.clean();
+                }
+                corrections += 1;
+            }
+        }
+        corrections
+    }
+
+    /// A balance change is the only thing that frees margin the exchange said we didn't have
+    pub fn balance_update(&mut self) {
+        self.margin_exhausted = false;
     }
 
-    pub fn balance_refresh(&mut self, balance: D128) {
-        self.pos_max_size = balance * 0.8 / 2;
+    pub fn set_max_size(&mut self, max_size: D128) {
+        self.pos_max_size = max_size;
     }
 
     pub fn new_limit(
@@ -348,11 +699,12 @@ impl Position {
         rem_margin: D128,
         rem_count: D128,
     ) -> bool {
-        if stage == Stage::Entry && class == OrderClassification::Rebase && (size > rem_margin || D128::ONE > rem_count) {
+        if stage == Stage::Entry && (class == OrderClassification::Rebase || class == OrderClassification::Algo) && (size > rem_margin || D128::ONE > rem_count) {
             // debug!("posrej {} rem: {}, count: {}", self.side, rem_margin, rem_count);
             return false;
         }
-        let ord = Order::new_rebate(id, price, size, class);
+        if stage == Stage::Entry && self.margin_exhausted { return false; }
+        let ord = Order::new_rebate(id, price, size, class, FEES.rates(Venue::Binance, &self.symbol));
         match stage {
             Stage::Entry => {
                 match self.opens.add_order(ord) {
@@ -385,8 +737,9 @@ impl Position {
         rem_margin: D128,
         rem_count: D128,
     ) -> bool {
-        if stage == Stage::Entry && class == OrderClassification::Rebase && (size > rem_margin || D128::ONE >= rem_count) { return false; }
-        let ord = Order::new_taker(id, expected_price, size, class);
+        if stage == Stage::Entry && (class == OrderClassification::Rebase || class == OrderClassification::Algo) && (size > rem_margin || D128::ONE >= rem_count) { return false; }
+        if stage == Stage::Entry && self.margin_exhausted { return false; }
+        let ord = Order::new_taker(id, expected_price, size, class, FEES.rates(Venue::Binance, &self.symbol));
         match stage {
             Stage::Entry => {
                 match self.opens.add_order(ord) {
@@ -409,9 +762,23 @@ impl Position {
         }
     }
 
+    pub fn send_amend(pool: Handle, order: &mut Order, price: D128, size: D128, side: Side, stage: Stage, symbol: String, sender: Sender<StrategyMessage>) {
+        let size = (size.to_float() * 1000.).round() / 1000.;
+        let price = (price.to_float() * 100.).round() / 100.;
+        let (id, order_class) = (order.id, order.order_class);
+        order.pre_amend();
+        pool.spawn(async move {
+            let amend_result = BROKER.amend_order(id, symbol, price, size, side, stage).await;
+            sender.send(StrategyMessage::AccountMessage(
+                AccountMessage::AmendResponse(OrderResponseContext::new(id, side, stage, order_class, amend_result)),
+            )).unwrap();
+        });
+    }
+
     pub fn send_cancel(pool: Handle, order: &mut Order, side: Side, stage: Stage, symbol: String,  sender: Sender<StrategyMessage>) {
         // I don't know why i do this
         order.pre_cancel();
+        RISK.on_cancel(Venue::Binance, &symbol);
         let sender = sender.clone();
         // TODO Reduce this to not only the fields needed
         let order = order.clone();
@@ -483,6 +850,20 @@ impl Position {
                     //}
                 });
             }
+            OrderType::StopMarket | OrderType::TakeProfitMarket => {
+                let trigger = price;
+                pool.spawn(async move {
+                    let order_result = BROKER.create_conditional(
+                        id, symbol, order_type,
+                        trigger, size, broker_side, stage,
+                    ).await;
+                    sender.send(StrategyMessage::AccountMessage(
+                        AccountMessage::OrderResponse(
+                            OrderResponseContext::new(id, side, stage, order_class, order_result),
+                        ),
+                    )).unwrap();
+                });
+            }
             _ => todo!(),
         }
     }
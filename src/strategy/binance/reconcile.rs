@@ -0,0 +1,62 @@
+/// Reconciliation pulls the exchange's view of our orders and positions so the portfolio
+/// can diff it against its own lists. Runs at startup, on a timer, and whenever the strategy smells a desync.
+
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+
+use crate::backend::binance::broker::BROKER;
+use crate::backend::binance::types::{OpenOrder, PositionRisk, OpenOrdersWrapper, PositionRiskWrapper};
+
+use super::{StrategyMessage, AccountMessage};
+
+/// Seconds between scheduled reconciles
+pub const RECONCILE_PERIOD: u64 = 60;
+/// Minimum milliseconds between desync triggered reconciles, keeps a stuck desync from hammering the API
+pub const RECONCILE_COOLDOWN: u128 = 1000;
+
+#[derive(Clone, Debug)]
+pub struct ReconcileSnapshot {
+    pub orders: Vec<OpenOrder>,
+    pub positions: Vec<PositionRisk>,
+}
+
+impl ReconcileSnapshot {
+    /// Returns None if either half fails, reconciling against half a picture would only make things worse
+    pub async fn fetch(symbol: String) -> Option<ReconcileSnapshot> {
+        let orders = match BROKER.open_orders(symbol.clone()).await {
+            Ok(OpenOrdersWrapper::Orders(orders)) => orders,
+            Ok(OpenOrdersWrapper::Error(_)) => return None,
+            Err(e) => {
+                info!("[RECONCILE] Failed to pull open orders for {}: {}", symbol, e);
+                return None;
+            },
+        };
+        let positions = match BROKER.position_risk(symbol.clone()).await {
+            Ok(PositionRiskWrapper::Positions(positions)) => positions,
+            Ok(PositionRiskWrapper::Error(_)) => return None,
+            Err(e) => {
+                info!("[RECONCILE] Failed to pull positions for {}: {}", symbol, e);
+                return None;
+            },
+        };
+        Some(ReconcileSnapshot { orders, positions })
+    }
+}
+
+/// Fetches a snapshot and hands it to the strategy.
+/// Failed fetches are still sent so the portfolio can clear its in flight flag.
+pub async fn send_reconcile(symbol: String, sender: Sender<StrategyMessage>) {
+    let snapshot = ReconcileSnapshot::fetch(symbol).await;
+    sender.send(StrategyMessage::AccountMessage(AccountMessage::Reconcile(snapshot))).unwrap();
+}
+
+/// Scheduled reconciles, the startup reconcile is sent separately once the server time is known
+pub async fn reconcile_timer(symbol: String, sender: Sender<StrategyMessage>) {
+    let period = Duration::from_secs(RECONCILE_PERIOD);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        send_reconcile(symbol.clone(), sender.clone()).await;
+    }
+}
@@ -0,0 +1,166 @@
+/// Hosts a strategy on the strategy thread.
+/// Takes every message off the strategy channel, keeps the portfolio in sync with the account, then hands the event to
+/// the strategy picked by STRATEGY.
+
+use std::io::{Error, ErrorKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::backend::binance::types::{AccountBalance, PositionUpdateData, OrderUpdateData};
+use crate::config::CONFIG;
+use crate::strategy::params::{CONTROL, ParamUpdate};
+use crate::telemetry::{LATENCY, METRICS, PORTFOLIO_PERIOD, Venue, LatencyStage};
+
+use super::{AccountMessage, ModelMessage, OpMessage, StrategyMessage, Portfolio, ExecutionReport, ParentProgress};
+use super::strategy::{self, Strategy, Context, DEFAULT_STRATEGY};
+
+pub struct Runtime {
+    pub strat_rx: Receiver<StrategyMessage>,
+    ctx: Context,
+    strategy: Box<dyn Strategy>,
+    /// Re-sizes the portfolio at the latest price and publishes it to the metrics, handled here rather than passed to the strategy
+    metrics_timer: u64,
+}
+
+impl Runtime {
+    pub fn new(
+        symbol: String,
+        strat_tx: Sender<StrategyMessage>,
+        strat_rx: Receiver<StrategyMessage>
+    ) -> tokio::io::Result<Runtime> {
+        let name = CONFIG.strategy.clone().unwrap_or_else(|| DEFAULT_STRATEGY.to_string());
+        let strategy = strategy::build(&name)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("No strategy named {}", name)))?;
+        info!("[INIT] Hosting strategy {}", strategy.name());
+        let mut ctx = Context::new(Portfolio::new(strat_tx.clone(), symbol)?, strat_tx.clone());
+        let control_tx = strat_tx.clone();
+        CONTROL.register(Venue::Binance, &ctx.portfolio.symbol, ctx.params, Box::new(move |update| {
+            control_tx.send(StrategyMessage::OpMessage(OpMessage::Params(update))).is_ok()
+        }));
+        // Queued rather than called here so the strategy hears it on its own thread
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        let _ = strat_tx.send(StrategyMessage::OpMessage(OpMessage::Init(started)));
+        let metrics_timer = ctx.timers.every(PORTFOLIO_PERIOD);
+        Ok(Runtime {
+            strat_rx,
+            ctx,
+            strategy,
+            metrics_timer,
+        })
+    }
+
+    pub fn listen(&mut self) {
+        loop {
+            // Orders placed while handling anything but a tick shouldn't count toward tick-to-send
+            LATENCY.tick(Venue::Binance, None);
+            let message = match self.strat_rx.recv() {
+                Ok(message) => message,
+                Err(_) => break,
+            };
+            METRICS.channel_depth(Venue::Binance, self.strat_rx.len());
+            match message {
+                StrategyMessage::ModelMessage(mm) => match mm {
+                    ModelMessage::TradeFlowMessage(tr) => {
+                        LATENCY.tick(Venue::Binance, Some(tr.test_timer));
+                        self.ctx.executor.trades(tr);
+                        self.strategy.on_trade(&mut self.ctx, tr);
+                        LATENCY.since(Venue::Binance, LatencyStage::Decision, tr.test_timer);
+                    },
+                    ModelMessage::OrderBookMessage(br) => {
+                        LATENCY.tick(Venue::Binance, Some(br.test_timer));
+                        self.strategy.on_book(&mut self.ctx, &br);
+                        LATENCY.since(Venue::Binance, LatencyStage::Decision, br.test_timer);
+                    },
+                    ModelMessage::TopsMessage(t) => {
+                        LATENCY.tick(Venue::Binance, Some(t.test_timer));
+                        self.strategy.on_tops(&mut self.ctx, t);
+                        self.ctx.portfolio.mark(&t);
+                        self.ctx.portfolio.trail(&t);
+                        self.ctx.executor.tops(t, &mut self.ctx.portfolio);
+                        LATENCY.since(Venue::Binance, LatencyStage::Decision, t.test_timer);
+                    },
+                },
+                StrategyMessage::AccountMessage(am) => match am {
+                    AccountMessage::PositionUpdate(pud) => self.position_update(pud),
+                    AccountMessage::OrderUpdate(oud) => self.order_update(oud),
+                    AccountMessage::OrderResponse(or) => self.ctx.portfolio.order_rest_response(or.id, or.side, or.stage, or.rest_response, or.policy),
+                    AccountMessage::CancelResponse(cr) => self.ctx.portfolio.cancel_response(cr.id, cr.side, cr.stage, cr.rest_response, cr.policy),
+                    AccountMessage::AmendResponse(ar) => self.ctx.portfolio.amend_response(ar.id, ar.side, ar.stage, ar.rest_response, ar.policy),
+                    AccountMessage::BalanceRefresh(br) => self.balance_refresh(br),
+                    AccountMessage::Reconcile(rs) => self.ctx.portfolio.reconcile(rs),
+                    AccountMessage::ExecutionReport(er) => self.execution_report(er),
+                    AccountMessage::MarginRefresh(mr) => self.ctx.portfolio.margin_refresh(mr),
+                    AccountMessage::MarginCall(mc) => self.ctx.portfolio.margin_call(mc),
+                },
+                StrategyMessage::OpMessage(op) => match op {
+                    OpMessage::Init(_) => self.strategy.on_init(&mut self.ctx),
+                    OpMessage::Timer(id) if id == self.metrics_timer => {
+                        // Sizing follows the price here rather than on every tick
+                        self.ctx.portfolio.limits_refresh();
+                        METRICS.portfolio(Venue::Binance, &self.ctx.portfolio.symbol, self.ctx.portfolio.metrics());
+                    },
+                    OpMessage::Timer(id) => {
+                        if self.ctx.timers.fired(id) {
+                            self.strategy.on_timer(&mut self.ctx, id);
+                        }
+                    },
+                    OpMessage::Params(update) => self.params_update(update),
+                },
+                StrategyMessage::SpotMessage(signal) => self.strategy.on_spot(&mut self.ctx, &signal),
+                StrategyMessage::SpotResponse(response) => self.strategy.on_spot_response(&mut self.ctx, &response),
+            }
+        }
+        self.strategy.on_shutdown(&mut self.ctx);
+    }
+
+    fn position_update(&mut self, pud: PositionUpdateData) {
+        // info!("{:?}", pud);
+        for balance in pud.balances.iter() {
+            if balance.asset == "BUSD" {
+                self.ctx.portfolio.balance_update(balance);
+                break;
+            }
+        }
+        self.strategy.on_position(&mut self.ctx, &pud);
+    }
+
+    fn order_update(&mut self, oud: OrderUpdateData) {
+        // info!("UPDATE {:?}", oud);
+        self.ctx.executor.order_update(&oud);
+        self.ctx.portfolio.order_update(oud.clone());
+        self.strategy.on_order_update(&mut self.ctx, &oud);
+        if oud.last_filled_qty.is_positive() {
+            self.strategy.on_fill(&mut self.ctx, &oud);
+        }
+    }
+
+    /// Applies the whole update or none of it, either way it goes in the audit log
+    fn params_update(&mut self, update: ParamUpdate) {
+        let result = self.ctx.params.apply(&update);
+        if let Ok(params) = &result {
+            self.ctx.params = *params;
+            self.ctx.portfolio.apply_params(params);
+            self.strategy.on_params(&mut self.ctx);
+        }
+        CONTROL.record(Venue::Binance, &self.ctx.portfolio.symbol, &update, result.as_ref());
+    }
+
+    fn balance_refresh(&mut self, balances: Vec<AccountBalance>) {
+        // info!("{:?}", balances);
+        for balance in balances {
+            if balance.asset == "BUSD" {
+                self.ctx.portfolio.balance_refresh(balance);
+                break;
+            }
+        }
+    }
+
+    fn execution_report(&mut self, er: ExecutionReport) {
+        // info!("{:?}", er);
+        if er.progress != ParentProgress::Working {
+            info!("[EXEC] {:?} parent {} {:?}: {}/{} filled at {}, {} bps slippage", er.algo, er.id, er.progress, er.filled, er.size, er.avg_price, er.slippage);
+        }
+        self.strategy.on_execution(&mut self.ctx, &er);
+    }
+}
@@ -0,0 +1,120 @@
+/// Spot and cross margin orders and loans for a strategy, on the symbol the spot streams follow.
+/// Requests run on the portfolio's pool and their answers come back through on_spot_response. Nothing here goes
+/// through the futures portfolio, its position limits or its risk checks, a hedge is the strategy's to size.
+
+use crossbeam_channel::Sender;
+use dec::D128;
+use tokio::runtime::Handle;
+use uuid::Uuid;
+
+use crate::backend::binance::spot::SPOT;
+use crate::backend::binance::types::{SpotAccount, SideEffectType, SpotOrderWrapper, MarginLoanWrapper, MarginAccountWrapper};
+use crate::backend::types::Side;
+use crate::config::CONFIG;
+
+use super::StrategyMessage;
+
+/// What the spot broker answered, orders and cancels carry the client id they were sent under
+#[derive(Clone, Debug)]
+pub enum SpotResponse {
+    Order(Uuid, SpotOrderWrapper),
+    Cancel(Uuid, SpotOrderWrapper),
+    Borrow(MarginLoanWrapper),
+    Repay(MarginLoanWrapper),
+    Account(MarginAccountWrapper),
+}
+
+/// Cross margin when BINANCE_SPOT_MARGIN is set, orders there leave loans alone unless the account is changed
+pub fn configured_account() -> SpotAccount {
+    if CONFIG.binance_spot_margin.unwrap_or(false) {
+        SpotAccount::CrossMargin(SideEffectType::NoSideEffect)
+    } else {
+        SpotAccount::Spot
+    }
+}
+
+pub struct Spot {
+    pub symbol: String,
+    /// Which account orders go against, a cross margin side effect can borrow or repay as orders fill
+    pub account: SpotAccount,
+    pool: Handle,
+    strat_tx: Sender<StrategyMessage>,
+}
+
+impl Spot {
+    /// None when BINANCE_SPOT_SYMBOL isn't set
+    pub fn new(pool: Handle, strat_tx: Sender<StrategyMessage>) -> Option<Spot> {
+        Some(Spot {
+            symbol: CONFIG.binance_spot_symbol.clone()?.to_uppercase(),
+            account: configured_account(),
+            pool,
+            strat_tx,
+        })
+    }
+
+    /// Post only limit, returns the client id its response comes back under
+    pub fn limit(&self, price: D128, size: D128, side: Side) -> Uuid {
+        let (id, symbol, account, sender) = (Uuid::new_v4(), self.symbol.clone(), self.account, self.strat_tx.clone());
+        let (price, size) = (price.to_float(), size.to_float());
+        self.pool.spawn(async move {
+            let res = SPOT.create_limit(id, symbol, price, size, side, account).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Order(id, res))).ok();
+        });
+        id
+    }
+
+    /// Takes up to price and cancels the rest
+    pub fn ioc(&self, price: D128, size: D128, side: Side) -> Uuid {
+        let (id, symbol, account, sender) = (Uuid::new_v4(), self.symbol.clone(), self.account, self.strat_tx.clone());
+        let (price, size) = (price.to_float(), size.to_float());
+        self.pool.spawn(async move {
+            let res = SPOT.create_ioc(id, symbol, price, size, side, account).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Order(id, res))).ok();
+        });
+        id
+    }
+
+    pub fn market(&self, size: D128, side: Side) -> Uuid {
+        let (id, symbol, account, sender) = (Uuid::new_v4(), self.symbol.clone(), self.account, self.strat_tx.clone());
+        let size = size.to_float();
+        self.pool.spawn(async move {
+            let res = SPOT.create_market(id, symbol, size, side, account).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Order(id, res))).ok();
+        });
+        id
+    }
+
+    pub fn cancel(&self, id: Uuid) {
+        let (symbol, account, sender) = (self.symbol.clone(), self.account, self.strat_tx.clone());
+        self.pool.spawn(async move {
+            let res = SPOT.cancel_order(id, symbol, account).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Cancel(id, res))).ok();
+        });
+    }
+
+    /// Cross margin loan of amount of asset, outside of any order
+    pub fn borrow(&self, asset: String, amount: D128) {
+        let (sender, amount) = (self.strat_tx.clone(), amount.to_float());
+        self.pool.spawn(async move {
+            let res = SPOT.margin_borrow(asset, amount).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Borrow(res))).ok();
+        });
+    }
+
+    pub fn repay(&self, asset: String, amount: D128) {
+        let (sender, amount) = (self.strat_tx.clone(), amount.to_float());
+        self.pool.spawn(async move {
+            let res = SPOT.margin_repay(asset, amount).await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Repay(res))).ok();
+        });
+    }
+
+    /// Balances, loans and margin level of the cross margin account
+    pub fn margin_account(&self) {
+        let sender = self.strat_tx.clone();
+        self.pool.spawn(async move {
+            let res = SPOT.margin_account().await;
+            sender.send(StrategyMessage::SpotResponse(SpotResponse::Account(res))).ok();
+        });
+    }
+}
@@ -0,0 +1,180 @@
+/// Grid strategy, see strategy/grid.rs for the levels and the rules they follow.
+
+use dec::D128;
+use uuid::Uuid;
+
+use crate::analysis::BookResult;
+use crate::backend::bybit::broker::Side;
+use crate::strategy::grid::{Breakout, GridModel, GridSettings, GridSlot, GridState};
+use crate::strategy::types::{OrderClassification, Stage};
+
+use super::IncomingOrderWS;
+use super::strategy::{Strategy, Context};
+
+pub struct Grid {
+    model: GridModel,
+    last_book: Option<BookResult>,
+    refresh_timer: Option<u64>,
+}
+
+impl Strategy for Grid {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn on_init(&mut self, ctx: &mut Context) {
+        let period = std::time::Duration::from_secs(self.model.settings.refresh_secs);
+        self.refresh_timer = Some(ctx.timers.every(period));
+    }
+
+    fn on_book(&mut self, ctx: &mut Context, book: &BookResult) {
+        self.last_book = Some(*book);
+        self.upkeep(ctx, book);
+    }
+
+    fn on_fill(&mut self, _ctx: &mut Context, update: &IncomingOrderWS) {
+        self.model.fill(update.id, update.cum_fill_size);
+    }
+
+    fn on_timer(&mut self, ctx: &mut Context, id: u64) {
+        if self.refresh_timer != Some(id) { return; }
+        if let Some(book) = self.last_book {
+            self.upkeep(ctx, &book);
+        }
+    }
+
+    fn on_shutdown(&mut self, ctx: &mut Context) {
+        for (id, buy, entry) in self.model.orders() {
+            ctx.portfolio.cancel_order(id, side(buy), stage(entry), ctx.strat_tx.clone());
+        }
+    }
+}
+
+fn side(buy: bool) -> Side {
+    if buy { Side::Buy } else { Side::Sell }
+}
+
+fn stage(entry: bool) -> Stage {
+    if entry { Stage::Entry } else { Stage::Exit }
+}
+
+impl Grid {
+    pub fn new() -> Grid {
+        Grid {
+            model: GridModel::new(GridSettings::load()),
+            last_book: None,
+            refresh_timer: None,
+        }
+    }
+
+    fn upkeep(&mut self, ctx: &mut Context, book: &BookResult) {
+        let best_bid = book.best_bid.0;
+        let best_ask = book.best_ask.0;
+        let mid: D128 = (best_bid + best_ask) / 2;
+        if mid.is_nan() { return; }
+        if self.model.anchor.is_nan() {
+            self.model.reanchor(self.model.settings.anchor.unwrap_or(mid));
+        }
+        match self.model.state {
+            GridState::Stopped => return,
+            GridState::Flattening => {
+                self.flatten(ctx, mid);
+                return;
+            },
+            _ => {},
+        }
+        if self.model.should_reanchor(mid) {
+            let cancels = self.model.reanchor(mid);
+            self.model.state = GridState::Running;
+            Grid::cancel(ctx, cancels);
+        } else if self.model.broken_out(mid) {
+            match self.model.settings.breakout {
+                Breakout::Pause => if self.model.state == GridState::Running {
+                    info!("[GRID] Mid {} broke out of the band around {}, pausing entries", mid, self.model.anchor);
+                    self.model.state = GridState::Paused;
+                    Grid::cancel(ctx, self.model.entries());
+                },
+                Breakout::Shift => {
+                    info!("[GRID] Mid {} broke out of the band around {}, shifting", mid, self.model.anchor);
+                    let cancels = self.model.reanchor(mid);
+                    Grid::cancel(ctx, cancels);
+                },
+                Breakout::Flatten => {
+                    info!("[GRID] Mid {} broke out of the band around {}, flattening", mid, self.model.anchor);
+                    self.model.state = GridState::Flattening;
+                    self.flatten(ctx, mid);
+                    return;
+                },
+            }
+        } else if self.model.state == GridState::Paused {
+            info!("[GRID] Mid {} is back inside the band, resuming entries", mid);
+            self.model.state = GridState::Running;
+        }
+        let arm = self.model.state == GridState::Running;
+        for slot in self.model.slots.iter_mut() {
+            Grid::work(ctx, slot, best_bid, best_ask, arm);
+        }
+        self.model.prune();
+    }
+
+    /// Catches the level up with its orders, then places whichever of its entry or take-profit is missing
+    fn work(ctx: &mut Context, slot: &mut GridSlot, best_bid: D128, best_ask: D128, arm: bool) {
+        let side = side(slot.level.buy);
+        if let Some(id) = slot.entry {
+            if !ctx.portfolio.order_working(id, side, Stage::Entry) { slot.entry_done(); }
+        }
+        if let Some(id) = slot.exit {
+            if !ctx.portfolio.order_working(id, side, Stage::Exit) { slot.exit_done(); }
+        }
+        if slot.wants_exit() {
+            let id = Uuid::new_v4();
+            if ctx.portfolio.new_limit(Some(id), slot.level.take_profit, slot.held, side, Stage::Exit, OrderClassification::Exit, ctx.strat_tx.clone()) {
+                debug!("[GRID] {} level {} take-profit {} at {}", side, slot.level.index, slot.held, slot.level.take_profit);
+                slot.exit = Some(id);
+            }
+        } else if arm && slot.wants_entry() {
+            // Post only, levels the mid has already passed wait for it to come back
+            let behind = if slot.level.buy { slot.level.price < best_ask } else { slot.level.price > best_bid };
+            if !behind { return; }
+            let id = Uuid::new_v4();
+            if ctx.portfolio.new_limit(Some(id), slot.level.price, slot.level.size, side, Stage::Entry, OrderClassification::Rebase, ctx.strat_tx.clone()) {
+                debug!("[GRID] {} level {} entry {} at {}", side, slot.level.index, slot.level.size, slot.level.price);
+                slot.entry = Some(id);
+            }
+        }
+    }
+
+    fn cancel(ctx: &mut Context, entries: Vec<(Uuid, bool)>) {
+        for (id, buy) in entries {
+            let side = side(buy);
+            if ctx.portfolio.order_cancellable(id, side, Stage::Entry) {
+                ctx.portfolio.cancel_order(id, side, Stage::Entry, ctx.strat_tx.clone());
+            }
+        }
+    }
+
+    /// Pulls every grid order, then once none are left working closes both sides at market and stops
+    fn flatten(&mut self, ctx: &mut Context, mid: D128) {
+        let mut working = false;
+        for (id, buy, entry) in self.model.orders() {
+            let (side, stage) = (side(buy), stage(entry));
+            if ctx.portfolio.order_working(id, side, stage) {
+                working = true;
+                if ctx.portfolio.order_cancellable(id, side, stage) {
+                    ctx.portfolio.cancel_order(id, side, stage, ctx.strat_tx.clone());
+                }
+            }
+        }
+        if working { return; }
+        for side in [Side::Buy, Side::Sell] {
+            let inv = side.deside(&ctx.portfolio.data.buy, &ctx.portfolio.data.sell).open_position.inv;
+            if inv.is_positive() {
+                info!("[GRID] Flattening {} {}", side, inv);
+                ctx.portfolio.new_market(None, mid, inv, side, Stage::Exit, OrderClassification::Exit, ctx.strat_tx.clone());
+            }
+        }
+        info!("[GRID] Stopped, restart to trade the grid again");
+        self.model.slots.clear();
+        self.model.state = GridState::Stopped;
+    }
+}
@@ -0,0 +1,154 @@
+/// The ladder strategy. Its quoting has been redacted, what's left is the skeleton the branches hang off.
+
+use dec::D128;
+
+use crate::analysis::BookResult;
+use crate::backend::bybit::broker::Side;
+use crate::telemetry::Venue;
+
+use super::{ApplyBookResultError, StratBranch, TradeFlowMessage};
+use super::strategy::{Strategy, Context, CHIRP, CHIRP_ON_FLIP};
+
+pub struct Ladder {
+    last_buy_branch: StratBranch,
+    last_sell_branch: StratBranch,
+}
+
+impl Strategy for Ladder {
+    fn name(&self) -> &'static str {
+        "ladder"
+    }
+
+    /// This is where the majority of the logic for the strategy will go
+    fn on_book(&mut self, ctx: &mut Context, book: &BookResult) {
+        // alright lets lose some money
+        let rebate = ctx.params.rebate(Venue::Bybit, &ctx.portfolio.symbol);
+        // Bullish side
+        self.apply_book_result_side(ctx, Side::Buy, D128::ONE - rebate, book);
+        ctx.portfolio.data_refresh();
+        // Bearish side
+        self.apply_book_result_side(ctx, Side::Sell, D128::ONE + rebate, book);
+    }
+
+    fn on_trade(&mut self, _ctx: &mut Context, _trade: &TradeFlowMessage) {
+        // Bullish
+        self.trade_update_side(true);
+        // Bearish
+        self.trade_update_side(false);
+    }
+}
+
+impl Ladder {
+    pub fn new() -> Ladder {
+        Ladder {
+            last_buy_branch: StratBranch::SSS,
+            last_sell_branch: StratBranch::SSS,
+        }
+    }
+
+    fn chirp(&mut self, branch: StratBranch, side: Side) -> bool {
+        match CHIRP {
+            true => match CHIRP_ON_FLIP {
+                true => match (match side { Side::Buy => self.last_buy_branch, Side::Sell => self.last_sell_branch }) != branch {
+                        true => {
+                            match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                            info!("{} {:?}", side, branch);
+                            true
+                        },
+                        false => {
+                            match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                            false
+                        },
+                    },
+                false => {
+                    match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                    info!("{} {:?}", side, branch);
+                    true
+                },
+            },
+            false => {
+                match side { Side::Buy => self.last_buy_branch = branch, Side::Sell => self.last_sell_branch = branch };
+                false
+            },
+        }
+    }
+
+    fn strat_branch(opens: bool, closes: bool, inventory: bool) -> StratBranch {
+        if opens {
+            if closes {
+                if inventory {
+                    StratBranch::NNN
+                } else {
+                    StratBranch::NNS
+                }
+            } else {
+                if inventory {
+                    StratBranch::NSN
+                } else {
+                    StratBranch::NSS
+                }
+            }
+        } else {
+            if closes {
+                if inventory {
+                    StratBranch::SNN
+                } else {
+                    StratBranch::SNS
+                }
+            } else {
+                if inventory {
+                    StratBranch::SSN
+                } else {
+                    StratBranch::SSS
+                }
+            }
+        }
+    }
+
+    fn apply_book_result_side(&mut self, ctx: &mut Context, side: Side, cb_rebate: D128, book: &BookResult) -> Result<(), ApplyBookResultError> {
+        // let timer = std::time::Instant::now();
+        let exit_side = !side;
+        let init_size = ctx.portfolio.init_size;
+        // info!("start pdata: {}", ctx.portfolio.data);
+        // [OPENS, CLOSES, INVENTORY]
+        // debug!("strat timer {}", timer.elapsed().as_nanos());
+        return Ok(());
+    }
+
+    fn trade_update_side(&mut self, bullish: bool) {
+        // let position = match bullish {true => &mut self.asset_portfolio.buy, false => &mut self.asset_portfolio.sell };
+        // // BULL SIDE
+        // if position.active_opens.len() == 0 {
+        //     if position.active_closes.len() == 0 {
+        //         if position.known_inventory <= D128::ZERO {
+        //             // Not in a position, no orders active
+        //         } else {
+        //             // In a position, no orders active
+        //             debug!("buyside close (sell)");
+        //         }
+        //     } else {
+        //         if position.known_inventory <= D128::ZERO {
+        //             // No position, closing orders active
+        //             debug!("\nPOSSIBLE DESYNC: CLOSING ORDERS RESTING WITH EMPTY POSITION: {:?}\n", position);
+        //         } else {
+        //             // In a position, closing orders active
+        //         }
+        //     }
+        // } else {
+        //     if position.active_closes.len() == 0 {
+        //         if position.known_inventory <= D128::ZERO {
+        //             // No position, opens active
+        //         } else {
+        //             // In a position, opens active
+        //         }
+        //     } else {
+        //         if position.known_inventory <= D128::ZERO {
+        //             // No position, opens and closes active
+        //             debug!("\nPOSSIBLE DESYNC: CLOSING (AND OPENING) ORDERS RESTING WITH EMPTY POSITION: {:?}\n", position);
+        //         } else {
+        //             // In a position, opens and closes active
+        //         }
+        //     }
+        // }
+    }
+}
@@ -0,0 +1,89 @@
+/// Margin requirements the portfolio sizes itself against.
+/// Leverage and margin mode are applied from config at startup, then read back along with the risk limit tiers.
+
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use dec::D128;
+
+use crate::backend::bybit::broker::{BROKER, RiskLimit};
+use crate::backend::bybit::CONTRACT;
+use crate::backend::bybit::errors::StatusOutcome;
+use crate::config::CONFIG;
+
+use super::{StrategyMessage, AccountMessage, MARGIN_USAGE};
+
+/// Seconds between fetches while the margin settings are still unknown
+pub const MARGIN_RETRY_PERIOD: u64 = 30;
+
+#[derive(Clone, Debug)]
+pub struct MarginSnapshot {
+    pub leverage: D128,
+    pub is_isolated: bool,
+    pub balance: D128,
+    /// Sorted by limit
+    pub risk_limits: Vec<RiskLimit>,
+}
+
+impl MarginSnapshot {
+    /// Applies the configured leverage and margin mode, then reads back what the exchange actually has.
+    /// Returns None if the read back fails.
+    pub async fn fetch(symbol: String) -> Option<MarginSnapshot> {
+        let leverage = CONFIG.leverage.map(|l| l as f64);
+        if let Some(margin_type) = &CONFIG.margin_type {
+            let is_isolated = margin_type.to_lowercase() == "isolated";
+            // Switching resets leverage, so carry the configured one or whatever the position has now
+            let current = match BROKER.position_list(symbol.clone()).await {
+                Ok(res) => res.result.and_then(|p| p.first().map(|p| p.leverage.to_float())),
+                Err(_) => None,
+            };
+            if let Some(switch_leverage) = leverage.or(current) {
+                match BROKER.switch_isolated(symbol.clone(), is_isolated, switch_leverage).await {
+                    Ok(res) if res.ret_code.outcome() == StatusOutcome::Ok => info!("[MARGIN] {} switched to {} margin", symbol, margin_type),
+                    Ok(res) => debug!("[MARGIN] {} margin mode left as is: {}", symbol, res.ret_msg),
+                    Err(e) => info!("[MARGIN] Failed to switch {} margin mode: {}", symbol, e),
+                }
+            }
+        }
+        if let Some(leverage) = leverage {
+            match BROKER.set_leverage(symbol.clone(), leverage).await {
+                Ok(res) if res.ret_code.outcome() == StatusOutcome::Ok => info!("[MARGIN] {} set to {}x", symbol, leverage),
+                Ok(res) => debug!("[MARGIN] {} leverage left as is: {}", symbol, res.ret_msg),
+                Err(e) => info!("[MARGIN] Failed to set {} leverage to {}x: {}", symbol, leverage, e),
+            }
+        }
+        let coin = CONTRACT.settle_coin(&symbol);
+        let position = BROKER.position_list(symbol.clone()).await.ok()?.result?.into_iter().next()?;
+        let mut risk_limits = BROKER.risk_limits(symbol).await.ok()?.result?;
+        risk_limits.sort_by(|a, b| a.limit.partial_cmp(&b.limit).unwrap());
+        let balance = BROKER.get_balance(coin.clone()).await.ok()?.result?.get(&coin)?.wallet_balance;
+        Some(MarginSnapshot { leverage: position.leverage, is_isolated: position.is_isolated, balance, risk_limits })
+    }
+
+    /// Largest position value the balance can carry, in the settle coin at our leverage, counting the maintenance margin of the tier it lands in
+    pub fn max_notional(&self) -> D128 {
+        let budget = self.balance * *MARGIN_USAGE;
+        if !budget.is_positive() || !self.leverage.is_positive() { return D128::ZERO; }
+        if self.risk_limits.is_empty() { return budget * self.leverage; }
+        let initial = D128::ONE / self.leverage;
+        let mut max = D128::ZERO;
+        for tier in self.risk_limits.iter() {
+            if tier.max_leverage < self.leverage { break; }
+            let notional = budget / (initial + tier.maintain_margin);
+            if notional <= tier.limit { return notional; }
+            max = tier.limit;
+        }
+        max
+    }
+}
+
+pub async fn send_margin(symbol: String, sender: Sender<StrategyMessage>) {
+    let snapshot = MarginSnapshot::fetch(symbol).await;
+    sender.send(StrategyMessage::AccountMessage(AccountMessage::MarginRefresh(snapshot))).unwrap();
+}
+
+/// Fetches again once the retry period is up, entries stay off until one lands
+pub async fn retry_margin(symbol: String, sender: Sender<StrategyMessage>) {
+    tokio::time::sleep(Duration::from_secs(MARGIN_RETRY_PERIOD)).await;
+    send_margin(symbol, sender).await;
+}
@@ -2,9 +2,10 @@ use std::collections::HashMap;
 
 use dec::D128;
 
-use crate::{analysis::BookResult, backend::bybit::{stream::{BybitOrderTick, BybitStopOrderTick, BybitExecutionTick, BybitPositionTick, BybitWalletTick}, broker::{RestResponse, Balance}}};
+use crate::{analysis::{BookResult, TradeResult}, backend::bybit::{stream::{BybitOrderTick, BybitStopOrderTick, BybitExecutionTick, BybitPositionTick, BybitWalletTick}, broker::{RestResponse, Balance}}};
+use crate::strategy::params::ParamUpdate;
 
-use super::{OrderResponse, CancelResponse};
+use super::{OrderResponse, CancelResponse, AmendResponse, ConditionalResponse, MarginSnapshot, ReconcileSnapshot, ExecutionReport};
 
 pub struct Timestamps {
     pub init: D128,
@@ -17,8 +18,10 @@ pub struct OrderBookMessage {
     pub orderbook_analysis: BookResult
 }
 
+/// Trade analysis off the public trade stream, timestamp is the last trade's time in ms
 pub struct TradeFlowMessage {
     pub timestamp: u128,
+    pub trade: TradeResult,
 }
 
 #[derive(Debug)]
@@ -34,6 +37,9 @@ pub enum ModelMessage {
 pub enum OrderMessage {
     OrderResult(OrderResponse),
     CancelResult(CancelResponse),
+    AmendResult(AmendResponse),
+    ConditionalResult(ConditionalResponse),
+    ConditionalCancelResult(ConditionalResponse),
     OrderUpdate(BybitOrderTickSignal),
     StopOrderUpdate(BybitStopOrderTick),
     ExecutionUpdate(BybitExecutionTick),
@@ -51,15 +57,23 @@ pub enum WalletMessage {
 pub enum AccountMessage {
     OrderMessage(OrderMessage),
     PositionMessage(PositionMessage),
+    WalletMessage(WalletMessage),
+    MarginRefresh(Option<MarginSnapshot>),
+    Reconcile(Option<ReconcileSnapshot>),
+    ExecutionReport(ExecutionReport),
 }
 
+/// Messages the runtime sends itself, Init carries the start time in ms and Timer a scheduler timer id.
+/// Params are parameter updates from CONTROL.
 pub enum OpMessage {
     Init(u128),
-    Timer(u128),
+    Timer(u64),
+    Params(ParamUpdate),
 }
 
 pub enum StrategyMessage {
     ModelMessage(ModelMessage),
     AccountMessage(AccountMessage),
+    OpMessage(OpMessage),
 }
 
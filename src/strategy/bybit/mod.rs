@@ -1,9 +1,16 @@
 mod account;
+mod execution;
+mod grid;
+mod ladder;
+mod market_maker;
 mod order;
 mod position;
 mod message;
 mod portfolio;
 mod order_list;
+mod margin;
+mod reconcile;
+mod runtime;
 pub mod strategy;
 
 use crossbeam_channel::Receiver;
@@ -18,10 +25,14 @@ use thiserror::Error;
 use crate::backend::bybit::broker::SetServerOffsetError;
 
 pub use self::account::*;
+pub use self::execution::*;
 pub use self::order::*;
 pub use self::position::*;
 pub use self::message::*;
 pub use self::portfolio::*;
+pub use self::margin::*;
+pub use self::reconcile::*;
+pub use self::runtime::*;
 
 
 pub const RISK: usize = 10;
@@ -33,9 +44,10 @@ pub const CHIRP: bool = false;
 pub const CHIRP_ON_FLIP: bool = true;
 
 lazy_static! {
-    pub static ref REBATE: D128 = D128::from(0.00025);
     pub static ref MAX_OPEN_DIST: D128 = D128::from(30);
     pub static ref TOP_OPEN_DIST: D128 = D128::from(6);
+    /// Share of the balance that can be committed as margin
+    pub static ref MARGIN_USAGE: D128 = D128::from(0.8);
 }
 
 #[derive(Error, Debug)]
@@ -8,13 +8,15 @@ use std::str::FromStr;
 // use std::time::Instant;
 use uuid::Uuid;
 
+use crate::backend::bybit::CONTRACT;
 use crate::backend::bybit::stream::BybitOrderData;
-use crate::backend::bybit::broker::{ RestResponse, CancelResult, CreateOrderStatus, OrderType, Side};
+use crate::backend::bybit::broker::{ RestResponse, CancelResult, ReplaceResult, CreateOrderStatus, OrderType, Side, StopOrderResult};
 use crate::backend::bybit::broker::{OrderResult, OrderStatus};
 use crate::backend::types::TimeInForce;
 use crate::strategy::types::{Stage, OrderClassification};
-
-use super::{REBATE};
+use crate::strategy::protection::ConditionalKind;
+use crate::strategy::fees::{FEES, FeeRates};
+use crate::telemetry::Venue;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OrderProgress {
@@ -56,6 +58,73 @@ pub struct CancelResponse {
     pub rest_response: RestResponse<CancelResult>,
 }
 
+/// Amend responses carry the price and size asked for, the ack only has the order id
+#[derive(Debug, Clone)]
+pub struct AmendResponse {
+    pub id: Uuid,
+    pub side: Side,
+    pub stage: Stage,
+    pub price: D128,
+    pub size: D128,
+    pub rest_response: RestResponse<ReplaceResult>,
+}
+
+/// Stop order create and cancel responses, the id is the conditional's order_link_id
+#[derive(Debug, Clone)]
+pub struct ConditionalResponse {
+    pub id: Uuid,
+    pub side: Side,
+    /// None when the request never got a response
+    pub rest_response: Option<RestResponse<StopOrderResult>>,
+}
+
+/// A stop order resting untriggered on the exchange.
+/// Once it triggers it becomes a regular close under the same id and is tracked in the closes from then on.
+#[derive(Debug, Clone, Copy)]
+pub struct ConditionalOrder {
+    pub id: Uuid,
+    pub kind: ConditionalKind,
+    pub trigger: D128,
+    pub size: D128,
+    /// Placed by hand, the automatic protection leaves these alone
+    pub manual: bool,
+    pub progress: OrderProgress,
+    pub cancel_in_flight: bool,
+}
+
+impl ConditionalOrder {
+    pub fn new(kind: ConditionalKind, trigger: D128, size: D128, manual: bool) -> ConditionalOrder {
+        ConditionalOrder {
+            id: Uuid::new_v4(),
+            kind,
+            trigger,
+            size,
+            manual,
+            progress: OrderProgress::Init,
+            cancel_in_flight: false,
+        }
+    }
+
+    /// Still waiting on its trigger
+    pub fn live(&self) -> bool {
+        self.progress == OrderProgress::Init || self.progress == OrderProgress::Resting
+    }
+
+    pub fn can_cancel(&self) -> bool {
+        self.live() && !self.cancel_in_flight
+    }
+
+    pub fn stop_order_update(&mut self, status: &str) {
+        match status {
+            "Untriggered" => if self.progress == OrderProgress::Init { self.progress = OrderProgress::Resting; },
+            "Triggered" | "Active" => self.progress = OrderProgress::Filled,
+            "Cancelled" | "Deactivated" => self.progress = OrderProgress::Cancelled,
+            "Rejected" => self.progress = OrderProgress::Failed,
+            _ => debug!("Unknown stop order status {} for {}", status, self.id),
+        }
+    }
+}
+
 impl OrderResponse {
     pub fn new(id: Uuid, side: Side, stage: Stage, sent: String, class: OrderClassification, rest_response: RestResponse<OrderResult>) -> OrderResponse {
         OrderResponse {
@@ -181,6 +250,10 @@ pub struct Order {
     pub order_type: OrderType,
     pub order_class: OrderClassification,
     pub progress: OrderProgress,
+    /// Resends spent on Retry/Resync outcomes, for both the order and its cancel
+    pub retries: usize,
+    /// Consecutive reconciles this order has been missing from the exchange
+    pub reconcile_misses: usize,
 }
 
 impl From<IncomingOrderWS> for Order {
@@ -190,11 +263,13 @@ impl From<IncomingOrderWS> for Order {
                 Some(incoming.id),
                 Some(incoming.price),
                 incoming.size,
+                FEES.rates(Venue::Bybit, CONTRACT.symbol()),
             ),
             OrderType::Market => Order::new_orphan(
                 Some(incoming.id),
                 None,
                 incoming.size,
+                FEES.rates(Venue::Bybit, CONTRACT.symbol()),
             ),
         };
         order.id = incoming.id;
@@ -215,11 +290,13 @@ impl From<IncomingOrderREST> for Order {
                 Some(incoming.id),
                 Some(incoming.price),
                 incoming.size,
+                FEES.rates(Venue::Bybit, CONTRACT.symbol()),
             ),
             OrderType::Market => Order::new_orphan(
                 Some(incoming.id),
                 None,
                 incoming.size,
+                FEES.rates(Venue::Bybit, CONTRACT.symbol()),
             ),
         };
         order.id = incoming.id;
@@ -251,7 +328,7 @@ impl Order {
         self.filled_size = order.cum_fill_size;
         self.filled_liq = order.cum_fill_liq;
         self.unfilled_size = order.cum_remaining_size;
-        self.unfilled_liq = order.cum_remaining_size * order.price;
+        self.unfilled_liq = CONTRACT.value(order.cum_remaining_size, order.price);
         self.cum_fee = order.cum_fill_fee;
     }
 
@@ -259,7 +336,7 @@ impl Order {
         self.filled_size = order.cum_fill_size;
         self.filled_liq = order.cum_fill_liq;
         self.unfilled_size = order.cum_remaining_size;
-        self.unfilled_liq = order.cum_remaining_size * order.price;
+        self.unfilled_liq = CONTRACT.value(order.cum_remaining_size, order.price);
         self.cum_fee = order.cum_fill_fee;
     }
 
@@ -368,6 +445,14 @@ impl Order {
 
     // Just broken out to remind me to handle later
     // A lot of these should be panics
+    /// The create's outcome is unknown, it's taken as resting so the reconcile either finds it by its link id or drops it after two misses
+    pub fn unknown_response(&mut self) {
+        self.in_flight = false;
+        if self.progress == OrderProgress::Init {
+            self.progress = OrderProgress::Resting;
+        }
+    }
+
     pub fn fail_response(&mut self) {
         self.in_flight = false;
         match self.progress {
@@ -389,6 +474,30 @@ impl Order {
         }
     }
 
+    /// Only resting limits can be moved, and not while anything else is in flight for them
+    pub fn can_amend(&self) -> bool {
+        matches!(self.order_type, OrderType::Limit)
+            && (self.progress == OrderProgress::Resting || self.progress == OrderProgress::PartiallyFilled)
+            && !self.in_flight && !self.cancel_in_flight
+    }
+
+    pub fn pre_amend(&mut self) {
+        self.in_flight = true;
+    }
+
+    pub fn amend_response(&mut self, price: D128, size: D128) {
+        self.in_flight = false;
+        self.price = price;
+        self.size = size;
+        self.unfilled_size = size - self.filled_size;
+        self.unfilled_liq = CONTRACT.value(self.unfilled_size, price);
+    }
+
+    /// The order is still resting where it was
+    pub fn fail_amend_response(&mut self) {
+        self.in_flight = false;
+    }
+
     pub fn cancel_response(&mut self) {
         self.cancel_in_flight = false;
         self.progress = OrderProgress::Cancelled;
@@ -398,11 +507,24 @@ impl Order {
         self.cancel_in_flight = false;
     }
 
+    /// Fails this order and hands back a fresh copy under a new id to resend in its place.
+    /// Bybit won't take an order_link_id twice, even off a rejected order.
+    pub fn retry(&mut self, fees: FeeRates) -> Order {
+        self.fail_response();
+        let mut ord = match self.order_type {
+            OrderType::Limit => Order::new_rebate(None, self.price, self.size, self.order_class, fees),
+            OrderType::Market => Order::new_taker(None, self.price, self.size, self.order_class, fees),
+        };
+        ord.retries = self.retries + 1;
+        ord
+    }
+
     pub fn new_taker(
         id: Option<Uuid>,
         expected_price: D128,
         size: D128,
         class: OrderClassification,
+        fees: FeeRates,
     ) -> Order {
         Order {
             id: match id {
@@ -413,7 +535,7 @@ impl Order {
             auto_gen: false,
             price: expected_price,
             size,
-            expected_fee: expected_price * size * 0.00075,
+            expected_fee: fees.taker_fee(CONTRACT.value(size, expected_price)),
             in_flight: false,
             cancel_in_flight: false,
             unfilled_size: size,
@@ -425,6 +547,8 @@ impl Order {
             cum_fee: D128::ZERO,
             progress: OrderProgress::Init,
             order_class: class,
+            retries: 0,
+            reconcile_misses: 0,
         }
     }
 
@@ -433,12 +557,13 @@ impl Order {
         price: D128,
         size: D128,
         class: OrderClassification,
+        fees: FeeRates,
     ) -> Order {
-        let mut ord = Order::new_taker(id, price, size, class);
-        ord.expected_fee = price * size * *REBATE * -1;
+        let mut ord = Order::new_taker(id, price, size, class, fees);
+        ord.expected_fee = fees.maker_fee(CONTRACT.value(size, price));
         ord.time_in_force = TimeInForce::PostOnly;
         ord.order_type = OrderType::Limit;
-        ord.unfilled_liq = ord.price * ord.size;
+        ord.unfilled_liq = CONTRACT.value(ord.size, ord.price);
         ord
     }
 
@@ -447,12 +572,24 @@ impl Order {
         id: Option<Uuid>,
         price: Option<D128>,
         size: D128,
+        fees: FeeRates,
     ) -> Order {
         let mut ord = match price {
-            Some(price) => Order::new_rebate(id, price, size, OrderClassification::None),
-            None => Order::new_taker(id, D128::NAN, size, OrderClassification::None),
+            Some(price) => Order::new_rebate(id, price, size, OrderClassification::None, fees),
+            None => Order::new_taker(id, D128::NAN, size, OrderClassification::None, fees),
         };
         ord.progress = OrderProgress::Untracked;
         ord
     }
+
+    /// A synthetic fill used by reconciliation to bring our inventory in line with the exchange's
+    pub fn new_adjustment(price: D128, size: D128) -> Order {
+        let mut ord = Order::new_orphan(None, Some(price), size, FeeRates::NONE);
+        ord.filled_size = size;
+        ord.filled_liq = CONTRACT.value(size, price);
+        ord.unfilled_size = D128::ZERO;
+        ord.unfilled_liq = D128::ZERO;
+        ord.progress = OrderProgress::Filled;
+        ord
+    }
 }
\ No newline at end of file
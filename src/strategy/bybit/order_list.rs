@@ -9,6 +9,9 @@ use std::collections::hash_map::Entry::Vacant;
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::backend::bybit::CONTRACT;
+use crate::backend::bybit::errors::MAX_RETRIES;
+use crate::strategy::fees::FeeRates;
 use crate::strategy::types::OrderClassification;
 
 use super::IncomingOrderREST;
@@ -63,10 +66,10 @@ impl OrderData {
     }
 
     pub fn process(&mut self) {
-        self.prebate_cb = self.liq / self.inv;
+        self.prebate_cb = CONTRACT.cost_basis(self.inv, self.liq);
 
         // sweet jesus kill me
-/*todo*/ self.cb = (self.liq + self.rebate) / self.inv; // the + here is side-specific and we have no clean way to inject side
+/*todo*/ self.cb = CONTRACT.cost_basis(self.inv, self.liq + self.rebate); // the + here is side-specific and we have no clean way to inject side
         // you will live to see manmade horrors beyond your comprehension
     } // it's over, it's completely over
 
@@ -225,13 +228,30 @@ impl OrderList {
                     occ.fail_response();
                 },
                 Vacant(vac) => {
-                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO));
+                    vac.insert(Order::new_orphan(Some(id), None, D128::ZERO, FeeRates::NONE));
                     debug!("REST response's context didn't match to a known order, making orphan");
                 },
             },
         }
     }
 
+    pub fn unknown_order(&mut self, id: Uuid) {
+        if let Some(occ) = self.order_map.get_mut(&id) {
+            occ.unknown_response();
+        }
+    }
+
+    pub fn rest_amend(&mut self, id: Uuid, price: D128, size: D128, success: bool) {
+        match self.order_map.get_mut(&id) {
+            Some(occ) if success => occ.amend_response(price, size),
+            Some(occ) => {
+                debug!("Amend of {} failed, leaving it where it was", id);
+                occ.fail_amend_response();
+            },
+            None => debug!("REST amend response's context didn't match to a known order"),
+        }
+    }
+
     pub fn rest_cancel(&mut self, id: Uuid, success: bool) {
         match self.order_map.entry(id) {
             Occupied(mut occ) => {
@@ -242,21 +262,64 @@ impl OrderList {
                 }
             },
             Vacant(vac) => {
-                vac.insert(Order::new_orphan(Some(id), None, D128::ZERO));
+                vac.insert(Order::new_orphan(Some(id), None, D128::ZERO, FeeRates::NONE));
                 info!("REST cancel response's context didn't match to a known order, making orphan");
             },
         }
     }
 
-    pub fn ws_order(&mut self, id: Uuid, order: IncomingOrderWS) {
-        match self.order_map.entry(id) {
-            Occupied(mut occ) => {
-                occ.get_mut().order_update(order);
+    /// Fails the order and files a replacement under a new id, None once it's out of retries
+    pub fn retry_order(&mut self, id: Uuid, fees: FeeRates) -> Option<&mut Order> {
+        let ord = match self.order_map.get_mut(&id) {
+            Some(occ) if occ.retries < MAX_RETRIES => occ.retry(fees),
+            Some(occ) => {
+                occ.fail_response();
+                info!("Order {} out of retries, dropping", id);
+                return None;
             },
-            Vacant(vac) => {
-                vac.insert(Order::from(order));
-                panic!("WS update didn't match a known order, making orphan");
+            None => return None,
+        };
+        self.add_order(ord).ok()
+    }
+
+    /// Clears the failed cancel and hands the order back if it's still worth cancelling
+    pub fn retry_cancel(&mut self, id: Uuid) -> Option<&mut Order> {
+        match self.order_map.get_mut(&id) {
+            Some(occ) => {
+                occ.fail_cancel_response();
+                if occ.retries < MAX_RETRIES && occ.can_cancel() {
+                    occ.retries += 1;
+                    Some(occ)
+                } else { None }
             },
+            None => None,
+        }
+    }
+
+    /// Returns true if the update didn't match a known order, meaning we've lost track of something
+    pub fn ws_order(&mut self, id: Uuid, order: IncomingOrderWS) -> bool {
+        match self.find_id(Some(id), order.auto_id) {
+            Some(known) => {
+                if let Some(occ) = self.order_map.get_mut(&known) { occ.order_update(order); }
+                false
+            },
+            None => {
+                self.order_map.insert(id, Order::from(order));
+                info!("WS update didn't match a known order, making orphan");
+                true
+            },
+        }
+    }
+
+    /// Looks an order up by order_link_id, falling back to the exchange's id for orphans
+    pub fn find_id(&self, link_id: Option<Uuid>, auto_id: Uuid) -> Option<Uuid> {
+        if let Some(id) = link_id {
+            if self.order_map.contains_key(&id) { return Some(id); }
+        }
+        if self.order_map.contains_key(&auto_id) { return Some(auto_id); }
+        match self.order_map.iter().find(|(_, order)| !auto_id.is_nil() && order.auto_id == auto_id) {
+            Some((id, _)) => Some(*id),
+            None => None,
         }
     }
 
@@ -284,7 +347,7 @@ impl OrderList {
         match self.get_top() {
             Some(order) => {
                 let mut od = OrderData::new();
-                od.update(order.size, order.size * order.price, order.expected_fee);
+                od.update(order.size, CONTRACT.value(order.size, order.price), order.expected_fee);
                 Some(od)
             },
             None => None,
@@ -1,4 +1,5 @@
 use std::fmt::{Display, Formatter};
+use std::time::Instant;
 
 use crossbeam_channel::Sender;
 /// Bybit Account --> account interface --> position interface --> position --> orders
@@ -7,11 +8,20 @@ use dec::D128;
 use tokio::runtime::{Runtime, Builder};
 use uuid::Uuid;
 
-use crate::backend::bybit::broker::Side;
+use crate::backend::bybit::broker::{Side, OrderStatus};
+use crate::backend::bybit::{CONTRACT, ContractType};
+use crate::backend::bybit::stream::BybitStopOrderData;
+use crate::risk::{RISK, BREAKER, OrderCheck, LiquidationLevel, DERISK_COOLDOWN};
+use crate::strategy::exposure::{Exposure, SideExposure};
+use crate::strategy::ledger::PNL;
+use crate::strategy::params::Params;
 use crate::strategy::types::{Stage, OrderClassification};
+use crate::strategy::protection::{ConditionalKind, ProtectionSettings};
+use crate::telemetry::{PortfolioMetrics, Venue};
 
 use super::order_list::OrderData;
 use super::{StrategyMessage, Position, IncomingOrderREST, IncomingOrderWS, IncomingPosition, PositionData, FinData, FindCancelRes, Order};
+use super::{MarginSnapshot, ReconcileSnapshot, send_reconcile, retry_margin, RECONCILE_COOLDOWN, MARGIN_RETRY_PERIOD};
 
 #[derive(Clone, Copy)]
 pub struct PortfolioData {
@@ -19,14 +29,13 @@ pub struct PortfolioData {
     pub sell: PositionData,
     pub remaining_margin: D128,
     pub remaining_count: D128,
-    // Someday lmao
-    // pub delta: PositionData,
+    pub exposure: Exposure,
 }
 
 impl Display for PortfolioData {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        write!(f, "Portfolio Data: \n{{\n    buys: {},\n    sells: {},\n    remaining margin: {}, remaining count: {}\n}}",
-        self.buy, self.sell, self.remaining_margin, self.remaining_count)
+        write!(f, "Portfolio Data: \n{{\n    buys: {},\n    sells: {},\n    remaining margin: {}, remaining count: {}\n    {}\n}}",
+        self.buy, self.sell, self.remaining_margin, self.remaining_count, self.exposure)
     }
 }
 
@@ -37,6 +46,7 @@ impl PortfolioData {
             sell: PositionData::new(),
             remaining_margin: D128::ZERO,
             remaining_count: D128::ZERO,
+            exposure: Exposure::new(),
         }
     }
 }
@@ -57,6 +67,22 @@ pub struct Portfolio {
     pub rebase_distance_limit: D128,
     /// Channel for sending updates back to the main strategy
     pub strat_tx: Sender<StrategyMessage>,
+    /// Set by a Halt outcome, blocks all new entries until restart
+    pub halted: bool,
+    /// Leverage, balance and risk limits max_size is worked out from, None until the startup fetch lands
+    pub margin: Option<MarginSnapshot>,
+    /// Mid price, converts the position value the margin allows into size
+    pub price: D128,
+    /// Set while the kill switch covers this symbol, orders were cancelled when it tripped
+    pub tripped: bool,
+    /// Entries on a disabled side are refused, exits still go out
+    pub buy_enabled: bool,
+    pub sell_enabled: bool,
+    /// When part of a position was last closed for being too close to liquidation
+    last_derisk: Option<Instant>,
+    /// Set while a desync triggered reconcile is waiting on the exchange
+    reconcile_in_flight: bool,
+    last_reconcile: Instant,
     pool: Runtime
 }
 
@@ -70,18 +96,30 @@ impl Portfolio {
             .build()?;
         let max = D128::from(8);
         let mut app = Portfolio {
-            buy: Position::new(pool.handle().clone(), symbol.clone(), Side::Buy, D128::from(1.3), max / 2),
-            sell: Position::new(pool.handle().clone(), symbol.clone(), Side::Sell, D128::from(1.3), max / 2),
+            buy: Position::new(pool.handle().clone(), symbol.clone(), Side::Buy, D128::ZERO, max / 2),
+            sell: Position::new(pool.handle().clone(), symbol.clone(), Side::Sell, D128::ZERO, max / 2),
             historical: vec![],
             init_size: D128::from(0.001),
             max_open_orders: max,
             rebase_distance_limit: D128::from(10),
-            max_size: D128::from(1.3),
+            max_size: D128::ZERO,
             symbol: symbol,
             strat_tx,
+            halted: false,
+            margin: None,
+            price: D128::ZERO,
+            tripped: false,
+            buy_enabled: true,
+            sell_enabled: true,
+            last_derisk: None,
+            reconcile_in_flight: false,
+            last_reconcile: Instant::now(),
             pool,
             data: PortfolioData::new(),
         };
+        let settings = ProtectionSettings::load();
+        app.buy.protection = settings;
+        app.sell.protection = settings;
         app.data_generate();
         Ok(app)
     }
@@ -94,11 +132,25 @@ impl Portfolio {
             sell: sell,
             remaining_margin: self.max_size - (buy.open_liqs.total_reserved.inv + sell.open_liqs.total_reserved.inv),
             remaining_count: self.max_open_orders - (buy.open_liqs.total_reserved.count + sell.open_liqs.total_reserved.count),
+            exposure: Exposure::from_sides(
+                Portfolio::side_exposure(&buy),
+                Portfolio::side_exposure(&sell),
+                |size| CONTRACT.value(size, self.price),
+                self.margin.as_ref().map_or(D128::ZERO, |margin| margin.max_notional()),
+            ),
         };
         // info!("pd {}", pd);
         pd
     }
 
+    fn side_exposure(data: &PositionData) -> SideExposure {
+        SideExposure {
+            filled: data.open_position.inv,
+            entries: data.open_liqs.total_reserved.inv,
+            exits: data.close_liqs.total_reserved.inv,
+        }
+    }
+
     pub fn data_refresh(&mut self) {
         self.data = self.data_generate();
         // info!("data: {}", self.data);
@@ -114,11 +166,14 @@ impl Portfolio {
         sender: Sender<StrategyMessage>,
     ) -> bool {
         // self.data_refresh();
+        if stage == Stage::Entry && self.halted { return false; }
+        if stage == Stage::Entry && !self.side_enabled(side) { return false; }
         if stage == Stage::Entry && (size > self.data.remaining_margin || D128::ONE > self.data.remaining_count) {
             // debug!("portrej {} rem: {}, count: {}", side, self.data.remaining_margin, self.data.remaining_count);
             return false;
         }
         if size.is_nan() { panic!("size is nan"); }
+        if !self.risk_check(price, size, side, stage) { return false; }
         // info!("{:?} {:?} order up for {}", side, stage, size);
         match side {
             Side::Buy => {
@@ -145,9 +200,12 @@ impl Portfolio {
         sender: Sender<StrategyMessage>,
     ) -> bool {
         // self.data_refresh();
+        if stage == Stage::Entry && self.halted { return false; }
+        if stage == Stage::Entry && !self.side_enabled(side) { return false; }
         if stage == Stage::Entry && (size > self.data.remaining_margin || D128::ONE > self.data.remaining_count) { return false; }
         if size.is_nan() { panic!("size is nan, dump: {}\n{:?}\n{:?}", self.data, self.buy, self.sell); }
         else if size.is_zero() { panic!("size is zero, dump: {}\n{:?}\n{:?}", self.data, self.buy, self.sell); }
+        if !self.risk_check(expected_price, size, side, stage) { return false; }
         match side {
             Side::Buy => {
                 let r = self.buy.new_market(id, expected_price, size, stage, class, self.data.buy.remaining_margin, self.data.buy.remaining_count, sender);
@@ -162,11 +220,60 @@ impl Portfolio {
         }
     }
 
+    /// Runs the order past the pre-trade risk checks, entries count against their side's filled and working inventory
+    fn risk_check(&self, price: D128, size: D128, side: Side, stage: Stage) -> bool {
+        self.risk_check_replacing(price, size, D128::ZERO, side, stage)
+    }
+
+    /// Risk checks for an order taking the place of a resting one of size replaced, only the growth is added to the side
+    fn risk_check_replacing(&self, price: D128, size: D128, replaced: D128, side: Side, stage: Stage) -> bool {
+        let reduces = stage == Stage::Exit;
+        let added = if reduces || size <= replaced { D128::ZERO } else { size - replaced };
+        let data = match side { Side::Buy => &self.data.buy, Side::Sell => &self.data.sell };
+        RISK.check(&OrderCheck {
+            venue: Venue::Bybit,
+            symbol: &self.symbol,
+            size,
+            price,
+            reference: self.price,
+            notional: CONTRACT.notional(size, price),
+            reduces,
+            position: data.open_position.inv + data.open_liqs.total_reserved.inv + added,
+            net: self.data.exposure.worst_net_with(side == Side::Buy, added),
+        }).is_ok()
+    }
+
     pub fn order_rest_response(&mut self, id: Uuid, side: Side, stage: Stage, order: Option<IncomingOrderREST>) {
         match side {
             Side::Buy => self.buy.order_rest_response(id, stage, order),
             Side::Sell => self.sell.order_rest_response(id, stage, order),
         };
+        self.data_refresh();
+    }
+
+    /// Reprices a resting limit in place, false if it isn't ours or can't be moved right now
+    pub fn amend_order(&mut self, id: Uuid, price: D128, size: D128, side: Side, stage: Stage, sender: Sender<StrategyMessage>) -> bool {
+        if stage == Stage::Entry && self.halted { return false; }
+        let position = match side { Side::Buy => &self.buy, Side::Sell => &self.sell };
+        let resting = match match stage { Stage::Entry => &position.opens, Stage::Exit => &position.closes }.order_map.get(&id) {
+            Some(order) => order.size,
+            None => return false,
+        };
+        if !self.risk_check_replacing(price, size, resting, side, stage) { return false; }
+        let r = match side {
+            Side::Buy => self.buy.amend_order(id, stage, price, size, sender),
+            Side::Sell => self.sell.amend_order(id, stage, price, size, sender),
+        };
+        self.data_refresh();
+        r
+    }
+
+    pub fn amend_response(&mut self, id: Uuid, side: Side, stage: Stage, price: D128, size: D128, success: bool) {
+        match side {
+            Side::Buy => self.buy.rest_amend(stage, id, price, size, success),
+            Side::Sell => self.sell.rest_amend(stage, id, price, size, success),
+        }
+        self.data_refresh();
     }
 
     pub fn cancel_response(&mut self, id: Uuid, auto_id: Uuid, side: Side, stage: Stage, success: bool) {
@@ -174,6 +281,7 @@ impl Portfolio {
             Side::Buy => self.buy.rest_cancel(stage, id, auto_id, success),
             Side::Sell => self.sell.rest_cancel(stage, id, auto_id, success),
         }
+        self.data_refresh();
         // match stage {
         //     Stage::Entry => match side {
         //         Side::Buy => self.buy.rest_cancel(stage, id, auto_id, success),
@@ -186,25 +294,427 @@ impl Portfolio {
         // }
     }
 
+    /// Holds an order whose create came back with an unknown outcome and reconciles to find out whether it landed
+    pub fn order_unknown(&mut self, id: Uuid, side: Side, stage: Stage) {
+        match side {
+            Side::Buy => self.buy.order_unknown(id, stage),
+            Side::Sell => self.sell.order_unknown(id, stage),
+        }
+        self.data_refresh();
+        self.request_reconcile();
+    }
+
+    /// Resends an order that came back with a Retry or Resync outcome.
+    /// Falls back to failing it so its reservation is released once it runs out of retries.
+    pub fn retry_order(&mut self, id: Uuid, side: Side, stage: Stage, sender: Sender<StrategyMessage>) -> bool {
+        let r = match side {
+            Side::Buy => self.buy.retry_order(stage, id, sender),
+            Side::Sell => self.sell.retry_order(stage, id, sender),
+        };
+        self.data_refresh();
+        r
+    }
+
+    /// Resends a cancel that came back with a Retry or Resync outcome
+    pub fn retry_cancel(&mut self, id: Uuid, side: Side, stage: Stage, sender: Sender<StrategyMessage>) -> bool {
+        match side {
+            Side::Buy => self.buy.retry_cancel(stage, id, sender),
+            Side::Sell => self.sell.retry_cancel(stage, id, sender),
+        }
+    }
+
+    /// Stops all new entries and pulls the resting ones.
+    /// Exits are left working, dumping inventory because of a bad key helps nobody.
+    pub fn halt(&mut self, sender: Sender<StrategyMessage>) {
+        if !self.halted { info!("Halting {}, no new entries will be sent", self.symbol); }
+        self.halted = true;
+        self.buy.cancel_all(Stage::Entry, sender.clone());
+        self.sell.cancel_all(Stage::Entry, sender);
+        self.data_refresh();
+    }
+
+    pub fn margin_refresh(&mut self, margin: Option<MarginSnapshot>) {
+        match margin {
+            Some(margin) => {
+                info!("[MARGIN] {} at {}x {}, balance {}", self.symbol, margin.leverage, if margin.is_isolated { "isolated" } else { "cross" }, margin.balance);
+                self.margin = Some(margin);
+            },
+            None if self.margin.is_none() => {
+                info!("[MARGIN] Failed to read margin settings for {}, entries stay off until they're known, retrying in {}s", self.symbol, MARGIN_RETRY_PERIOD);
+                let symbol = self.symbol.clone();
+                let sender = self.strat_tx.clone();
+                self.pool.spawn(async move { retry_margin(symbol, sender).await; });
+            },
+            None => info!("[MARGIN] Failed to refresh margin settings for {}, keeping the last ones", self.symbol),
+        }
+        self.limits_refresh();
+    }
+
+    /// Keeps the balance max_size is worked out from in line with the wallet, the sizing picks it up on the portfolio timer
+    pub fn wallet_update(&mut self, balance: D128) {
+        if let Some(margin) = &mut self.margin {
+            margin.balance = balance;
+        }
+    }
+
+    /// Keeps the price current, max_size only follows it on the portfolio timer except for the first price
+    pub fn mark(&mut self, best_bid: D128, best_ask: D128) {
+        let price: D128 = (best_bid + best_ask) / 2;
+        if price.is_nan() || !price.is_positive() || price == self.price { return; }
+        let first = !self.price.is_positive();
+        self.price = price;
+        if first { self.limits_refresh(); }
+        self.check_breaker();
+        self.watch_liquidation();
+    }
+
+    /// Warns as either side nears liquidation and closes part of it while it's inside the de-risk threshold
+    fn watch_liquidation(&mut self) {
+        if !self.price.is_positive() { return; }
+        let limits = RISK.limits();
+        for side in [Side::Buy, Side::Sell] {
+            let (position, inv) = match side {
+                Side::Buy => (&mut self.buy, self.data.buy.open_position.inv),
+                Side::Sell => (&mut self.sell, self.data.sell.open_position.inv),
+            };
+            let level = if inv.is_positive() { position.liquidation.assess(self.price, &limits) } else { LiquidationLevel::Safe };
+            let previous = std::mem::replace(&mut position.liquidation.level, level);
+            if level != previous {
+                info!("[LIQUIDATION] {} {} {:?} -> {:?}, liq price: {}, mark: {}, distance: {:?}, margin ratio: {:?}",
+                    self.symbol, side, previous, level, position.liquidation.liq_price, self.price,
+                    position.liquidation.distance(self.price), position.liquidation.margin_ratio());
+            }
+            let due = self.last_derisk.map_or(true, |at| at.elapsed() > DERISK_COOLDOWN);
+            if level == LiquidationLevel::Reduce && due {
+                self.derisk(side, limits.liq_reduce_fraction(), self.strat_tx.clone());
+            }
+        }
+    }
+
+    /// Stops adding to a side and closes fraction of what it holds at market
+    fn derisk(&mut self, side: Side, fraction: D128, sender: Sender<StrategyMessage>) {
+        self.last_derisk = Some(Instant::now());
+        let inv = match side {
+            Side::Buy => self.data.buy.open_position.inv,
+            Side::Sell => self.data.sell.open_position.inv,
+        };
+        if !inv.is_positive() { return; }
+        match side {
+            Side::Buy => self.buy.cancel_all(Stage::Entry, sender.clone()),
+            Side::Sell => self.sell.cancel_all(Stage::Entry, sender.clone()),
+        }
+        let mut size = inv * fraction;
+        // Too small to trade closes the lot
+        if size > inv || CONTRACT.qty(size) == 0.0 { size = inv; }
+        info!("[LIQUIDATION] De-risking {} {}, closing {} of {}", self.symbol, side, size, inv);
+        self.new_market(None, self.price, size, side, Stage::Exit, OrderClassification::Exit, sender);
+    }
+
+    /// Trims the heavier side with reduce-only exits while filled net delta is past the hedge threshold.
+    /// Exits already working on that side count toward the trim so repeated fills don't stack hedges.
+    fn hedge(&mut self) {
+        let threshold = match RISK.limits().hedge_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let excess = self.data.exposure.hedge_excess(threshold);
+        if excess.is_zero() || !self.price.is_positive() { return; }
+        let side = if excess.is_positive() { Side::Buy } else { Side::Sell };
+        let working = match side { Side::Buy => self.data.buy.close_liqs.total_reserved.inv, Side::Sell => self.data.sell.close_liqs.total_reserved.inv };
+        let size = excess.abs() - working;
+        if !size.is_positive() || CONTRACT.qty(size) == 0.0 { return; }
+        info!("[HEDGE] {} net {} past {}, trimming {} {}", self.symbol, self.data.exposure.net, threshold, side, size);
+        self.new_market(None, self.price, size, side, Stage::Exit, OrderClassification::Exit, self.strat_tx.clone());
+    }
+
+    /// Realized plus unrealized PnL net of fees since startup, in the settle coin at the mid
+    pub fn pnl(&self) -> D128 {
+        (self.buy.pnl(self.price) + self.sell.pnl(self.price)).net()
+    }
+
+    /// What the metrics endpoint shows for this symbol
+    pub fn metrics(&self) -> PortfolioMetrics {
+        let mut metrics = PortfolioMetrics {
+            buy_inventory: self.data.buy.open_position.inv.to_float(),
+            sell_inventory: self.data.sell.open_position.inv.to_float(),
+            pnl: self.pnl().to_float(),
+            remaining_margin: self.data.remaining_margin.to_float(),
+            remaining_count: self.data.remaining_count.to_float(),
+            ..PortfolioMetrics::default()
+        };
+        for (side, position) in [("buy", &self.buy), ("sell", &self.sell)] {
+            for (stage, list) in [("entry", &position.opens), ("exit", &position.closes)] {
+                for order in list.order_map.values() {
+                    *metrics.orders.entry((side, stage, format!("{:?}", order.progress).to_lowercase())).or_insert(0) += 1;
+                }
+            }
+        }
+        metrics
+    }
+
+    /// Reports PnL to the kill switch, standing down once it covers this symbol and standing back up after a reset
+    fn check_breaker(&mut self) {
+        if !self.price.is_positive() { return; }
+        PNL.publish(Venue::Bybit, &self.symbol, self.buy.pnl(self.price), self.sell.pnl(self.price));
+        BREAKER.update(Venue::Bybit, &self.symbol, self.pnl());
+        match (self.tripped, BREAKER.is_tripped(Venue::Bybit, &self.symbol)) {
+            (false, true) => {
+                self.tripped = true;
+                self.stand_down(self.strat_tx.clone());
+            },
+            (true, false) => {
+                self.tripped = false;
+                info!("[KILL SWITCH] {} cleared, entries back on", self.symbol);
+            },
+            _ => {},
+        }
+    }
+
+    /// Pulls every order and, if the limits say so, closes out what's held at market
+    fn stand_down(&mut self, sender: Sender<StrategyMessage>) {
+        info!("[KILL SWITCH] {} tripped, cancelling all orders", self.symbol);
+        self.buy.cancel_all(Stage::Entry, sender.clone());
+        self.sell.cancel_all(Stage::Entry, sender.clone());
+        self.buy.cancel_all(Stage::Exit, sender.clone());
+        self.sell.cancel_all(Stage::Exit, sender.clone());
+        self.data_refresh();
+        if !RISK.limits().flatten_on_trip() { return; }
+        for side in [Side::Buy, Side::Sell] {
+            let inv = match side { Side::Buy => self.data.buy.open_position.inv, Side::Sell => self.data.sell.open_position.inv };
+            if inv.is_positive() {
+                info!("[KILL SWITCH] Flattening {} {} {}", self.symbol, side, inv);
+                self.new_market(None, self.price, inv, side, Stage::Exit, OrderClassification::Exit, sender.clone());
+            }
+        }
+    }
+
+    /// Max size is whatever the margin allows at the current price, split evenly between the sides
+    pub fn limits_refresh(&mut self) {
+        let notional = match &self.margin {
+            Some(margin) => margin.max_notional(),
+            None => D128::ZERO,
+        };
+        self.max_size = if self.price.is_positive() { CONTRACT.contracts(notional, self.price).round_down(-3) } else { D128::ZERO };
+        self.buy.pos_max_size = self.max_size / 2;
+        self.sell.pos_max_size = self.max_size / 2;
+        self.data_refresh();
+    }
+
+    /// Kicks off an out of cycle reconcile, returns false if one is already running or ran too recently
+    pub fn request_reconcile(&mut self) -> bool {
+        if self.reconcile_in_flight || self.last_reconcile.elapsed().as_millis() < RECONCILE_COOLDOWN {
+            return false;
+        }
+        self.reconcile_in_flight = true;
+        let symbol = self.symbol.clone();
+        let sender = self.strat_tx.clone();
+        self.pool.spawn(async move { send_reconcile(symbol, sender).await; });
+        true
+    }
+
+    /// Brings both positions in line with the exchange's snapshot
+    pub fn reconcile(&mut self, snapshot: Option<ReconcileSnapshot>) {
+        self.reconcile_in_flight = false;
+        self.last_reconcile = Instant::now();
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                info!("[RECONCILE] Failed to pull exchange state, waiting for the next cycle");
+                return;
+            },
+        };
+        // Exits reduce the opposite side's position
+        let (sells, buys): (Vec<_>, Vec<_>) = snapshot.orders.into_iter()
+            .partition(|order| matches!((order.side, order.reduce_only), (Side::Sell, false) | (Side::Buy, true)));
+        let held = |side: Side| snapshot.positions.iter().find(|pos| pos.side == side && pos.size.is_positive());
+        // One-way positions only report the held side, the other one is flat
+        let flat = |side: Side| snapshot.positions.first().map(|pos| {
+            let mut flat = pos.clone();
+            flat.side = side;
+            flat.size = D128::ZERO;
+            flat.position_value = D128::ZERO;
+            flat
+        });
+        let buy_position = held(Side::Buy).cloned().or_else(|| flat(Side::Buy));
+        let sell_position = held(Side::Sell).cloned().or_else(|| flat(Side::Sell));
+        let corrections = self.buy.reconcile(&buys, buy_position.as_ref(), self.strat_tx.clone())
+            + self.sell.reconcile(&sells, sell_position.as_ref(), self.strat_tx.clone());
+        self.data_refresh();
+        self.protect(self.strat_tx.clone());
+        self.watch_liquidation();
+        if corrections > 0 {
+            info!("[RECONCILE] Made {} corrections\n{}", corrections, self.data);
+        }
+    }
+
     pub fn position_update(&mut self, position: IncomingPosition) {
+        if CONTRACT.eq(&ContractType::Inverse) {
+            // Inverse positions are one-way, the side not holding the position is flat
+            let mut flat = position.clone();
+            flat.side = !position.side;
+            flat.size = D128::ZERO;
+            match flat.side {
+                Side::Buy => self.buy.position_update(flat),
+                Side::Sell => self.sell.position_update(flat),
+            }
+        }
         match position.side {
             Side::Buy => self.buy.position_update(position),
             Side::Sell => self.sell.position_update(position),
         }
+        self.watch_liquidation();
     }
 
     pub fn order_update(&mut self, order: IncomingOrderWS, sender: Sender<StrategyMessage>) {
         // info!("orderws {:?}", order);
-        match order.stage {
+        if matches!(order.order_status, OrderStatus::Filled | OrderStatus::PartiallyFilled) {
+            RISK.on_fill(Venue::Bybit, &self.symbol);
+        }
+        // Inverse positions are one-way, an entry filling while the other side holds inventory closes that first
+        let netting = match (*CONTRACT, order.stage) {
+            (ContractType::Inverse, Stage::Entry) => {
+                let (size, _, _) = match order.side { Side::Buy => self.buy.new_fill(&order), Side::Sell => self.sell.new_fill(&order) };
+                Some((order.side, order.last_fill_price, size))
+            },
+            _ => None,
+        };
+        let desync = match order.stage {
             Stage::Entry => match order.side {
-                Side::Buy => self.buy.order_update(order, sender),
-                Side::Sell => self.sell.order_update(order, sender),
+                Side::Buy => self.buy.order_update(order, sender.clone()),
+                Side::Sell => self.sell.order_update(order, sender.clone()),
             },
             Stage::Exit => match order.side {
-                Side::Buy => { self.sell.order_update(order, sender); },
-                Side::Sell => { self.buy.order_update(order, sender); },
+                Side::Buy => self.sell.order_update(order, sender.clone()),
+                Side::Sell => self.buy.order_update(order, sender.clone()),
             },
         };
+        if let Some((side, price, size)) = netting {
+            if size.is_positive() { self.net(side, price, size); }
+        }
+        if desync && self.request_reconcile() {
+            info!("[RECONCILE] Order update didn't match a known order, reconciling");
+        }
+        self.protect(sender);
+        self.check_breaker();
+        self.hedge();
+    }
+
+    /// The overlap is booked as a close on both sides, leaving each with what the exchange actually holds
+    fn net(&mut self, side: Side, price: D128, size: D128) {
+        let (entered, opposite) = match side {
+            Side::Buy => (&mut self.buy, &mut self.sell),
+            Side::Sell => (&mut self.sell, &mut self.buy),
+        };
+        let held = opposite.data_refresh().open_position.inv;
+        let overlap = (if held < size { held } else { size }).round_down(0);
+        if !overlap.is_positive() { return; }
+        info!("{} entry of {} at {} nets out {} of the {} inventory", side, size, price, overlap, !side);
+        opposite.net_out(price, overlap);
+        entered.net_out(price, overlap);
+        self.data_refresh();
+    }
+
+    pub fn side_enabled(&self, side: Side) -> bool {
+        match side { Side::Buy => self.buy_enabled, Side::Sell => self.sell_enabled }
+    }
+
+    /// Takes on a runtime parameter change, entries resting on a side that was just disabled are pulled
+    pub fn apply_params(&mut self, params: &Params) {
+        self.init_size = params.init_size;
+        self.max_open_orders = params.max_open_orders;
+        self.rebase_distance_limit = params.rebase_distance;
+        self.buy.pos_max_orders = params.max_open_orders / 2;
+        self.sell.pos_max_orders = params.max_open_orders / 2;
+        if self.buy_enabled && !params.buy_enabled {
+            self.buy.cancel_all(Stage::Entry, self.strat_tx.clone());
+        }
+        if self.sell_enabled && !params.sell_enabled {
+            self.sell.cancel_all(Stage::Entry, self.strat_tx.clone());
+        }
+        self.buy_enabled = params.buy_enabled;
+        self.sell_enabled = params.sell_enabled;
+        self.data_refresh();
+    }
+
+    pub fn set_protection(&mut self, settings: ProtectionSettings) {
+        self.buy.protection = settings;
+        self.sell.protection = settings;
+        self.protect(self.strat_tx.clone());
+    }
+
+    /// Brings both sides' stop loss and take profit in line with their inventory
+    pub fn protect(&mut self, sender: Sender<StrategyMessage>) {
+        self.buy.protect(sender.clone());
+        self.sell.protect(sender);
+        self.data_refresh();
+    }
+
+    /// Longs exit into the bid and shorts into the ask, so that's what their trailing stops follow
+    pub fn trail(&mut self, best_bid: D128, best_ask: D128, sender: Sender<StrategyMessage>) {
+        self.buy.trail(best_bid, sender.clone());
+        self.sell.trail(best_ask, sender);
+        self.data_refresh();
+    }
+
+    pub fn new_conditional(&mut self, kind: ConditionalKind, trigger: D128, size: D128, side: Side, sender: Sender<StrategyMessage>) -> bool {
+        if size.is_nan() || size.is_zero() { return false; }
+        match side {
+            Side::Buy => self.buy.new_conditional(kind, trigger, size, sender),
+            Side::Sell => self.sell.new_conditional(kind, trigger, size, sender),
+        }
+    }
+
+    pub fn stop_order_update(&mut self, stop_order: &BybitStopOrderData) {
+        if !self.buy.stop_order_update(stop_order) && !self.sell.stop_order_update(stop_order) {
+            debug!("Stop order {} ({}) isn't one of ours", stop_order.stop_order_id, stop_order.order_link_id);
+        }
+    }
+
+    pub fn conditional_response(&mut self, id: Uuid, side: Side, success: bool) {
+        match side {
+            Side::Buy => self.buy.conditional_response(id, success),
+            Side::Sell => self.sell.conditional_response(id, success),
+        };
+    }
+
+    pub fn conditional_cancel_response(&mut self, id: Uuid, side: Side, success: bool) {
+        match side {
+            Side::Buy => self.buy.conditional_cancel_response(id, success),
+            Side::Sell => self.sell.conditional_cancel_response(id, success),
+        };
+        // The replacement goes out once the old one is confirmed gone
+        if success { self.protect(self.strat_tx.clone()); }
+    }
+
+    /// Cancels one order, false if it isn't there or can't take a cancel right now
+    pub fn cancel_order(&mut self, id: Uuid, side: Side, stage: Stage, sender: Sender<StrategyMessage>) -> bool {
+        let position = match side { Side::Buy => &mut self.buy, Side::Sell => &mut self.sell };
+        let pool = position.pool.clone();
+        let symbol = position.symbol.clone();
+        let r = match match stage { Stage::Entry => &mut position.opens, Stage::Exit => &mut position.closes }.order_map.get_mut(&id) {
+            Some(order) if order.can_cancel() => Position::cancel_order(pool, order, side, stage, symbol, sender).is_ok(),
+            _ => false,
+        };
+        self.data_refresh();
+        r
+    }
+
+    /// Whether an order is still on its way to or resting on the book
+    pub fn order_working(&self, id: Uuid, side: Side, stage: Stage) -> bool {
+        let position = match side { Side::Buy => &self.buy, Side::Sell => &self.sell };
+        match match stage { Stage::Entry => &position.opens, Stage::Exit => &position.closes }.order_map.get(&id) {
+            Some(order) => order.progress.incomplete_unfailed(),
+            None => false,
+        }
+    }
+
+    /// Whether an order can take a cancel right now, false while one's already in flight
+    pub fn order_cancellable(&self, id: Uuid, side: Side, stage: Stage) -> bool {
+        let position = match side { Side::Buy => &self.buy, Side::Sell => &self.sell };
+        match match stage { Stage::Entry => &position.opens, Stage::Exit => &position.closes }.order_map.get(&id) {
+            Some(order) => order.can_cancel(),
+            None => false,
+        }
     }
 
     pub fn get_top(&self, side: Side, stage: Stage) -> Option<&Order> {
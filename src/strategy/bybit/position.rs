@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::fmt::{Display, Formatter};
 
 use crossbeam_channel::{SendError, Sender};
@@ -7,13 +8,20 @@ use tokio::runtime::Handle;
 use uuid::Uuid;
 use thiserror::Error;
 
-use crate::backend::bybit::broker::{OrderStatus, CreateOrderError, CancelOrderError, OrderResult, OrderType, Side};
+use crate::backend::bybit::broker::{OrderStatus, CreateOrderError, CancelOrderError, OrderResult, OrderType, Side, QueryAllActiveOrdersResult, PositionListResult};
 use crate::backend::bybit::stream::BybitPositionData;
 use crate::backend::bybit::broker::BROKER;
+use crate::backend::bybit::CONTRACT;
 use crate::strategy::types::{Stage, OrderClassification};
+use crate::strategy::protection::{ConditionalKind, ProtectionSettings, TrailingStop};
+use crate::backend::bybit::stream::BybitStopOrderData;
+use crate::risk::{RISK, OrderCheck, LiquidationState};
+use crate::strategy::fees::FEES;
+use crate::strategy::ledger::{Ledger, LedgerReport};
+use crate::telemetry::Venue;
 
 use super::order_list::{OrderList, OrderListError, AllLiqs, OrderData};
-use super::{StrategyMessage, Order, IncomingOrderREST, IncomingOrderWS, AccountMessage, OrderMessage, OrderResponse, CancelResponse, message, REBATE, OrderProgress};
+use super::{StrategyMessage, Order, IncomingOrderREST, IncomingOrderWS, AccountMessage, OrderMessage, OrderResponse, CancelResponse, AmendResponse, message, OrderProgress, ConditionalOrder, ConditionalResponse};
 
 
 #[derive(Clone, Copy, PartialEq)]
@@ -53,7 +61,7 @@ impl FinData {
     }
 
     pub fn process(&mut self) {
-        self.cb = self.liq / self.inv;
+        self.cb = CONTRACT.cost_basis(self.inv, self.liq);
     }
 
     pub fn update(&mut self, inv: D128, liq: D128, rebate: D128) {
@@ -136,10 +144,17 @@ pub enum CancelOrderPositionError {
 
 impl From<&BybitPositionData> for IncomingPosition {
     fn from(position: &BybitPositionData) -> Self {
+        IncomingPosition::with_side(position, Side::try_from(position.side.clone()).expect("incoming position"))
+    }
+}
+
+impl IncomingPosition {
+    /// One-way positions, which is all inverse has, report their side as None once flat
+    pub fn with_side(position: &BybitPositionData, side: Side) -> Self {
         IncomingPosition {
             symbol: position.symbol.clone(),
             size: D128::from(position.size),
-            side: Side::try_from(position.side.clone()).expect("incoming position"),
+            side,
             liq: D128::from(position.position_value),
             price: D128::from(position.entry_price),
             leverage_multiplier: D128::from(position.leverage),
@@ -147,6 +162,10 @@ impl From<&BybitPositionData> for IncomingPosition {
             position_margin_available: D128::from(position.position_margin),
             realised_pnl: D128::from(position.realised_pnl),
             cum_realised_pnl: D128::from(position.cum_realized_pnl),
+            take_profit: D128::from(position.take_profit),
+            stop_loss: D128::from(position.stop_loss),
+            liq_price: D128::from(if position.liq_price > 0.0 { position.liq_price } else { position.bust_price }),
+            maint_margin: D128::from(position.maint_margin),
         }
     }
 }
@@ -163,6 +182,13 @@ pub struct IncomingPosition {
     pub position_margin_available: D128,
     pub realised_pnl: D128,
     pub cum_realised_pnl: D128,
+    /// Position level trading stops, zero when unset
+    pub take_profit: D128,
+    pub stop_loss: D128,
+    /// Liquidation price, the bust price where the stream doesn't send one
+    pub liq_price: D128,
+    /// Zero where the stream doesn't send it
+    pub maint_margin: D128,
 }
 
 #[derive(Debug)]
@@ -178,7 +204,18 @@ pub struct Position {
     pub known_liq: D128,
     pub known_available_liq: D128,
     pub known_prebate_pnl: D128,
+    pub known_take_profit: D128,
+    pub known_stop_loss: D128,
     pub sequence: D128,
+    /// Average cost inventory and PnL, in the settle coin
+    pub ledger: Ledger,
+    pub liquidation: LiquidationState,
+    /// Resting stop orders, keyed by order_link_id
+    pub protective: HashMap<Uuid, ConditionalOrder>,
+    pub protection: ProtectionSettings,
+    pub trailing: Option<TrailingStop>,
+    /// Inventory drift seen on the last reconcile, only corrected once it's seen twice in a row
+    pub reconcile_drift: D128,
     pub pool: Handle
 }
 
@@ -190,6 +227,8 @@ impl Position {
             opens: OrderList::new(),
             closes: OrderList::new(),
             sequence: D128::ZERO,
+            ledger: Ledger::new(CONTRACT.rising(open_side)),
+            liquidation: LiquidationState::new(),
             pos_max_size: max_size,
             pos_max_orders: max_count,
             known_prebate_pnl: D128::ZERO,
@@ -197,6 +236,12 @@ impl Position {
             known_price: D128::ZERO,
             known_liq: D128::ZERO,
             known_available_liq: D128::ZERO,
+            known_take_profit: D128::ZERO,
+            known_stop_loss: D128::ZERO,
+            protective: HashMap::new(),
+            protection: ProtectionSettings::none(),
+            trailing: None,
+            reconcile_drift: D128::ZERO,
             pool,
         }
     }
@@ -291,29 +336,220 @@ impl Position {
         }
     }
 
+    /// Returns true if the update didn't match a known order
     pub fn order_update(
         &mut self,
         order: IncomingOrderWS,
         sender: Sender<StrategyMessage>
-    ) {
+    ) -> bool {
+        // A triggered stop comes through as a fresh close under the conditional's id
+        if let Some(cond) = self.protective.get_mut(&order.id) {
+            cond.progress = OrderProgress::Filled;
+            if !self.closes.order_map.contains_key(&order.id) {
+                info!("{} {:?} triggered, closing {}", self.side, cond.kind, order.size);
+                let class = if cond.manual { OrderClassification::Exit } else { OrderClassification::Protect };
+                let _ = self.closes.add_order(Order::new_taker(Some(order.id), cond.trigger, order.size, class, FEES.rates(Venue::Bybit, &self.symbol)));
+            }
+        }
+        self.book_fill(&order);
         match order.stage {
             Stage::Entry => self.opens.ws_order(order.id, order),
             Stage::Exit => {
-                
-                self.closes.ws_order(order.id, order);
-                let pd = self.data_refresh();
-                if pd.open_position.inv <= D128::ZERO {
-                    let prebate = pd.open_liqs.filled.liq - pd.close_liqs.filled.liq;
-                    let rebate = pd.open_liqs.filled.rebate + pd.close_liqs.filled.rebate;
-                    let pnl = prebate - rebate;
-                    info!("{}side CLOSED OUT: prebate pnl: {}, fee/rebates: {}, pnl: {}\n{}\n\n",
-                    self.side, pd, prebate, rebate, pnl);
+                let desync = self.closes.ws_order(order.id, order);
+                self.check_closed_out();
+                desync
+            },
+        }
+    }
+
+    /// Books inventory netted away on a one-way inverse position as a close at the fill price
+    pub fn net_out(&mut self, price: D128, size: D128) {
+        self.closes.add_order(Order::new_adjustment(price, size)).ok();
+        self.ledger.exit(size, CONTRACT.value(size, price), D128::ZERO);
+        self.check_closed_out();
+    }
+
+    fn check_closed_out(&mut self) {
+        let pd = self.data_refresh();
+        if pd.open_position.inv <= D128::ZERO {
+            let prebate = CONTRACT.pnl(self.side, pd.open_liqs.filled.liq, pd.close_liqs.filled.liq);
+            let rebate = pd.open_liqs.filled.rebate + pd.close_liqs.filled.rebate;
+            let pnl = prebate - rebate;
+            info!("{}side CLOSED OUT: prebate pnl: {}, fee/rebates: {}, pnl: {}\n{}\n\n",
+            self.side, pd, prebate, rebate, pnl);
+            self.opens.clean();
+            self.closes.clean();
+            self.protective.retain(|_, cond| cond.live());
+            self.trailing = None;
+            info!("post clean: {}", self.data_refresh());
+        }
+    }
+
+    /// Diffs the exchange's active orders and position for this side against our own, correcting whatever drifted.
+    /// Returns the number of corrections made.
+    pub fn reconcile(&mut self, orders: &[QueryAllActiveOrdersResult], position: Option<&PositionListResult>, sender: Sender<StrategyMessage>) -> usize {
+        let mut corrections = 0;
+        let mut seen: Vec<Uuid> = vec![];
+
+        for open in orders.iter() {
+            let link_id = Uuid::from_str(&open.order_link_id).ok();
+            // Conditionals are tracked apart from opens and closes
+            if link_id.map_or(false, |id| self.protective.contains_key(&id)) { continue; }
+            let auto_id = Uuid::from_str(&open.order_id).unwrap_or_else(|_| Uuid::nil());
+            let stage = Stage::from(open.reduce_only);
+            // Orders without a UUID link id weren't placed by us, we'll track them but they aren't ours to cancel
+            let foreign = link_id.is_none();
+            let (size, price, filled) = (D128::from(open.qty), D128::from(open.price), D128::from(open.cum_exec_qty));
+            let (side, symbol, pool) = (self.side, self.symbol.clone(), self.pool.clone());
+            let list = stage.aggress_mut(&mut self.opens, &mut self.closes);
+            match list.find_id(link_id, auto_id) {
+                Some(id) => {
+                    seen.push(id);
+                    let order = list.order_map.get_mut(&id).unwrap();
+                    order.reconcile_misses = 0;
+                    if order.progress == OrderProgress::Untracked {
+                        if !foreign && !order.cancel_in_flight {
+                            info!("[RECONCILE] {} {:?} stray {} is still open, cancelling again", side, stage, id);
+                            let _ = Position::cancel_order(pool, order, side, stage, symbol, sender.clone());
+                            corrections += 1;
+                        }
+                    } else if !order.progress.incomplete_unfailed() {
+                        info!("[RECONCILE] {} {:?} order {} is {:?} here but open on the exchange, cancelling", side, stage, id, order.progress);
+                        order.progress = OrderProgress::Untracked;
+                        if !order.cancel_in_flight {
+                            let _ = Position::cancel_order(pool, order, side, stage, symbol, sender.clone());
+                        }
+                        corrections += 1;
+                    } else if filled > order.filled_size {
+                        info!("[RECONCILE] {} {:?} order {} missed fills, filled {} here vs {} on the exchange", side, stage, id, order.filled_size, filled);
+                        order.in_flight = false;
+                        order.progress = OrderProgress::PartiallyFilled;
+                        order.filled_size = filled;
+                        order.filled_liq = D128::from(open.cum_exec_value);
+                        order.unfilled_size = size - filled;
+                        order.unfilled_liq = CONTRACT.value(order.unfilled_size, price);
+                        corrections += 1;
+                    }
+                },
+                None => {
+                    let id = link_id.unwrap_or(auto_id);
+                    let mut orphan = Order::new_orphan(Some(id), Some(price), size, FEES.rates(Venue::Bybit, &symbol));
+                    orphan.auto_id = auto_id;
+                    orphan.auto_gen = true;
+                    orphan.filled_size = filled;
+                    orphan.filled_liq = D128::from(open.cum_exec_value);
+                    orphan.unfilled_size = size - filled;
+                    orphan.unfilled_liq = CONTRACT.value(orphan.unfilled_size, price);
+                    seen.push(id);
+                    if let Ok(order) = list.add_order(orphan) {
+                        if foreign {
+                            info!("[RECONCILE] Adopted foreign {} {:?} order {} for {} at {}, leaving it be", side, stage, open.order_id, size, price);
+                        } else {
+                            info!("[RECONCILE] Adopted stray {} {:?} order {} for {} at {}, cancelling", side, stage, id, size, price);
+                            let _ = Position::cancel_order(pool, order, side, stage, symbol, sender.clone());
+                        }
+                    }
+                    corrections += 1;
+                },
+            }
+        }
+
+        // Anything we think is working that the exchange doesn't know about missed its final update.
+        // Takes two misses since an order can rest between the snapshot being taken and it being read.
+        for stage in [Stage::Entry, Stage::Exit] {
+            let side = self.side;
+            for (id, order) in stage.aggress_mut(&mut self.opens, &mut self.closes).order_map.iter_mut()
+            .filter(|(id, ord)|
+            (ord.progress == OrderProgress::Resting || ord.progress == OrderProgress::PartiallyFilled) &&
+            !ord.in_flight && !seen.contains(*id)) {
+                order.reconcile_misses += 1;
+                if order.reconcile_misses > 1 {
+                    info!("[RECONCILE] {} {:?} order {} is {:?} here but gone from the exchange, marking cancelled", side, stage, id, order.progress);
+                    order.cancel_in_flight = false;
+                    order.progress = OrderProgress::Cancelled;
+                    corrections += 1;
+                }
+            }
+        }
+
+        if let Some(position) = position {
+            self.known_size = position.size;
+            self.known_price = position.entry_price;
+            self.known_liq = position.position_value;
+            self.liquidation.liq_price = if position.size.is_zero() { D128::ZERO } else { position.liq_price };
+
+            let drift = (self.known_size - self.data_refresh().open_position.inv).round_down(-3);
+            if drift.is_zero() {
+                self.reconcile_drift = D128::ZERO;
+            } else if drift != self.reconcile_drift {
+                // Fills can land between the snapshot and now, so wait to see the same drift twice
+                info!("[RECONCILE] {} inventory drift of {} awaiting confirmation", self.side, drift);
+                self.reconcile_drift = drift;
+            } else {
+                info!("[RECONCILE] {} inventory is {} here vs {} on the exchange, booking the difference", self.side, self.known_size - drift, self.known_size);
+                self.reconcile_drift = D128::ZERO;
+                if drift.is_positive() {
+                    self.opens.add_order(Order::new_adjustment(position.entry_price, drift)).ok();
+                    self.ledger.entry(drift, CONTRACT.value(drift, position.entry_price), D128::ZERO);
+                } else {
+                    self.closes.add_order(Order::new_adjustment(position.entry_price, drift.abs())).ok();
+                    self.ledger.exit(drift.abs(), CONTRACT.value(drift.abs(), position.entry_price), D128::ZERO);
+                }
+                if self.known_size.is_zero() {
                     self.opens.clean();
                     self.closes.clean();
-                    info!("post clean: {}", self.data_refresh());
                 }
-            },
+                corrections += 1;
+            }
+        }
+        corrections
+    }
+
+    /// Ledger snapshot with the open inventory marked at price
+    pub fn pnl(&self, price: D128) -> LedgerReport {
+        self.ledger.report(CONTRACT.value(self.ledger.inv, price))
+    }
+
+    /// Books whatever the update filled since the last one, the stream only sends cumulative fills
+    /// Size, value and fee an update filled since the last one we saw for the order
+    pub fn new_fill(&self, order: &IncomingOrderWS) -> (D128, D128, D128) {
+        let list = match order.stage { Stage::Entry => &self.opens, Stage::Exit => &self.closes };
+        let (size, liq, fee) = match list.order_map.get(&order.id) {
+            Some(known) => (known.filled_size, known.filled_liq, known.cum_fee),
+            None => (D128::ZERO, D128::ZERO, D128::ZERO),
+        };
+        (order.cum_fill_size - size, order.cum_fill_liq - liq, order.cum_fill_fee - fee)
+    }
+
+    fn book_fill(&mut self, order: &IncomingOrderWS) {
+        let (qty, value, fee) = self.new_fill(order);
+        if !qty.is_positive() { return; }
+        match order.stage {
+            Stage::Entry => self.ledger.entry(qty, value, fee),
+            Stage::Exit => { self.ledger.exit(qty, value, fee); },
+        }
+    }
+
+    /// Moves a resting limit instead of cancelling and replacing it, false if the order can't be moved right now
+    pub fn amend_order(&mut self, id: Uuid, stage: Stage, price: D128, size: D128, sender: Sender<StrategyMessage>) -> bool {
+        let list = match stage {
+            Stage::Entry => &mut self.opens,
+            Stage::Exit => &mut self.closes,
         };
+        match list.order_map.get_mut(&id) {
+            Some(order) if order.can_amend() => {
+                Position::send_amend(self.pool.clone(), order, price, size, self.side, stage, self.symbol.clone(), sender);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn rest_amend(&mut self, stage: Stage, id: Uuid, price: D128, size: D128, success: bool) {
+        match stage {
+            Stage::Entry => self.opens.rest_amend(id, price, size, success),
+            Stage::Exit => self.closes.rest_amend(id, price, size, success),
+        }
     }
 
     pub fn rest_cancel(&mut self, stage: Stage, id: Uuid, auto_id: Uuid, success: bool) {
@@ -330,12 +566,229 @@ impl Position {
         }
     }
 
+    pub fn order_unknown(&mut self, id: Uuid, stage: Stage) {
+        match stage {
+            Stage::Entry => self.opens.unknown_order(id),
+            Stage::Exit => self.closes.unknown_order(id),
+        }
+    }
+
+    /// Resends a failed order under a new id, returns false once it's out of retries and was dropped instead
+    pub fn retry_order(&mut self, stage: Stage, id: Uuid, sender: Sender<StrategyMessage>) -> bool {
+        let order = match stage {
+            Stage::Entry => self.opens.retry_order(id, FEES.rates(Venue::Bybit, &self.symbol)),
+            Stage::Exit => self.closes.retry_order(id, FEES.rates(Venue::Bybit, &self.symbol)),
+        };
+        match order {
+            Some(order) => {
+                info!("Retrying {:?} {:?} order {} as {}", self.side, stage, id, order.id);
+                Position::send_order(self.pool.clone(), order, self.side, stage, self.symbol.clone(), sender).unwrap();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Resends a failed cancel, returns false if the order is gone or out of retries
+    pub fn retry_cancel(&mut self, stage: Stage, id: Uuid, sender: Sender<StrategyMessage>) -> bool {
+        let order = match stage {
+            Stage::Entry => self.opens.retry_cancel(id),
+            Stage::Exit => self.closes.retry_cancel(id),
+        };
+        match order {
+            Some(order) => {
+                Position::cancel_order(self.pool.clone(), order, self.side, stage, self.symbol.clone(), sender).unwrap();
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&mut self, stage: Stage, sender: Sender<StrategyMessage>) {
+        for (_, order)
+        in match stage { Stage::Entry => &mut self.opens, Stage::Exit => &mut self.closes }
+        .order_map.iter_mut()
+        .filter(|(_, ord)| ord.can_cancel()) {
+            Position::cancel_order(self.pool.clone(), order, self.side, stage, self.symbol.clone(), sender.clone()).unwrap();
+        }
+    }
+
     pub fn position_update(&mut self, position: IncomingPosition) {
         self.known_size = position.size;
         self.known_price = position.price;
         self.known_liq = position.liq;
         self.known_available_liq = position.position_margin_available;
         self.known_prebate_pnl = position.realised_pnl;
+        self.liquidation.liq_price = position.liq_price;
+        self.liquidation.maint_margin = position.maint_margin;
+        self.liquidation.margin_balance = position.position_margin_available;
+        if position.take_profit != self.known_take_profit || position.stop_loss != self.known_stop_loss {
+            // Protection runs on stop orders, anything here was set from outside and its close won't match a known order
+            if !position.take_profit.is_zero() || !position.stop_loss.is_zero() {
+                info!("{} has a position trading stop set outside the strategy, tp: {}, sl: {}", self.side, position.take_profit, position.stop_loss);
+            }
+        }
+        self.known_take_profit = position.take_profit;
+        self.known_stop_loss = position.stop_loss;
+    }
+
+    /// Keeps a stop loss and take profit resting for the whole open inventory, cancel-replacing them as it changes size
+    pub fn protect(&mut self, sender: Sender<StrategyMessage>) {
+        let position = self.data_refresh().open_position;
+        let size = position.inv.round_down(-3);
+        for kind in [ConditionalKind::StopLoss, ConditionalKind::TakeProfit] {
+            let trigger = if size.is_positive() { self.protection.trigger(kind, self.side.into(), position.cb) } else { None };
+            let live = self.protective.iter_mut().find(|(_, cond)| !cond.manual && cond.kind == kind && cond.live());
+            match (live, trigger) {
+                (Some((_, cond)), Some(_)) => {
+                    if cond.size != size && cond.can_cancel() {
+                        Position::cancel_conditional(self.pool.clone(), cond, self.side, self.symbol.clone(), sender.clone());
+                    }
+                },
+                (Some((_, cond)), None) => {
+                    if cond.can_cancel() {
+                        Position::cancel_conditional(self.pool.clone(), cond, self.side, self.symbol.clone(), sender.clone());
+                    }
+                },
+                (None, Some(trigger)) => {
+                    // Don't keep hammering a trigger the exchange already refused at this size
+                    if self.protective.values().any(|cond| !cond.manual && cond.kind == kind && cond.progress == OrderProgress::Failed && cond.size == size) {
+                        continue;
+                    }
+                    if !self.conditional_allowed(trigger, size) { continue; }
+                    let cond = ConditionalOrder::new(kind, trigger, size, false);
+                    info!("{} attaching {:?} for {} at {}", self.side, kind, size, trigger);
+                    Position::send_conditional(self.pool.clone(), &cond, self.side, position.cb, self.symbol.clone(), sender.clone());
+                    self.protective.insert(cond.id, cond);
+                },
+                (None, None) => {},
+            }
+        }
+    }
+
+    /// Conditionals only ever close, but they still go past the allowlist, sanity and rate checks
+    fn conditional_allowed(&self, trigger: D128, size: D128) -> bool {
+        RISK.check(&OrderCheck::reducing(Venue::Bybit, &self.symbol, size, trigger, CONTRACT.notional(size, trigger))).is_ok()
+    }
+
+    /// Places a conditional by hand, these are left alone by the automatic protection
+    pub fn new_conditional(&mut self, kind: ConditionalKind, trigger: D128, size: D128, sender: Sender<StrategyMessage>) -> bool {
+        if !self.conditional_allowed(trigger, size) { return false; }
+        let cond = ConditionalOrder::new(kind, trigger, size, true);
+        let base_price = if self.known_price.is_zero() { self.data_refresh().open_position.cb } else { self.known_price };
+        if base_price.is_nan() || base_price.is_zero() { return false; }
+        Position::send_conditional(self.pool.clone(), &cond, self.side, base_price, self.symbol.clone(), sender);
+        self.protective.insert(cond.id, cond);
+        true
+    }
+
+    /// Moves the local trailing stop along with the exit price, dumping inventory at market once it's hit
+    pub fn trail(&mut self, price: D128, sender: Sender<StrategyMessage>) {
+        let distance = match self.protection.trailing { Some(distance) => distance, None => return, };
+        let size = self.data_refresh().open_position.inv.round_down(-3);
+        if !size.is_positive() {
+            self.trailing = None;
+            return;
+        }
+        let side = self.side;
+        let trailing = self.trailing.get_or_insert(TrailingStop::new(distance, price));
+        if trailing.update(price, side.into()) {
+            info!("{} trailing stop hit at {}, best was {}, closing {} at market", side, price, trailing.extreme, size);
+            self.cancel_all(Stage::Exit, sender.clone());
+            let ord = Order::new_taker(None, price, size, OrderClassification::Protect, FEES.rates(Venue::Bybit, &self.symbol));
+            if let Ok(order) = self.closes.add_order(ord) {
+                Position::send_order(self.pool.clone(), order, self.side, Stage::Exit, self.symbol.clone(), sender).unwrap();
+            }
+        }
+    }
+
+    /// Returns false if the id isn't one of ours
+    pub fn stop_order_update(&mut self, stop_order: &BybitStopOrderData) -> bool {
+        let id = match Uuid::parse_str(&stop_order.order_link_id) {
+            Ok(id) => id,
+            Err(_) => return false,
+        };
+        match self.protective.get_mut(&id) {
+            Some(cond) => {
+                cond.stop_order_update(&stop_order.order_status);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns false if the id isn't one of ours
+    pub fn conditional_response(&mut self, id: Uuid, success: bool) -> bool {
+        match self.protective.get_mut(&id) {
+            Some(cond) => {
+                match success {
+                    true => if cond.progress == OrderProgress::Init { cond.progress = OrderProgress::Resting; },
+                    false => {
+                        info!("{} {:?} {} at {} was refused", self.side, cond.kind, id, cond.trigger);
+                        cond.progress = OrderProgress::Failed;
+                    },
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns false if the id isn't one of ours
+    pub fn conditional_cancel_response(&mut self, id: Uuid, success: bool) -> bool {
+        match self.protective.get_mut(&id) {
+            Some(cond) => {
+                cond.cancel_in_flight = false;
+                if success { cond.progress = OrderProgress::Cancelled; }
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn send_conditional(pool: Handle, cond: &ConditionalOrder, side: Side, base_price: D128, symbol: String, sender: Sender<StrategyMessage>) {
+        let size = CONTRACT.qty(cond.size);
+        let cond = *cond;
+        pool.spawn(async move {
+            // Conditionals always close, so they trade against the position
+            let result = BROKER.create_conditional(cond.id, symbol, cond.trigger, base_price, size, !side).await;
+            let rest_response = match result {
+                Ok(res) => Some(res),
+                Err(e) => {
+                    info!("{} conditional {} got no response: {}", side, cond.id, e);
+                    None
+                },
+            };
+            sender.send(StrategyMessage::AccountMessage(
+                AccountMessage::OrderMessage(OrderMessage::ConditionalResult(ConditionalResponse {
+                    id: cond.id,
+                    side,
+                    rest_response,
+                })),
+            )).unwrap();
+        });
+    }
+
+    pub fn cancel_conditional(pool: Handle, cond: &mut ConditionalOrder, side: Side, symbol: String, sender: Sender<StrategyMessage>) {
+        cond.cancel_in_flight = true;
+        let id = cond.id;
+        pool.spawn(async move {
+            let result = BROKER.cancel_conditional(symbol, id).await;
+            let rest_response = match result {
+                Ok(res) => Some(res),
+                Err(e) => {
+                    info!("{} conditional cancel {} got no response: {}", side, id, e);
+                    None
+                },
+            };
+            sender.send(StrategyMessage::AccountMessage(
+                AccountMessage::OrderMessage(OrderMessage::ConditionalCancelResult(ConditionalResponse {
+                    id,
+                    side,
+                    rest_response,
+                })),
+            )).unwrap();
+        });
     }
 
     pub fn new_limit(
@@ -352,7 +805,7 @@ impl Position {
             // debug!("posrej {} rem: {}, count: {}", self.side, rem_margin, rem_count);
             return false;
         }
-        let ord = Order::new_rebate(id, price, size, class);
+        let ord = Order::new_rebate(id, price, size, class, FEES.rates(Venue::Bybit, &self.symbol));
         match stage {
             Stage::Entry => {
                 match self.opens.add_order(ord) {
@@ -387,7 +840,7 @@ impl Position {
         sender: Sender<StrategyMessage>,
     ) -> bool {
         if stage == Stage::Entry && (size > rem_margin || D128::ONE >= rem_count) { return false; }
-        let ord = Order::new_taker(id, expected_price, size, class);
+        let ord = Order::new_taker(id, expected_price, size, class, FEES.rates(Venue::Bybit, &self.symbol));
         match stage {
             Stage::Entry => {
                 match self.opens.add_order(ord) {
@@ -410,9 +863,29 @@ impl Position {
         }
     }
 
+    pub fn send_amend(pool: Handle, order: &mut Order, price: D128, size: D128, side: Side, stage: Stage, symbol: String, sender: Sender<StrategyMessage>) {
+        let qty = CONTRACT.qty(size);
+        let id = order.id;
+        order.pre_amend();
+        pool.spawn(async move {
+            let amend_result = BROKER.amend_order(symbol, id, price, qty).await;
+            sender.send(StrategyMessage::AccountMessage(
+                AccountMessage::OrderMessage(OrderMessage::AmendResult(AmendResponse {
+                    id,
+                    side,
+                    stage,
+                    price,
+                    size: D128::from(qty),
+                    rest_response: amend_result.unwrap(),
+                })),
+            )).unwrap();
+        });
+    }
+
     pub fn cancel_order(pool: Handle, order: &mut Order, side: Side, stage: Stage, symbol: String,  sender: Sender<StrategyMessage>) -> Result<(), CancelOrderPositionError>{
         // I don't know why i do this
         order.pre_cancel();
+        RISK.on_cancel(Venue::Bybit, &symbol);
         let sender = sender.clone();
         // TODO Reduce this to not only the fields needed
         let order = order.clone();
@@ -435,7 +908,7 @@ impl Position {
     }
 
     pub fn send_order(pool: Handle, order: &mut Order, side: Side, stage: Stage, symbol: String, sender: Sender<StrategyMessage>) -> Result<(), SendOrderPositionError> {
-        let size = (order.size.to_float() * 1000.).round() / 1000.;
+        let size = CONTRACT.qty(order.size);
         // Fixing side/stage kabookie is more the Position's concern than the Broker's, probably
         // We'll keep it here for now anyway
         let broker_side = match stage {
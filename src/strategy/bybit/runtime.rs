@@ -0,0 +1,388 @@
+/// Hosts a strategy on the strategy thread.
+/// Takes every message off the strategy channel, handles REST responses and keeps the portfolio in sync with the
+/// account, then hands the event to the strategy picked by STRATEGY.
+
+use std::io::{Error, ErrorKind};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{Receiver, Sender};
+use dec::D128;
+
+use crate::backend::bybit::broker::{BROKER, OrderStatus, Side};
+use crate::backend::bybit::errors::{PerpetualStatus, StatusOutcome};
+use crate::config::CONFIG;
+use crate::strategy::params::{CONTROL, ParamUpdate};
+use crate::telemetry::{LATENCY, METRICS, PORTFOLIO_PERIOD, Venue, LatencyStage};
+
+use super::{AccountMessage, ModelMessage, OpMessage, OrderMessage, PositionMessage, WalletMessage, StrategyMessage, Portfolio, ExecutionReport, ParentProgress};
+use super::{CancelResponse, AmendResponse, ConditionalResponse, IncomingOrderREST, IncomingOrderWS, IncomingPosition, OrderResponse};
+use super::{CancelOrderResponseError, OrderResponseError, StrategyRuntimeError, UnauthorizedRequestError};
+use super::strategy::{self, Strategy, Context, DEFAULT_STRATEGY};
+
+pub struct Runtime {
+    pub strat_rx: Receiver<StrategyMessage>,
+    ctx: Context,
+    strategy: Box<dyn Strategy>,
+    /// Re-sizes the portfolio at the latest price and publishes it to the metrics, handled here rather than passed to the strategy
+    metrics_timer: u64,
+}
+
+impl Runtime {
+    pub fn new(
+        symbol: String,
+        strat_tx: Sender<StrategyMessage>,
+        strat_rx: Receiver<StrategyMessage>
+    ) -> tokio::io::Result<Runtime> {
+        let name = CONFIG.strategy.clone().unwrap_or_else(|| DEFAULT_STRATEGY.to_string());
+        let strategy = strategy::build(&name)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("No strategy named {}", name)))?;
+        info!("[INIT] Hosting strategy {}", strategy.name());
+        let mut ctx = Context::new(Portfolio::new(strat_tx.clone(), symbol)?, strat_tx.clone());
+        let control_tx = strat_tx.clone();
+        CONTROL.register(Venue::Bybit, &ctx.portfolio.symbol, ctx.params, Box::new(move |update| {
+            control_tx.send(StrategyMessage::OpMessage(OpMessage::Params(update))).is_ok()
+        }));
+        // Queued rather than called here so the strategy hears it on its own thread
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        let _ = strat_tx.send(StrategyMessage::OpMessage(OpMessage::Init(started)));
+        let metrics_timer = ctx.timers.every(PORTFOLIO_PERIOD);
+        Ok(Runtime {
+            strat_rx,
+            ctx,
+            strategy,
+            metrics_timer,
+        })
+    }
+
+    /// Starts the event loop that receives strategy signals, returns once the strategy channel closes
+    pub fn listen(&mut self) -> Result<(), StrategyRuntimeError> {
+        loop {
+            if self.strat_rx.len() > 1 {
+                debug!("Strategy fell behind. {} messages were waiting to be processed", self.strat_rx.len());
+            }
+            // Orders placed while handling anything but a tick shouldn't count toward tick-to-send
+            LATENCY.tick(Venue::Bybit, None);
+            let message = match self.strat_rx.recv() {
+                Ok(message) => message,
+                Err(_) => break,
+            };
+            METRICS.channel_depth(Venue::Bybit, self.strat_rx.len());
+            match message {
+                StrategyMessage::ModelMessage(mm) => match mm {
+                    ModelMessage::OrderBookMessage(obm) => {
+                        let book = &obm.orderbook_analysis;
+                        let timer = book.test_timer;
+                        LATENCY.tick(Venue::Bybit, Some(timer));
+                        self.ctx.portfolio.mark(book.best_bid.0, book.best_ask.0);
+                        self.ctx.portfolio.trail(book.best_bid.0, book.best_ask.0, self.ctx.strat_tx.clone());
+                        self.ctx.portfolio.data_refresh();
+                        self.ctx.executor.tops(book.best_bid.0, book.best_ask.0, &mut self.ctx.portfolio);
+                        self.strategy.on_book(&mut self.ctx, book);
+                        LATENCY.since(Venue::Bybit, LatencyStage::Decision, timer);
+                    }
+                    ModelMessage::TradeFlowMessage(tfm) => {
+                        self.ctx.executor.trades(tfm.trade);
+                        self.strategy.on_trade(&mut self.ctx, &tfm);
+                    }
+                },
+                StrategyMessage::AccountMessage(acc) => match acc {
+                    AccountMessage::OrderMessage(om) => match om {
+                        OrderMessage::OrderResult(or) => {
+                            if let Err(OrderResponseError::ContactSupportError(fatal_err)) = self.order_response(or) {
+                                self.strategy.on_shutdown(&mut self.ctx);
+                                return Err(StrategyRuntimeError::ContactSupportError(fatal_err))
+                            }
+                        }
+                        OrderMessage::OrderUpdate(ot) => {
+                            // info!("order update {:?}", ot);
+                            for data in ot.order_tick.data.iter() {
+                                self.order_update(IncomingOrderWS::from(data));
+                            }
+                        }
+                        OrderMessage::CancelResult(cr) => {
+                            if let Err(CancelOrderResponseError::ContactSupportError(fatal_err)) = self.cancel_order_response(cr) {
+                                self.strategy.on_shutdown(&mut self.ctx);
+                                return Err(StrategyRuntimeError::ContactSupportError(fatal_err))
+                            }
+                        }
+                        OrderMessage::AmendResult(ar) => self.amend_response(ar),
+                        OrderMessage::StopOrderUpdate(sot) => {
+                            for data in sot.data.iter() {
+                                self.ctx.portfolio.stop_order_update(data);
+                            }
+                        }
+                        OrderMessage::ConditionalResult(cr) => self.conditional_response(cr),
+                        OrderMessage::ConditionalCancelResult(cr) => self.conditional_cancel_response(cr),
+                        OrderMessage::ExecutionUpdate(_) => {},
+                    },
+                    AccountMessage::PositionMessage(pm) => match pm {
+                        PositionMessage::PositionUpdate(pt) => {
+                            for pos in pt.data {
+                                if pos.side == "None" {
+                                    self.position_update(IncomingPosition::with_side(&pos, Side::Buy));
+                                    self.position_update(IncomingPosition::with_side(&pos, Side::Sell));
+                                } else {
+                                    self.position_update(IncomingPosition::from(&pos));
+                                }
+                            }
+                        }
+                    },
+                    AccountMessage::WalletMessage(wm) => match wm {
+                        WalletMessage::WalletUpdate(wt) => {
+                            if let Some(wallet) = wt.data.last() {
+                                self.ctx.portfolio.wallet_update(D128::from(wallet.wallet_balance));
+                            }
+                        },
+                        WalletMessage::BalanceRefresh(_) => {},
+                    },
+                    AccountMessage::MarginRefresh(mr) => self.ctx.portfolio.margin_refresh(mr),
+                    AccountMessage::Reconcile(rs) => self.ctx.portfolio.reconcile(rs),
+                    AccountMessage::ExecutionReport(er) => self.execution_report(er),
+                },
+                StrategyMessage::OpMessage(op) => match op {
+                    OpMessage::Init(_) => self.strategy.on_init(&mut self.ctx),
+                    OpMessage::Timer(id) if id == self.metrics_timer => {
+                        // Sizing follows the price here rather than on every tick
+                        self.ctx.portfolio.limits_refresh();
+                        METRICS.portfolio(Venue::Bybit, &self.ctx.portfolio.symbol, self.ctx.portfolio.metrics());
+                    },
+                    OpMessage::Timer(id) => {
+                        if self.ctx.timers.fired(id) {
+                            self.strategy.on_timer(&mut self.ctx, id);
+                        }
+                    },
+                    OpMessage::Params(update) => self.params_update(update),
+                },
+            }
+        }
+        self.strategy.on_shutdown(&mut self.ctx);
+        Ok(())
+    }
+
+    fn order_update(&mut self, order: IncomingOrderWS) {
+        self.ctx.executor.order_update(&order);
+        self.ctx.portfolio.order_update(order, self.ctx.strat_tx.clone());
+        self.strategy.on_order_update(&mut self.ctx, &order);
+        if let OrderStatus::Filled | OrderStatus::PartiallyFilled = order.order_status {
+            self.strategy.on_fill(&mut self.ctx, &order);
+        }
+    }
+
+    fn execution_report(&mut self, er: ExecutionReport) {
+        if er.progress != ParentProgress::Working {
+            info!("[EXEC] {:?} parent {} {:?}: {}/{} filled at {}, {} bps slippage", er.algo, er.id, er.progress, er.filled, er.size, er.avg_price, er.slippage);
+        }
+        self.strategy.on_execution(&mut self.ctx, &er);
+    }
+
+    fn position_update(&mut self, position: IncomingPosition) {
+        self.ctx.portfolio.position_update(position.clone());
+        self.strategy.on_position(&mut self.ctx, &position);
+    }
+
+    /// Applies the whole update or none of it, either way it goes in the audit log
+    fn params_update(&mut self, update: ParamUpdate) {
+        let result = self.ctx.params.apply(&update);
+        if let Ok(params) = &result {
+            self.ctx.params = *params;
+            self.ctx.portfolio.apply_params(params);
+            self.strategy.on_params(&mut self.ctx);
+        }
+        CONTROL.record(Venue::Bybit, &self.ctx.portfolio.symbol, &update, result.as_ref());
+    }
+
+    fn order_response(&mut self, or: OrderResponse) -> Result<(), OrderResponseError> {
+        // debug!("Processing order result: {:?}", or);
+        let id = or.id;
+        let side = or.side;
+        let stage = or.stage;
+        let sent = or.sent;
+        let class = or.class;
+        let or = or.rest_response;
+        // if class == OrderClassification::Top { info!("top res: {:?}", or); }
+
+        let outcome = or.ret_code.outcome();
+        count_error(or.ret_code);
+        let mut resend = false;
+        match outcome {
+            StatusOutcome::Ok => {},
+            StatusOutcome::Retry => {
+                debug!("Order {} got {:?}, retrying: {}", id, or.ret_code, or.ret_msg);
+                resend = true;
+            }
+            StatusOutcome::Unknown => {
+                info!("Order {} got {:?}, leaving it for the reconcile to find: {}", id, or.ret_code, or.ret_msg);
+                self.ctx.portfolio.order_unknown(id, side, stage);
+                return Ok(());
+            }
+            StatusOutcome::Resync => match self.handle_unauthorized_request(or.ret_msg.clone()) {
+                Ok(offset) => {
+                    BROKER.set_server_offset(offset)?;
+                    resend = true;
+                },
+                Err(e) => debug!("Couldn't resync off {:?}, dropping order {}: {}", or.ret_msg, id, e),
+            },
+            StatusOutcome::DropOrder => {
+                debug!("Order {} dropped on {:?}: {}\noriginal ord: {}", id, or.ret_code, or.ret_msg, sent);
+            }
+            StatusOutcome::Halt => {
+                info!("Order {} got {:?}, halting: {}", id, or.ret_code, or.ret_msg);
+                self.ctx.portfolio.order_rest_response(id, side, stage, None);
+                self.ctx.portfolio.halt(self.ctx.strat_tx.clone());
+                if or.ret_code == PerpetualStatus::SystemNotRespondingContactSupport {
+                    return Err(OrderResponseError::ContactSupportError(or.ret_msg));
+                }
+                return Ok(());
+            }
+        }
+        // A failed resend has already dropped the order, the rest response below is a no-op for it
+        if resend && self.ctx.portfolio.retry_order(id, side, stage, self.ctx.strat_tx.clone()) { return Ok(()); }
+        let res = match (outcome, or.result) {
+            (StatusOutcome::Ok, Some(ordres)) => Some(IncomingOrderREST::from(ordres)),
+            _ => None,
+        };
+        // info!("sent: {:?}\ngot: {}", res, sent);
+        self.ctx.portfolio.order_rest_response(id, side, stage, res);
+        Ok(())
+    }
+
+    /// A failed amend leaves the order resting where it was, the strategy moves it again or cancels it on its next pass
+    fn amend_response(&mut self, amend: AmendResponse) {
+        let res = amend.rest_response;
+        count_error(res.ret_code);
+        let success = match res.ret_code.outcome() {
+            StatusOutcome::Ok => true,
+            StatusOutcome::Halt => {
+                info!("Amend {} got {:?}, halting: {}", amend.id, res.ret_code, res.ret_msg);
+                self.ctx.portfolio.halt(self.ctx.strat_tx.clone());
+                false
+            },
+            _ => {
+                debug!("Amend {} failed on {:?}: {}", amend.id, res.ret_code, res.ret_msg);
+                false
+            },
+        };
+        self.ctx.portfolio.amend_response(amend.id, amend.side, amend.stage, amend.price, amend.size, success);
+    }
+
+    fn cancel_order_response(&mut self, cancel: CancelResponse) -> Result<(), CancelOrderResponseError>{
+        // info!("cancel res: {:?}", cancel);
+        let id = cancel.id;
+        let auto_id = cancel.auto_id;
+        let side = cancel.side;
+        let stage = cancel.stage;
+        let cancel = cancel.rest_response;
+        let mut success = false;
+        let mut resend = false;
+        count_error(cancel.ret_code);
+        match cancel.ret_code.outcome() {
+            StatusOutcome::Ok => {
+                // info!("Cancel successful, dropping order {:?}", id);
+                success = true;
+            }
+            StatusOutcome::Retry | StatusOutcome::Unknown => resend = true,
+            StatusOutcome::Resync => match self.handle_unauthorized_request(cancel.ret_msg.clone()) {
+                Ok(offset) => {
+                    BROKER.set_server_offset(offset)?;
+                    resend = true;
+                },
+                Err(e) => debug!("Couldn't resync off {:?}, giving up cancel {}: {}", cancel.ret_msg, id, e),
+            },
+            // Usually too late, the fill or cancel will still arrive over WS
+            StatusOutcome::DropOrder => {
+                info!("Cancel {} failed on {:?}: {}", id, cancel.ret_code, cancel.ret_msg);
+            }
+            StatusOutcome::Halt => {
+                info!("Cancel {} got {:?}, halting: {}", id, cancel.ret_code, cancel.ret_msg);
+                self.ctx.portfolio.cancel_response(id, auto_id, side, stage, false);
+                self.ctx.portfolio.halt(self.ctx.strat_tx.clone());
+                if cancel.ret_code == PerpetualStatus::SystemNotRespondingContactSupport {
+                    return Err(CancelOrderResponseError::ContactSupportError(cancel.ret_msg));
+                }
+                return Ok(());
+            }
+        }
+        if resend && self.ctx.portfolio.retry_cancel(id, side, stage, self.ctx.strat_tx.clone()) { return Ok(()); }
+        self.ctx.portfolio.cancel_response(id, auto_id, side, stage, success);
+        return Ok(());
+    }
+
+    /// Conditionals aren't retried, the next protect pass sends a fresh one if it's still wanted
+    /// A conditional that never got a response is marked failed, it may have landed so the same size isn't sent again
+    fn conditional_response(&mut self, cr: ConditionalResponse) {
+        let success = match cr.rest_response {
+            Some(res) => {
+                count_error(res.ret_code);
+                match res.ret_code.outcome() {
+                    StatusOutcome::Ok => true,
+                    StatusOutcome::Halt => {
+                        info!("Conditional {} got {:?}, halting: {}", cr.id, res.ret_code, res.ret_msg);
+                        self.ctx.portfolio.halt(self.ctx.strat_tx.clone());
+                        false
+                    },
+                    _ => {
+                        debug!("Conditional {} failed on {:?}: {}", cr.id, res.ret_code, res.ret_msg);
+                        false
+                    },
+                }
+            },
+            None => false,
+        };
+        self.ctx.portfolio.conditional_response(cr.id, cr.side, success);
+    }
+
+    fn conditional_cancel_response(&mut self, cr: ConditionalResponse) {
+        let success = match cr.rest_response {
+            Some(res) => {
+                count_error(res.ret_code);
+                match res.ret_code.outcome() {
+                    StatusOutcome::Ok => true,
+                    _ => {
+                        debug!("Conditional cancel {} failed on {:?}: {}", cr.id, res.ret_code, res.ret_msg);
+                        false
+                    },
+                }
+            },
+            None => false,
+        };
+        self.ctx.portfolio.conditional_cancel_response(cr.id, cr.side, success);
+    }
+
+    fn handle_unauthorized_request(&mut self, ret_msg: String) -> Result<i128, UnauthorizedRequestError> {
+        debug!("Unauthorized request sent");
+        match ret_msg.find("req_timestamp: ") {
+            Some(mut req_start) => {
+                req_start += 15;
+
+                match ret_msg.find(" server_timestamp: ") {
+                    Some(mut server_start) => {
+                        let req_end = server_start;
+                        server_start += 19;
+                        match ret_msg.find(" recv_window: ") {
+                            Some(mut server_end) => {
+                                let req = ret_msg[req_start..req_end].to_string();
+                                let server = ret_msg[server_start..server_end].to_string();
+                                let req: i128 = req.parse()?;
+                                let server: i128 = server.parse()?;
+                                let diff = server - req;
+                                Ok(diff / 2)
+                            }
+                            None => Err(UnauthorizedRequestError::ParseResponseError("error parsing timestamp error message's recv_window index".to_string())),
+                        }
+                    }
+                    None => {
+                        Err(UnauthorizedRequestError::ParseResponseError("error parsing timestamp error message's server_timestamp index".to_string()))
+                    }
+                }
+            }
+            None => Err(UnauthorizedRequestError::ParseResponseError("error parsing timestamp error message's req_timestamp index".to_string())),
+        }
+    }
+}
+
+/// Anything but a plain Ok goes into the error counts under its numeric code
+fn count_error(status: PerpetualStatus) {
+    if status != PerpetualStatus::Ok {
+        METRICS.error(Venue::Bybit, (status as u32).to_string());
+    }
+}
@@ -0,0 +1,83 @@
+/// Net and gross exposure across the buy and sell positions of a hedge mode portfolio.
+/// Sizes are in the venue's order units, worst cases assume every resting and in flight order
+/// that would push exposure that way fills and none of the others do.
+
+use std::fmt::{Display, Formatter};
+
+use dec::D128;
+
+/// What one side holds and has working
+#[derive(Clone, Copy, Debug)]
+pub struct SideExposure {
+    pub filled: D128,
+    /// Resting and in flight entries
+    pub entries: D128,
+    /// Resting and in flight exits
+    pub exits: D128,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Exposure {
+    /// Filled buy inventory less filled sell inventory
+    pub net: D128,
+    /// Filled inventory on both sides
+    pub gross: D128,
+    /// Net if buy entries and sell exits all filled
+    pub worst_long: D128,
+    /// Net if sell entries and buy exits all filled, negative when short
+    pub worst_short: D128,
+    /// Gross if every entry filled
+    pub worst_gross: D128,
+    /// Value of worst_gross over what the margin allows, zero while either is unknown
+    pub margin_usage: D128,
+}
+
+impl Display for Exposure {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "Exposure: {{ net: {}, gross: {}, worst long: {}, worst short: {}, worst gross: {}, margin usage: {} }}",
+        self.net, self.gross, self.worst_long, self.worst_short, self.worst_gross, self.margin_usage)
+    }
+}
+
+impl Exposure {
+    pub fn new() -> Exposure {
+        Exposure {
+            net: D128::ZERO,
+            gross: D128::ZERO,
+            worst_long: D128::ZERO,
+            worst_short: D128::ZERO,
+            worst_gross: D128::ZERO,
+            margin_usage: D128::ZERO,
+        }
+    }
+
+    /// worst_value is what worst_gross is worth in the units max_value is in
+    pub fn from_sides(buy: SideExposure, sell: SideExposure, worst_value: impl Fn(D128) -> D128, max_value: D128) -> Exposure {
+        let net = buy.filled - sell.filled;
+        let worst_gross = buy.filled + buy.entries + sell.filled + sell.entries;
+        Exposure {
+            net,
+            gross: buy.filled + sell.filled,
+            worst_long: net + buy.entries + sell.exits,
+            worst_short: net - sell.entries - buy.exits,
+            worst_gross,
+            margin_usage: if max_value.is_positive() { worst_value(worst_gross) / max_value } else { D128::ZERO },
+        }
+    }
+
+    /// Net once an entry of size on the buy side, or the sell side when buy is false, is added to the worst case
+    pub fn worst_net_with(&self, buy: bool, size: D128) -> D128 {
+        if buy { self.worst_long + size } else { self.worst_short - size }
+    }
+
+    /// How far net is past the hedge threshold, positive when long
+    pub fn hedge_excess(&self, threshold: D128) -> D128 {
+        if self.net > threshold {
+            self.net - threshold
+        } else if self.net < -threshold {
+            self.net + threshold
+        } else {
+            D128::ZERO
+        }
+    }
+}
@@ -0,0 +1,140 @@
+/// Maker and taker rates per venue and symbol.
+/// Rates follow the account's fee tier, so they're pulled from the exchange at startup and refreshed on a timer.
+/// Until the first pull lands, or if it fails, the venue's base tier is assumed. Rates are fractions of an order's
+/// value in the settle coin and negative when they're rebates.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use dec::D128;
+
+use crate::backend::binance::broker::BROKER as BINANCE_BROKER;
+use crate::backend::binance::types::CommissionRateWrapper;
+use crate::backend::bybit::broker::BROKER as BYBIT_BROKER;
+use crate::backend::bybit::errors::StatusOutcome;
+use crate::telemetry::Venue;
+
+lazy_static! {
+    pub static ref FEES: FeeBook = FeeBook::new();
+    /// How often the account's rates are pulled again, tiers are recalculated daily
+    static ref REFRESH_PERIOD: Duration = Duration::from_secs(3600);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRates {
+    pub maker: D128,
+    pub taker: D128,
+}
+
+impl Display for FeeRates {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "maker: {}, taker: {}", self.maker, self.taker)
+    }
+}
+
+impl FeeRates {
+    /// Nothing charged, for orders we didn't place and have nothing reserved against
+    pub const NONE: FeeRates = FeeRates { maker: D128::ZERO, taker: D128::ZERO };
+
+    /// Base tier rates, used until the exchange says otherwise
+    pub fn base(venue: Venue) -> FeeRates {
+        match venue {
+            Venue::Binance => FeeRates { maker: D128::from(-0.0001), taker: D128::from(0.00075) },
+            Venue::Bybit => FeeRates { maker: D128::from(-0.00025), taker: D128::from(0.00075) },
+        }
+    }
+
+    /// Fee on a resting order worth value, negative when it's a rebate
+    pub fn maker_fee(&self, value: D128) -> D128 {
+        value * self.maker
+    }
+
+    /// Fee on a taking order worth value
+    pub fn taker_fee(&self, value: D128) -> D128 {
+        value * self.taker
+    }
+
+    /// What a maker fill earns as a fraction of its value, negative when makers pay
+    pub fn rebate(&self) -> D128 {
+        -self.maker
+    }
+}
+
+pub struct FeeBook {
+    rates: RwLock<HashMap<(Venue, String), FeeRates>>,
+}
+
+impl FeeBook {
+    fn new() -> FeeBook {
+        FeeBook { rates: RwLock::new(HashMap::new()) }
+    }
+
+    /// The symbol's rates, the venue's base tier while they haven't been pulled
+    pub fn rates(&self, venue: Venue, symbol: &str) -> FeeRates {
+        self.rates.read().unwrap()
+            .get(&(venue, symbol.to_uppercase()))
+            .copied()
+            .unwrap_or_else(|| FeeRates::base(venue))
+    }
+
+    pub fn set(&self, venue: Venue, symbol: &str, rates: FeeRates) {
+        let previous = self.rates.write().unwrap().insert((venue, symbol.to_uppercase()), rates);
+        if previous != Some(rates) {
+            info!("[FEES] {:?} {} now {}", venue, symbol, rates);
+        }
+    }
+
+    /// Pulls the symbol's rates from the venue, keeps what it had if the pull fails
+    pub async fn refresh(&self, venue: Venue, symbol: &str) {
+        let symbol = symbol.to_uppercase();
+        let rates = match venue {
+            Venue::Binance => match BINANCE_BROKER.commission_rate(symbol.clone()).await {
+                Ok(CommissionRateWrapper::Rate(rate)) => Some(FeeRates { maker: rate.maker_commission_rate, taker: rate.taker_commission_rate }),
+                Ok(CommissionRateWrapper::Error(e)) => {
+                    info!("[FEES] Failed to pull {} commission rates: {}", symbol, e.msg);
+                    None
+                },
+                Err(e) => {
+                    info!("[FEES] Failed to pull {} commission rates: {}", symbol, e);
+                    None
+                },
+            },
+            Venue::Bybit => match BYBIT_BROKER.fee_rate(symbol.clone()).await {
+                Ok(res) if res.ret_code.outcome() == StatusOutcome::Ok => res.result.map(|rate| FeeRates { maker: rate.maker_fee_rate, taker: rate.taker_fee_rate }),
+                Ok(res) => {
+                    info!("[FEES] Failed to pull {} fee rates: {}", symbol, res.ret_msg);
+                    None
+                },
+                Err(e) => {
+                    info!("[FEES] Failed to pull {} fee rates: {}", symbol, e);
+                    None
+                },
+            },
+        };
+        if let Some(rates) = rates {
+            self.set(venue, &symbol, rates);
+        }
+    }
+
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for ((venue, symbol), rates) in self.rates.read().unwrap().iter() {
+            writeln!(out, "{:?} {}: {}", venue, symbol, rates).unwrap();
+        }
+        for venue in Venue::ALL {
+            writeln!(out, "{:?} base: {}", venue, FeeRates::base(venue)).unwrap();
+        }
+        out
+    }
+}
+
+/// Pulls the symbol's rates at startup and again every refresh period
+pub async fn fee_timer(venue: Venue, symbol: String) {
+    let mut interval = tokio::time::interval(*REFRESH_PERIOD);
+    loop {
+        interval.tick().await;
+        FEES.refresh(venue, &symbol).await;
+    }
+}
@@ -0,0 +1,344 @@
+/// Grid levels shared by both brokers' grid strategies.
+/// Buy levels step down from an anchor and sell levels step up from it, either a fixed distance apart or a fixed
+/// ratio apart. Entries rest on the ladder as rebase orders, and each filled entry is paired with a take-profit exit
+/// one level back toward the anchor, the anchor itself for the first level. Once the exit fills the level is armed
+/// again. Sizes can grow level by level away from the anchor.
+/// Re-anchoring: while the grid holds nothing and the mid has wandered more than reanchor_levels levels from the
+/// anchor, the grid is rebuilt around the mid.
+/// Breakouts: once the mid is breakout_levels levels past the outermost level the grid either pauses its entries until
+/// the mid is back inside the band, shifts the whole grid onto the mid, or cancels everything, flattens at market and
+/// stops. Take-profit exits keep working through a pause or shift.
+/// Settings come from the JSON file GRID_CONFIG points at (see grid.sample.json), anything left out takes the
+/// default below.
+
+use dec::D128;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::config::CONFIG;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Spacing {
+    /// Levels step apart by step in price
+    Fixed,
+    /// Levels step apart by a ratio of 1 + step
+    Geometric,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Breakout {
+    Pause,
+    Shift,
+    Flatten,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridState {
+    Running,
+    /// Broke out of the band, entries are pulled until the mid comes back
+    Paused,
+    /// Broke out of the band, waiting on cancels before closing out at market
+    Flattening,
+    /// Flattened, nothing more is placed until a restart
+    Stopped,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+struct GridConfig {
+    spacing: Option<Spacing>,
+    step: Option<D128>,
+    levels: Option<usize>,
+    anchor: Option<D128>,
+    size: Option<D128>,
+    size_scale: Option<D128>,
+    tick: Option<D128>,
+    reanchor_levels: Option<D128>,
+    breakout: Option<Breakout>,
+    breakout_levels: Option<D128>,
+    refresh_secs: Option<u64>,
+}
+
+impl GridConfig {
+    /// Rejects values the grid can't run on, anything left out is fine since it takes the default
+    fn validate(&self) -> Result<(), String> {
+        let positive = [("step", self.step), ("size", self.size), ("size_scale", self.size_scale), ("tick", self.tick)];
+        for (name, value) in positive {
+            if let Some(value) = value {
+                if !value.is_positive() { return Err(format!("{} must be positive, got {}", name, value)); }
+            }
+        }
+        let non_negative = [("reanchor_levels", self.reanchor_levels), ("breakout_levels", self.breakout_levels)];
+        for (name, value) in non_negative {
+            if let Some(value) = value {
+                if value.is_negative() { return Err(format!("{} can't be negative, got {}", name, value)); }
+            }
+        }
+        if let Some(anchor) = self.anchor {
+            if !anchor.is_positive() { return Err(format!("anchor must be positive, got {}", anchor)); }
+        }
+        if self.levels == Some(0) { return Err("levels must be at least 1".to_string()); }
+        if self.refresh_secs == Some(0) { return Err("refresh_secs must be at least 1".to_string()); }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GridSettings {
+    pub spacing: Spacing,
+    /// Price between levels when fixed, fraction between levels when geometric
+    pub step: D128,
+    /// Levels either side of the anchor
+    pub levels: usize,
+    /// Price to build the first grid around, the mid when unset
+    pub anchor: Option<D128>,
+    /// Size of the first level either side
+    pub size: D128,
+    /// Each level out is this many times the size of the one before it, 1 for equal sizes
+    pub size_scale: D128,
+    pub tick: D128,
+    /// Levels the mid can wander from the anchor while flat before the grid is rebuilt around it, 0 to never, e.g. to
+    /// hold a configured anchor
+    pub reanchor_levels: D128,
+    pub breakout: Breakout,
+    /// Levels past the outermost level the mid has to go for a breakout
+    pub breakout_levels: D128,
+    /// Seconds between grid upkeep while the book is quiet
+    pub refresh_secs: u64,
+}
+
+impl GridSettings {
+    /// Reads GRID_CONFIG, falling back to the defaults if it's unset, unreadable or invalid
+    pub fn load() -> GridSettings {
+        let config = match &CONFIG.grid_config {
+            Some(path) => match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_str::<GridConfig>(&raw).map_err(|e| e.to_string()))
+                .and_then(|config| config.validate().map(|_| config)) {
+                Ok(config) => config,
+                Err(e) => {
+                    info!("[GRID] Failed to load settings from {}, using defaults: {}", path, e);
+                    GridConfig::default()
+                },
+            },
+            None => GridConfig::default(),
+        };
+        let spacing = config.spacing.unwrap_or(Spacing::Fixed);
+        let settings = GridSettings {
+            spacing,
+            step: config.step.unwrap_or(match spacing {
+                Spacing::Fixed => D128::from(10),
+                Spacing::Geometric => D128::from(0.001),
+            }),
+            levels: config.levels.unwrap_or(5),
+            anchor: config.anchor,
+            size: config.size.unwrap_or(D128::from(0.001)),
+            size_scale: config.size_scale.unwrap_or(D128::ONE),
+            tick: config.tick.unwrap_or(D128::from(0.1)),
+            reanchor_levels: config.reanchor_levels.unwrap_or(D128::from(2)),
+            breakout: config.breakout.unwrap_or(Breakout::Pause),
+            breakout_levels: config.breakout_levels.unwrap_or(D128::ONE),
+            refresh_secs: config.refresh_secs.unwrap_or(5),
+        };
+        info!("[GRID] Settings: {:?}", settings);
+        settings
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GridLevel {
+    /// Buy levels sit below the anchor, sell levels above
+    pub buy: bool,
+    /// 1 for the levels next to the anchor
+    pub index: usize,
+    pub price: D128,
+    /// Where a filled entry on this level is exited
+    pub take_profit: D128,
+    pub size: D128,
+}
+
+/// One level's orders and the inventory its entries have filled that its exits haven't closed yet
+#[derive(Debug, Clone, Copy)]
+pub struct GridSlot {
+    pub level: GridLevel,
+    pub entry: Option<Uuid>,
+    pub exit: Option<Uuid>,
+    pub held: D128,
+    /// Filled so far on the working entry and exit, fills arrive as running totals
+    entry_filled: D128,
+    exit_filled: D128,
+    /// Cleared once the grid moves away from this level, it's left to work off what it holds
+    pub rearm: bool,
+}
+
+impl GridSlot {
+    fn new(level: GridLevel) -> GridSlot {
+        GridSlot {
+            level,
+            entry: None,
+            exit: None,
+            held: D128::ZERO,
+            entry_filled: D128::ZERO,
+            exit_filled: D128::ZERO,
+            rearm: true,
+        }
+    }
+
+    pub fn entry_done(&mut self) {
+        self.entry = None;
+        self.entry_filled = D128::ZERO;
+    }
+
+    pub fn exit_done(&mut self) {
+        self.exit = None;
+        self.exit_filled = D128::ZERO;
+    }
+
+    /// Whether the level's entry should be placed, it needs to be armed and hold nothing
+    pub fn wants_entry(&self) -> bool {
+        self.rearm && self.entry.is_none() && self.exit.is_none() && !self.held.is_positive()
+    }
+
+    /// Whether the level's take-profit should be placed, it goes out once the entry is done filling
+    pub fn wants_exit(&self) -> bool {
+        self.entry.is_none() && self.exit.is_none() && self.held.is_positive()
+    }
+}
+
+pub struct GridModel {
+    pub settings: GridSettings,
+    /// NaN until the first book sets it
+    pub anchor: D128,
+    pub state: GridState,
+    pub slots: Vec<GridSlot>,
+}
+
+impl GridModel {
+    pub fn new(settings: GridSettings) -> GridModel {
+        GridModel {
+            settings,
+            anchor: D128::NAN,
+            state: GridState::Running,
+            slots: Vec::new(),
+        }
+    }
+
+    /// Unrounded price of the index-th level out from the anchor, the anchor itself at 0
+    fn level_price(&self, buy: bool, index: usize) -> D128 {
+        let settings = &self.settings;
+        match settings.spacing {
+            Spacing::Fixed => {
+                let offset = settings.step * D128::from(index as u64);
+                if buy { self.anchor - offset } else { self.anchor + offset }
+            },
+            Spacing::Geometric => {
+                let mut ratio = D128::ONE;
+                for _ in 0..index { ratio *= D128::ONE + settings.step; }
+                if buy { self.anchor / ratio } else { self.anchor * ratio }
+            },
+        }
+    }
+
+    /// Rounds onto the tick, down for bids and up for offers
+    fn on_tick(&self, price: D128, down: bool) -> D128 {
+        let ticks = price / self.settings.tick;
+        if down { ticks.round_down(0) * self.settings.tick } else { ticks.round_up(0) * self.settings.tick }
+    }
+
+    pub fn levels(&self) -> Vec<GridLevel> {
+        let mut levels = Vec::with_capacity(self.settings.levels * 2);
+        let mut size = self.settings.size;
+        for index in 1..=self.settings.levels {
+            for buy in [true, false] {
+                levels.push(GridLevel {
+                    buy,
+                    index,
+                    price: self.on_tick(self.level_price(buy, index), buy),
+                    // The exit sells for a buy level and buys for a sell level, keep it on the far side of the entry
+                    take_profit: self.on_tick(self.level_price(buy, index - 1), !buy),
+                    size,
+                });
+            }
+            size *= self.settings.size_scale;
+        }
+        levels
+    }
+
+    /// Rebuilds the levels around anchor, returns the working entries that have to be cancelled as (id, buy)
+    /// Levels still holding inventory or working orders stay on to finish their exits without being armed again
+    pub fn reanchor(&mut self, anchor: D128) -> Vec<(Uuid, bool)> {
+        let mut cancels = Vec::new();
+        for slot in self.slots.iter_mut() {
+            slot.rearm = false;
+            if let Some(id) = slot.entry {
+                cancels.push((id, slot.level.buy));
+            }
+        }
+        self.anchor = anchor;
+        let levels = self.levels();
+        self.slots.extend(levels.into_iter().map(GridSlot::new));
+        info!("[GRID] Anchored at {}, {} levels from {} to {}", anchor, self.settings.levels,
+            self.level_price(true, self.settings.levels), self.level_price(false, self.settings.levels));
+        cancels
+    }
+
+    /// Working entries as (id, buy)
+    pub fn entries(&self) -> Vec<(Uuid, bool)> {
+        self.slots.iter().filter_map(|slot| slot.entry.map(|id| (id, slot.level.buy))).collect()
+    }
+
+    /// Working entries and exits as (id, buy, entry)
+    pub fn orders(&self) -> Vec<(Uuid, bool, bool)> {
+        self.slots.iter().flat_map(|slot| {
+            let entry = slot.entry.map(|id| (id, slot.level.buy, true));
+            let exit = slot.exit.map(|id| (id, slot.level.buy, false));
+            entry.into_iter().chain(exit)
+        }).collect()
+    }
+
+    /// Books a fill against the level the order belongs to, filled is the order's running total
+    pub fn fill(&mut self, id: Uuid, filled: D128) {
+        for slot in self.slots.iter_mut() {
+            if slot.entry == Some(id) {
+                slot.held += filled - slot.entry_filled;
+                slot.entry_filled = filled;
+                debug!("[GRID] {} level {} entry filled {}, holding {}", if slot.level.buy { "Buy" } else { "Sell" }, slot.level.index, filled, slot.held);
+                return;
+            }
+            if slot.exit == Some(id) {
+                slot.held -= filled - slot.exit_filled;
+                slot.exit_filled = filled;
+                debug!("[GRID] {} level {} exit filled {}, holding {}", if slot.level.buy { "Buy" } else { "Sell" }, slot.level.index, filled, slot.held);
+                return;
+            }
+        }
+    }
+
+    /// Drops levels left behind by a re-anchor once they've worked off everything
+    pub fn prune(&mut self) {
+        self.slots.retain(|slot| slot.rearm || slot.entry.is_some() || slot.exit.is_some() || slot.held.is_positive());
+    }
+
+    /// Whether no level is holding inventory or working an exit
+    pub fn flat(&self) -> bool {
+        self.slots.iter().all(|slot| slot.exit.is_none() && !slot.held.is_positive())
+    }
+
+    /// Past the band by breakout_levels of the outermost spacing
+    pub fn broken_out(&self, mid: D128) -> bool {
+        let outer = self.settings.levels;
+        let low = self.level_price(true, outer);
+        let high = self.level_price(false, outer);
+        let buffer = self.settings.breakout_levels;
+        mid < low - (self.level_price(true, outer - 1) - low) * buffer
+            || mid > high + (high - self.level_price(false, outer - 1)) * buffer
+    }
+
+    /// Flat and more than reanchor_levels of the first spacing away from the anchor
+    pub fn should_reanchor(&self, mid: D128) -> bool {
+        if !self.settings.reanchor_levels.is_positive() || !self.flat() { return false; }
+        let spacing = self.anchor - self.level_price(true, 1);
+        (mid - self.anchor).abs() > spacing * self.settings.reanchor_levels
+    }
+}
@@ -0,0 +1,171 @@
+/// Average cost PnL accounting.
+/// Each side of a position keeps a ledger of the inventory it holds and what it cost, booking realized PnL as exits
+/// reduce it and keeping fees paid apart from maker rebates received. Values are in the settle coin, which makes the
+/// same ledger work for inverse contracts as long as the caller says which way the side makes money.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::ops::Add;
+use std::sync::Mutex;
+
+use dec::D128;
+
+use crate::telemetry::Venue;
+
+lazy_static! {
+    pub static ref PNL: PnlBoard = PnlBoard::new();
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ledger {
+    /// True when the side gains as the value of its inventory rises, longs on linear and shorts on inverse
+    rising: bool,
+    pub inv: D128,
+    /// What inv cost to put on
+    pub cost: D128,
+    /// Gross of fees
+    pub realized: D128,
+    /// Taker and maker fees paid
+    pub fees: D128,
+    /// Maker rebates received
+    pub rebates: D128,
+}
+
+impl Ledger {
+    pub fn new(rising: bool) -> Ledger {
+        Ledger {
+            rising,
+            inv: D128::ZERO,
+            cost: D128::ZERO,
+            realized: D128::ZERO,
+            fees: D128::ZERO,
+            rebates: D128::ZERO,
+        }
+    }
+
+    /// An entry fill of qty worth value, fees below zero are rebates
+    pub fn entry(&mut self, qty: D128, value: D128, fee: D128) {
+        self.inv += qty;
+        self.cost += value;
+        self.book_fee(fee);
+    }
+
+    /// An exit fill of qty worth value, returns the PnL it realized.
+    /// Closing more than is held only realizes against what's held.
+    pub fn exit(&mut self, qty: D128, value: D128, fee: D128) -> D128 {
+        self.book_fee(fee);
+        if !self.inv.is_positive() || !qty.is_positive() { return D128::ZERO; }
+        let (qty, value) = if qty > self.inv { (self.inv, value * self.inv / qty) } else { (qty, value) };
+        let basis = self.cost * qty / self.inv;
+        let pnl = if self.rising { value - basis } else { basis - value };
+        self.inv -= qty;
+        self.cost -= basis;
+        if self.inv.is_zero() { self.cost = D128::ZERO; }
+        self.realized += pnl;
+        pnl
+    }
+
+    fn book_fee(&mut self, fee: D128) {
+        if fee.is_negative() { self.rebates -= fee; } else { self.fees += fee; }
+    }
+
+    /// PnL of what's still held if it were closed at value
+    pub fn unrealized(&self, value: D128) -> D128 {
+        if !self.inv.is_positive() { return D128::ZERO; }
+        if self.rising { value - self.cost } else { self.cost - value }
+    }
+
+    /// Snapshot with what's held marked at value
+    pub fn report(&self, value: D128) -> LedgerReport {
+        LedgerReport {
+            inv: self.inv,
+            cost: self.cost,
+            realized: self.realized,
+            unrealized: self.unrealized(value),
+            fees: self.fees,
+            rebates: self.rebates,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LedgerReport {
+    pub inv: D128,
+    pub cost: D128,
+    pub realized: D128,
+    pub unrealized: D128,
+    pub fees: D128,
+    pub rebates: D128,
+}
+
+impl LedgerReport {
+    pub fn new() -> LedgerReport {
+        LedgerReport {
+            inv: D128::ZERO,
+            cost: D128::ZERO,
+            realized: D128::ZERO,
+            unrealized: D128::ZERO,
+            fees: D128::ZERO,
+            rebates: D128::ZERO,
+        }
+    }
+
+    /// Realized plus unrealized, less fees, plus rebates
+    pub fn net(&self) -> D128 {
+        self.realized + self.unrealized - self.fees + self.rebates
+    }
+}
+
+impl Add for LedgerReport {
+    type Output = Self;
+    fn add(self, other: Self) -> Self::Output {
+        LedgerReport {
+            inv: self.inv + other.inv,
+            cost: self.cost + other.cost,
+            realized: self.realized + other.realized,
+            unrealized: self.unrealized + other.unrealized,
+            fees: self.fees + other.fees,
+            rebates: self.rebates + other.rebates,
+        }
+    }
+}
+
+impl Display for LedgerReport {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "inv: {}, cost: {}, realized: {}, unrealized: {}, fees: {}, rebates: {}, net: {}",
+        self.inv, self.cost, self.realized, self.unrealized, self.fees, self.rebates, self.net())
+    }
+}
+
+/// Latest ledger reports from every portfolio, for reporting per side, symbol and account
+pub struct PnlBoard {
+    reports: Mutex<HashMap<(Venue, String), (LedgerReport, LedgerReport)>>,
+}
+
+impl PnlBoard {
+    fn new() -> PnlBoard {
+        PnlBoard { reports: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn publish(&self, venue: Venue, symbol: &str, buy: LedgerReport, sell: LedgerReport) {
+        self.reports.lock().unwrap().insert((venue, symbol.to_string()), (buy, sell));
+    }
+
+    /// Sum over every symbol on a venue
+    pub fn account(&self, venue: Venue) -> LedgerReport {
+        self.reports.lock().unwrap().iter()
+            .filter(|((report_venue, _), _)| *report_venue == venue)
+            .fold(LedgerReport::new(), |sum, (_, (buy, sell))| sum + *buy + *sell)
+    }
+
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for ((venue, symbol), (buy, sell)) in self.reports.lock().unwrap().iter() {
+            writeln!(out, "{:?} {}\n    buy: {}\n    sell: {}\n    total: {}", venue, symbol, buy, sell, *buy + *sell).unwrap();
+        }
+        for venue in Venue::ALL {
+            writeln!(out, "{:?} account: {}", venue, self.account(venue)).unwrap();
+        }
+        out
+    }
+}
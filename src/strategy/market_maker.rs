@@ -0,0 +1,208 @@
+/// Avellaneda–Stoikov quoting shared by both brokers' market maker strategies.
+/// The mid's variance per second is realized from book updates over a rolling window, order arrival intensity
+/// A * e^(-k * depth) comes from the trade flow riding on each book result. Quotes sit either side of a reservation
+/// price skewed away from inventory:
+///     r = mid - q * gamma * sigma^2 * tau
+///     spread = gamma * sigma^2 * tau + (2 / gamma) * ln(1 + gamma / k)
+/// where q is net inventory in order sized lots and tau a rolling horizon, perpetuals have no session end to run to.
+/// Settings come from the JSON file MARKET_MAKER_CONFIG points at (see market_maker.sample.json), anything left out
+/// takes the default below. Prices are in the quote currency and sizes in the venue's order units.
+
+use std::time::Instant;
+
+use dec::D128;
+use serde::Deserialize;
+
+use crate::analysis::BookResult;
+use crate::analysis::stats::RegularStats;
+use crate::config::CONFIG;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+struct MarketMakerConfig {
+    gamma: Option<D128>,
+    horizon_secs: Option<D128>,
+    volatility_window_secs: Option<u64>,
+    kappa: Option<D128>,
+    min_trade_rate: Option<D128>,
+    order_size: Option<D128>,
+    max_inventory: Option<D128>,
+    min_spread: Option<D128>,
+    requote: Option<D128>,
+    tick: Option<D128>,
+    refresh_secs: Option<u64>,
+}
+
+impl MarketMakerConfig {
+    /// Rejects values the model can't quote with, anything left out is fine since it takes the default
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("gamma", self.gamma), ("horizon_secs", self.horizon_secs), ("kappa", self.kappa),
+            ("order_size", self.order_size), ("max_inventory", self.max_inventory), ("tick", self.tick),
+        ];
+        for (name, value) in positive {
+            if let Some(value) = value {
+                if !value.is_positive() { return Err(format!("{} must be positive, got {}", name, value)); }
+            }
+        }
+        let non_negative = [("min_trade_rate", self.min_trade_rate), ("min_spread", self.min_spread), ("requote", self.requote)];
+        for (name, value) in non_negative {
+            if let Some(value) = value {
+                if value.is_negative() { return Err(format!("{} can't be negative, got {}", name, value)); }
+            }
+        }
+        if self.volatility_window_secs == Some(0) { return Err("volatility_window_secs must be at least 1".to_string()); }
+        if self.refresh_secs == Some(0) { return Err("refresh_secs must be at least 1".to_string()); }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarketMakerSettings {
+    /// Risk aversion, how hard inventory skews the quotes and widens the spread
+    pub gamma: D128,
+    /// Seconds of inventory risk priced into every quote
+    pub horizon_secs: D128,
+    /// Seconds of mid changes the variance is realized over
+    pub volatility_window_secs: u64,
+    /// Order book liquidity k used until the trade flow has enough trades to estimate it
+    pub kappa: D128,
+    /// Trades per second needed before k is taken from the trade flow
+    pub min_trade_rate: D128,
+    pub order_size: D128,
+    /// Net inventory in lots past which the side adding to it stops quoting
+    pub max_inventory: D128,
+    /// Narrowest spread quoted, as a fraction of mid
+    pub min_spread: D128,
+    /// How far a quote's target can move, as a fraction of mid, before the resting order is replaced
+    pub requote: D128,
+    pub tick: D128,
+    /// Seconds between requotes while the book is quiet
+    pub refresh_secs: u64,
+}
+
+impl MarketMakerSettings {
+    /// Reads MARKET_MAKER_CONFIG, falling back to the defaults if it's unset, unreadable or invalid
+    pub fn load() -> MarketMakerSettings {
+        let config = match &CONFIG.market_maker_config {
+            Some(path) => match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_str::<MarketMakerConfig>(&raw).map_err(|e| e.to_string()))
+                .and_then(|config| config.validate().map(|_| config)) {
+                Ok(config) => config,
+                Err(e) => {
+                    info!("[MM] Failed to load settings from {}, using defaults: {}", path, e);
+                    MarketMakerConfig::default()
+                },
+            },
+            None => MarketMakerConfig::default(),
+        };
+        let settings = MarketMakerSettings {
+            gamma: config.gamma.unwrap_or(D128::from(0.01)),
+            horizon_secs: config.horizon_secs.unwrap_or(D128::from(10)),
+            volatility_window_secs: config.volatility_window_secs.unwrap_or(300),
+            kappa: config.kappa.unwrap_or(D128::ONE),
+            min_trade_rate: config.min_trade_rate.unwrap_or(D128::from(5)),
+            order_size: config.order_size.unwrap_or(D128::from(0.001)),
+            max_inventory: config.max_inventory.unwrap_or(D128::from(5)),
+            min_spread: config.min_spread.unwrap_or(D128::from(0.0001)),
+            requote: config.requote.unwrap_or(D128::from(0.00005)),
+            tick: config.tick.unwrap_or(D128::from(0.1)),
+            refresh_secs: config.refresh_secs.unwrap_or(5),
+        };
+        info!("[MM] Settings: {:?}", settings);
+        settings
+    }
+}
+
+/// Where the model wants to quote, prices are already on the tick and behind the touch
+#[derive(Debug, Clone, Copy)]
+pub struct Quotes {
+    pub reservation: D128,
+    pub spread: D128,
+    pub bid: D128,
+    pub ask: D128,
+    /// Fills per second expected at either quote
+    pub intensity: D128,
+}
+
+pub struct QuoteModel {
+    pub settings: MarketMakerSettings,
+    /// Mid changes keyed by ms since the model started
+    returns: RegularStats,
+    last_mid: D128,
+    started: Instant,
+}
+
+impl QuoteModel {
+    pub fn new(settings: MarketMakerSettings) -> QuoteModel {
+        QuoteModel {
+            settings,
+            returns: RegularStats::new(),
+            last_mid: D128::NAN,
+            started: Instant::now(),
+        }
+    }
+
+    /// Feeds a book update's mid into the variance
+    pub fn observe(&mut self, book: &BookResult) {
+        let mid: D128 = (book.best_bid.0 + book.best_ask.0) / 2;
+        if mid.is_nan() { return; }
+        let now = self.started.elapsed().as_millis() as u64;
+        if !self.last_mid.is_nan() {
+            self.returns.add(now, mid - self.last_mid);
+            self.returns.prune(now.saturating_sub(self.settings.volatility_window_secs * 1000));
+        }
+        self.last_mid = mid;
+    }
+
+    /// Realized variance of the mid per second, NaN for the first second
+    pub fn variance(&self) -> D128 {
+        let covered = self.started.elapsed().as_millis().min(self.settings.volatility_window_secs as u128 * 1000);
+        if covered < 1000 { return D128::NAN; }
+        self.returns.squared_sum_dependent * 1000 / D128::from(covered as u64)
+    }
+
+    /// k from how far from the mid takers have been trading, the configured k while the flow is thin
+    pub fn kappa(&self, book: &BookResult) -> D128 {
+        if book.arrival_rate >= self.settings.min_trade_rate && book.trade_depth.is_positive() {
+            D128::ONE / book.trade_depth
+        } else {
+            self.settings.kappa
+        }
+    }
+
+    /// Quotes for the book at a net inventory in the venue's order units, None while there's nothing to go on
+    pub fn quote(&self, book: &BookResult, inventory: D128) -> Option<Quotes> {
+        let best_bid = book.best_bid.0;
+        let best_ask = book.best_ask.0;
+        let mid: D128 = (best_bid + best_ask) / 2;
+        let variance = self.variance();
+        if mid.is_nan() || variance.is_nan() { return None; }
+        let settings = &self.settings;
+        let kappa = self.kappa(book);
+        let lots = inventory / settings.order_size;
+        let risk = settings.gamma * variance * settings.horizon_secs;
+        let reservation = mid - lots * risk;
+        let mut spread = risk + (D128::from(2) / settings.gamma) * (D128::ONE + settings.gamma / kappa).ln();
+        if spread < settings.min_spread * mid {
+            spread = settings.min_spread * mid;
+        }
+        // Post only, never cross the touch
+        let half: D128 = spread / 2;
+        let mut bid = ((reservation - half) / settings.tick).round_down(0) * settings.tick;
+        let mut ask = ((reservation + half) / settings.tick).round_up(0) * settings.tick;
+        if bid > best_bid { bid = best_bid; }
+        if ask < best_ask { ask = best_ask; }
+        let intensity = if book.arrival_rate.is_nan() {
+            D128::ZERO
+        } else {
+            D128::from(book.arrival_rate.fast_float() * (-(kappa * half).fast_float()).exp())
+        };
+        Some(Quotes { reservation, spread, bid, ask, intensity })
+    }
+
+    /// Whether a resting quote at price is close enough to target to leave alone
+    pub fn within_requote(&self, price: D128, target: D128) -> bool {
+        (price - target).abs() <= self.settings.requote * target
+    }
+}
@@ -1,3 +1,11 @@
 pub mod bybit;
 pub mod binance;
+pub mod exposure;
+pub mod fees;
+pub mod grid;
+pub mod ledger;
+pub mod market_maker;
+pub mod params;
+pub mod protection;
+pub mod scheduler;
 pub mod types;
\ No newline at end of file
@@ -0,0 +1,315 @@
+/// Strategy parameters that can be changed while running.
+/// Changes come in as an update of one or more key=value pairs through CONTROL, the console's `set` command being the
+/// only source for now. Each runtime registers a way into its strategy channel, so an update is applied on the strategy
+/// thread between events. Every change in an update is checked against the resulting parameter set before any of it
+/// is applied, and the outcome is appended to the audit log along with who asked for it.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::fs::OpenOptions;
+use std::io::Write as IoWrite;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dec::D128;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::config::CONFIG;
+use crate::strategy::fees::FEES;
+use crate::telemetry::Venue;
+
+lazy_static! {
+    pub static ref CONTROL: Control = Control::new();
+    /// Rebates past this either way are a typo, not a fee tier
+    static ref MAX_REBATE: D128 = D128::from(0.01);
+}
+
+/// Where updates are logged when PARAMS_AUDIT_PATH isn't set
+const DEFAULT_AUDIT_PATH: &str = "params_audit.log";
+
+#[derive(Error, Debug)]
+pub enum ParamError {
+    #[error("Unknown parameter {0}")]
+    UnknownKey(String),
+    #[error("Can't read {1} as a value for {0}")]
+    BadValue(String, String),
+    #[error("{0} must be {1}")]
+    OutOfRange(&'static str, &'static str),
+    #[error("No {0:?} strategy is running")]
+    NoRuntime(Venue),
+    #[error("Usage: set <venue> <key>=<value> ...")]
+    Usage,
+}
+
+/// The parameter set a strategy and its portfolio run with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    /// Size of top orders and the first rebase
+    pub init_size: D128,
+    /// Open order cap across both sides, split evenly between them
+    pub max_open_orders: D128,
+    /// How far a rebase can drift from the top before it's pulled
+    pub rebase_distance: D128,
+    pub max_open_dist: D128,
+    pub top_open_dist: D128,
+    /// Maker rebate the strategy prices with, the fee model's when None
+    pub rebate: Option<D128>,
+    /// Entries on a disabled side are refused, exits still go out
+    pub buy_enabled: bool,
+    pub sell_enabled: bool,
+}
+
+impl Display for Params {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "init_size={} max_open_orders={} rebase_distance={} max_open_dist={} top_open_dist={} rebate={} buy={} sell={}",
+            self.init_size, self.max_open_orders, self.rebase_distance, self.max_open_dist, self.top_open_dist,
+            self.rebate.map_or("auto".to_string(), |rebate| rebate.to_string()),
+            on_off(self.buy_enabled), on_off(self.sell_enabled))
+    }
+}
+
+impl Params {
+    /// Maker rebate to price with, negative when makers pay
+    pub fn rebate(&self, venue: Venue, symbol: &str) -> D128 {
+        self.rebate.unwrap_or_else(|| FEES.rates(venue, symbol).rebate())
+    }
+
+    pub fn side_enabled(&self, buy: bool) -> bool {
+        if buy { self.buy_enabled } else { self.sell_enabled }
+    }
+
+    /// The parameters with every change in the update applied, or the first reason the result isn't valid
+    pub fn apply(&self, update: &ParamUpdate) -> Result<Params, ParamError> {
+        let mut params = *self;
+        for change in update.changes.iter() {
+            match *change {
+                ParamChange::InitSize(size) => params.init_size = size,
+                ParamChange::MaxOpenOrders(count) => params.max_open_orders = count,
+                ParamChange::RebaseDistance(distance) => params.rebase_distance = distance,
+                ParamChange::MaxOpenDist(distance) => params.max_open_dist = distance,
+                ParamChange::TopOpenDist(distance) => params.top_open_dist = distance,
+                ParamChange::Rebate(rebate) => params.rebate = rebate,
+                ParamChange::BuyEnabled(enabled) => params.buy_enabled = enabled,
+                ParamChange::SellEnabled(enabled) => params.sell_enabled = enabled,
+            }
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    fn validate(&self) -> Result<(), ParamError> {
+        if !self.init_size.is_positive() {
+            return Err(ParamError::OutOfRange("init_size", "positive"));
+        }
+        if self.max_open_orders < D128::from(2) || self.max_open_orders != self.max_open_orders.round_down(0) {
+            return Err(ParamError::OutOfRange("max_open_orders", "a whole number of at least 2"));
+        }
+        if !self.rebase_distance.is_positive() {
+            return Err(ParamError::OutOfRange("rebase_distance", "positive"));
+        }
+        if !self.max_open_dist.is_positive() || !self.top_open_dist.is_positive() {
+            return Err(ParamError::OutOfRange("max_open_dist and top_open_dist", "positive"));
+        }
+        if self.top_open_dist > self.max_open_dist {
+            return Err(ParamError::OutOfRange("top_open_dist", "no more than max_open_dist"));
+        }
+        if let Some(rebate) = self.rebate {
+            if rebate.abs() > *MAX_REBATE {
+                return Err(ParamError::OutOfRange("rebate", "within 0.01 either way"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamChange {
+    InitSize(D128),
+    MaxOpenOrders(D128),
+    RebaseDistance(D128),
+    MaxOpenDist(D128),
+    TopOpenDist(D128),
+    Rebate(Option<D128>),
+    BuyEnabled(bool),
+    SellEnabled(bool),
+}
+
+impl Display for ParamChange {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ParamChange::InitSize(size) => write!(f, "init_size={}", size),
+            ParamChange::MaxOpenOrders(count) => write!(f, "max_open_orders={}", count),
+            ParamChange::RebaseDistance(distance) => write!(f, "rebase_distance={}", distance),
+            ParamChange::MaxOpenDist(distance) => write!(f, "max_open_dist={}", distance),
+            ParamChange::TopOpenDist(distance) => write!(f, "top_open_dist={}", distance),
+            ParamChange::Rebate(rebate) => write!(f, "rebate={}", rebate.map_or("auto".to_string(), |rebate| rebate.to_string())),
+            ParamChange::BuyEnabled(enabled) => write!(f, "buy={}", on_off(*enabled)),
+            ParamChange::SellEnabled(enabled) => write!(f, "sell={}", on_off(*enabled)),
+        }
+    }
+}
+
+impl ParamChange {
+    /// Reads one key=value pair, rebate=auto goes back to the fee model and sides take on or off
+    pub fn parse(pair: &str) -> Result<ParamChange, ParamError> {
+        let (key, value) = pair.split_once('=').ok_or(ParamError::Usage)?;
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let number = || value.parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .map(D128::from)
+            .ok_or_else(|| ParamError::BadValue(key.clone(), value.to_string()));
+        let switch = || match value.to_lowercase().as_str() {
+            "on" | "true" => Ok(true),
+            "off" | "false" => Ok(false),
+            _ => Err(ParamError::BadValue(key.clone(), value.to_string())),
+        };
+        match key.as_str() {
+            "init_size" => Ok(ParamChange::InitSize(number()?)),
+            "max_open_orders" => Ok(ParamChange::MaxOpenOrders(number()?)),
+            "rebase_distance" => Ok(ParamChange::RebaseDistance(number()?)),
+            "max_open_dist" => Ok(ParamChange::MaxOpenDist(number()?)),
+            "top_open_dist" => Ok(ParamChange::TopOpenDist(number()?)),
+            "rebate" if value.eq_ignore_ascii_case("auto") => Ok(ParamChange::Rebate(None)),
+            "rebate" => Ok(ParamChange::Rebate(Some(number()?))),
+            "buy" => Ok(ParamChange::BuyEnabled(switch()?)),
+            "sell" => Ok(ParamChange::SellEnabled(switch()?)),
+            _ => Err(ParamError::UnknownKey(key)),
+        }
+    }
+}
+
+/// A set of changes applied together or not at all
+#[derive(Debug, Clone)]
+pub struct ParamUpdate {
+    pub changes: Vec<ParamChange>,
+    /// Who asked for the change
+    pub source: String,
+    /// Unix seconds
+    pub at: u64,
+}
+
+impl Display for ParamUpdate {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let changes: Vec<String> = self.changes.iter().map(|change| change.to_string()).collect();
+        write!(f, "{}", changes.join(" "))
+    }
+}
+
+impl ParamUpdate {
+    pub fn new(changes: Vec<ParamChange>, source: String) -> ParamUpdate {
+        ParamUpdate { changes, source, at: unix_secs() }
+    }
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    at: u64,
+    applied_at: u64,
+    source: &'a str,
+    venue: String,
+    symbol: &'a str,
+    changes: String,
+    outcome: String,
+}
+
+type Deliver = Box<dyn Fn(ParamUpdate) -> bool + Send>;
+
+/// Routes parameter updates to the runtimes and keeps the audit trail
+pub struct Control {
+    runtimes: Mutex<HashMap<Venue, Deliver>>,
+    /// What each runtime last reported it's running with
+    current: Mutex<HashMap<(Venue, String), Params>>,
+}
+
+impl Control {
+    fn new() -> Control {
+        Control {
+            runtimes: Mutex::new(HashMap::new()),
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Called by a runtime as it starts, deliver pushes the update into its strategy channel
+    pub fn register(&self, venue: Venue, symbol: &str, params: Params, deliver: Deliver) {
+        self.runtimes.lock().unwrap().insert(venue, deliver);
+        self.current.lock().unwrap().insert((venue, symbol.to_string()), params);
+    }
+
+    pub fn submit(&self, venue: Venue, update: ParamUpdate) -> Result<(), ParamError> {
+        match self.runtimes.lock().unwrap().get(&venue) {
+            Some(deliver) if deliver(update) => Ok(()),
+            _ => Err(ParamError::NoRuntime(venue)),
+        }
+    }
+
+    /// Handles the console's `set <venue> <key>=<value> ...`
+    pub fn command(&self, args: &str) -> Result<(), ParamError> {
+        let mut words = args.split_whitespace();
+        let venue = words.next()
+            .and_then(|name| Venue::ALL.iter().copied().find(|venue| format!("{:?}", venue).eq_ignore_ascii_case(name)))
+            .ok_or(ParamError::Usage)?;
+        let changes = words.map(ParamChange::parse).collect::<Result<Vec<ParamChange>, ParamError>>()?;
+        if changes.is_empty() {
+            return Err(ParamError::Usage);
+        }
+        let source = format!("console ({})", std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()));
+        let update = ParamUpdate::new(changes, source);
+        info!("[PARAMS] {} asked for {:?} {}", update.source, venue, update);
+        self.submit(venue, update)
+    }
+
+    /// Called by the runtime once it has applied or refused an update
+    pub fn record(&self, venue: Venue, symbol: &str, update: &ParamUpdate, result: Result<&Params, &ParamError>) {
+        let outcome = match result {
+            Ok(params) => {
+                self.current.lock().unwrap().insert((venue, symbol.to_string()), *params);
+                info!("[PARAMS] {:?} {} now running with {}", venue, symbol, params);
+                "applied".to_string()
+            },
+            Err(e) => {
+                info!("[PARAMS] {:?} {} refused {}: {}", venue, symbol, update, e);
+                format!("refused: {}", e)
+            },
+        };
+        let entry = AuditEntry {
+            at: update.at,
+            applied_at: unix_secs(),
+            source: &update.source,
+            venue: format!("{:?}", venue),
+            symbol,
+            changes: update.to_string(),
+            outcome,
+        };
+        let result = serde_json::to_string(&entry).map_err(|e| e.to_string()).and_then(|line| {
+            OpenOptions::new().create(true).append(true).open(audit_path())
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            info!("[PARAMS] Failed to write the audit log to {}: {}", audit_path(), e);
+        }
+    }
+
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for ((venue, symbol), params) in self.current.lock().unwrap().iter() {
+            writeln!(out, "{:?} {}: {}", venue, symbol, params).unwrap();
+        }
+        out
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "on" } else { "off" }
+}
+
+fn audit_path() -> &'static str {
+    CONFIG.params_audit_path.as_deref().unwrap_or(DEFAULT_AUDIT_PATH)
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
@@ -0,0 +1,122 @@
+/// Protective exits shared by both brokers' positions.
+/// Stop loss and take profit rest on the exchange as conditional orders, trailing stops are watched locally
+/// since the exchange side ones can't be re-based off our own cost basis.
+
+use dec::D128;
+
+use crate::backend::types::Side;
+use crate::config::CONFIG;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConditionalKind {
+    StopLoss,
+    TakeProfit,
+}
+
+/// Distances are fractions of the cost basis, None leaves that protection off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtectionSettings {
+    pub stop_loss: Option<D128>,
+    pub take_profit: Option<D128>,
+    pub trailing: Option<D128>,
+    /// The symbol's price increment, triggers have to land on it
+    pub tick: D128,
+}
+
+impl ProtectionSettings {
+    pub fn none() -> ProtectionSettings {
+        ProtectionSettings { stop_loss: None, take_profit: None, trailing: None, tick: price_tick() }
+    }
+
+    /// Reads the PROTECT_* settings, anything unset or out of range is left off
+    pub fn load() -> ProtectionSettings {
+        let distance = |name: &str, value: Option<D128>| match value {
+            Some(d) if d.is_positive() && d < D128::ONE => Some(d),
+            Some(d) => {
+                info!("[PROTECT] Ignoring {} of {}, it has to be between 0 and 1", name, d);
+                None
+            },
+            None => None,
+        };
+        let settings = ProtectionSettings {
+            stop_loss: distance("stop loss", CONFIG.protect_stop_loss),
+            take_profit: distance("take profit", CONFIG.protect_take_profit),
+            trailing: distance("trailing stop", CONFIG.protect_trailing),
+            tick: price_tick(),
+        };
+        info!("[PROTECT] Settings: {:?}", settings);
+        settings
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.stop_loss.is_none() && self.take_profit.is_none() && self.trailing.is_none()
+    }
+
+    pub fn distance(&self, kind: ConditionalKind) -> Option<D128> {
+        match kind {
+            ConditionalKind::StopLoss => self.stop_loss,
+            ConditionalKind::TakeProfit => self.take_profit,
+        }
+    }
+
+    /// Trigger price for a position of the given side, rounded onto the tick toward the cost basis so rounding never
+    /// widens a stop
+    pub fn trigger(&self, kind: ConditionalKind, side: Side, cost_basis: D128) -> Option<D128> {
+        let distance = self.distance(kind)?;
+        let offset = match kind {
+            ConditionalKind::StopLoss => D128::ONE - distance,
+            ConditionalKind::TakeProfit => D128::ONE + distance,
+        };
+        let trigger = match side {
+            Side::Buy => cost_basis * offset,
+            Side::Sell => cost_basis * (D128::from(2) - offset),
+        };
+        let ticks = trigger / self.tick;
+        let ticks = if trigger > cost_basis { ticks.round_down(0) } else { ticks.round_up(0) };
+        Some(ticks * self.tick)
+    }
+}
+
+fn price_tick() -> D128 {
+    match CONFIG.price_tick {
+        Some(tick) if tick.is_positive() => tick,
+        _ => D128::from(0.1),
+    }
+}
+
+/// Follows the best exit price and fires once price comes back by the distance
+#[derive(Debug, Clone, Copy)]
+pub struct TrailingStop {
+    pub distance: D128,
+    /// Best price seen since the stop was armed, highest for longs and lowest for shorts
+    pub extreme: D128,
+    pub triggered: bool,
+}
+
+impl TrailingStop {
+    pub fn new(distance: D128, price: D128) -> TrailingStop {
+        TrailingStop { distance, extreme: price, triggered: false }
+    }
+
+    pub fn stop_price(&self, side: Side) -> D128 {
+        match side {
+            Side::Buy => self.extreme * (D128::ONE - self.distance),
+            Side::Sell => self.extreme * (D128::ONE + self.distance),
+        }
+    }
+
+    /// Returns true the first time price crosses the stop
+    pub fn update(&mut self, price: D128, side: Side) -> bool {
+        if self.triggered || price.is_nan() || price.is_zero() { return false; }
+        match side {
+            Side::Buy => if price > self.extreme { self.extreme = price; },
+            Side::Sell => if price < self.extreme { self.extreme = price; },
+        }
+        let hit = match side {
+            Side::Buy => price <= self.stop_price(side),
+            Side::Sell => price >= self.stop_price(side),
+        };
+        if hit { self.triggered = true; }
+        hit
+    }
+}
@@ -0,0 +1,177 @@
+/// Timers for strategies.
+/// Strategies otherwise only run when market data arrives, so a quiet market leaves stale quotes resting. Each runtime
+/// owns a scheduler whose thread sleeps until the next timer is due and pushes a timer message into the strategy
+/// channel, so timers are handled in order with everything else on the strategy thread.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, unbounded};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimerKind {
+    /// Fires once after the delay
+    Once(Duration),
+    /// Fires every period, starting one period from now
+    Every(Duration),
+    /// Fires on wall clock multiples of the period shifted by the offset, e.g. every 8h for funding
+    Aligned { period: Duration, offset: Duration },
+}
+
+impl TimerKind {
+    /// Time between firings, None for one-shot timers
+    fn period(&self) -> Option<Duration> {
+        match *self {
+            TimerKind::Once(_) => None,
+            TimerKind::Every(period) | TimerKind::Aligned { period, .. } => Some(period),
+        }
+    }
+
+    fn first_due(&self, now: Instant) -> Instant {
+        match *self {
+            TimerKind::Once(delay) => now + delay,
+            TimerKind::Every(period) => now + period,
+            TimerKind::Aligned { period, offset } => now + until_boundary(period, offset),
+        }
+    }
+
+    /// When the timer is due after firing at due, None once it's done
+    fn next_due(&self, due: Instant, now: Instant) -> Option<Instant> {
+        match *self {
+            TimerKind::Once(_) => None,
+            // Stays on its original cadence, skipping any periods the strategy thread was too busy for
+            TimerKind::Every(period) => {
+                let mut next = due + period;
+                while next <= now {
+                    next += period;
+                }
+                Some(next)
+            },
+            // Wall and monotonic clocks drift apart, never land on the boundary that just fired again
+            TimerKind::Aligned { period, offset } => Some((now + until_boundary(period, offset)).max(due + period / 2)),
+        }
+    }
+}
+
+/// Time until the next wall clock boundary, boundaries are offset past every multiple of period since the epoch
+fn until_boundary(period: Duration, offset: Duration) -> Duration {
+    let period = period.as_millis().max(1);
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+    let into_period = (since_epoch + period - offset.as_millis() % period) % period;
+    Duration::from_millis((period - into_period) as u64)
+}
+
+enum Command {
+    Add(u64, TimerKind),
+    Cancel(u64),
+}
+
+pub struct Scheduler {
+    commands: Sender<Command>,
+    /// Timers that haven't been cancelled or run out, timer messages for anything else are dropped
+    live: HashMap<u64, TimerKind>,
+    next_id: u64,
+}
+
+impl Scheduler {
+    /// Spawns the timer thread, wrap turns a timer id into the venue's strategy message
+    pub fn new<T: Send + 'static>(strat_tx: Sender<T>, wrap: fn(u64) -> T) -> Scheduler {
+        let (commands, command_rx) = unbounded();
+        thread::spawn(move || run(command_rx, strat_tx, wrap));
+        Scheduler {
+            commands,
+            live: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Registers a timer and returns its id, the id is what on_timer gets called with. A repeating timer with a zero
+    /// period would fire in a tight loop, it's refused and the id never fires
+    pub fn schedule(&mut self, kind: TimerKind) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        if kind.period() == Some(Duration::ZERO) {
+            info!("[TIMER] Refusing timer {} with a zero period {:?}", id, kind);
+            return id;
+        }
+        self.live.insert(id, kind);
+        if self.commands.send(Command::Add(id, kind)).is_err() {
+            info!("[TIMER] Scheduler thread is gone, timer {} won't fire", id);
+        }
+        debug!("[TIMER] Scheduled {} {:?}", id, kind);
+        id
+    }
+
+    pub fn once(&mut self, delay: Duration) -> u64 {
+        self.schedule(TimerKind::Once(delay))
+    }
+
+    pub fn every(&mut self, period: Duration) -> u64 {
+        self.schedule(TimerKind::Every(period))
+    }
+
+    pub fn aligned(&mut self, period: Duration, offset: Duration) -> u64 {
+        self.schedule(TimerKind::Aligned { period, offset })
+    }
+
+    /// Stops the timer, returns false if it had already run out or been cancelled
+    pub fn cancel(&mut self, id: u64) -> bool {
+        match self.live.remove(&id) {
+            Some(_) => {
+                let _ = self.commands.send(Command::Cancel(id));
+                debug!("[TIMER] Cancelled {}", id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_live(&self, id: u64) -> bool {
+        self.live.contains_key(&id)
+    }
+
+    /// Called by the runtime as a timer message comes off the channel, false if it was cancelled while in flight
+    pub fn fired(&mut self, id: u64) -> bool {
+        match self.live.get(&id) {
+            Some(TimerKind::Once(_)) => {
+                self.live.remove(&id);
+                true
+            },
+            Some(_) => true,
+            None => false,
+        }
+    }
+}
+
+/// Sleeps until the earliest timer is due or a command arrives, exits once the scheduler or strategy channel is dropped
+fn run<T>(commands: Receiver<Command>, strat_tx: Sender<T>, wrap: fn(u64) -> T) {
+    let mut timers: HashMap<u64, (TimerKind, Instant)> = HashMap::new();
+    loop {
+        let command = match timers.values().map(|(_, due)| *due).min() {
+            Some(due) => commands.recv_timeout(due.saturating_duration_since(Instant::now())),
+            None => commands.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match command {
+            Ok(Command::Add(id, kind)) => { timers.insert(id, (kind, kind.first_due(Instant::now()))); },
+            Ok(Command::Cancel(id)) => { timers.remove(&id); },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let now = Instant::now();
+        let due: Vec<u64> = timers.iter()
+            .filter(|(_, (_, due))| *due <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in due {
+            if strat_tx.send(wrap(id)).is_err() {
+                return;
+            }
+            let (kind, due) = timers[&id];
+            match kind.next_due(due, now) {
+                Some(next) => { timers.insert(id, (kind, next)); },
+                None => { timers.remove(&id); },
+            }
+        }
+    }
+}
@@ -16,19 +16,20 @@ impl From<bool> for Stage {
 }
 
 impl Stage {
-    pub fn from_binance_side(side: BinanceSide, position_side: BinanceSide) -> Self {
+    /// One-way orders trade BOTH, so their stage only shows in reduce only
+    pub fn from_binance_side(side: BinanceSide, position_side: BinanceSide, reduce_only: bool) -> Self {
         match position_side {
             BinanceSide::Buy => match side {
                 BinanceSide::Buy => Stage::Entry,
                 BinanceSide::Sell => Stage::Exit,
-                BinanceSide::Both => todo!(),
+                BinanceSide::Both => Stage::from(reduce_only),
             },
             BinanceSide::Sell => match side {
                 BinanceSide::Buy => Stage::Exit,
                 BinanceSide::Sell => Stage::Entry,
-                BinanceSide::Both => todo!(),
+                BinanceSide::Both => Stage::from(reduce_only),
             },
-            BinanceSide::Both => todo!(),
+            BinanceSide::Both => Stage::from(reduce_only),
         }
     }
 
@@ -48,5 +49,9 @@ pub enum OrderClassification {
     Top,
     Rebase,
     Exit,
+    /// Child slice of an execution algorithm's parent order
+    Algo,
+    /// Stop loss or take profit resting as a conditional on the exchange
+    Protect,
     None,
 }
\ No newline at end of file
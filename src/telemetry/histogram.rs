@@ -0,0 +1,118 @@
+/// Log-linear histogram in the style of HdrHistogram.
+/// Values under 64ns are kept exactly, above that every power of two is split into 32 buckets,
+/// so any recorded value is reported within ~3% of what it really was.
+/// Recording is a handful of relaxed atomic adds, it's safe to call from every thread on the hot path.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const BUCKETS: usize = ((64 - SUB_BUCKET_BITS as usize) * SUB_BUCKETS as usize) + SUB_BUCKETS as usize;
+
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+/// Point in time copy of a histogram, what summaries and dumps are built from
+#[derive(Debug, Clone, Copy)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS * 2 { return value as usize; }
+    let shift = 63 - value.leading_zeros() - SUB_BUCKET_BITS;
+    (shift as u64 * SUB_BUCKETS + (value >> shift)) as usize
+}
+
+/// Highest value that lands in the bucket
+fn bucket_value(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS * 2 { return index; }
+    let shift = index / SUB_BUCKETS - 1;
+    let sub = index % SUB_BUCKETS + SUB_BUCKETS;
+    ((sub + 1) << shift) - 1
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, value: Duration) {
+        let nanos = value.as_nanos().min(u64::MAX as u128) as u64;
+        self.buckets[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(nanos, Ordering::Relaxed);
+        self.min.fetch_min(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Value at the given percentile, 0 to 100
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let count = self.count();
+        if count == 0 { return Duration::ZERO; }
+        let target = ((percentile / 100.0) * count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= target {
+                return Duration::from_nanos(bucket_value(index).min(self.max.load(Ordering::Relaxed)));
+            }
+        }
+        Duration::from_nanos(self.max.load(Ordering::Relaxed))
+    }
+
+    pub fn summary(&self) -> HistogramSummary {
+        let count = self.count();
+        HistogramSummary {
+            count,
+            min: if count == 0 { Duration::ZERO } else { Duration::from_nanos(self.min.load(Ordering::Relaxed)) },
+            mean: if count == 0 { Duration::ZERO } else { Duration::from_nanos(self.sum.load(Ordering::Relaxed) / count) },
+            p50: self.percentile(50.0),
+            p90: self.percentile(90.0),
+            p99: self.percentile(99.0),
+            p999: self.percentile(99.9),
+            max: Duration::from_nanos(self.max.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Non empty buckets as (highest value in bucket, count), for dumping the full distribution
+    pub fn buckets(&self) -> Vec<(Duration, u64)> {
+        self.buckets.iter().enumerate()
+            .map(|(index, bucket)| (index, bucket.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .map(|(index, count)| (Duration::from_nanos(bucket_value(index)), count))
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() { bucket.store(0, Ordering::Relaxed); }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,260 @@
+/// Prometheus metrics.
+/// Counters are bumped where things happen: book and trade updates as they come off the socket, websocket connects,
+/// and error responses by code. The strategy runtimes publish channel depth as they take each message and a portfolio
+/// snapshot every second. metrics_server answers GET /metrics on METRICS_ADDR in the text exposition format, with the
+/// latency histograms (REST round trips included) exported as summaries.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::CONFIG;
+
+use super::{LATENCY, LatencyStage, Venue};
+
+lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+/// How often the runtimes publish their portfolio
+pub const PORTFOLIO_PERIOD: Duration = Duration::from_secs(1);
+
+/// A portfolio as the runtime last published it
+#[derive(Debug, Clone, Default)]
+pub struct PortfolioMetrics {
+    /// Tracked orders keyed by side, stage and progress
+    pub orders: HashMap<(&'static str, &'static str, String), u64>,
+    pub buy_inventory: f64,
+    pub sell_inventory: f64,
+    /// Realized plus unrealized net of fees
+    pub pnl: f64,
+    pub remaining_margin: f64,
+    pub remaining_count: f64,
+}
+
+/// Reads one per symbol gauge off a published portfolio
+type PortfolioGauge = fn(&PortfolioMetrics) -> f64;
+
+pub struct Metrics {
+    book_updates: Vec<AtomicU64>,
+    trade_updates: Vec<AtomicU64>,
+    /// Messages waiting on the strategy channel when the runtime last took one off
+    channel_depth: Vec<AtomicU64>,
+    connects: Mutex<HashMap<(Venue, &'static str), u64>>,
+    errors: Mutex<HashMap<(Venue, String), u64>>,
+    portfolios: Mutex<HashMap<(Venue, String), PortfolioMetrics>>,
+}
+
+fn label(venue: Venue) -> String {
+    format!("{:?}", venue).to_lowercase()
+}
+
+/// Prometheus spells the special values its own way
+fn value(v: f64) -> String {
+    if v.is_nan() { "NaN".to_string() }
+    else if v == f64::INFINITY { "+Inf".to_string() }
+    else if v == f64::NEG_INFINITY { "-Inf".to_string() }
+    else { v.to_string() }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        Metrics {
+            book_updates: Venue::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            trade_updates: Venue::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            channel_depth: Venue::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            connects: Mutex::new(HashMap::new()),
+            errors: Mutex::new(HashMap::new()),
+            portfolios: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn book_update(&self, venue: Venue) {
+        self.book_updates[venue as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn trade_update(&self, venue: Venue) {
+        self.trade_updates[venue as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn channel_depth(&self, venue: Venue, depth: usize) {
+        self.channel_depth[venue as usize].store(depth as u64, Ordering::Relaxed);
+    }
+
+    /// A websocket came up, every connect after a stream's first is a reconnect
+    pub fn connected(&self, venue: Venue, stream: &'static str) {
+        if let Ok(mut connects) = self.connects.lock() {
+            *connects.entry((venue, stream)).or_insert(0) += 1;
+        }
+    }
+
+    /// An error response, code is whatever names it best on the venue
+    pub fn error(&self, venue: Venue, code: String) {
+        if let Ok(mut errors) = self.errors.lock() {
+            *errors.entry((venue, code)).or_insert(0) += 1;
+        }
+    }
+
+    pub fn portfolio(&self, venue: Venue, symbol: &str, portfolio: PortfolioMetrics) {
+        if let Ok(mut portfolios) = self.portfolios.lock() {
+            portfolios.insert((venue, symbol.to_string()), portfolio);
+        }
+    }
+
+    /// Everything in the text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, help, counters) in [
+            ("trader_book_updates_total", "Order book updates received off the socket", &self.book_updates),
+            ("trader_trade_updates_total", "Trades received off the socket", &self.trade_updates),
+        ] {
+            header(&mut out, name, "counter", help);
+            for venue in Venue::ALL {
+                writeln!(out, "{}{{venue=\"{}\"}} {}", name, label(venue), counters[venue as usize].load(Ordering::Relaxed)).unwrap();
+            }
+        }
+        header(&mut out, "trader_strategy_channel_depth", "gauge", "Messages waiting on the strategy channel");
+        for venue in Venue::ALL {
+            writeln!(out, "trader_strategy_channel_depth{{venue=\"{}\"}} {}", label(venue), self.channel_depth[venue as usize].load(Ordering::Relaxed)).unwrap();
+        }
+
+        let mut connects: Vec<((Venue, &'static str), u64)> = match self.connects.lock() {
+            Ok(connects) => connects.iter().map(|(key, count)| (*key, *count)).collect(),
+            Err(_) => Vec::new(),
+        };
+        connects.sort_by_key(|((venue, stream), _)| (*venue as usize, *stream));
+        header(&mut out, "trader_ws_connects_total", "counter", "Websocket connections opened");
+        for ((venue, stream), count) in connects.iter() {
+            writeln!(out, "trader_ws_connects_total{{venue=\"{}\",stream=\"{}\"}} {}", label(*venue), stream, count).unwrap();
+        }
+        header(&mut out, "trader_ws_reconnects_total", "counter", "Websocket connections opened after a stream's first");
+        for ((venue, stream), count) in connects.iter() {
+            writeln!(out, "trader_ws_reconnects_total{{venue=\"{}\",stream=\"{}\"}} {}", label(*venue), stream, count.saturating_sub(1)).unwrap();
+        }
+
+        let mut errors: Vec<((Venue, String), u64)> = match self.errors.lock() {
+            Ok(errors) => errors.iter().map(|(key, count)| (key.clone(), *count)).collect(),
+            Err(_) => Vec::new(),
+        };
+        errors.sort_by(|((a_venue, a_code), _), ((b_venue, b_code), _)| (*a_venue as usize, a_code).cmp(&(*b_venue as usize, b_code)));
+        header(&mut out, "trader_errors_total", "counter", "Error responses from the exchange by code");
+        for ((venue, code), count) in errors {
+            writeln!(out, "trader_errors_total{{venue=\"{}\",code=\"{}\"}} {}", label(venue), code.replace('"', "'"), count).unwrap();
+        }
+
+        header(&mut out, "trader_latency_seconds", "summary", "Tick-to-trade stage latencies since startup, rest_ack is the REST round trip");
+        for (venue, stage, summary) in LATENCY.summaries() {
+            let labels = format!("venue=\"{}\",stage=\"{}\"", label(venue), stage_label(stage));
+            for (quantile, duration) in [("0.5", summary.p50), ("0.9", summary.p90), ("0.99", summary.p99), ("0.999", summary.p999)] {
+                writeln!(out, "trader_latency_seconds{{{},quantile=\"{}\"}} {}", labels, quantile, duration.as_secs_f64()).unwrap();
+            }
+            writeln!(out, "trader_latency_seconds_sum{{{}}} {}", labels, summary.mean.as_secs_f64() * summary.count as f64).unwrap();
+            writeln!(out, "trader_latency_seconds_count{{{}}} {}", labels, summary.count).unwrap();
+        }
+
+        let mut portfolios: Vec<((Venue, String), PortfolioMetrics)> = match self.portfolios.lock() {
+            Ok(portfolios) => portfolios.iter().map(|(key, portfolio)| (key.clone(), portfolio.clone())).collect(),
+            Err(_) => Vec::new(),
+        };
+        portfolios.sort_by(|((a_venue, a_symbol), _), ((b_venue, b_symbol), _)| (*a_venue as usize, a_symbol).cmp(&(*b_venue as usize, b_symbol)));
+        header(&mut out, "trader_orders", "gauge", "Orders the portfolio is tracking by side, stage and progress");
+        for ((venue, symbol), portfolio) in portfolios.iter() {
+            let mut orders: Vec<_> = portfolio.orders.iter().collect();
+            orders.sort();
+            for ((side, stage, progress), count) in orders {
+                writeln!(out, "trader_orders{{venue=\"{}\",symbol=\"{}\",side=\"{}\",stage=\"{}\",progress=\"{}\"}} {}",
+                    label(*venue), symbol, side, stage, progress, count).unwrap();
+            }
+        }
+        header(&mut out, "trader_inventory", "gauge", "Filled inventory per side");
+        for ((venue, symbol), portfolio) in portfolios.iter() {
+            for (side, inventory) in [("buy", portfolio.buy_inventory), ("sell", portfolio.sell_inventory)] {
+                writeln!(out, "trader_inventory{{venue=\"{}\",symbol=\"{}\",side=\"{}\"}} {}", label(*venue), symbol, side, value(inventory)).unwrap();
+            }
+        }
+        let gauges: [(&str, &str, PortfolioGauge); 3] = [
+            ("trader_pnl", "Realized plus unrealized PnL net of fees since startup", |p| p.pnl),
+            ("trader_remaining_margin", "Size that can still be committed to entries", |p| p.remaining_margin),
+            ("trader_remaining_count", "Entries that can still be opened", |p| p.remaining_count),
+        ];
+        for (name, help, get) in gauges {
+            header(&mut out, name, "gauge", help);
+            for ((venue, symbol), portfolio) in portfolios.iter() {
+                writeln!(out, "{}{{venue=\"{}\",symbol=\"{}\"}} {}", name, label(*venue), symbol, value(get(portfolio))).unwrap();
+            }
+        }
+        out
+    }
+}
+
+fn stage_label(stage: LatencyStage) -> &'static str {
+    match stage {
+        LatencyStage::SocketReceive => "socket_receive",
+        LatencyStage::Deserialize => "deserialize",
+        LatencyStage::ModelUpdate => "model_update",
+        LatencyStage::Analysis => "analysis",
+        LatencyStage::Decision => "decision",
+        LatencyStage::RestSend => "rest_send",
+        LatencyStage::RestAck => "rest_ack",
+        LatencyStage::UserStreamConfirm => "user_stream_confirm",
+    }
+}
+
+/// Serves GET /metrics on METRICS_ADDR for as long as the program runs. Does nothing when it isn't set.
+pub async fn metrics_server() {
+    let addr = match &CONFIG.metrics_addr {
+        Some(addr) => addr.clone(),
+        None => {
+            info!("[METRICS] No METRICS_ADDR set, not serving metrics");
+            return;
+        },
+    };
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            info!("[METRICS] Failed to bind {}: {}", addr, e);
+            return;
+        },
+    };
+    info!("[METRICS] Serving http://{}/metrics", addr);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => { tokio::spawn(async move { respond(stream).await; }); },
+            Err(e) => debug!("[METRICS] Failed to accept a connection: {}", e),
+        }
+    }
+}
+
+/// One request per connection, anything but GET /metrics is a 404
+async fn respond(mut stream: TcpStream) {
+    let mut request = Vec::with_capacity(1024);
+    let mut buf = [0u8; 1024];
+    // Only the request line matters, but the head is read through so the client isn't reset mid send
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        match tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Ok(Err(_)) | Err(_) => return,
+            Ok(Ok(n)) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+    let line = String::from_utf8_lossy(&request);
+    let mut parts = line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) if path == "/metrics" || path.starts_with("/metrics?") => {
+            let body = METRICS.render();
+            format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+        },
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
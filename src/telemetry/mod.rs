@@ -0,0 +1,256 @@
+/// Tick-to-trade latency telemetry.
+/// Every stage a market data tick passes through on its way to an order is timed into a histogram per venue.
+/// The market data stages are measured from the moment the message came off the socket, so each one reads as
+/// "how long after receive was this done". REST ack and user stream confirm are measured from when the request went out.
+/// The last tick the strategy acted on is kept so the broker can tell how long after it an order hit the wire.
+
+mod histogram;
+mod metrics;
+
+pub use self::histogram::*;
+pub use self::metrics::*;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::BufRead;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::backend::binance::broker::BROKER;
+use crate::config::CONFIG;
+use crate::risk::{RISK, BREAKER};
+use crate::strategy::fees::FEES;
+use crate::strategy::ledger::PNL;
+use crate::strategy::params::CONTROL;
+
+lazy_static! {
+    pub static ref LATENCY: Telemetry = Telemetry::new();
+    /// Ticks are stored as nanos since this so they fit in an atomic
+    static ref EPOCH: Instant = Instant::now();
+    /// Orders that never confirm (rejected, lost) are dropped from the in flight map after this
+    static ref CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Venue {
+    Binance,
+    Bybit,
+}
+
+impl Venue {
+    pub const ALL: [Venue; 2] = [Venue::Binance, Venue::Bybit];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyStage {
+    /// Exchange event time to us reading it off the socket, only millisecond resolution and as good as the clock offset
+    SocketReceive,
+    Deserialize,
+    ModelUpdate,
+    Analysis,
+    /// Strategy finished reacting to the tick
+    Decision,
+    /// Tick receive to the order request going out
+    RestSend,
+    /// Request out to response back
+    RestAck,
+    /// Request out to the order showing up on the user stream
+    UserStreamConfirm,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 8] = [
+        LatencyStage::SocketReceive,
+        LatencyStage::Deserialize,
+        LatencyStage::ModelUpdate,
+        LatencyStage::Analysis,
+        LatencyStage::Decision,
+        LatencyStage::RestSend,
+        LatencyStage::RestAck,
+        LatencyStage::UserStreamConfirm,
+    ];
+}
+
+pub struct Telemetry {
+    /// Everything since startup, what dumps report
+    total: Vec<Histogram>,
+    /// Since the last periodic summary
+    interval: Vec<Histogram>,
+    /// Receive time of the last tick the strategy acted on per venue, 0 when the strategy is handling something else
+    ticks: Vec<AtomicU64>,
+    in_flight: Mutex<HashMap<String, (Venue, Instant)>>,
+}
+
+fn slot(venue: Venue, stage: LatencyStage) -> usize {
+    venue as usize * LatencyStage::ALL.len() + stage as usize
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}us", duration.as_nanos() as f64 / 1000.0)
+}
+
+impl Telemetry {
+    fn new() -> Telemetry {
+        let slots = Venue::ALL.len() * LatencyStage::ALL.len();
+        Telemetry {
+            total: (0..slots).map(|_| Histogram::new()).collect(),
+            interval: (0..slots).map(|_| Histogram::new()).collect(),
+            ticks: Venue::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, venue: Venue, stage: LatencyStage, value: Duration) {
+        self.total[slot(venue, stage)].record(value);
+        self.interval[slot(venue, stage)].record(value);
+    }
+
+    /// Records the time elapsed since the given instant
+    pub fn since(&self, venue: Venue, stage: LatencyStage, start: Instant) {
+        self.record(venue, stage, start.elapsed());
+    }
+
+    /// Marks the tick the strategy is currently reacting to, None once it moves on to anything else
+    pub fn tick(&self, venue: Venue, received: Option<Instant>) {
+        let nanos = match received {
+            Some(received) => received.saturating_duration_since(*EPOCH).as_nanos() as u64 + 1,
+            None => 0,
+        };
+        self.ticks[venue as usize].store(nanos, Ordering::Relaxed);
+    }
+
+    /// Called right before an order request goes out.
+    /// Records tick-to-send if the order came from a tick, and starts the clock on the ack and the user stream confirm.
+    pub fn sending(&self, venue: Venue, id: String) -> Instant {
+        let now = Instant::now();
+        let tick = self.ticks[venue as usize].load(Ordering::Relaxed);
+        if tick != 0 {
+            self.record(venue, LatencyStage::RestSend, now.saturating_duration_since(*EPOCH + Duration::from_nanos(tick - 1)));
+        }
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.insert(id, (venue, now));
+        }
+        now
+    }
+
+    /// First user stream update for an order we sent, later ones for the same id are ignored
+    pub fn confirmed(&self, id: &str) {
+        let sent = match self.in_flight.lock() {
+            Ok(mut in_flight) => in_flight.remove(id),
+            Err(_) => None,
+        };
+        if let Some((venue, sent)) = sent {
+            self.since(venue, LatencyStage::UserStreamConfirm, sent);
+        }
+    }
+
+    fn table(histograms: &Vec<Histogram>) -> String {
+        let mut out = String::new();
+        writeln!(out, "{:<8} {:<18} {:>9} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11}", "venue", "stage", "count", "mean", "p50", "p90", "p99", "p99.9", "max").unwrap();
+        for venue in Venue::ALL {
+            for stage in LatencyStage::ALL {
+                let summary = histograms[slot(venue, stage)].summary();
+                if summary.count == 0 { continue; }
+                writeln!(out, "{:<8} {:<18} {:>9} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11}",
+                    format!("{:?}", venue), format!("{:?}", stage), summary.count,
+                    format_duration(summary.mean), format_duration(summary.p50), format_duration(summary.p90),
+                    format_duration(summary.p99), format_duration(summary.p999), format_duration(summary.max)).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Percentiles since the last summary, then starts a new interval
+    pub fn summary(&self) -> String {
+        let out = Telemetry::table(&self.interval);
+        for histogram in self.interval.iter() { histogram.reset(); }
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.retain(|_, (_, sent)| sent.elapsed() < *CONFIRM_TIMEOUT);
+        }
+        out
+    }
+
+    /// Percentiles since startup followed by the full bucket distribution of every stage
+    pub fn dump(&self) -> String {
+        let mut out = Telemetry::table(&self.total);
+        for venue in Venue::ALL {
+            for stage in LatencyStage::ALL {
+                let buckets = self.total[slot(venue, stage)].buckets();
+                if buckets.is_empty() { continue; }
+                writeln!(out, "{:?} {:?}", venue, stage).unwrap();
+                for (value, count) in buckets {
+                    writeln!(out, "  <= {:>11} {}", format_duration(value), count).unwrap();
+                }
+            }
+        }
+        out
+    }
+
+    /// Every stage since startup that has seen a sample
+    pub fn summaries(&self) -> Vec<(Venue, LatencyStage, HistogramSummary)> {
+        let mut out = Vec::new();
+        for venue in Venue::ALL {
+            for stage in LatencyStage::ALL {
+                let summary = self.total[slot(venue, stage)].summary();
+                if summary.count > 0 { out.push((venue, stage, summary)); }
+            }
+        }
+        out
+    }
+
+    pub fn reset(&self) {
+        for histogram in self.total.iter().chain(self.interval.iter()) { histogram.reset(); }
+    }
+}
+
+/// Logs a latency summary every LATENCY_SUMMARY_SECS, a minute by default
+pub async fn summary_timer() {
+    let period = Duration::from_secs(CONFIG.latency_summary_secs.unwrap_or(60).max(1));
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        info!("[LATENCY] last {}s\n{}", period.as_secs(), LATENCY.summary());
+    }
+}
+
+/// Blocks reading commands off stdin, "latency" dumps the histograms and "latency reset" clears them,
+/// "risk" dumps the risk limits, rejects and kill switch, "risk reload" rereads the limits file and "risk reset" clears the kill switch.
+/// "pnl" dumps the PnL ledgers per side, symbol and account. "resume" lifts any order halts set by exchange errors.
+/// "log" shows the log filter and "log <filter>" swaps it, e.g. "log info,strategy::grid=debug".
+/// Returns when stdin closes.
+pub fn console() {
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        match line.trim() {
+            "latency" => info!("[LATENCY] since startup\n{}", LATENCY.dump()),
+            "latency reset" => {
+                LATENCY.reset();
+                info!("[LATENCY] histograms cleared");
+            },
+            "risk" => info!("[RISK] current state\n{}{}", RISK.dump(), BREAKER.dump()),
+            "risk reload" => RISK.reload(),
+            "risk reset" => BREAKER.reset(),
+            "pnl" => info!("[PNL] ledgers\n{}", PNL.dump()),
+            "resume" => BROKER.resume(),
+            "fees" => info!("[FEES] rates\n{}", FEES.dump()),
+            "params" => info!("[PARAMS] running with\n{}", CONTROL.dump()),
+            "log" => info!("[LOG] filter is {}", logging::filter()),
+            spec if spec.starts_with("log ") => match logging::set_filter(&spec[4..]) {
+                Ok(()) => info!("[LOG] filter is now {}", logging::filter()),
+                Err(e) => info!("[LOG] {}", e),
+            },
+            set if set.starts_with("set ") => {
+                if let Err(e) = CONTROL.command(&set[4..]) {
+                    info!("[PARAMS] {}", e);
+                }
+            },
+            "" => {},
+            other => info!("Unknown command {}", other),
+        }
+    }
+}
@@ -170,6 +170,24 @@ impl TradeFlow {
         tradeflow
     }
 
+    /// Taker trades per second over the culling window, the A in an order arrival intensity of A * e^(-k * depth)
+    pub fn arrival_rate(&self) -> D128 {
+        D128::from((self.buys.len() + self.sells.len()) as u64) * 1000 / D128::from(self.culling_threshold)
+    }
+
+    /// Average distance from mid that takers traded at over the window, 1 / depth estimates the k in the intensity.
+    /// Buys lift asks above the mid and sells hit bids below it.
+    pub fn depth(&self, mid: D128) -> D128 {
+        let buys = self.buy_metrics.price.length;
+        let sells = self.sell_metrics.price.length;
+        if !(buys + sells).is_positive() {
+            return D128::NAN;
+        }
+        let buy_depth = if buys.is_positive() { (self.buy_metrics.price.mean - mid) * buys } else { D128::ZERO };
+        let sell_depth = if sells.is_positive() { (mid - self.sell_metrics.price.mean) * sells } else { D128::ZERO };
+        (buy_depth + sell_depth) / (buys + sells)
+    }
+
     fn exchange_check(&mut self, exchange: Exchange) {
         if self.exchange == Exchange::None {
             self.exchange = exchange